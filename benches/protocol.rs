@@ -0,0 +1,164 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use zkp_chaum_padersen::arith::{Group, ModArith};
+use zkp_chaum_padersen::montgomery::Montgomery;
+use zkp_chaum_padersen::params::ParameterSet;
+use zkp_chaum_padersen::proof::{PublicKey, Secret};
+use zkp_chaum_padersen::ZKP;
+
+// 8192 bits takes seconds per proof, it has to be asked for by name:
+// ZKP_BENCH_ALL_SETS=1 cargo bench
+fn parameter_sets() -> Vec<ParameterSet> {
+    let sets = ParameterSet::ALL.to_vec();
+    if std::env::var("ZKP_BENCH_ALL_SETS").is_ok() {
+        return sets;
+    }
+    sets.into_iter()
+        .filter(|set| *set != ParameterSet::Rfc3526_8192)
+        .collect()
+}
+
+fn keygen(c: &mut Criterion) {
+    let mut group = c.benchmark_group("keygen");
+    for set in parameter_sets() {
+        let zkp = set.zkp();
+        group.bench_function(BenchmarkId::from_parameter(set.id()), |b| {
+            b.iter(|| zkp.public_key(&Secret::random(&zkp)))
+        });
+    }
+    group.finish();
+}
+
+fn prove(c: &mut Criterion) {
+    let mut group = c.benchmark_group("prove");
+    for set in parameter_sets() {
+        let zkp = set.zkp();
+        let secret = Secret::random(&zkp);
+        let public_key = zkp.public_key(&secret);
+        group.bench_function(BenchmarkId::from_parameter(set.id()), |b| {
+            b.iter(|| zkp.prove(black_box(&secret), &public_key))
+        });
+    }
+    group.finish();
+}
+
+// The same check with plain modpow, with the Montgomery contexts `ZKP`
+// uses (and Shamir's trick) and on the `arith` backends.
+fn verify(c: &mut Criterion) {
+    let mut group = c.benchmark_group("verify");
+    for set in parameter_sets() {
+        let zkp = set.zkp();
+        let secret = Secret::random(&zkp);
+        let public_key = zkp.public_key(&secret);
+        let proof = zkp.prove(&secret, &public_key);
+        let challenge = zkp.challenge(&public_key, &proof.r1, &proof.r2);
+
+        group.bench_function(BenchmarkId::new("modpow", set.id()), |b| {
+            b.iter(|| {
+                let (p, s, c) = (&zkp.p, &proof.s, &challenge);
+                let r1 = (ZKP::exponetiate(&zkp.alpha, s, p)
+                    * ZKP::exponetiate(&public_key.y1, c, p))
+                    % p;
+                let r2 = (ZKP::exponetiate(&zkp.beta, s, p)
+                    * ZKP::exponetiate(&public_key.y2, c, p))
+                    % p;
+                r1 == proof.r1 && r2 == proof.r2
+            })
+        });
+        group.bench_function(BenchmarkId::new("zkp", set.id()), |b| {
+            b.iter(|| {
+                assert!(zkp.verify_proof_without_expiry(&public_key, black_box(proof.clone())))
+            })
+        });
+
+        bench_backend::<Montgomery>(&mut group, "arith-montgomery", set);
+        #[cfg(feature = "crypto-bigint")]
+        match set {
+            ParameterSet::Rfc5114_1024_160 => {
+                use crypto_bigint::U1024;
+                use zkp_chaum_padersen::arith::CryptoBigint;
+                bench_backend::<CryptoBigint<{ U1024::LIMBS }>>(&mut group, "crypto-bigint", set)
+            }
+            ParameterSet::Rfc3526_2048 => {
+                use crypto_bigint::U2048;
+                use zkp_chaum_padersen::arith::CryptoBigint;
+                bench_backend::<CryptoBigint<{ U2048::LIMBS }>>(&mut group, "crypto-bigint", set)
+            }
+            ParameterSet::Rfc3526_3072 => {
+                use crypto_bigint::U3072;
+                use zkp_chaum_padersen::arith::CryptoBigint;
+                bench_backend::<CryptoBigint<{ U3072::LIMBS }>>(&mut group, "crypto-bigint", set)
+            }
+            ParameterSet::Rfc3526_8192 => {
+                use crypto_bigint::U8192;
+                use zkp_chaum_padersen::arith::CryptoBigint;
+                bench_backend::<CryptoBigint<{ U8192::LIMBS }>>(&mut group, "crypto-bigint", set)
+            }
+        }
+    }
+    group.finish();
+}
+
+fn bench_backend<A: ModArith>(
+    group: &mut criterion::BenchmarkGroup<criterion::measurement::WallTime>,
+    name: &str,
+    set: ParameterSet,
+) {
+    let backend = Group::<A>::from_zkp(&set.zkp()).expect("backend supports the group");
+    let x = backend.q.random_nonzero_below();
+    let (y1, y2) = backend.public_key(&x);
+    let (k, r1, r2) = backend.commit();
+    let c = backend.q.random_below();
+    let s = backend.solve(&k, &c, &x);
+    group.bench_function(BenchmarkId::new(name, set.id()), |b| {
+        b.iter(|| assert!(backend.verify((&r1, &r2), (&y1, &y2), black_box(&s), &c)))
+    });
+}
+
+// 16 statements: one proof each (proved one by one or with the fixed-base
+// tables of `prove_batch`), or a single aggregate proof over all of them.
+fn batch(c: &mut Criterion) {
+    const STATEMENTS: usize = 16;
+    let mut group = c.benchmark_group("batch");
+    group.sample_size(10);
+    for set in parameter_sets() {
+        let zkp = set.zkp();
+        let statements: Vec<(Secret, PublicKey)> = (0..STATEMENTS)
+            .map(|_| {
+                let secret = Secret::random(&zkp);
+                let public_key = zkp.public_key(&secret);
+                (secret, public_key)
+            })
+            .collect();
+        let public_keys: Vec<PublicKey> = statements.iter().map(|(_, key)| key.clone()).collect();
+        let proofs = zkp.prove_batch(&statements);
+        let aggregate = zkp.prove_aggregate(&statements);
+
+        group.bench_function(BenchmarkId::new("prove-each", set.id()), |b| {
+            b.iter(|| {
+                statements
+                    .iter()
+                    .map(|(secret, key)| zkp.prove(secret, key))
+                    .collect::<Vec<_>>()
+            })
+        });
+        group.bench_function(BenchmarkId::new("prove-batch", set.id()), |b| {
+            b.iter(|| zkp.prove_batch(black_box(&statements)))
+        });
+        group.bench_function(BenchmarkId::new("verify-each", set.id()), |b| {
+            b.iter(|| {
+                assert!(public_keys
+                    .iter()
+                    .zip(&proofs)
+                    .all(|(key, proof)| zkp.verify_proof_without_expiry(key, proof.clone())))
+            })
+        });
+        group.bench_function(BenchmarkId::new("verify-aggregate", set.id()), |b| {
+            b.iter(|| assert!(zkp.verify_aggregate(black_box(&public_keys), &aggregate)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, keygen, prove, verify, batch);
+criterion_main!(benches);
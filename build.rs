@@ -1,4 +1,9 @@
 fn main() {
+    // zkp_auth.rs is only compiled with std, and embedded builds shouldn't
+    // need protoc
+    if std::env::var_os("CARGO_FEATURE_STD").is_none() {
+        return;
+    }
     tonic_build::configure()
         .build_server(true)
         .out_dir("src/") // you can change the generated code's location
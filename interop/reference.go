@@ -0,0 +1,68 @@
+// Reference prover for InteropProfile::Go (src/interop.rs), standard library
+// only. Prints one proof per group as json.Marshal writes it, after the
+// challenge in hex:
+//
+//	go run interop/reference.go
+package main
+
+import (
+	"crypto/sha256"
+	"encoding/json"
+	"fmt"
+	"math/big"
+)
+
+type proof struct {
+	R1 string `json:"r1"`
+	R2 string `json:"r2"`
+	S  string `json:"s"`
+}
+
+func hex(s string) *big.Int {
+	n, ok := new(big.Int).SetString(s, 16)
+	if !ok {
+		panic(s)
+	}
+	return n
+}
+
+func main() {
+	rfc5114P := hex("B10B8F96A080E01DDE92DE5EAE5D54EC52C99FBCFB06A3C69A6A9DCA52D23B616073E28675A23D189838EF1E2EE652C0" +
+		"13ECB4AEA906112324975C3CD49B83BFACCBDD7D90C4BD7098488E9C219A73724EFFD6FAE5644738FAA31A4FF55BCCC0" +
+		"A151AF5F0DC8B4BD45BF37DF365C1A65E68CFDA76D4DA708DF1FB2BC2E4A4371")
+	rfc5114Alpha := hex("A4D1CBD5C3FD34126765A442EFB99905F8104DD258AC507FD6406CFF14266D31266FEA1E5C41564B777E690F5504F213" +
+		"160217B4B01B886A5E91547F9E2749F4D7FBD7D3B9A92EE1909D0D2263F80A76A6A24C087A091F531DBF0A0169B6A28A" +
+		"D662A4D18E73AFA32D779D5918D08BC8858F4DCEF97C2A24855E6EEB22B3B2E5")
+	cases := []struct {
+		name                    string
+		p, q, alpha, beta, x, k *big.Int
+	}{
+		{"toy", big.NewInt(2039), big.NewInt(1019), big.NewInt(4), big.NewInt(9), big.NewInt(123), big.NewInt(456)},
+		{
+			"rfc5114-1024-160", rfc5114P, hex("F518AA8781A8DF278ABA4E7D64B7CB9D49462353"), rfc5114Alpha,
+			new(big.Int).Exp(rfc5114Alpha, hex("266FEA1E5C41564B777E69"), rfc5114P),
+			hex("1234567890ABCDEF"), hex("FEDCBA0987654321"),
+		},
+	}
+
+	for _, tc := range cases {
+		exp := func(base, e *big.Int) *big.Int { return new(big.Int).Exp(base, e, tc.p) }
+		y1, y2 := exp(tc.alpha, tc.x), exp(tc.beta, tc.x)
+		r1, r2 := exp(tc.alpha, tc.k), exp(tc.beta, tc.k)
+
+		h := sha256.New()
+		for _, v := range []*big.Int{tc.alpha, tc.beta, y1, y2, r1, r2} {
+			h.Write(v.Bytes())
+		}
+		c := new(big.Int).Mod(new(big.Int).SetBytes(h.Sum(nil)), tc.q)
+		s := new(big.Int).Sub(tc.k, new(big.Int).Mul(c, tc.x))
+		s.Mod(s, tc.q)
+
+		out, err := json.Marshal(proof{R1: r1.Text(16), R2: r2.Text(16), S: s.Text(16)})
+		if err != nil {
+			panic(err)
+		}
+		fmt.Println(tc.name, c.Text(16))
+		fmt.Println(string(out))
+	}
+}
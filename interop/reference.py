@@ -0,0 +1,55 @@
+# Reference prover for InteropProfile::Python (src/interop.rs), standard
+# library only. Prints one proof per group as json.dumps writes it, after
+# the challenge:
+#
+#     python3 interop/reference.py
+#
+# The known answers in interop.rs are this script's output.
+import hashlib
+import json
+
+RFC5114_1024_P = int(
+    "B10B8F96A080E01DDE92DE5EAE5D54EC52C99FBCFB06A3C69A6A9DCA52D23B616073E28675A23D189838EF1E2EE652C0"
+    "13ECB4AEA906112324975C3CD49B83BFACCBDD7D90C4BD7098488E9C219A73724EFFD6FAE5644738FAA31A4FF55BCCC0"
+    "A151AF5F0DC8B4BD45BF37DF365C1A65E68CFDA76D4DA708DF1FB2BC2E4A4371",
+    16,
+)
+RFC5114_1024_Q = int("F518AA8781A8DF278ABA4E7D64B7CB9D49462353", 16)
+RFC5114_1024_ALPHA = int(
+    "A4D1CBD5C3FD34126765A442EFB99905F8104DD258AC507FD6406CFF14266D31266FEA1E5C41564B777E690F5504F213"
+    "160217B4B01B886A5E91547F9E2749F4D7FBD7D3B9A92EE1909D0D2263F80A76A6A24C087A091F531DBF0A0169B6A28A"
+    "D662A4D18E73AFA32D779D5918D08BC8858F4DCEF97C2A24855E6EEB22B3B2E5",
+    16,
+)
+RFC5114_1024_BETA = pow(RFC5114_1024_ALPHA, int("266FEA1E5C41564B777E69", 16), RFC5114_1024_P)
+
+# name, (p, q, alpha, beta), x, k
+CASES = [
+    ("toy", (2039, 1019, 4, 9), 123, 456),
+    (
+        "rfc5114-1024-160",
+        (RFC5114_1024_P, RFC5114_1024_Q, RFC5114_1024_ALPHA, RFC5114_1024_BETA),
+        0x1234567890ABCDEF,
+        0xFEDCBA0987654321,
+    ),
+]
+
+
+def challenge(q, values):
+    joined = ",".join(str(v) for v in values)
+    return int(hashlib.sha256(joined.encode()).hexdigest(), 16) % q
+
+
+def prove(group, x, k):
+    p, q, alpha, beta = group
+    y1, y2 = pow(alpha, x, p), pow(beta, x, p)
+    r1, r2 = pow(alpha, k, p), pow(beta, k, p)
+    c = challenge(q, (alpha, beta, y1, y2, r1, r2))
+    s = (k - c * x) % q
+    return c, {"r1": r1, "r2": r2, "s": s}
+
+
+for name, group, x, k in CASES:
+    c, proof = prove(group, x, k)
+    print(name, c)
+    print(json.dumps(proof))
@@ -0,0 +1,318 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use crate::audit::AuditEvent;
+use crate::store::{UserInfo, UserStatus, UserStore};
+use crate::verifier::{Purpose, Verifier};
+use crate::Error;
+
+// What operators do to a running verifier: look at the users and the
+// challenges waiting for an answer, disable and delete users, and make a
+// user rotate their key before the next login. Every change is audited and
+// the statuses live in the user store, so they hold for every verifier on
+// the same store.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserSummary {
+    pub user_info: UserInfo,
+    pub status: UserStatus,
+    // sessions open right now
+    pub sessions: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChallengeKind {
+    Login,
+    Rotation,
+    Action,
+    Recovery,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingChallenge {
+    pub auth_id: String,
+    pub user_name: String,
+    pub kind: ChallengeKind,
+    pub age: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParameterSetStats {
+    pub id: String,
+    pub is_default: bool,
+    pub users: usize,
+    pub pending_challenges: usize,
+}
+
+impl ChallengeKind {
+    pub fn id(&self) -> &'static str {
+        match self {
+            ChallengeKind::Login => "login",
+            ChallengeKind::Rotation => "rotation",
+            ChallengeKind::Action => "action",
+            ChallengeKind::Recovery => "recovery",
+        }
+    }
+}
+
+impl<S: UserStore> Verifier<S> {
+    // sorted by user name
+    pub fn list_users(&self) -> Vec<UserSummary> {
+        self.users
+            .user_names()
+            .into_iter()
+            .filter_map(|user_name| {
+                let user_info = self.users.get(&user_name)?;
+                let sessions = self
+                    .sessions
+                    .values()
+                    .filter(|(owner, _)| *owner == user_name)
+                    .count();
+                Some(UserSummary {
+                    status: self.users.status(&user_name),
+                    user_info,
+                    sessions,
+                })
+            })
+            .collect()
+    }
+
+    // Ends the user's sessions and pending challenges, no new ones are
+    // issued until `enable_user`.
+    pub fn disable_user(&mut self, user_name: &str) -> Result<(), Error> {
+        self.update_status(user_name, |status| status.disabled = true)?;
+        self.end_sessions_and_challenges(user_name);
+        trace_info!(user = %user_name, "user disabled");
+        self.audit(AuditEvent::UserDisabled {
+            user: user_name.to_string(),
+        });
+        Ok(())
+    }
+
+    pub fn enable_user(&mut self, user_name: &str) -> Result<(), Error> {
+        self.update_status(user_name, |status| status.disabled = false)?;
+        trace_info!(user = %user_name, "user enabled");
+        self.audit(AuditEvent::UserEnabled {
+            user: user_name.to_string(),
+        });
+        Ok(())
+    }
+
+    // output => the record that was removed
+    pub fn delete_user(&mut self, user_name: &str) -> Result<UserInfo, Error> {
+        let user_info = self
+            .users
+            .remove(user_name)?
+            .ok_or_else(|| Error::UserNotFound(user_name.to_string()))?;
+        self.end_sessions_and_challenges(user_name);
+        trace_info!(user = %user_name, "user deleted");
+        self.audit(AuditEvent::UserDeleted {
+            user: user_name.to_string(),
+            parameter_set: user_info.parameter_set.clone(),
+        });
+        Ok(user_info)
+    }
+
+    // For a key that may have leaked: ends the user's sessions, and only a
+    // key rotation (proving the current key) is accepted until one succeeds.
+    pub fn force_key_rotation(&mut self, user_name: &str) -> Result<(), Error> {
+        self.update_status(user_name, |status| status.rotation_required = true)?;
+        self.end_sessions_and_challenges(user_name);
+        trace_info!(user = %user_name, "key rotation forced");
+        self.audit(AuditEvent::RotationForced {
+            user: user_name.to_string(),
+        });
+        Ok(())
+    }
+
+    // oldest first; detached challenges (streaming logins) are not kept here
+    pub fn pending_challenges(&self) -> Vec<PendingChallenge> {
+        let mut pending: Vec<PendingChallenge> = self
+            .challenges
+            .iter()
+            .map(|(auth_id, challenge)| PendingChallenge {
+                auth_id: auth_id.clone(),
+                user_name: challenge.user_name.clone(),
+                kind: match challenge.purpose {
+                    Purpose::Login => ChallengeKind::Login,
+                    Purpose::Rotation(_) => ChallengeKind::Rotation,
+                    Purpose::Action(_) => ChallengeKind::Action,
+                    Purpose::Recovery { .. } => ChallengeKind::Recovery,
+                },
+                age: challenge.issued.elapsed(),
+            })
+            .collect();
+        pending.sort_by_key(|pending| std::cmp::Reverse(pending.age));
+        pending
+    }
+
+    // One entry per parameter set the verifier accepts, sorted by id, plus
+    // any the store still has users on that it no longer accepts.
+    pub fn parameter_set_stats(&self) -> Vec<ParameterSetStats> {
+        let new = |id: &str| ParameterSetStats {
+            id: id.to_string(),
+            is_default: id == self.default_parameter_set(),
+            users: 0,
+            pending_challenges: 0,
+        };
+        let mut stats: BTreeMap<String, ParameterSetStats> = self
+            .parameter_set_ids()
+            .into_iter()
+            .map(|id| (id.clone(), new(&id)))
+            .collect();
+
+        // user name => parameter set
+        let mut users = HashMap::new();
+        for user_name in self.users.user_names() {
+            if let Some(user_info) = self.users.get(&user_name) {
+                let id = user_info.parameter_set;
+                stats.entry(id.clone()).or_insert_with(|| new(&id)).users += 1;
+                users.insert(user_name, id);
+            }
+        }
+        for challenge in self.challenges.values() {
+            if let Some(stats) = users
+                .get(&challenge.user_name)
+                .and_then(|id| stats.get_mut(id))
+            {
+                stats.pending_challenges += 1;
+            }
+        }
+        stats.into_values().collect()
+    }
+
+    fn update_status(
+        &mut self,
+        user_name: &str,
+        update: impl FnOnce(&mut UserStatus),
+    ) -> Result<(), Error> {
+        if self.users.get(user_name).is_none() {
+            return Err(Error::UserNotFound(user_name.to_string()));
+        }
+        let mut status = self.users.status(user_name);
+        update(&mut status);
+        if !self.users.set_status(user_name, status)? {
+            return Err(Error::InvalidParameters(
+                "the user store can't keep user statuses".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn end_sessions_and_challenges(&mut self, user_name: &str) {
+        self.sessions.retain(|_, (owner, _)| owner != user_name);
+        self.challenges
+            .retain(|_, challenge| challenge.user_name != user_name);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+    use crate::proof::Secret;
+    use crate::ZKP;
+
+    fn login(
+        verifier: &mut Verifier,
+        zkp: &ZKP,
+        secret: &Secret,
+        user: &str,
+    ) -> Result<String, Error> {
+        let k = ZKP::generate_random_nonzero_below(&zkp.q);
+        let (r1, r2) = (zkp.pow(&zkp.alpha, &k), zkp.pow(&zkp.beta, &k));
+        let (auth_id, c) = verifier.create_challenge(user, r1, r2)?;
+        verifier.verify(&auth_id, &zkp.solve(&k, &c, secret.x()))
+    }
+
+    fn setup() -> (Verifier, ZKP, Secret) {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let zkp = set.zkp();
+        let secret = Secret::random(&zkp);
+        let public_key = zkp.public_key(&secret);
+        let mut verifier = Verifier::with_parameter_sets(set, &[]);
+        verifier
+            .register("alice", public_key.y1, public_key.y2)
+            .unwrap();
+        (verifier, zkp, secret)
+    }
+
+    #[test]
+    fn test_disable_and_delete() {
+        let (mut verifier, zkp, secret) = setup();
+        let session_id = login(&mut verifier, &zkp, &secret, "alice").unwrap();
+        let users = verifier.list_users();
+        assert_eq!(users.len(), 1);
+        assert_eq!(
+            (users[0].sessions, users[0].status),
+            (1, UserStatus::default())
+        );
+
+        verifier.disable_user("alice").unwrap();
+        assert_eq!(verifier.session_user(&session_id), None);
+        assert_eq!(
+            login(&mut verifier, &zkp, &secret, "alice"),
+            Err(Error::UserDisabled("alice".to_string()))
+        );
+        assert!(verifier.list_users()[0].status.disabled);
+
+        verifier.enable_user("alice").unwrap();
+        login(&mut verifier, &zkp, &secret, "alice").unwrap();
+
+        verifier.delete_user("alice").unwrap();
+        assert!(verifier.user("alice").is_none());
+        assert_eq!(
+            verifier.disable_user("alice"),
+            Err(Error::UserNotFound("alice".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_forced_rotation() {
+        let (mut verifier, zkp, secret) = setup();
+        verifier.force_key_rotation("alice").unwrap();
+        assert_eq!(
+            login(&mut verifier, &zkp, &secret, "alice"),
+            Err(Error::RotationRequired("alice".to_string()))
+        );
+
+        let new_secret = Secret::random(&zkp);
+        let new_key = zkp.public_key(&new_secret);
+        let k = ZKP::generate_random_nonzero_below(&zkp.q);
+        let (r1, r2) = (zkp.pow(&zkp.alpha, &k), zkp.pow(&zkp.beta, &k));
+        let (auth_id, c) = verifier
+            .create_rotation_challenge("alice", r1, r2, new_key.y1, new_key.y2)
+            .unwrap();
+        let pending = verifier.pending_challenges();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(
+            (pending[0].auth_id.as_str(), pending[0].kind),
+            (auth_id.as_str(), ChallengeKind::Rotation)
+        );
+
+        verifier
+            .verify_rotation(&auth_id, &zkp.solve(&k, &c, secret.x()))
+            .unwrap();
+        assert!(!verifier.list_users()[0].status.rotation_required);
+        login(&mut verifier, &zkp, &new_secret, "alice").unwrap();
+    }
+
+    #[test]
+    fn test_parameter_set_stats() {
+        let (mut verifier, zkp, _) = setup();
+        let k = ZKP::generate_random_nonzero_below(&zkp.q);
+        let (r1, r2) = (zkp.pow(&zkp.alpha, &k), zkp.pow(&zkp.beta, &k));
+        verifier.create_challenge("alice", r1, r2).unwrap();
+
+        let stats = verifier.parameter_set_stats();
+        assert_eq!(
+            stats,
+            vec![ParameterSetStats {
+                id: ParameterSet::Rfc5114_1024_160.id().to_string(),
+                is_default: true,
+                users: 1,
+                pending_challenges: 1,
+            }]
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+use crate::modmath::{mod_mul, mod_reduce};
+use crate::proof::{PublicKey, Secret};
+use crate::ZKP;
+
+const WEIGHT_LABEL: &[u8] = b"zkp-chaum-pedersen/aggregate/weight";
+const CHALLENGE_LABEL: &[u8] = b"zkp-chaum-pedersen/aggregate/challenge";
+
+// One proof for n statements (y1_i, y2_i) of the same prover. Every statement
+// gets a 128-bit weight z_i = H(all statements, i) and the prover proves
+// that the combined pair
+//     Y1 = prod y1_i^z_i,  Y2 = prod y2_i^z_i
+// shares the discrete log X = sum z_i * x_i. If any single pair doesn't,
+// the combination fails except with probability about 2^-128, so the n
+// statements stand or fall together. Verification costs two short
+// exponentiations per statement plus one Chaum-Pedersen check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AggregateProof {
+    pub r1: BigUint,
+    pub r2: BigUint,
+    pub s: BigUint,
+}
+
+impl ZKP {
+    pub fn prove_aggregate(&self, statements: &[(Secret, PublicKey)]) -> AggregateProof {
+        assert!(!statements.is_empty(), "nothing to prove");
+
+        let public_keys: Vec<PublicKey> = statements.iter().map(|(_, pk)| pk.clone()).collect();
+        let weights = self.aggregation_weights(&public_keys);
+
+        let x = statements
+            .iter()
+            .zip(&weights)
+            .fold(BigUint::from(0u32), |acc, ((secret, _), z)| {
+                mod_reduce(&(acc + z * secret.x()), &self.q)
+            });
+        let combined = self.combine(&public_keys, &weights);
+
+        let k = ZKP::generate_random_nonzero_below(&self.q);
+        let r1 = self.pow(&self.alpha, &k);
+        let r2 = self.pow(&self.beta, &k);
+
+        let c = self.hash_to_scalar(CHALLENGE_LABEL, &[&combined.y1, &combined.y2, &r1, &r2]);
+        let s = self.solve(&k, &c, &x);
+
+        AggregateProof { r1, r2, s }
+    }
+
+    // Every key has to be in the subgroup: the combination would hide a
+    // component of small order whenever its weight is a multiple of that
+    // order.
+    pub fn verify_aggregate(&self, public_keys: &[PublicKey], proof: &AggregateProof) -> bool {
+        if public_keys.is_empty() || !public_keys.iter().all(|key| self.is_subgroup_key(key)) {
+            return false;
+        }
+
+        let weights = self.aggregation_weights(public_keys);
+        let combined = self.combine(public_keys, &weights);
+
+        let c = self.hash_to_scalar(
+            CHALLENGE_LABEL,
+            &[&combined.y1, &combined.y2, &proof.r1, &proof.r2],
+        );
+        self.verify(
+            &proof.r1,
+            &proof.r2,
+            &combined.y1,
+            &combined.y2,
+            &proof.s,
+            &c,
+        )
+    }
+
+    // z_i = H(y1_0, y2_0, ..., y1_n, y2_n, i) truncated to 128 bits
+    fn aggregation_weights(&self, public_keys: &[PublicKey]) -> Vec<BigUint> {
+        let mut statement_hasher = Sha256::new();
+        statement_hasher.update(WEIGHT_LABEL);
+        statement_hasher.update((public_keys.len() as u64).to_be_bytes());
+        for public_key in public_keys {
+            for n in [&public_key.y1, &public_key.y2] {
+                let bytes = self.element_to_bytes(n);
+                statement_hasher.update((bytes.len() as u32).to_be_bytes());
+                statement_hasher.update(&bytes);
+            }
+        }
+
+        (0..public_keys.len() as u64)
+            .map(|i| {
+                let mut hasher = statement_hasher.clone();
+                hasher.update(i.to_be_bytes());
+                BigUint::from_bytes_be(&hasher.finalize()[..16])
+            })
+            .collect()
+    }
+
+    fn combine(&self, public_keys: &[PublicKey], weights: &[BigUint]) -> PublicKey {
+        let mut y1 = BigUint::from(1u32);
+        let mut y2 = BigUint::from(1u32);
+        for (public_key, z) in public_keys.iter().zip(weights) {
+            y1 = mod_mul(&y1, &self.pow(&public_key.y1, z), &self.p);
+            y2 = mod_mul(&y2, &self.pow(&public_key.y2, z), &self.p);
+        }
+
+        PublicKey { y1, y2 }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+
+    fn statements(zkp: &ZKP, n: usize) -> Vec<(Secret, PublicKey)> {
+        (0..n)
+            .map(|_| {
+                let secret = Secret::random(zkp);
+                let public_key = zkp.public_key(&secret);
+                (secret, public_key)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_aggregate_proof() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let statements = statements(&zkp, 5);
+        let public_keys: Vec<PublicKey> = statements.iter().map(|(_, pk)| pk.clone()).collect();
+
+        let proof = zkp.prove_aggregate(&statements);
+        assert!(zkp.verify_aggregate(&public_keys, &proof));
+
+        // the proof covers exactly these statements, in this order
+        assert!(!zkp.verify_aggregate(&public_keys[..4], &proof));
+        let mut swapped = public_keys.clone();
+        swapped.swap(0, 1);
+        assert!(!zkp.verify_aggregate(&swapped, &proof));
+        assert!(!zkp.verify_aggregate(&[], &proof));
+    }
+
+    #[test]
+    fn test_one_bad_statement_breaks_the_bundle() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let mut statements = statements(&zkp, 3);
+
+        // y2 computed with another secret than y1
+        let other = Secret::random(&zkp);
+        statements[1].1.y2 = zkp.public_key(&other).y2;
+        let public_keys: Vec<PublicKey> = statements.iter().map(|(_, pk)| pk.clone()).collect();
+
+        let proof = zkp.prove_aggregate(&statements);
+        assert!(!zkp.verify_aggregate(&public_keys, &proof));
+    }
+
+    #[test]
+    fn test_keys_outside_the_subgroup_are_rejected() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let minus_one = &zkp.p - 1u32;
+
+        // -y1 has a component of order 2, which an even weight cancels: the
+        // combined pair is the honest one and the proof checks out
+        let (statements, public_keys) = loop {
+            let mut statements = statements(&zkp, 2);
+            statements[0].1.y1 = mod_mul(&statements[0].1.y1, &minus_one, &zkp.p);
+            let public_keys: Vec<PublicKey> = statements.iter().map(|(_, pk)| pk.clone()).collect();
+            if !zkp.aggregation_weights(&public_keys)[0].bit(0) {
+                break (statements, public_keys);
+            }
+        };
+        let proof = zkp.prove_aggregate(&statements);
+        let combined = zkp.combine(&public_keys, &zkp.aggregation_weights(&public_keys));
+        let c = zkp.hash_to_scalar(
+            CHALLENGE_LABEL,
+            &[&combined.y1, &combined.y2, &proof.r1, &proof.r2],
+        );
+        assert!(zkp.verify(
+            &proof.r1,
+            &proof.r2,
+            &combined.y1,
+            &combined.y2,
+            &proof.s,
+            &c
+        ));
+        assert!(!zkp.verify_aggregate(&public_keys, &proof));
+    }
+}
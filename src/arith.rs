@@ -0,0 +1,332 @@
+use std::fmt::Debug;
+
+use num_bigint::BigUint;
+
+use crate::encoding::{from_bytes_be_exact, to_bytes_be_padded};
+use crate::modmath;
+use crate::montgomery::Montgomery;
+use crate::{Error, ZKP};
+
+// Integer arithmetic mod one modulus, all the protocol needs from a bignum
+// library. `Group` runs Chaum-Pedersen on top of it, so another backend is
+// one impl of this trait and nothing else changes.
+//
+// `ZKP`, `Verifier` and the wire formats stay on `BigUint` with the
+// `montgomery` contexts; `Group` is for comparing backends (benches,
+// `testing`), not a drop-in for them.
+//
+//     num-bigint     `Montgomery`, always available
+//     crypto-bigint  `CryptoBigint<LIMBS>`, feature "crypto-bigint",
+//                    constant time, moduli up to `Uint<LIMBS>::BITS`
+//
+// There is no rug backend: GMP can't be built everywhere this crate is, and
+// `Montgomery` is within a small factor of it at these sizes.
+pub trait ModArith: Sized {
+    type Int: Clone + Debug + PartialEq + Ord;
+
+    // None if the backend can't work mod this modulus
+    fn with_modulus(modulus: &BigUint) -> Option<Self>;
+
+    fn modpow(&self, base: &Self::Int, exponent: &Self::Int) -> Self::Int;
+
+    fn modmul(&self, a: &Self::Int, b: &Self::Int) -> Self::Int;
+
+    fn modsub(&self, a: &Self::Int, b: &Self::Int) -> Self::Int;
+
+    // uniform in [0, modulus)
+    fn random_below(&self) -> Self::Int;
+
+    // uniform in [1, modulus), for secrets and nonces
+    fn random_nonzero_below(&self) -> Self::Int {
+        loop {
+            let n = self.random_below();
+            if self.encode(&n).iter().any(|byte| *byte != 0) {
+                return n;
+            }
+        }
+    }
+
+    // big-endian, padded to the byte length of the modulus
+    fn encode(&self, n: &Self::Int) -> Vec<u8>;
+
+    // exactly the byte length of the modulus, and below it
+    fn decode(&self, bytes: &[u8]) -> Result<Self::Int, Error>;
+}
+
+impl ModArith for Montgomery {
+    type Int = BigUint;
+
+    fn with_modulus(modulus: &BigUint) -> Option<Self> {
+        Montgomery::new(modulus)
+    }
+
+    fn modpow(&self, base: &BigUint, exponent: &BigUint) -> BigUint {
+        self.pow(base, exponent)
+    }
+
+    fn modmul(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        modmath::mod_mul(a, b, self.modulus())
+    }
+
+    fn modsub(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        modmath::mod_sub(a, b, self.modulus())
+    }
+
+    fn random_below(&self) -> BigUint {
+        ZKP::generate_random_below(self.modulus())
+    }
+
+    fn encode(&self, n: &BigUint) -> Vec<u8> {
+        to_bytes_be_padded(n, byte_len(self.modulus()))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<BigUint, Error> {
+        let n = from_bytes_be_exact(bytes, byte_len(self.modulus()))?;
+        if n >= *self.modulus() {
+            return Err(Error::InvalidEncoding(
+                "value not below the modulus".to_string(),
+            ));
+        }
+        Ok(n)
+    }
+}
+
+fn byte_len(modulus: &BigUint) -> usize {
+    modulus.bits().div_ceil(8) as usize
+}
+
+// Chaum-Pedersen over any backend: elements mod p, scalars mod q.
+#[derive(Debug, Clone)]
+pub struct Group<A: ModArith> {
+    pub p: A,
+    pub q: A,
+    pub alpha: A::Int,
+    pub beta: A::Int,
+    zero: A::Int,
+    one: A::Int,
+}
+
+impl<A: ModArith> Group<A> {
+    pub fn from_zkp(zkp: &ZKP) -> Result<Self, Error> {
+        let unsupported =
+            || Error::InvalidParameters("modulus not supported by this backend".to_string());
+        let p = A::with_modulus(&zkp.p).ok_or_else(unsupported)?;
+        let q = A::with_modulus(&zkp.q).ok_or_else(unsupported)?;
+        let alpha = p.decode(&zkp.element_to_bytes(&zkp.alpha))?;
+        let beta = p.decode(&zkp.element_to_bytes(&zkp.beta))?;
+        let zero = p.decode(&zkp.element_to_bytes(&BigUint::from(0u32)))?;
+        let one = p.decode(&zkp.element_to_bytes(&BigUint::from(1u32)))?;
+        Ok(Group {
+            p,
+            q,
+            alpha,
+            beta,
+            zero,
+            one,
+        })
+    }
+
+    // output => (alpha^x, beta^x)
+    pub fn public_key(&self, x: &A::Int) -> (A::Int, A::Int) {
+        (self.p.modpow(&self.alpha, x), self.p.modpow(&self.beta, x))
+    }
+
+    // output => (k, r1, r2)
+    pub fn commit(&self) -> (A::Int, A::Int, A::Int) {
+        let k = self.q.random_nonzero_below();
+        let (r1, r2) = self.public_key(&k);
+        (k, r1, r2)
+    }
+
+    // output => s = k - c * x mod q
+    pub fn solve(&self, k: &A::Int, c: &A::Int, x: &A::Int) -> A::Int {
+        self.q.modsub(k, &self.q.modmul(c, x))
+    }
+
+    // r1 = alpha^s * y1^c and r2 = beta^s * y2^c mod p, with the same range
+    // checks as `ZKP::verify`: scalars below q, elements in (1, p)
+    pub fn verify(
+        &self,
+        (r1, r2): (&A::Int, &A::Int),
+        (y1, y2): (&A::Int, &A::Int),
+        s: &A::Int,
+        c: &A::Int,
+    ) -> bool {
+        let in_range = |e: &A::Int| {
+            *e != self.zero && *e != self.one && self.p.decode(&self.p.encode(e)).is_ok()
+        };
+        let s_in_range = self.q.decode(&self.q.encode(s)).is_ok();
+        if !s_in_range || ![r1, r2, y1, y2].into_iter().all(in_range) {
+            return false;
+        }
+
+        let check = |base: &A::Int, y: &A::Int| {
+            self.p.modmul(&self.p.modpow(base, s), &self.p.modpow(y, c))
+        };
+        check(&self.alpha, y1) == *r1 && check(&self.beta, y2) == *r2
+    }
+}
+
+#[cfg(feature = "crypto-bigint")]
+pub use self::crypto::CryptoBigint;
+
+#[cfg(feature = "crypto-bigint")]
+mod crypto {
+    use crypto_bigint::modular::runtime_mod::{DynResidue, DynResidueParams};
+    use crypto_bigint::{NonZero, RandomMod, Uint};
+    use num_bigint::BigUint;
+
+    use super::{byte_len, ModArith};
+    use crate::Error;
+
+    #[derive(Debug, Clone)]
+    pub struct CryptoBigint<const LIMBS: usize> {
+        params: DynResidueParams<LIMBS>,
+        modulus: NonZero<Uint<LIMBS>>,
+        // byte length of the modulus
+        len: usize,
+    }
+
+    impl<const LIMBS: usize> CryptoBigint<LIMBS> {
+        fn residue(&self, n: &Uint<LIMBS>) -> DynResidue<LIMBS> {
+            DynResidue::new(n, self.params)
+        }
+    }
+
+    impl<const LIMBS: usize> ModArith for CryptoBigint<LIMBS> {
+        type Int = Uint<LIMBS>;
+
+        // None for moduli that are even or too wide for LIMBS
+        fn with_modulus(modulus: &BigUint) -> Option<Self> {
+            let len = byte_len(modulus);
+            if len > Uint::<LIMBS>::BYTES || !modulus.bit(0) || *modulus == BigUint::from(1u32) {
+                return None;
+            }
+            let n = from_be_bytes(&modulus.to_bytes_be());
+            Some(CryptoBigint {
+                params: DynResidueParams::new(&n),
+                modulus: Option::from(NonZero::new(n))?,
+                len,
+            })
+        }
+
+        fn modpow(&self, base: &Uint<LIMBS>, exponent: &Uint<LIMBS>) -> Uint<LIMBS> {
+            self.residue(base).pow(exponent).retrieve()
+        }
+
+        fn modmul(&self, a: &Uint<LIMBS>, b: &Uint<LIMBS>) -> Uint<LIMBS> {
+            (self.residue(a) * self.residue(b)).retrieve()
+        }
+
+        fn modsub(&self, a: &Uint<LIMBS>, b: &Uint<LIMBS>) -> Uint<LIMBS> {
+            (self.residue(a) - self.residue(b)).retrieve()
+        }
+
+        fn random_below(&self) -> Uint<LIMBS> {
+            Uint::random_mod(&mut rand::thread_rng(), &self.modulus)
+        }
+
+        fn encode(&self, n: &Uint<LIMBS>) -> Vec<u8> {
+            let bytes: Vec<u8> = n
+                .as_words()
+                .iter()
+                .rev()
+                .flat_map(|word| word.to_be_bytes())
+                .collect();
+            bytes[bytes.len() - self.len..].to_vec()
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Result<Uint<LIMBS>, Error> {
+            if bytes.len() != self.len {
+                return Err(Error::InvalidEncoding(format!(
+                    "expected {} bytes, got {}",
+                    self.len,
+                    bytes.len()
+                )));
+            }
+            let n = from_be_bytes(bytes);
+            if n >= *self.modulus {
+                return Err(Error::InvalidEncoding(
+                    "value not below the modulus".to_string(),
+                ));
+            }
+            Ok(n)
+        }
+    }
+
+    // at most Uint::<LIMBS>::BYTES long
+    fn from_be_bytes<const LIMBS: usize>(bytes: &[u8]) -> Uint<LIMBS> {
+        let mut padded = vec![0u8; Uint::<LIMBS>::BYTES - bytes.len()];
+        padded.extend_from_slice(bytes);
+        Uint::from_be_slice(&padded)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+
+    fn run_protocol<A: ModArith>(zkp: &ZKP) {
+        let group = Group::<A>::from_zkp(zkp).unwrap();
+        let x = group.q.random_below();
+        let (y1, y2) = group.public_key(&x);
+        let (k, r1, r2) = group.commit();
+        let c = group.q.random_below();
+        let s = group.solve(&k, &c, &x);
+        assert!(group.verify((&r1, &r2), (&y1, &y2), &s, &c));
+
+        let other = group.q.random_below();
+        let s = group.solve(&k, &c, &other);
+        assert!(!group.verify((&r1, &r2), (&y1, &y2), &s, &c));
+    }
+
+    #[test]
+    fn test_montgomery_backend() {
+        run_protocol::<Montgomery>(&ParameterSet::Rfc5114_1024_160.zkp());
+    }
+
+    #[test]
+    fn test_backend_agrees_with_zkp() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let group = Group::<Montgomery>::from_zkp(&zkp).unwrap();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let (y1, y2) = group.public_key(&x);
+        let (k, r1, r2) = group.commit();
+        let c = ZKP::generate_random_below(&zkp.q);
+        let s = group.solve(&k, &c, &x);
+        assert!(zkp.verify(&r1, &r2, &y1, &y2, &s, &c));
+
+        // out of range values are rejected like `ZKP::verify` does
+        let one = BigUint::from(1u32);
+        assert!(!group.verify((&r1, &r2), (&y1, &one), &s, &c));
+
+        // y2 = r2 = 0 satisfies r2 = beta^s * y2^c for any c != 0
+        let zero = BigUint::from(0u32);
+        assert!(!group.verify((&r1, &zero), (&y1, &zero), &s, &c));
+        assert!(!group.verify((&r1, &r2), (&y1, &y2), &(&s + &zkp.q), &c));
+        assert!(group.p.decode(&[0xff; 128]).is_err());
+    }
+
+    #[cfg(feature = "crypto-bigint")]
+    #[test]
+    fn test_crypto_bigint_backend() {
+        use crypto_bigint::{U1024, U2048};
+
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        run_protocol::<CryptoBigint<{ U1024::LIMBS }>>(&zkp);
+        run_protocol::<CryptoBigint<{ U2048::LIMBS }>>(&ParameterSet::Rfc3526_2048.zkp());
+        assert!(Group::<CryptoBigint<{ U1024::LIMBS }>>::from_zkp(
+            &ParameterSet::Rfc3526_2048.zkp()
+        )
+        .is_err());
+
+        // same bytes in, same bytes out as num-bigint
+        let mont = Group::<Montgomery>::from_zkp(&zkp).unwrap();
+        let ct = Group::<CryptoBigint<{ U1024::LIMBS }>>::from_zkp(&zkp).unwrap();
+        let x = ZKP::generate_random_below(&zkp.q);
+        let (y1, _) = mont.public_key(&x);
+        let (ct_y1, _) = ct.public_key(&ct.q.decode(&zkp.scalar_to_bytes(&x)).unwrap());
+        assert_eq!(ct.p.encode(&ct_y1), mont.p.encode(&y1));
+    }
+}
@@ -0,0 +1,187 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::Error;
+
+// What the verifier did, one event per step of the protocol. Public keys,
+// commitments and answers are left out, the log only says who did what.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AuditEvent {
+    Registered {
+        user: String,
+        parameter_set: String,
+    },
+    RegistrationRejected {
+        user: String,
+        parameter_set: String,
+        reason: String,
+    },
+    ChallengeIssued {
+        user: String,
+        parameter_set: String,
+        auth_id: String,
+        rotation: bool,
+    },
+    ChallengeRejected {
+        user: String,
+        reason: String,
+    },
+    Verified {
+        user: String,
+        parameter_set: String,
+        auth_id: String,
+        passed: bool,
+    },
+    UnknownChallenge {
+        auth_id: String,
+    },
+    KeyRotated {
+        user: String,
+        parameter_set: String,
+    },
+    Migrated {
+        user: String,
+        from: String,
+        to: String,
+    },
+    // operator actions, see `crate::admin`
+    UserDisabled {
+        user: String,
+    },
+    UserEnabled {
+        user: String,
+    },
+    UserDeleted {
+        user: String,
+        parameter_set: String,
+    },
+    RotationForced {
+        user: String,
+    },
+    RecoveryKeysEnrolled {
+        user: String,
+        keys: usize,
+    },
+    Recovered {
+        user: String,
+        parameter_set: String,
+        recovery_keys_left: usize,
+    },
+    // revoked, not expired or evicted by the per-user limit
+    SessionsRevoked {
+        user: String,
+        sessions: usize,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuditRecord {
+    // milliseconds since the unix epoch
+    pub timestamp_ms: u64,
+    #[serde(flatten)]
+    pub event: AuditEvent,
+}
+
+impl AuditRecord {
+    pub fn now(event: AuditEvent) -> Self {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or(0);
+        AuditRecord {
+            timestamp_ms,
+            event,
+        }
+    }
+}
+
+// Receives every record of a verifier, see `Verifier::add_audit_sink`.
+pub trait AuditSink {
+    fn record(&mut self, record: &AuditRecord) -> Result<(), Error>;
+}
+
+// One JSON object per line, flushed after every record:
+//
+//     {"timestamp_ms":1700000000000,"event":"registered","user":"alice","parameter_set":"rfc3526-2048"}
+pub struct JsonlAuditSink<W = File> {
+    writer: W,
+}
+
+impl JsonlAuditSink<File> {
+    // Appends to path, creating it if needed. Existing records are never
+    // rewritten.
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| Error::Io(e.to_string()))?;
+        Ok(JsonlAuditSink::new(file))
+    }
+}
+
+impl<W: Write> JsonlAuditSink<W> {
+    pub fn new(writer: W) -> Self {
+        JsonlAuditSink { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> AuditSink for JsonlAuditSink<W> {
+    fn record(&mut self, record: &AuditRecord) -> Result<(), Error> {
+        let mut line = serde_json::to_vec(record).map_err(|e| Error::Io(e.to_string()))?;
+        line.push(b'\n');
+        // one write per record, so lines of concurrent appenders don't mix
+        self.writer
+            .write_all(&line)
+            .and_then(|_| self.writer.flush())
+            .map_err(|e| Error::Io(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_jsonl_lines() {
+        let mut sink = JsonlAuditSink::new(Vec::new());
+        let registered = AuditRecord {
+            timestamp_ms: 1,
+            event: AuditEvent::Registered {
+                user: "alice".to_string(),
+                parameter_set: "rfc3526-2048".to_string(),
+            },
+        };
+        let verified = AuditRecord {
+            timestamp_ms: 2,
+            event: AuditEvent::Verified {
+                user: "alice".to_string(),
+                parameter_set: "rfc3526-2048".to_string(),
+                auth_id: "abc".to_string(),
+                passed: false,
+            },
+        };
+        sink.record(&registered).unwrap();
+        sink.record(&verified).unwrap();
+
+        let output = String::from_utf8(sink.into_inner()).unwrap();
+        assert_eq!(
+            output,
+            concat!(
+                r#"{"timestamp_ms":1,"event":"registered","user":"alice","parameter_set":"rfc3526-2048"}"#,
+                "\n",
+                r#"{"timestamp_ms":2,"event":"verified","user":"alice","parameter_set":"rfc3526-2048","auth_id":"abc","passed":false}"#,
+                "\n",
+            )
+        );
+    }
+}
@@ -0,0 +1,265 @@
+use std::collections::BTreeMap;
+
+use num_bigint::BigUint;
+use zeroize::Zeroize;
+
+use crate::bundle::unix_now;
+use crate::der::{pem_decode, pem_encode};
+use crate::keystore::{open, seal, KdfParams, Keystore};
+use crate::params::ParameterSet;
+use crate::proof::{PublicKey, Secret};
+use crate::Error;
+
+// A prover's credential packed for moving to another device: the same user
+// and secret keep working there, nothing has to be registered again. Sealed
+// like the keystore (Argon2id, XChaCha20-Poly1305, the header authenticated
+// as associated data), but it also carries the user name and free-form
+// metadata (device, note) and has a format of its own so the two can
+// evolve apart.
+//
+// Layout inside the PEM block, integers big-endian:
+//     version   u8 (1)
+//     m_cost    u32, t_cost u32, p_cost u32
+//     salt      [u8; 16]
+//     nonce     [u8; 24]
+//     sealed    user name       u16 length, utf-8
+//               parameter set   u16 length, utf-8 id
+//               created_at      u64, unix seconds
+//               x               u16 length, big-endian
+//               metadata        u16 count, then per entry
+//                               u16 length, utf-8 key,
+//                               u32 length, utf-8 value
+//               nothing after the last entry
+//
+// Readers reject other versions; a new field means a new version.
+pub const PEM_LABEL: &str = "ZKP CREDENTIAL BACKUP";
+
+const VERSION: u8 = 1;
+
+#[derive(Debug, Clone)]
+pub struct Backup {
+    pub user_name: String,
+    pub parameter_set: String,
+    pub secret: Secret,
+    // unix seconds
+    pub created_at: u64,
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl Backup {
+    pub fn new(user_name: &str, keystore: &Keystore) -> Self {
+        Backup {
+            user_name: user_name.to_string(),
+            parameter_set: keystore.parameter_set.clone(),
+            secret: keystore.secret.clone(),
+            created_at: unix_now(),
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    pub fn keystore(&self) -> Keystore {
+        Keystore::new(&self.parameter_set, self.secret.clone())
+    }
+
+    // The key to compare with the one registered, for the named parameter
+    // sets; custom groups have to be looked up by the caller.
+    pub fn public_key(&self) -> Result<PublicKey, Error> {
+        let zkp = self.parameter_set.parse::<ParameterSet>()?.zkp();
+        Ok(zkp.public_key(&self.secret))
+    }
+
+    pub fn export(&self, passphrase: &str, params: KdfParams) -> Result<String, Error> {
+        let mut plaintext = Vec::new();
+        put_u16_bytes(&mut plaintext, self.user_name.as_bytes())?;
+        put_u16_bytes(&mut plaintext, self.parameter_set.as_bytes())?;
+        plaintext.extend_from_slice(&self.created_at.to_be_bytes());
+        let mut x = self.secret.x().to_bytes_be();
+        let put_x = put_u16_bytes(&mut plaintext, &x);
+        x.zeroize();
+        put_x?;
+        if self.metadata.len() > u16::MAX as usize {
+            plaintext.zeroize();
+            return Err(Error::InvalidEncoding(
+                "too many metadata entries".to_string(),
+            ));
+        }
+        plaintext.extend_from_slice(&(self.metadata.len() as u16).to_be_bytes());
+        for (key, value) in &self.metadata {
+            put_u16_bytes(&mut plaintext, key.as_bytes())?;
+            if value.len() > u32::MAX as usize {
+                plaintext.zeroize();
+                return Err(Error::InvalidEncoding(
+                    "metadata value too long".to_string(),
+                ));
+            }
+            plaintext.extend_from_slice(&(value.len() as u32).to_be_bytes());
+            plaintext.extend_from_slice(value.as_bytes());
+        }
+
+        let sealed = seal(VERSION, passphrase, params, &plaintext);
+        plaintext.zeroize();
+        Ok(pem_encode(PEM_LABEL, &sealed?))
+    }
+
+    pub fn import(pem: &str, passphrase: &str) -> Result<Backup, Error> {
+        let bytes = pem_decode(pem, PEM_LABEL)?;
+        let mut plaintext = open(&bytes, VERSION, "backup", passphrase)?;
+
+        let backup = parse_plaintext(&plaintext);
+        plaintext.zeroize();
+        backup
+    }
+}
+
+fn put_u16_bytes(out: &mut Vec<u8>, bytes: &[u8]) -> Result<(), Error> {
+    if bytes.len() > u16::MAX as usize {
+        out.zeroize();
+        return Err(Error::InvalidEncoding("backup field too long".to_string()));
+    }
+    out.extend_from_slice(&(bytes.len() as u16).to_be_bytes());
+    out.extend_from_slice(bytes);
+    Ok(())
+}
+
+fn parse_plaintext(plaintext: &[u8]) -> Result<Backup, Error> {
+    let mut reader = Reader(plaintext);
+    let user_name = reader.string(2)?;
+    let parameter_set = reader.string(2)?;
+    let created_at = u64::from_be_bytes(reader.take(8)?.try_into().unwrap());
+    let n = reader.length(2)?;
+    let secret = Secret::new(BigUint::from_bytes_be(reader.take(n)?));
+
+    let mut metadata = BTreeMap::new();
+    for _ in 0..reader.length(2)? {
+        let key = reader.string(2)?;
+        metadata.insert(key, reader.string(4)?);
+    }
+    if !reader.0.is_empty() {
+        return Err(malformed());
+    }
+
+    Ok(Backup {
+        user_name,
+        parameter_set,
+        secret,
+        created_at,
+        metadata,
+    })
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        if self.0.len() < n {
+            return Err(malformed());
+        }
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    // a big-endian length of `width` bytes
+    fn length(&mut self, width: usize) -> Result<usize, Error> {
+        Ok(self
+            .take(width)?
+            .iter()
+            .fold(0, |n, byte| (n << 8) | *byte as usize))
+    }
+
+    fn string(&mut self, width: usize) -> Result<String, Error> {
+        let n = self.length(width)?;
+        std::str::from_utf8(self.take(n)?)
+            .map(str::to_string)
+            .map_err(|_| malformed())
+    }
+}
+
+fn malformed() -> Error {
+    Error::InvalidEncoding("malformed backup content".to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::keystore::HEADER_LEN;
+
+    // the defaults take seconds in debug builds
+    const WEAK: KdfParams = KdfParams {
+        m_cost: 64,
+        t_cost: 1,
+        p_cost: 1,
+    };
+
+    fn backup() -> Backup {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let keystore = Keystore::new(set.id(), Secret::random(&set.zkp()));
+        let mut backup = Backup::new("alice", &keystore);
+        backup
+            .metadata
+            .insert("device".to_string(), "old phone".to_string());
+        backup
+            .metadata
+            .insert("note".to_string(), "ünïcode ✓".to_string());
+        backup
+    }
+
+    #[test]
+    fn test_export_import_roundtrip() {
+        let backup = backup();
+        let pem = backup.export("correct horse", WEAK).unwrap();
+        assert!(pem.starts_with("-----BEGIN ZKP CREDENTIAL BACKUP-----"));
+
+        let imported = Backup::import(&pem, "correct horse").unwrap();
+        assert_eq!(imported.user_name, "alice");
+        assert_eq!(imported.parameter_set, backup.parameter_set);
+        assert_eq!(imported.secret.x(), backup.secret.x());
+        assert_eq!(imported.created_at, backup.created_at);
+        assert_eq!(imported.metadata, backup.metadata);
+        assert_eq!(imported.public_key(), backup.public_key());
+        assert_eq!(imported.keystore().secret.x(), backup.secret.x());
+
+        assert_eq!(
+            Backup::import(&pem, "battery staple").unwrap_err(),
+            Error::WrongPassphrase
+        );
+        // a keystore is not a backup
+        let keystore = backup.keystore().encrypt("correct horse", WEAK).unwrap();
+        assert!(Backup::import(&keystore, "correct horse").is_err());
+    }
+
+    #[test]
+    fn test_layout_is_checked() {
+        let pem = backup().export("passphrase", WEAK).unwrap();
+        let bytes = pem_decode(&pem, PEM_LABEL).unwrap();
+
+        let mut newer = bytes.clone();
+        newer[0] = 2;
+        assert_eq!(
+            Backup::import(&pem_encode(PEM_LABEL, &newer), "passphrase").unwrap_err(),
+            Error::InvalidEncoding("unsupported backup version 2".to_string())
+        );
+        // the header is authenticated
+        let mut tampered = bytes.clone();
+        tampered[HEADER_LEN - 1] ^= 1;
+        assert_eq!(
+            Backup::import(&pem_encode(PEM_LABEL, &tampered), "passphrase").unwrap_err(),
+            Error::WrongPassphrase
+        );
+        assert!(Backup::import(&pem_encode(PEM_LABEL, &bytes[..20]), "passphrase").is_err());
+
+        // trailing plaintext
+        let mut plaintext = Vec::new();
+        put_u16_bytes(&mut plaintext, b"alice").unwrap();
+        put_u16_bytes(&mut plaintext, b"rfc5114-1024-160").unwrap();
+        plaintext.extend_from_slice(&0u64.to_be_bytes());
+        put_u16_bytes(&mut plaintext, &[7]).unwrap();
+        plaintext.extend_from_slice(&0u16.to_be_bytes());
+        assert_eq!(
+            parse_plaintext(&plaintext).unwrap().secret.x(),
+            &BigUint::from(7u32)
+        );
+        plaintext.push(0);
+        assert_eq!(parse_plaintext(&plaintext).unwrap_err(), malformed());
+    }
+}
@@ -0,0 +1,124 @@
+use num_bigint::{BigUint, RandBigInt};
+
+use crate::modmath::mod_reduce;
+use crate::proof::{Proof, PublicKey, Secret};
+use crate::ZKP;
+
+const WINDOW_BITS: u64 = 4;
+
+// Precomputed powers of a fixed base: table[i][j] = base^(j * 2^(w * i))
+// mod p. An exponentiation then costs one multiplication per w-bit window
+// of the exponent and no squarings, which pays off as soon as the same base
+// is raised to many exponents.
+#[derive(Debug, Clone)]
+pub struct FixedBaseTable {
+    p: BigUint,
+    table: Vec<Vec<BigUint>>,
+}
+
+impl FixedBaseTable {
+    // exponents must be below 2^max_exponent_bits
+    pub fn new(base: &BigUint, p: &BigUint, max_exponent_bits: u64) -> Self {
+        let windows = max_exponent_bits.div_ceil(WINDOW_BITS).max(1);
+        let mut table = Vec::with_capacity(windows as usize);
+
+        let mut window_base = mod_reduce(base, p);
+        for _ in 0..windows {
+            let mut row = Vec::with_capacity(1 << WINDOW_BITS);
+            let mut acc = BigUint::from(1u32);
+            for _ in 0..(1 << WINDOW_BITS) {
+                row.push(acc.clone());
+                acc = (&acc * &window_base) % p;
+            }
+            // acc is now window_base^(2^w)
+            window_base = acc;
+            table.push(row);
+        }
+
+        FixedBaseTable {
+            p: p.clone(),
+            table,
+        }
+    }
+
+    pub fn pow(&self, exponent: &BigUint) -> BigUint {
+        assert!(
+            exponent.bits() <= self.table.len() as u64 * WINDOW_BITS,
+            "exponent too large for the precomputed table"
+        );
+
+        let mut result = BigUint::from(1u32);
+        let digits = exponent.to_radix_le(1 << WINDOW_BITS);
+        for (row, digit) in self.table.iter().zip(digits) {
+            if digit != 0 {
+                result = (&result * &row[digit as usize]) % &self.p;
+            }
+        }
+        result
+    }
+}
+
+impl ZKP {
+    // Same proofs as calling `prove` for every pair, but alpha and beta are
+    // precomputed once and a single RNG handle is used for all nonces.
+    pub fn prove_batch(&self, statements: &[(Secret, PublicKey)]) -> Vec<Proof> {
+        let alpha_table = FixedBaseTable::new(&self.alpha, &self.p, self.q.bits());
+        let beta_table = FixedBaseTable::new(&self.beta, &self.p, self.q.bits());
+        let mut rng = rand::thread_rng();
+        let one = BigUint::from(1u32);
+
+        statements
+            .iter()
+            .map(|(secret, public_key)| {
+                let k = rng.gen_biguint_range(&one, &self.q);
+                let r1 = alpha_table.pow(&k);
+                let r2 = beta_table.pow(&k);
+
+                let c = self.challenge(public_key, &r1, &r2);
+                let s = self.solve(&k, &c, secret.x());
+
+                Proof { r1, r2, s }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+
+    #[test]
+    fn test_fixed_base_table_matches_modpow() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let table = FixedBaseTable::new(&zkp.alpha, &zkp.p, zkp.q.bits());
+
+        for exponent in [
+            BigUint::from(0u32),
+            BigUint::from(1u32),
+            BigUint::from(16u32),
+            &zkp.q - 1u32,
+            ZKP::generate_random_below(&zkp.q),
+        ] {
+            assert_eq!(table.pow(&exponent), zkp.alpha.modpow(&exponent, &zkp.p));
+        }
+    }
+
+    #[test]
+    fn test_prove_batch() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let statements: Vec<(Secret, PublicKey)> = (0..10)
+            .map(|_| {
+                let secret = Secret::random(&zkp);
+                let public_key = zkp.public_key(&secret);
+                (secret, public_key)
+            })
+            .collect();
+
+        let proofs = zkp.prove_batch(&statements);
+        assert_eq!(proofs.len(), statements.len());
+        for ((_, public_key), proof) in statements.iter().zip(proofs) {
+            assert!(zkp.verify_proof_without_expiry(public_key, proof));
+        }
+    }
+}
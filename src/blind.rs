@@ -0,0 +1,209 @@
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+use crate::modmath::mod_reduce;
+use crate::proof::{CompactProof, PublicKey, Secret};
+use crate::store::UserStore;
+use crate::verifier::Verifier;
+use crate::{Error, ZKP};
+
+// Blinded registration: instead of (y1, y2) the prover registers the key of
+// x + r for a blinding factor r derived from x and the service, so every
+// service sees a different, uniformly random looking key. Two services
+// comparing their user tables learn nothing; the prover keeps only x and
+// re-derives the blinded secret for each service.
+//
+// The registration comes with a proof of correct blinding: a proof of
+// knowledge of x + r for the blinded pair, bound to the user name and the
+// parameter set. It shows the server that log_alpha(y1') = log_beta(y2') (the
+// key can be logged in with at all) and that whoever registers it holds its
+// secret. It says nothing about the unblinded key, that is the point.
+const BLINDING_LABEL: &[u8] = b"zkp-chaum-pedersen/blinding/v1";
+const REGISTRATION_LABEL: &[u8] = b"zkp-chaum-pedersen/blinded-registration/v1";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlindedRegistration {
+    pub public_key: PublicKey,
+    pub proof: CompactProof,
+}
+
+impl Secret {
+    // r = H(x, service) expanded to 16 bytes past the length of q and
+    // reduced, so r is uniform mod q to within 2^-128 and unknown to whoever
+    // doesn't know x.
+    pub fn blinding_factor(&self, zkp: &ZKP, service: &str) -> Secret {
+        let x = zkp.scalar_to_bytes(&mod_reduce(self.x(), &zkp.q));
+        let mut wide = Vec::new();
+        let mut counter = 0u32;
+        while wide.len() < zkp.scalar_len() + 16 {
+            let mut hasher = Sha256::new();
+            hasher.update(BLINDING_LABEL);
+            hasher.update(counter.to_be_bytes());
+            hasher.update((x.len() as u32).to_be_bytes());
+            hasher.update(&x);
+            hasher.update(service.as_bytes());
+            wide.extend_from_slice(&hasher.finalize());
+            counter += 1;
+        }
+        Secret::new(mod_reduce(&BigUint::from_bytes_be(&wide), &zkp.q))
+    }
+
+    // x + r, the secret to log in to `service` with
+    pub fn blind(&self, zkp: &ZKP, service: &str) -> Secret {
+        self.rerandomize(zkp, &self.blinding_factor(zkp, service))
+    }
+}
+
+impl ZKP {
+    // `secret` already blinded, see `Secret::blind`
+    pub fn blinded_registration(
+        &self,
+        blinded: &Secret,
+        user_name: &str,
+        parameter_set: &str,
+    ) -> BlindedRegistration {
+        let public_key = self.public_key(blinded);
+        let context = registration_context(user_name, parameter_set);
+        BlindedRegistration {
+            proof: self.prove_compact_action(blinded, &public_key, &context),
+            public_key,
+        }
+    }
+
+    pub fn verify_blinded_registration(
+        &self,
+        user_name: &str,
+        parameter_set: &str,
+        registration: &BlindedRegistration,
+    ) -> bool {
+        let context = registration_context(user_name, parameter_set);
+        self.verify_compact_action(&registration.public_key, &registration.proof, &context)
+    }
+}
+
+impl BlindedRegistration {
+    // c || s, each padded to the byte length of q
+    pub fn proof_to_bytes(&self, zkp: &ZKP) -> Vec<u8> {
+        [
+            zkp.scalar_to_bytes(&self.proof.c),
+            zkp.scalar_to_bytes(&self.proof.s),
+        ]
+        .concat()
+    }
+
+    pub fn from_parts(zkp: &ZKP, public_key: PublicKey, proof: &[u8]) -> Result<Self, Error> {
+        if proof.len() != 2 * zkp.scalar_len() {
+            return Err(Error::InvalidEncoding(format!(
+                "blinding proof must be {} bytes, got {}",
+                2 * zkp.scalar_len(),
+                proof.len()
+            )));
+        }
+        let (c, s) = proof.split_at(zkp.scalar_len());
+        Ok(BlindedRegistration {
+            public_key,
+            proof: CompactProof {
+                c: zkp.scalar_from_bytes(c)?,
+                s: zkp.scalar_from_bytes(s)?,
+            },
+        })
+    }
+}
+
+impl<S: UserStore> Verifier<S> {
+    // Registers the blinded key once its proof checks out; logins are the
+    // usual ones, for the blinded secret.
+    pub fn register_blinded(
+        &mut self,
+        user_name: &str,
+        parameter_set: &str,
+        registration: BlindedRegistration,
+    ) -> Result<(), Error> {
+        let zkp = self
+            .parameter_set(parameter_set)
+            .ok_or_else(|| Error::UnknownParameterSet(parameter_set.to_string()))?;
+        if !zkp.is_subgroup_element(&registration.public_key.y1)
+            || !zkp.is_subgroup_element(&registration.public_key.y2)
+        {
+            return Err(Error::InvalidPublicKey);
+        }
+        if !zkp.verify_blinded_registration(user_name, parameter_set, &registration) {
+            trace_warn!("blinded registration with a bad proof");
+            return Err(Error::InvalidProof);
+        }
+        let PublicKey { y1, y2 } = registration.public_key;
+        self.register_with_parameter_set(user_name, parameter_set, y1, y2)
+    }
+}
+
+fn registration_context(user_name: &str, parameter_set: &str) -> Vec<u8> {
+    let mut context = REGISTRATION_LABEL.to_vec();
+    for part in [user_name, parameter_set] {
+        context.extend_from_slice(&(part.len() as u32).to_be_bytes());
+        context.extend_from_slice(part.as_bytes());
+    }
+    context
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+
+    #[test]
+    fn test_blinded_keys_are_unlinkable_and_log_in() {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let zkp = set.zkp();
+        let secret = Secret::random(&zkp);
+
+        let mail = secret.blind(&zkp, "mail.example.com");
+        let bank = secret.blind(&zkp, "bank.example.com");
+        assert_eq!(
+            mail.x(),
+            secret.blind(&zkp, "mail.example.com").x(),
+            "blinding is deterministic"
+        );
+        assert_ne!(zkp.public_key(&mail), zkp.public_key(&bank));
+        assert_ne!(zkp.public_key(&mail), zkp.public_key(&secret));
+
+        let mut verifier = Verifier::with_parameter_sets(set, &[]);
+        let registration = zkp.blinded_registration(&mail, "alice", set.id());
+        verifier
+            .register_blinded("alice", set.id(), registration.clone())
+            .unwrap();
+
+        let public_key = zkp.public_key(&mail);
+        let wire = registration.proof_to_bytes(&zkp);
+        assert_eq!(
+            BlindedRegistration::from_parts(&zkp, public_key.clone(), &wire).unwrap(),
+            registration
+        );
+        let user = verifier.user("alice").unwrap();
+        assert_eq!((&user.y1, &user.y2), (&public_key.y1, &public_key.y2));
+        assert!(zkp.verify_proof_without_expiry(&public_key, zkp.prove(&mail, &public_key)));
+    }
+
+    #[test]
+    fn test_registration_proof_is_checked() {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let zkp = set.zkp();
+        let blinded = Secret::random(&zkp).blind(&zkp, "mail.example.com");
+        let mut verifier = Verifier::with_parameter_sets(set, &[]);
+
+        // made for another user name
+        let registration = zkp.blinded_registration(&blinded, "alice", set.id());
+        assert_eq!(
+            verifier.register_blinded("mallory", set.id(), registration.clone()),
+            Err(Error::InvalidProof)
+        );
+
+        // a pair with two different logs can't be proven
+        let mut mismatched = registration;
+        mismatched.public_key.y2 = zkp.public_key(&Secret::random(&zkp)).y2;
+        assert_eq!(
+            verifier.register_blinded("alice", set.id(), mismatched),
+            Err(Error::InvalidProof)
+        );
+        assert!(verifier.user("alice").is_none());
+    }
+}
@@ -0,0 +1,355 @@
+use bls12_381::{G1Affine, G1Projective, Scalar};
+use ff::Field;
+use group::Curve;
+use sha2::{Digest, Sha512};
+
+use crate::Error;
+
+// Chaum-Pedersen over G1 of BLS12-381, in the curve's own types: secrets,
+// challenges and answers are `bls12_381::Scalar`, everything else G1 points,
+// so the proofs compose with pairing-based schemes over the same curve
+// without going through BigUint. Written additively, the group of order q
+// (the scalar field modulus) generated by g, with h a second generator:
+//     y1 = x * g, y2 = x * h
+//     r1 = k * g, r2 = k * h, s = k - c * x mod q
+// and the verifier checks r1 = s * g + c * y1, r2 = s * h + c * y2.
+
+const GENERATOR_LABEL: &[u8] = b"zkp-chaum-pedersen/bls12-381-g1/generator/v1";
+const CHALLENGE_LABEL: &[u8] = b"zkp-chaum-pedersen/bls12-381-g1/fiat-shamir/v1";
+
+// compressed G1 point
+pub const POINT_LEN: usize = 48;
+pub const SCALAR_LEN: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct G1Zkp {
+    pub g: G1Projective,
+    pub h: G1Projective,
+}
+
+// The prover's secret x.
+#[derive(Clone)]
+pub struct G1Secret(Scalar);
+
+impl G1Secret {
+    pub fn new(x: Scalar) -> Self {
+        G1Secret(x)
+    }
+
+    pub fn random() -> Self {
+        G1Secret(G1Zkp::random_nonzero_scalar())
+    }
+
+    pub fn x(&self) -> &Scalar {
+        &self.0
+    }
+}
+
+// Never print the secret by accident.
+impl std::fmt::Debug for G1Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("G1Secret(..)")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct G1PublicKey {
+    pub y1: G1Projective,
+    pub y2: G1Projective,
+}
+
+impl G1PublicKey {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [point_to_bytes(&self.y1), point_to_bytes(&self.y2)].concat()
+    }
+
+    // Both halves must be non-identity points of G1, the identity is the key
+    // of x = 0.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != 2 * POINT_LEN {
+            return Err(Error::InvalidEncoding(format!(
+                "expected {} bytes, got {}",
+                2 * POINT_LEN,
+                bytes.len()
+            )));
+        }
+        let key = G1PublicKey {
+            y1: point_from_bytes(&bytes[..POINT_LEN])?,
+            y2: point_from_bytes(&bytes[POINT_LEN..])?,
+        };
+        if bool::from(key.y1.is_identity()) || bool::from(key.y2.is_identity()) {
+            return Err(Error::InvalidPublicKey);
+        }
+        Ok(key)
+    }
+}
+
+// Non-interactive proof carrying the commitments: (r1, r2, s).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct G1Proof {
+    pub r1: G1Projective,
+    pub r2: G1Projective,
+    pub s: Scalar,
+}
+
+impl G1Proof {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [
+            point_to_bytes(&self.r1),
+            point_to_bytes(&self.r2),
+            self.s.to_bytes().to_vec(),
+        ]
+        .concat()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != 2 * POINT_LEN + SCALAR_LEN {
+            return Err(Error::InvalidEncoding(format!(
+                "expected {} bytes, got {}",
+                2 * POINT_LEN + SCALAR_LEN,
+                bytes.len()
+            )));
+        }
+        let (r1, rest) = bytes.split_at(POINT_LEN);
+        let (r2, s) = rest.split_at(POINT_LEN);
+        Ok(G1Proof {
+            r1: point_from_bytes(r1)?,
+            r2: point_from_bytes(r2)?,
+            s: scalar_from_bytes(s)?,
+        })
+    }
+}
+
+impl G1Zkp {
+    // The standard generator for g and h derived from it by hashing, see
+    // `derive_generator`, so nobody knows log_g(h).
+    pub fn new() -> Self {
+        G1Zkp {
+            g: G1Projective::generator(),
+            h: G1Zkp::derive_generator(GENERATOR_LABEL),
+        }
+    }
+
+    // For generators agreed on elsewhere, e.g. the ones of a credential
+    // scheme. They must differ and neither may be the identity.
+    pub fn with_generators(g: G1Projective, h: G1Projective) -> Result<Self, Error> {
+        if bool::from(g.is_identity()) || bool::from(h.is_identity()) || g == h {
+            return Err(Error::InvalidParameters(
+                "generators must be distinct non-identity points".to_string(),
+            ));
+        }
+        Ok(G1Zkp { g, h })
+    }
+
+    // Try-and-increment: SHA-512(label, counter) as the x coordinate of a
+    // curve point, then the cofactor cleared to land in G1. The discrete log
+    // of the result to any base is unknown, unlike for a multiple of g.
+    pub fn derive_generator(label: &[u8]) -> G1Projective {
+        for counter in 0u32.. {
+            let digest = Sha512::new()
+                .chain_update(label)
+                .chain_update(counter.to_be_bytes())
+                .finalize();
+            let mut bytes = [0u8; POINT_LEN];
+            bytes.copy_from_slice(&digest[..POINT_LEN]);
+            // compression flag set, infinity flag clear, sign from the hash
+            bytes[0] = (bytes[0] & 0b0011_1111) | 0b1000_0000;
+            let point = G1Affine::from_compressed_unchecked(&bytes);
+            if bool::from(point.is_some()) {
+                let point = G1Projective::from(point.unwrap()).clear_cofactor();
+                if !bool::from(point.is_identity()) {
+                    return point;
+                }
+            }
+        }
+        unreachable!("ran out of counters deriving a generator")
+    }
+
+    // Uniform in [1, q), for secrets and nonces.
+    pub fn random_nonzero_scalar() -> Scalar {
+        let mut rng = rand::thread_rng();
+        loop {
+            let scalar = Scalar::random(&mut rng);
+            if !bool::from(scalar.is_zero()) {
+                return scalar;
+            }
+        }
+    }
+
+    pub fn public_key(&self, secret: &G1Secret) -> G1PublicKey {
+        G1PublicKey {
+            y1: self.g * secret.x(),
+            y2: self.h * secret.x(),
+        }
+    }
+
+    // output = (k, r1, r2), k stays with the prover
+    pub fn commit(&self) -> (Scalar, G1Projective, G1Projective) {
+        let k = G1Zkp::random_nonzero_scalar();
+        (k, self.g * k, self.h * k)
+    }
+
+    // output = s = k - c * x mod q
+    pub fn solve(&self, k: &Scalar, c: &Scalar, x: &Scalar) -> Scalar {
+        k - c * x
+    }
+
+    // cond1: r1 = s * g + c * y1
+    // cond2: r2 = s * h + c * y2
+    pub fn verify(
+        &self,
+        r1: &G1Projective,
+        r2: &G1Projective,
+        y1: &G1Projective,
+        y2: &G1Projective,
+        s: &Scalar,
+        c: &Scalar,
+    ) -> bool {
+        let cond1 = *r1 == self.g * s + y1 * c;
+        let cond2 = *r2 == self.h * s + y2 * c;
+        cond1 && cond2
+    }
+
+    // c = H(g, h, y1, y2, r1, r2) reduced from 512 bits, the points
+    // compressed so every input has the same length.
+    pub fn challenge(
+        &self,
+        public_key: &G1PublicKey,
+        r1: &G1Projective,
+        r2: &G1Projective,
+    ) -> Scalar {
+        let mut hasher = Sha512::new();
+        hasher.update(CHALLENGE_LABEL);
+        for point in [&self.g, &self.h, &public_key.y1, &public_key.y2, r1, r2] {
+            hasher.update(point_to_bytes(point));
+        }
+        let mut wide = [0u8; 64];
+        wide.copy_from_slice(&hasher.finalize());
+        Scalar::from_bytes_wide(&wide)
+    }
+
+    pub fn prove(&self, secret: &G1Secret) -> G1Proof {
+        let public_key = self.public_key(secret);
+        let (k, r1, r2) = self.commit();
+        let c = self.challenge(&public_key, &r1, &r2);
+        G1Proof {
+            r1,
+            r2,
+            s: self.solve(&k, &c, secret.x()),
+        }
+    }
+
+    pub fn verify_proof(&self, public_key: &G1PublicKey, proof: &G1Proof) -> bool {
+        if bool::from(public_key.y1.is_identity()) || bool::from(public_key.y2.is_identity()) {
+            return false;
+        }
+        let c = self.challenge(public_key, &proof.r1, &proof.r2);
+        self.verify(
+            &proof.r1,
+            &proof.r2,
+            &public_key.y1,
+            &public_key.y2,
+            &proof.s,
+            &c,
+        )
+    }
+}
+
+impl Default for G1Zkp {
+    fn default() -> Self {
+        G1Zkp::new()
+    }
+}
+
+pub fn point_to_bytes(point: &G1Projective) -> Vec<u8> {
+    point.to_affine().to_compressed().to_vec()
+}
+
+// Compressed, on the curve and in G1; the identity is allowed here.
+pub fn point_from_bytes(bytes: &[u8]) -> Result<G1Projective, Error> {
+    let bytes: &[u8; POINT_LEN] = bytes
+        .try_into()
+        .map_err(|_| Error::InvalidEncoding(format!("expected a {} byte point", POINT_LEN)))?;
+    Option::<G1Affine>::from(G1Affine::from_compressed(bytes))
+        .map(G1Projective::from)
+        .ok_or_else(|| Error::InvalidEncoding("not a point of G1".to_string()))
+}
+
+// Little-endian and canonical, below q.
+pub fn scalar_from_bytes(bytes: &[u8]) -> Result<Scalar, Error> {
+    let bytes: &[u8; SCALAR_LEN] = bytes
+        .try_into()
+        .map_err(|_| Error::InvalidEncoding(format!("expected a {} byte scalar", SCALAR_LEN)))?;
+    Option::<Scalar>::from(Scalar::from_bytes(bytes))
+        .ok_or_else(|| Error::InvalidEncoding("scalar is not below q".to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_interactive_round() {
+        let zkp = G1Zkp::new();
+        let secret = G1Secret::random();
+        let public_key = zkp.public_key(&secret);
+
+        let (k, r1, r2) = zkp.commit();
+        let c = Scalar::random(rand::thread_rng());
+        let s = zkp.solve(&k, &c, secret.x());
+        assert!(zkp.verify(&r1, &r2, &public_key.y1, &public_key.y2, &s, &c));
+
+        let other = zkp.public_key(&G1Secret::random());
+        assert!(!zkp.verify(&r1, &r2, &other.y1, &other.y2, &s, &c));
+        // same x to both bases is the statement, y2 for another x fails
+        assert!(!zkp.verify(&r1, &r2, &public_key.y1, &other.y2, &s, &c));
+    }
+
+    #[test]
+    fn test_proofs_roundtrip_and_verify() {
+        let zkp = G1Zkp::new();
+        let secret = G1Secret::random();
+        let public_key = zkp.public_key(&secret);
+        let proof = zkp.prove(&secret);
+        assert!(zkp.verify_proof(&public_key, &proof));
+
+        let decoded = G1Proof::from_bytes(&proof.to_bytes()).unwrap();
+        assert_eq!(decoded, proof);
+        let key = G1PublicKey::from_bytes(&public_key.to_bytes()).unwrap();
+        assert!(zkp.verify_proof(&key, &decoded));
+
+        let tampered = G1Proof {
+            s: proof.s + Scalar::ONE,
+            ..proof
+        };
+        assert!(!zkp.verify_proof(&public_key, &tampered));
+
+        // another h is another group for the challenge
+        let other = G1Zkp::with_generators(zkp.g, G1Zkp::derive_generator(b"other")).unwrap();
+        assert!(!other.verify_proof(&public_key, &proof));
+    }
+
+    #[test]
+    fn test_decoding_rejects_bad_input() {
+        let zkp = G1Zkp::new();
+        assert!(bool::from(zkp.h.to_affine().is_torsion_free()));
+        assert_ne!(zkp.g, zkp.h);
+        assert!(G1Zkp::with_generators(zkp.g, zkp.g).is_err());
+        assert!(G1Zkp::with_generators(zkp.g, G1Projective::identity()).is_err());
+
+        let identity = point_to_bytes(&G1Projective::identity());
+        let key = [identity.clone(), identity].concat();
+        assert!(matches!(
+            G1PublicKey::from_bytes(&key),
+            Err(Error::InvalidPublicKey)
+        ));
+
+        let mut proof = zkp.prove(&G1Secret::random()).to_bytes();
+        assert!(G1Proof::from_bytes(&proof[1..]).is_err());
+        // s = 2^256 - 1 isn't below q
+        proof[2 * POINT_LEN..].fill(0xff);
+        assert!(G1Proof::from_bytes(&proof).is_err());
+        proof[0] ^= 0b1000_0000;
+        assert!(G1Proof::from_bytes(&proof).is_err());
+    }
+}
@@ -0,0 +1,337 @@
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::context::ContextDigest;
+use crate::der::{pem_decode, pem_encode};
+use crate::params::ParameterSet;
+use crate::proof::{CompactProof, PublicKey, Secret};
+use crate::store::UserStore;
+use crate::verifier::{Purpose, Verifier};
+use crate::Error;
+
+// Proof made on an air-gapped machine and carried over as a file. The proof
+// is a compact action proof whose context is the signed part of the bundle,
+// so it signs the parameter set, the creation time and the context under the
+// public key: none of them can be changed without x.
+//
+// Layout inside the PEM block:
+//     signed   version u8 (1), id length u8, parameter set id,
+//              created_at u64 (unix seconds), context length u32, context
+//     y1, y2   byte length of p each
+//     c, s     byte length of q each
+//
+// A bundle verifies for as long as the policy lets it, the context is what
+// tells the verifier it wasn't meant for someone else (a nonce it handed
+// out, a document hash).
+pub const PEM_LABEL: &str = "ZKP PROOF BUNDLE";
+
+const VERSION: u8 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofBundle {
+    pub parameter_set: ParameterSet,
+    pub public_key: PublicKey,
+    pub context: Vec<u8>,
+    // unix seconds, from the prover's clock
+    pub created_at: u64,
+    pub proof: CompactProof,
+}
+
+// How old a bundle may be, and how far ahead of the verifier the prover's
+// clock may run (air-gapped clocks drift).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BundlePolicy {
+    pub max_age: Duration,
+    pub max_clock_skew: Duration,
+}
+
+impl Default for BundlePolicy {
+    fn default() -> Self {
+        BundlePolicy {
+            max_age: Duration::from_secs(24 * 60 * 60),
+            max_clock_skew: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+impl ProofBundle {
+    pub fn prove(parameter_set: ParameterSet, secret: &Secret, context: &[u8]) -> Self {
+        ProofBundle::prove_at(parameter_set, secret, context, unix_now())
+    }
+
+    pub fn prove_at(
+        parameter_set: ParameterSet,
+        secret: &Secret,
+        context: &[u8],
+        created_at: u64,
+    ) -> Self {
+        let zkp = parameter_set.zkp();
+        let public_key = zkp.public_key(secret);
+        let signed = signed_bytes(parameter_set, created_at, context);
+        ProofBundle {
+            parameter_set,
+            proof: zkp.prove_compact_action(secret, &public_key, &signed),
+            public_key,
+            context: context.to_vec(),
+            created_at,
+        }
+    }
+
+    pub fn to_pem(&self) -> String {
+        let zkp = self.parameter_set.zkp();
+        let mut bytes = signed_bytes(self.parameter_set, self.created_at, &self.context);
+        for e in [&self.public_key.y1, &self.public_key.y2] {
+            bytes.extend_from_slice(&zkp.element_to_bytes(e));
+        }
+        for n in [&self.proof.c, &self.proof.s] {
+            bytes.extend_from_slice(&zkp.scalar_to_bytes(n));
+        }
+        pem_encode(PEM_LABEL, &bytes)
+    }
+
+    pub fn from_pem(pem: &str) -> Result<Self, Error> {
+        let bytes = pem_decode(pem, PEM_LABEL)?;
+        let truncated = || Error::InvalidEncoding("truncated proof bundle".to_string());
+        let take = |rest: &[u8], n: usize| -> Result<(Vec<u8>, Vec<u8>), Error> {
+            if rest.len() < n {
+                return Err(truncated());
+            }
+            let (head, tail) = rest.split_at(n);
+            Ok((head.to_vec(), tail.to_vec()))
+        };
+
+        let (version, rest) = take(&bytes, 1)?;
+        if version[0] != VERSION {
+            return Err(Error::InvalidEncoding(format!(
+                "unsupported proof bundle version {}",
+                version[0]
+            )));
+        }
+        let (id_len, rest) = take(&rest, 1)?;
+        let (id, rest) = take(&rest, id_len[0] as usize)?;
+        let parameter_set: ParameterSet = std::str::from_utf8(&id)
+            .map_err(|_| Error::InvalidEncoding("parameter set id is not utf-8".to_string()))?
+            .parse()?;
+        let (created_at, rest) = take(&rest, 8)?;
+        let (context_len, rest) = take(&rest, 4)?;
+        let context_len = u32::from_be_bytes(context_len.try_into().unwrap()) as usize;
+        let (context, rest) = take(&rest, context_len)?;
+
+        let zkp = parameter_set.zkp();
+        let (y1, rest) = take(&rest, zkp.element_len())?;
+        let (y2, rest) = take(&rest, zkp.element_len())?;
+        let (c, rest) = take(&rest, zkp.scalar_len())?;
+        let (s, rest) = take(&rest, zkp.scalar_len())?;
+        if !rest.is_empty() {
+            return Err(Error::InvalidEncoding(
+                "trailing bytes after proof bundle".to_string(),
+            ));
+        }
+
+        Ok(ProofBundle {
+            parameter_set,
+            public_key: PublicKey {
+                y1: zkp.element_from_bytes(&y1)?,
+                y2: zkp.element_from_bytes(&y2)?,
+            },
+            context,
+            created_at: u64::from_be_bytes(created_at.try_into().unwrap()),
+            proof: CompactProof {
+                c: zkp.scalar_from_bytes(&c)?,
+                s: zkp.scalar_from_bytes(&s)?,
+            },
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        fs::write(path, self.to_pem()).map_err(|e| Error::Io(e.to_string()))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let pem = fs::read_to_string(path).map_err(|e| Error::Io(e.to_string()))?;
+        ProofBundle::from_pem(&pem)
+    }
+
+    // The proof, the key and the age against the verifier's clock. Whether
+    // the key and the context are the expected ones is up to the caller, see
+    // `Verifier::verify_bundle` for registered users.
+    pub fn verify(&self, policy: &BundlePolicy) -> Result<(), Error> {
+        self.verify_at(policy, unix_now())
+    }
+
+    pub fn verify_at(&self, policy: &BundlePolicy, now: u64) -> Result<(), Error> {
+        if self.created_at > now.saturating_add(policy.max_clock_skew.as_secs())
+            || now.saturating_sub(self.created_at) > policy.max_age.as_secs()
+        {
+            return Err(Error::Expired);
+        }
+
+        let zkp = self.parameter_set.zkp();
+        if !zkp.is_subgroup_element(&self.public_key.y1)
+            || !zkp.is_subgroup_element(&self.public_key.y2)
+        {
+            return Err(Error::InvalidPublicKey);
+        }
+        let signed = signed_bytes(self.parameter_set, self.created_at, &self.context);
+        if !zkp.verify_compact_action(&self.public_key, &self.proof, &signed) {
+            return Err(Error::InvalidProof);
+        }
+
+        Ok(())
+    }
+}
+
+impl<S: UserStore> Verifier<S> {
+    // A bundle of a registered user, made with the key and parameter set on
+    // record.
+    pub fn verify_bundle(
+        &self,
+        user_name: &str,
+        bundle: &ProofBundle,
+        policy: &BundlePolicy,
+    ) -> Result<(), Error> {
+        let user_info = self
+            .user(user_name)
+            .ok_or_else(|| Error::UserNotFound(user_name.to_string()))?;
+        self.check_status(
+            user_name,
+            &Purpose::Action(ContextDigest::of(&bundle.context)),
+        )?;
+        if user_info.parameter_set != bundle.parameter_set.id()
+            || user_info.y1 != bundle.public_key.y1
+            || user_info.y2 != bundle.public_key.y2
+        {
+            trace_warn!("bundle made with another key");
+            return Err(Error::InvalidProof);
+        }
+        bundle.verify(policy)
+    }
+}
+
+fn signed_bytes(parameter_set: ParameterSet, created_at: u64, context: &[u8]) -> Vec<u8> {
+    let id = parameter_set.id().as_bytes();
+    let mut bytes = vec![VERSION, id.len() as u8];
+    bytes.extend_from_slice(id);
+    bytes.extend_from_slice(&created_at.to_be_bytes());
+    bytes.extend_from_slice(&(context.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(context);
+    bytes
+}
+
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bundle_roundtrip_and_age() {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let secret = Secret::random(&set.zkp());
+        let bundle = ProofBundle::prove_at(set, &secret, b"invoice 0042", 1_000_000);
+
+        let pem = bundle.to_pem();
+        assert!(pem.starts_with("-----BEGIN ZKP PROOF BUNDLE-----"));
+        let read = ProofBundle::from_pem(&pem).unwrap();
+        assert_eq!(read, bundle);
+
+        let policy = BundlePolicy::default();
+        assert_eq!(read.verify_at(&policy, 1_000_000 + 60), Ok(()));
+        assert_eq!(
+            read.verify_at(&policy, 1_000_000 + 2 * 24 * 60 * 60),
+            Err(Error::Expired)
+        );
+        // a prover clock a minute ahead is fine, a day ahead isn't
+        assert_eq!(read.verify_at(&policy, 1_000_000 - 60), Ok(()));
+        assert_eq!(
+            read.verify_at(&policy, 1_000_000 - 24 * 60 * 60),
+            Err(Error::Expired)
+        );
+
+        assert!(ProofBundle::from_pem(&pem.replace("BUNDLE", "BUNDLES")).is_err());
+        let mut trailing = pem_decode(&pem, PEM_LABEL).unwrap();
+        trailing.push(0);
+        assert!(ProofBundle::from_pem(&pem_encode(PEM_LABEL, &trailing)).is_err());
+    }
+
+    #[test]
+    fn test_signed_fields_cannot_change() {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let zkp = set.zkp();
+        let secret = Secret::random(&zkp);
+        let bundle = ProofBundle::prove(set, &secret, b"invoice 0042");
+        let policy = BundlePolicy::default();
+        assert_eq!(bundle.verify(&policy), Ok(()));
+
+        let mut backdated = bundle.clone();
+        backdated.created_at += 60;
+        assert_eq!(backdated.verify(&policy), Err(Error::InvalidProof));
+
+        let mut recontexted = bundle.clone();
+        recontexted.context = b"invoice 0043".to_vec();
+        assert_eq!(recontexted.verify(&policy), Err(Error::InvalidProof));
+
+        let mut rekeyed = bundle.clone();
+        rekeyed.public_key = zkp.public_key(&Secret::random(&zkp));
+        assert_eq!(rekeyed.verify(&policy), Err(Error::InvalidProof));
+    }
+
+    #[test]
+    fn test_verifier_checks_the_registered_key() {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let zkp = set.zkp();
+        let mut verifier = Verifier::with_parameter_sets(set, &[]);
+        let secret = Secret::random(&zkp);
+        let public_key = zkp.public_key(&secret);
+        verifier
+            .register("alice", public_key.y1, public_key.y2)
+            .unwrap();
+
+        let policy = BundlePolicy::default();
+        let bundle = ProofBundle::prove(set, &secret, b"nonce=17");
+        assert_eq!(verifier.verify_bundle("alice", &bundle, &policy), Ok(()));
+
+        let stranger = ProofBundle::prove(set, &Secret::random(&zkp), b"nonce=17");
+        assert_eq!(
+            verifier.verify_bundle("alice", &stranger, &policy),
+            Err(Error::InvalidProof)
+        );
+        assert_eq!(
+            verifier.verify_bundle("bob", &bundle, &policy),
+            Err(Error::UserNotFound("bob".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_verifier_checks_the_user_status() {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let zkp = set.zkp();
+        let mut verifier = Verifier::with_parameter_sets(set, &[]);
+        let secret = Secret::random(&zkp);
+        let public_key = zkp.public_key(&secret);
+        verifier
+            .register("alice", public_key.y1, public_key.y2)
+            .unwrap();
+        let policy = BundlePolicy::default();
+        let bundle = ProofBundle::prove(set, &secret, b"nonce=17");
+
+        verifier.disable_user("alice").unwrap();
+        assert_eq!(
+            verifier.verify_bundle("alice", &bundle, &policy),
+            Err(Error::UserDisabled("alice".to_string()))
+        );
+        verifier.enable_user("alice").unwrap();
+
+        verifier.force_key_rotation("alice").unwrap();
+        assert_eq!(
+            verifier.verify_bundle("alice", &bundle, &policy),
+            Err(Error::RotationRequired("alice".to_string()))
+        );
+    }
+}
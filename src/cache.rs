@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use sha2::{Digest, Sha256};
+
+use crate::store::UserInfo;
+
+// Users whose stored public key was lately found to be in the order q
+// subgroup. The check costs two full exponentiations, as much as the proof
+// check itself, so it's done once per key rather than once per login.
+// Entries are keyed by user and remember which key they were made for: a
+// rotated or re-registered key is checked again.
+#[derive(Debug)]
+pub struct KeyCache {
+    // user_name => (H(parameter_set, y1, y2), checked at)
+    entries: HashMap<String, ([u8; 32], Instant)>,
+    capacity: usize,
+    ttl: Duration,
+}
+
+impl KeyCache {
+    pub const DEFAULT_CAPACITY: usize = 10_000;
+
+    pub const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+
+    // A capacity of 0 turns the cache off.
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        KeyCache {
+            entries: HashMap::new(),
+            capacity,
+            ttl,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains(&self, user_info: &UserInfo) -> bool {
+        match self.entries.get(&user_info.user_name) {
+            Some((fingerprint, checked_at)) => {
+                *fingerprint == key_fingerprint(user_info) && checked_at.elapsed() < self.ttl
+            }
+            None => false,
+        }
+    }
+
+    // Expired entries go first when full, then the oldest one.
+    pub fn insert(&mut self, user_info: &UserInfo) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&user_info.user_name) {
+            let ttl = self.ttl;
+            self.entries
+                .retain(|_, (_, checked_at)| checked_at.elapsed() < ttl);
+        }
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&user_info.user_name) {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, checked_at))| *checked_at)
+                .map(|(user_name, _)| user_name.clone());
+            if let Some(user_name) = oldest {
+                self.entries.remove(&user_name);
+            }
+        }
+
+        self.entries.insert(
+            user_info.user_name.clone(),
+            (key_fingerprint(user_info), Instant::now()),
+        );
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl Default for KeyCache {
+    fn default() -> Self {
+        KeyCache::new(KeyCache::DEFAULT_CAPACITY, KeyCache::DEFAULT_TTL)
+    }
+}
+
+fn key_fingerprint(user_info: &UserInfo) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update((user_info.parameter_set.len() as u32).to_be_bytes());
+    hasher.update(user_info.parameter_set.as_bytes());
+    for y in [&user_info.y1, &user_info.y2] {
+        let bytes = y.to_bytes_be();
+        hasher.update((bytes.len() as u32).to_be_bytes());
+        hasher.update(&bytes);
+    }
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use num_bigint::BigUint;
+
+    fn user(name: &str, y: u32) -> UserInfo {
+        UserInfo {
+            user_name: name.to_string(),
+            parameter_set: "default".to_string(),
+            y1: BigUint::from(y),
+            y2: BigUint::from(y),
+        }
+    }
+
+    #[test]
+    fn test_entries_follow_the_key() {
+        let mut cache = KeyCache::default();
+        cache.insert(&user("alice", 4));
+        assert!(cache.contains(&user("alice", 4)));
+        assert!(!cache.contains(&user("alice", 5)));
+        assert!(!cache.contains(&user("bob", 4)));
+
+        let mut expired = KeyCache::new(10, Duration::ZERO);
+        expired.insert(&user("alice", 4));
+        assert!(!expired.contains(&user("alice", 4)));
+
+        let mut off = KeyCache::new(0, KeyCache::DEFAULT_TTL);
+        off.insert(&user("alice", 4));
+        assert!(off.is_empty());
+    }
+
+    #[test]
+    fn test_capacity_evicts_the_oldest() {
+        let mut cache = KeyCache::new(2, KeyCache::DEFAULT_TTL);
+        cache.insert(&user("alice", 4));
+        cache.insert(&user("bob", 4));
+        cache.insert(&user("alice", 4));
+        assert_eq!(cache.len(), 2);
+
+        cache.insert(&user("carol", 4));
+        assert_eq!(cache.len(), 2);
+        assert!(!cache.contains(&user("bob", 4)));
+        assert!(cache.contains(&user("alice", 4)));
+        assert!(cache.contains(&user("carol", 4)));
+    }
+}
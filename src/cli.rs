@@ -0,0 +1,222 @@
+use std::env;
+use std::io::{stdin, Read};
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use zkp_chaum_padersen::bundle::{BundlePolicy, ProofBundle};
+use zkp_chaum_padersen::keystore::{KdfParams, Keystore};
+use zkp_chaum_padersen::params::ParameterSet;
+use zkp_chaum_padersen::proof::Secret;
+use zkp_chaum_padersen::Error;
+
+const USAGE: &str = "usage: zkp [--output text|json] <command>
+    params [parameter-set-id]
+    keygen <parameter-set-id> <keystore>
+    prove <keystore> <context> [bundle]
+    verify <bundle> [max-age-secs]
+The keystore passphrase is read from ZKP_PASSPHRASE, or stdin if unset.";
+
+// exit codes: 0 ok, 1 the command failed, 2 bad usage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Output {
+    Text,
+    Json,
+}
+
+// What a command prints: the JSON object, and the lines for people.
+struct Report {
+    json: Value,
+    text: String,
+}
+
+fn main() -> ExitCode {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let mut output = Output::Text;
+    if let Some(i) = args.iter().position(|arg| arg == "--output") {
+        output = match args.get(i + 1).map(String::as_str) {
+            Some("json") => Output::Json,
+            Some("text") => Output::Text,
+            _ => return usage(Output::Text),
+        };
+        args.drain(i..i + 2);
+    }
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    let result = match args.as_slice() {
+        ["params"] => Ok(params(&ParameterSet::ALL)),
+        ["params", id] => id.parse().map(|set| params(&[set])),
+        ["keygen", id, path] => id.parse().and_then(|set| keygen(set, Path::new(path))),
+        ["prove", keystore, context] => prove(Path::new(keystore), context, None),
+        ["prove", keystore, context, bundle] => {
+            prove(Path::new(keystore), context, Some(Path::new(bundle)))
+        }
+        ["verify", bundle] => verify(Path::new(bundle), None),
+        ["verify", bundle, max_age] => match max_age.parse() {
+            Ok(secs) => verify(Path::new(bundle), Some(Duration::from_secs(secs))),
+            Err(_) => return usage(output),
+        },
+        _ => return usage(output),
+    };
+
+    match (result, output) {
+        (Ok(report), Output::Json) => {
+            let mut json = report.json;
+            json["ok"] = Value::Bool(true);
+            println!("{}", json);
+            ExitCode::SUCCESS
+        }
+        (Ok(report), Output::Text) => {
+            println!("{}", report.text);
+            ExitCode::SUCCESS
+        }
+        (Err(error), Output::Json) => {
+            let json = json!({
+                "ok": false,
+                "error": { "code": error.code(), "message": error.to_string() },
+            });
+            println!("{}", json);
+            ExitCode::FAILURE
+        }
+        (Err(error), Output::Text) => {
+            eprintln!("error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn usage(output: Output) -> ExitCode {
+    match output {
+        Output::Json => println!(
+            "{}",
+            json!({ "ok": false, "error": { "code": "usage", "message": USAGE } })
+        ),
+        Output::Text => eprintln!("{}", USAGE),
+    }
+    ExitCode::from(2)
+}
+
+fn params(sets: &[ParameterSet]) -> Report {
+    let rows: Vec<Value> = sets
+        .iter()
+        .map(|set| {
+            let zkp = set.zkp();
+            json!({
+                "id": set.id(),
+                "p_bits": zkp.p.bits(),
+                "q_bits": zkp.q.bits(),
+                "security_bits": set.security_bits(),
+            })
+        })
+        .collect();
+    let text = rows
+        .iter()
+        .map(|row| {
+            format!(
+                "{}: p {} bits, q {} bits, ~{}-bit security",
+                row["id"].as_str().unwrap(),
+                row["p_bits"],
+                row["q_bits"],
+                row["security_bits"]
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    Report {
+        json: json!({ "parameter_sets": rows }),
+        text,
+    }
+}
+
+fn keygen(set: ParameterSet, path: &Path) -> Result<Report, Error> {
+    let zkp = set.zkp();
+    let secret = Secret::random(&zkp);
+    let public_key = zkp.public_key(&secret);
+    Keystore::new(set.id(), secret).save(path, &passphrase()?, KdfParams::default())?;
+
+    let (y1, y2) = (
+        public_key.y1.to_str_radix(16),
+        public_key.y2.to_str_radix(16),
+    );
+    Ok(Report {
+        text: format!(
+            "wrote {}\nparameter set: {}\ny1: {}\ny2: {}",
+            path.display(),
+            set.id(),
+            y1,
+            y2
+        ),
+        json: json!({
+            "keystore": path.display().to_string(),
+            "parameter_set": set.id(),
+            "public_key": { "y1": y1, "y2": y2 },
+        }),
+    })
+}
+
+// The bundle goes to the file if one is given, it is printed as well.
+fn prove(keystore: &Path, context: &str, bundle_path: Option<&Path>) -> Result<Report, Error> {
+    let keystore = Keystore::load(keystore, &passphrase()?)?;
+    let set: ParameterSet = keystore.parameter_set.parse()?;
+    let bundle = ProofBundle::prove(set, &keystore.secret, context.as_bytes());
+    if let Some(path) = bundle_path {
+        bundle.save(path)?;
+    }
+
+    let pem = bundle.to_pem();
+    Ok(Report {
+        json: json!({
+            "parameter_set": set.id(),
+            "created_at": bundle.created_at,
+            "bundle": pem,
+            "path": bundle_path.map(|path| path.display().to_string()),
+        }),
+        text: pem.trim_end().to_string(),
+    })
+}
+
+// The key comes from the bundle itself, so it is printed for the caller to
+// check against the one they expect.
+fn verify(path: &Path, max_age: Option<Duration>) -> Result<Report, Error> {
+    let bundle = ProofBundle::load(path)?;
+    let mut policy = BundlePolicy::default();
+    if let Some(max_age) = max_age {
+        policy.max_age = max_age;
+    }
+    bundle.verify(&policy)?;
+
+    let context = String::from_utf8_lossy(&bundle.context).into_owned();
+    let (y1, y2) = (
+        bundle.public_key.y1.to_str_radix(16),
+        bundle.public_key.y2.to_str_radix(16),
+    );
+    Ok(Report {
+        text: format!(
+            "valid proof for {} made at {} (parameter set {})\ny1: {}\ny2: {}",
+            context,
+            bundle.created_at,
+            bundle.parameter_set.id(),
+            y1,
+            y2
+        ),
+        json: json!({
+            "parameter_set": bundle.parameter_set.id(),
+            "created_at": bundle.created_at,
+            "context": context,
+            "public_key": { "y1": y1, "y2": y2 },
+        }),
+    })
+}
+
+fn passphrase() -> Result<String, Error> {
+    if let Ok(passphrase) = env::var("ZKP_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+    let mut line = String::new();
+    stdin()
+        .read_to_string(&mut line)
+        .map_err(|e| Error::Io(e.to_string()))?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
@@ -1,3 +1,328 @@
-fn main() {
-    println!("Hi, I'm the client");
+use std::env;
+use std::io::stdin;
+use std::path::Path;
+use std::time::Duration;
+
+use num_bigint::BigUint;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use zkp_chaum_padersen::{
+    config::ClientConfig, params::ParameterSet, proof::Secret, protocol::ProtocolVersion,
+    retry::RetryPolicy, ZKP,
+};
+
+pub mod zkp_auth {
+    include!("./zkp_auth.rs");
+}
+
+use zkp_auth::{
+    auth_client::AuthClient, authentication_stream_request, authentication_stream_response,
+    AuthenticationAnswerRequest, AuthenticationChallengeRequest, AuthenticationStreamRequest,
+    NegotiateRequest, RegisterRequest,
+};
+
+fn read_line(prompt: &str) -> String {
+    println!("{}", prompt);
+    let mut buf = String::new();
+    stdin()
+        .read_line(&mut buf)
+        .expect("Could not get the input from stdin");
+    buf.trim().to_string()
+}
+
+#[tokio::main]
+async fn main() {
+    #[cfg(feature = "tracing")]
+    tracing_subscriber::fmt::init();
+
+    // usage: client [parameter-set-id]
+    let parameter_set = match env::args().nth(1) {
+        Some(id) => id.parse().expect("unknown parameter set"),
+        None => ParameterSet::Rfc3526_2048,
+    };
+    let zkp = parameter_set.zkp();
+    let (alpha, beta, p) = (&zkp.alpha, &zkp.beta, &zkp.p);
+
+    // ZKP_CLIENT_CONFIG=path, see `ClientConfig` for the file and the
+    // environment variables overriding it
+    let config_path = env::var("ZKP_CLIENT_CONFIG").ok();
+    let config =
+        ClientConfig::load(config_path.as_deref().map(Path::new)).expect("invalid configuration");
+    let channel = config
+        .endpoint()
+        .expect("invalid server address or TLS settings")
+        // connects on the first call, so an unreachable server is retried
+        // like any other transient failure
+        .connect_lazy();
+    let mut client = AuthClient::new(channel);
+    let policy = config.retry.policy();
+
+    let version = negotiate(&mut client, &policy).await;
+    println!("✅ Connected to the server");
+    println!("✅ Speaking protocol version {}", version.number());
+
+    // ZKP_BLIND_SERVICE=name registers the key blinded for that service, so
+    // it can't be linked to the registrations at other services
+    let service = env::var("ZKP_BLIND_SERVICE").ok();
+
+    let username = read_line("Please provide the username:");
+    let password = read_line("Please provide the password:");
+    let x = secret_for(&zkp, &password, service.as_deref());
+
+    let y1 = ZKP::exponetiate(alpha, &x, p);
+    let y2 = ZKP::exponetiate(beta, &x, p);
+    let blinding_proof = match service {
+        Some(_) => zkp
+            .blinded_registration(&Secret::new(x.clone()), &username, parameter_set.id())
+            .proof_to_bytes(&zkp),
+        None => Vec::new(),
+    };
+
+    let request = RegisterRequest {
+        user: username.clone(),
+        y1: version.encode_element(&zkp, &y1),
+        y2: version.encode_element(&zkp, &y2),
+        parameter_set: parameter_set.id().to_string(),
+        version: version.number(),
+        blinding_proof,
+    };
+    register(&mut client, &policy, request).await;
+
+    let password = read_line("Please provide the password (to login):");
+    let x = secret_for(&zkp, &password, service.as_deref());
+
+    // ZKP_STREAMING=1 logs in over the bidirectional stream
+    let session_id = if env::var("ZKP_STREAMING").is_ok() {
+        login_stream(&mut client, &policy, version, &zkp, &username, &x).await
+    } else {
+        login(&mut client, &policy, version, &zkp, &username, &x).await
+    };
+    println!("✅ Logging successful! session_id: {}", session_id);
+}
+
+fn secret_for(zkp: &ZKP, password: &str, service: Option<&str>) -> BigUint {
+    let x = Secret::new(BigUint::from_bytes_be(password.as_bytes()));
+    match service {
+        Some(service) => x.blind(zkp, service).x().clone(),
+        None => x.x().clone(),
+    }
+}
+
+// Servers from before the negotiation step only speak version 1.
+async fn negotiate(
+    client: &mut AuthClient<tonic::transport::Channel>,
+    policy: &RetryPolicy,
+) -> ProtocolVersion {
+    let request = NegotiateRequest {
+        versions: ProtocolVersion::ALL.iter().map(|v| v.number()).collect(),
+    };
+    let response = policy
+        .run(|| {
+            let (mut client, request) = (client.clone(), request.clone());
+            async move { client.negotiate(request).await }
+        })
+        .await;
+    match response {
+        Ok(response) => ProtocolVersion::from_number(response.into_inner().version)
+            .expect("Server picked a version we don't speak"),
+        Err(status) if status.code() == tonic::Code::Unimplemented => ProtocolVersion::V1,
+        Err(status) => panic!("Could not negotiate a protocol version: {}", status),
+    }
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "prover.register", skip_all, fields(user = %request.user))
+)]
+async fn register(
+    client: &mut AuthClient<tonic::transport::Channel>,
+    policy: &RetryPolicy,
+    request: RegisterRequest,
+) {
+    // A retry of a registration that landed finds the name taken, as does a
+    // second run for the same user; the login that follows tells whether
+    // the key registered is ours.
+    let response = policy
+        .run(|| {
+            let (mut client, request) = (client.clone(), request.clone());
+            async move { client.register(request).await }
+        })
+        .await;
+    match response {
+        Ok(_) => println!("✅ Registration was successful"),
+        Err(status) if status.code() == tonic::Code::AlreadyExists => {
+            println!("✅ Already registered")
+        }
+        Err(status) => panic!("Could not register in server: {}", status),
+    }
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "prover.login", skip_all, fields(user = %username, auth_id))
+)]
+async fn login(
+    client: &mut AuthClient<tonic::transport::Channel>,
+    policy: &RetryPolicy,
+    version: ProtocolVersion,
+    zkp: &ZKP,
+    username: &str,
+    x: &BigUint,
+) -> String {
+    // A commitment is accepted once and an answer is only good for its
+    // challenge, which is gone once answered, expired, lost by a restarted
+    // server or on another replica. Neither call is repeated as is: when
+    // the commitment didn't get through or the server no longer knows the
+    // challenge, the round starts over with a fresh nonce.
+    let mut round = 1;
+    loop {
+        match login_round(client, policy, version, zkp, username, x).await {
+            Err(status)
+                if round < policy.attempts
+                    && (status.code() == tonic::Code::NotFound
+                        || RetryPolicy::is_transient(&status)) =>
+            {
+                println!("⏳ Starting the login over with a fresh commitment");
+                tokio::time::sleep(policy.backoff(round)).await;
+                round += 1;
+            }
+            result => return result.expect("Could not verify authentication in server"),
+        }
+    }
+}
+
+async fn login_round(
+    client: &mut AuthClient<tonic::transport::Channel>,
+    policy: &RetryPolicy,
+    version: ProtocolVersion,
+    zkp: &ZKP,
+    username: &str,
+    x: &BigUint,
+) -> Result<String, tonic::Status> {
+    let k = ZKP::generate_random_nonzero_below(&zkp.q);
+    let r1 = ZKP::exponetiate(&zkp.alpha, &k, &zkp.p);
+    let r2 = ZKP::exponetiate(&zkp.beta, &k, &zkp.p);
+
+    let request = AuthenticationChallengeRequest {
+        user: username.to_string(),
+        r1: version.encode_element(zkp, &r1),
+        r2: version.encode_element(zkp, &r2),
+        version: version.number(),
+    };
+    let response = policy
+        .once()
+        .run(|| {
+            let (mut client, request) = (client.clone(), request.clone());
+            async move { client.create_authentication_challenge(request).await }
+        })
+        .await?
+        .into_inner();
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("auth_id", response.auth_id.as_str());
+
+    let c = version
+        .decode_scalar(zkp, &response.c)
+        .expect("Malformed challenge from server");
+    let s = zkp.solve(&k, &c, x);
+
+    // The first answer may have landed and consumed the challenge, a retry
+    // then reads as NotFound and a new round.
+    let request = AuthenticationAnswerRequest {
+        auth_id: response.auth_id,
+        s: version.encode_scalar(zkp, &s),
+        version: version.number(),
+    };
+    let response = policy
+        .run(|| {
+            let (mut client, request) = (client.clone(), request.clone());
+            async move { client.verify_authentication(request).await }
+        })
+        .await;
+    #[cfg(feature = "tracing")]
+    tracing::info!(
+        outcome = if response.is_ok() {
+            "accepted"
+        } else {
+            "rejected"
+        },
+        "verification result"
+    );
+
+    response.map(|response| response.into_inner().session_id)
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "prover.login_stream", skip_all, fields(user = %username))
+)]
+async fn login_stream(
+    client: &mut AuthClient<tonic::transport::Channel>,
+    policy: &RetryPolicy,
+    version: ProtocolVersion,
+    zkp: &ZKP,
+    username: &str,
+    x: &BigUint,
+) -> String {
+    use authentication_stream_request::Step;
+    use authentication_stream_response::Step as Reply;
+
+    let k = ZKP::generate_random_nonzero_below(&zkp.q);
+    let r1 = ZKP::exponetiate(&zkp.alpha, &k, &zkp.p);
+    let r2 = ZKP::exponetiate(&zkp.beta, &k, &zkp.p);
+
+    let (outbound, receiver) = mpsc::channel(1);
+    let commitment = AuthenticationChallengeRequest {
+        user: username.to_string(),
+        r1: version.encode_element(zkp, &r1),
+        r2: version.encode_element(zkp, &r2),
+        version: version.number(),
+    };
+    outbound
+        .send(AuthenticationStreamRequest {
+            step: Some(Step::Commitment(commitment)),
+        })
+        .await
+        .expect("Could not send the commitment");
+
+    // the stream carries the round's state, so only the opening is bounded
+    let opening = tokio::time::timeout(
+        policy.timeout.unwrap_or(Duration::MAX),
+        client.authenticate(ReceiverStream::new(receiver)),
+    );
+    let mut inbound = opening
+        .await
+        .expect("Timed out opening the authentication stream")
+        .expect("Could not open the authentication stream")
+        .into_inner();
+    let challenge = match next_step(&mut inbound).await {
+        Reply::Challenge(challenge) => challenge,
+        Reply::Session(_) => panic!("Server skipped the challenge"),
+    };
+    let c = version
+        .decode_scalar(zkp, &challenge.c)
+        .expect("Malformed challenge from server");
+    let s = zkp.solve(&k, &c, x);
+    outbound
+        .send(AuthenticationStreamRequest {
+            step: Some(Step::S(version.encode_scalar(zkp, &s))),
+        })
+        .await
+        .expect("Could not send the answer");
+
+    match next_step(&mut inbound).await {
+        Reply::Session(session) => session.session_id,
+        Reply::Challenge(_) => panic!("Server sent a second challenge"),
+    }
+}
+
+async fn next_step(
+    inbound: &mut tonic::Streaming<zkp_auth::AuthenticationStreamResponse>,
+) -> authentication_stream_response::Step {
+    inbound
+        .message()
+        .await
+        .expect("Authentication stream failed")
+        .and_then(|response| response.step)
+        .expect("Server ended the stream early")
 }
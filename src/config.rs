@@ -0,0 +1,709 @@
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Deserialize;
+use tonic::transport::{Certificate, ClientTlsConfig, Endpoint, Identity, ServerTlsConfig};
+
+use crate::audit::JsonlAuditSink;
+use crate::cache::KeyCache;
+use crate::params::ParameterSet;
+use crate::ratelimit::RateLimiter;
+use crate::retry::RetryPolicy;
+use crate::store::{InMemoryUserStore, JsonFileUserStore, UserStore};
+use crate::verifier::Verifier;
+use crate::Error;
+
+// Settings of the server binary, read from a TOML file and then overridden
+// by environment variables. Everything is optional:
+//
+//     listen = "0.0.0.0:50051"                 # ZKP_LISTEN
+//     metrics_listen = "0.0.0.0:9000"          # ZKP_METRICS_LISTEN
+//     parameter_set = "rfc3526-2048"           # ZKP_PARAMETER_SET
+//     accepted_parameter_sets = ["rfc5114-1024-160", "rfc3526-2048"]
+//     audit_log = "/var/log/zkp/audit.jsonl"   # ZKP_AUDIT_LOG
+//     transcript = "/var/log/zkp/wire.jsonl"   # ZKP_TRANSCRIPT
+//     shutdown_grace_secs = 30                 # ZKP_SHUTDOWN_GRACE_SECS
+//     admin_token = "..."                      # ZKP_ADMIN_TOKEN, no admin service if unset,
+//                                              # needs [tls] or a loopback listen
+//
+//     [storage]
+//     path = "/var/lib/zkp/users.json"         # ZKP_STORAGE_PATH, in memory if unset
+//
+//     [sessions]
+//     ttl_secs = 3600                          # ZKP_SESSION_TTL_SECS, no expiry if unset
+//     max_per_user = 5                         # ZKP_MAX_SESSIONS_PER_USER, unlimited if unset
+//
+//     [key_cache]
+//     size = 10000                             # ZKP_KEY_CACHE_SIZE
+//     ttl_secs = 3600                          # ZKP_KEY_CACHE_TTL_SECS
+//
+//     [rate_limit]
+//     challenges = 20                          # ZKP_RATE_LIMIT_CHALLENGES, unlimited if unset
+//     window_secs = 60                         # ZKP_RATE_LIMIT_WINDOW_SECS
+//
+//     [tls]
+//     cert = "/etc/zkp/server.pem"             # ZKP_TLS_CERT
+//     key = "/etc/zkp/server.key"              # ZKP_TLS_KEY
+//     client_ca = "/etc/zkp/clients.pem"       # ZKP_TLS_CLIENT_CA
+pub const MIN_ADMIN_TOKEN_LEN: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub listen: String,
+    pub metrics_listen: String,
+    pub parameter_set: String,
+    pub accepted_parameter_sets: Vec<String>,
+    pub audit_log: Option<PathBuf>,
+    pub transcript: Option<PathBuf>,
+    // how long in-flight requests get to finish after SIGTERM
+    pub shutdown_grace_secs: u64,
+    // bearer token of the admin service, which is only served with one
+    pub admin_token: Option<String>,
+    pub storage: StorageConfig,
+    pub sessions: SessionConfig,
+    pub key_cache: KeyCacheConfig,
+    pub rate_limit: RateLimitConfig,
+    pub tls: Option<TlsConfig>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionConfig {
+    pub ttl_secs: Option<u64>,
+    pub max_per_user: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeyCacheConfig {
+    pub size: usize,
+    pub ttl_secs: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    pub challenges: Option<usize>,
+    pub window_secs: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+    pub client_ca: Option<PathBuf>,
+}
+
+impl Default for ServerConfig {
+    // what the server did before it had a config
+    fn default() -> Self {
+        ServerConfig {
+            listen: "127.0.0.1:50051".to_string(),
+            metrics_listen: "127.0.0.1:9000".to_string(),
+            parameter_set: ParameterSet::Rfc3526_2048.id().to_string(),
+            accepted_parameter_sets: ParameterSet::ALL
+                .iter()
+                .map(|set| set.id().to_string())
+                .collect(),
+            audit_log: None,
+            transcript: None,
+            shutdown_grace_secs: 30,
+            admin_token: None,
+            storage: StorageConfig::default(),
+            sessions: SessionConfig::default(),
+            key_cache: KeyCacheConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            tls: None,
+        }
+    }
+}
+
+impl Default for KeyCacheConfig {
+    fn default() -> Self {
+        KeyCacheConfig {
+            size: KeyCache::DEFAULT_CAPACITY,
+            ttl_secs: KeyCache::DEFAULT_TTL.as_secs(),
+        }
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            challenges: None,
+            window_secs: 60,
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn from_toml(text: &str) -> Result<Self, Error> {
+        toml::from_str(text).map_err(|e| Error::InvalidParameters(e.to_string()))
+    }
+
+    // The file if there is one, then the environment on top.
+    pub fn load(path: Option<&Path>) -> Result<Self, Error> {
+        let mut config = match path {
+            Some(path) => {
+                let text = std::fs::read_to_string(path).map_err(|e| Error::Io(e.to_string()))?;
+                ServerConfig::from_toml(&text)?
+            }
+            None => ServerConfig::default(),
+        };
+        config.apply_env(|name| std::env::var(name).ok())?;
+        Ok(config)
+    }
+
+    pub fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<(), Error> {
+        let number = |name: &str| -> Result<Option<u64>, Error> {
+            var(name)
+                .map(|value| {
+                    value
+                        .parse()
+                        .map_err(|_| Error::InvalidParameters(format!("{} is not a number", name)))
+                })
+                .transpose()
+        };
+
+        if let Some(listen) = var("ZKP_LISTEN") {
+            self.listen = listen;
+        }
+        if let Some(listen) = var("ZKP_METRICS_LISTEN") {
+            self.metrics_listen = listen;
+        }
+        if let Some(id) = var("ZKP_PARAMETER_SET") {
+            self.parameter_set = id;
+        }
+        if let Some(path) = var("ZKP_AUDIT_LOG") {
+            self.audit_log = Some(path.into());
+        }
+        if let Some(path) = var("ZKP_TRANSCRIPT") {
+            self.transcript = Some(path.into());
+        }
+        if let Some(grace) = number("ZKP_SHUTDOWN_GRACE_SECS")? {
+            self.shutdown_grace_secs = grace;
+        }
+        if let Some(token) = var("ZKP_ADMIN_TOKEN") {
+            self.admin_token = Some(token);
+        }
+        if let Some(path) = var("ZKP_STORAGE_PATH") {
+            self.storage.path = Some(path.into());
+        }
+        if let Some(ttl) = number("ZKP_SESSION_TTL_SECS")? {
+            self.sessions.ttl_secs = Some(ttl);
+        }
+        if let Some(max) = number("ZKP_MAX_SESSIONS_PER_USER")? {
+            self.sessions.max_per_user = Some(max as usize);
+        }
+        if let Some(size) = number("ZKP_KEY_CACHE_SIZE")? {
+            self.key_cache.size = size as usize;
+        }
+        if let Some(ttl) = number("ZKP_KEY_CACHE_TTL_SECS")? {
+            self.key_cache.ttl_secs = ttl;
+        }
+        if let Some(challenges) = number("ZKP_RATE_LIMIT_CHALLENGES")? {
+            self.rate_limit.challenges = Some(challenges as usize);
+        }
+        if let Some(window) = number("ZKP_RATE_LIMIT_WINDOW_SECS")? {
+            self.rate_limit.window_secs = window;
+        }
+
+        let (cert, key) = (var("ZKP_TLS_CERT"), var("ZKP_TLS_KEY"));
+        let client_ca = var("ZKP_TLS_CLIENT_CA");
+        if cert.is_some() || key.is_some() || client_ca.is_some() {
+            let current = self.tls.take();
+            let tls = TlsConfig {
+                cert: cert
+                    .map(PathBuf::from)
+                    .or_else(|| current.as_ref().map(|tls| tls.cert.clone()))
+                    .ok_or_else(|| Error::InvalidParameters("TLS without a cert".to_string()))?,
+                key: key
+                    .map(PathBuf::from)
+                    .or_else(|| current.as_ref().map(|tls| tls.key.clone()))
+                    .ok_or_else(|| Error::InvalidParameters("TLS without a key".to_string()))?,
+                client_ca: client_ca
+                    .map(PathBuf::from)
+                    .or_else(|| current.and_then(|tls| tls.client_ca)),
+            };
+            self.tls = Some(tls);
+        }
+
+        Ok(())
+    }
+
+    // output => (default, accepted)
+    pub fn parameter_sets(&self) -> Result<(ParameterSet, Vec<ParameterSet>), Error> {
+        let default = self.parameter_set.parse()?;
+        let accepted = self
+            .accepted_parameter_sets
+            .iter()
+            .map(|id| id.parse())
+            .collect::<Result<_, _>>()?;
+        Ok((default, accepted))
+    }
+
+    // None if the admin service is off. It shares the listener with the
+    // public service, so a token short enough to guess is refused rather
+    // than served, and so is one that would cross the network in the clear:
+    // without TLS the server has to listen on loopback only.
+    pub fn admin_token(&self) -> Result<Option<&str>, Error> {
+        let loopback = self
+            .listen
+            .parse::<SocketAddr>()
+            .is_ok_and(|addr| addr.ip().is_loopback());
+        match self.admin_token.as_deref() {
+            Some(token) if token.len() < MIN_ADMIN_TOKEN_LEN => {
+                Err(Error::InvalidParameters(format!(
+                    "admin_token must be at least {} characters",
+                    MIN_ADMIN_TOKEN_LEN
+                )))
+            }
+            Some(_) if self.tls.is_none() && !loopback => Err(Error::InvalidParameters(
+                "the admin service needs [tls] unless listen is a loopback address".to_string(),
+            )),
+            token => Ok(token),
+        }
+    }
+
+    pub fn verifier(&self) -> Result<Verifier<Box<dyn UserStore + Send>>, Error> {
+        let (default, accepted) = self.parameter_sets()?;
+        let store: Box<dyn UserStore + Send> = match &self.storage.path {
+            Some(path) => Box::new(JsonFileUserStore::open(path)?),
+            None => Box::new(InMemoryUserStore::new()),
+        };
+
+        let mut verifier = Verifier::with_parameter_sets(default, &accepted).with_store(store);
+        verifier.set_key_cache(KeyCache::new(
+            self.key_cache.size,
+            Duration::from_secs(self.key_cache.ttl_secs),
+        ));
+        verifier.set_session_ttl(self.sessions.ttl_secs.map(Duration::from_secs));
+        verifier.set_max_sessions_per_user(self.sessions.max_per_user)?;
+        if let Some(challenges) = self.rate_limit.challenges {
+            verifier.set_rate_limiter(RateLimiter::new(
+                challenges,
+                Duration::from_secs(self.rate_limit.window_secs),
+            ));
+        }
+        if let Some(path) = &self.audit_log {
+            verifier.add_audit_sink(JsonlAuditSink::open(path)?);
+        }
+
+        Ok(verifier)
+    }
+}
+
+impl TlsConfig {
+    // A client_ca turns on mutual TLS: connections without a client
+    // certificate signed by it are refused before any rpc, on top of the
+    // proof of the user.
+    pub fn server_tls(&self) -> Result<ServerTlsConfig, Error> {
+        let identity = Identity::from_pem(read(&self.cert)?, read(&self.key)?);
+        let mut tls = ServerTlsConfig::new().identity(identity);
+        if let Some(ca) = &self.client_ca {
+            tls = tls.client_ca_root(Certificate::from_pem(read(ca)?));
+        }
+        Ok(tls)
+    }
+}
+
+// Settings of the client binary, from the TOML file in ZKP_CLIENT_CONFIG
+// and the environment:
+//
+//     server = "https://auth.example.com:50051"   # ZKP_SERVER
+//
+//     [tls]
+//     ca = "/etc/zkp/ca.pem"                       # ZKP_TLS_CA
+//     domain = "auth.example.com"                  # ZKP_TLS_DOMAIN
+//     cert = "/etc/zkp/client.pem"                 # ZKP_TLS_CLIENT_CERT, for mTLS
+//     key = "/etc/zkp/client.key"                  # ZKP_TLS_CLIENT_KEY
+//
+//     [retry]
+//     attempts = 3                                 # ZKP_RETRY_ATTEMPTS, 1 turns retries off
+//     initial_backoff_ms = 100
+//     max_backoff_ms = 2000
+//     timeout_ms = 10000                           # ZKP_TIMEOUT_MS, per call
+//     connect_timeout_ms = 5000                    # ZKP_CONNECT_TIMEOUT_MS
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClientConfig {
+    pub server: String,
+    pub tls: Option<ClientTls>,
+    pub retry: RetryConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryConfig {
+    pub attempts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub timeout_ms: Option<u64>,
+    pub connect_timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClientTls {
+    // trusted roots, the system ones are not loaded
+    pub ca: Option<PathBuf>,
+    // name the server certificate is checked against, the host of `server`
+    // if unset
+    pub domain: Option<String>,
+    pub cert: Option<PathBuf>,
+    pub key: Option<PathBuf>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        ClientConfig {
+            server: "http://127.0.0.1:50051".to_string(),
+            tls: None,
+            retry: RetryConfig::default(),
+        }
+    }
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        let policy = RetryPolicy::default();
+        RetryConfig {
+            attempts: policy.attempts,
+            initial_backoff_ms: policy.initial_backoff.as_millis() as u64,
+            max_backoff_ms: policy.max_backoff.as_millis() as u64,
+            timeout_ms: policy.timeout.map(|timeout| timeout.as_millis() as u64),
+            connect_timeout_ms: Some(5000),
+        }
+    }
+}
+
+impl RetryConfig {
+    pub fn policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            attempts: self.attempts.max(1),
+            initial_backoff: Duration::from_millis(self.initial_backoff_ms),
+            max_backoff: Duration::from_millis(self.max_backoff_ms),
+            timeout: self.timeout_ms.map(Duration::from_millis),
+        }
+    }
+}
+
+impl ClientConfig {
+    pub fn from_toml(text: &str) -> Result<Self, Error> {
+        toml::from_str(text).map_err(|e| Error::InvalidParameters(e.to_string()))
+    }
+
+    pub fn load(path: Option<&Path>) -> Result<Self, Error> {
+        let mut config = match path {
+            Some(path) => {
+                let text = std::fs::read_to_string(path).map_err(|e| Error::Io(e.to_string()))?;
+                ClientConfig::from_toml(&text)?
+            }
+            None => ClientConfig::default(),
+        };
+        config.apply_env(|name| std::env::var(name).ok())?;
+        Ok(config)
+    }
+
+    pub fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<(), Error> {
+        let number = |name: &str| -> Result<Option<u64>, Error> {
+            var(name)
+                .map(|value| {
+                    value
+                        .parse()
+                        .map_err(|_| Error::InvalidParameters(format!("{} is not a number", name)))
+                })
+                .transpose()
+        };
+
+        if let Some(server) = var("ZKP_SERVER") {
+            self.server = server;
+        }
+        if let Some(attempts) = number("ZKP_RETRY_ATTEMPTS")? {
+            self.retry.attempts = attempts as u32;
+        }
+        if let Some(timeout) = number("ZKP_TIMEOUT_MS")? {
+            self.retry.timeout_ms = Some(timeout);
+        }
+        if let Some(timeout) = number("ZKP_CONNECT_TIMEOUT_MS")? {
+            self.retry.connect_timeout_ms = Some(timeout);
+        }
+        let tls_vars = [
+            "ZKP_TLS_CA",
+            "ZKP_TLS_DOMAIN",
+            "ZKP_TLS_CLIENT_CERT",
+            "ZKP_TLS_CLIENT_KEY",
+        ];
+        if tls_vars.iter().all(|name| var(name).is_none()) {
+            return Ok(());
+        }
+        let tls = self.tls.get_or_insert_with(ClientTls::default);
+        if let Some(ca) = var("ZKP_TLS_CA") {
+            tls.ca = Some(ca.into());
+        }
+        if let Some(domain) = var("ZKP_TLS_DOMAIN") {
+            tls.domain = Some(domain);
+        }
+        if let Some(cert) = var("ZKP_TLS_CLIENT_CERT") {
+            tls.cert = Some(cert.into());
+        }
+        if let Some(key) = var("ZKP_TLS_CLIENT_KEY") {
+            tls.key = Some(key.into());
+        }
+        Ok(())
+    }
+
+    pub fn endpoint(&self) -> Result<Endpoint, Error> {
+        let mut endpoint = Endpoint::from_shared(self.server.clone())
+            .map_err(|e| Error::InvalidParameters(e.to_string()))?;
+        if let Some(timeout) = self.retry.connect_timeout_ms {
+            endpoint = endpoint.connect_timeout(Duration::from_millis(timeout));
+        }
+        let tls = match &self.tls {
+            Some(tls) => tls,
+            None => return Ok(endpoint),
+        };
+
+        let identity = match (&tls.cert, &tls.key) {
+            (Some(cert), Some(key)) => Some((cert, key)),
+            (None, None) => None,
+            _ => {
+                return Err(Error::InvalidParameters(
+                    "a client certificate needs both cert and key".to_string(),
+                ))
+            }
+        };
+
+        let mut client_tls = ClientTlsConfig::new();
+        if let Some(ca) = &tls.ca {
+            client_tls = client_tls.ca_certificate(Certificate::from_pem(read(ca)?));
+        }
+        if let Some(domain) = &tls.domain {
+            client_tls = client_tls.domain_name(domain.clone());
+        }
+        if let Some((cert, key)) = identity {
+            client_tls = client_tls.identity(Identity::from_pem(read(cert)?, read(key)?));
+        }
+        endpoint
+            .tls_config(client_tls)
+            .map_err(|e| Error::InvalidParameters(e.to_string()))
+    }
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, Error> {
+    std::fs::read(path).map_err(|e| Error::Io(format!("{}: {}", path.display(), e)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_file_then_environment() {
+        let mut config = ServerConfig::from_toml(
+            r#"
+            listen = "0.0.0.0:50051"
+            parameter_set = "rfc3526-3072"
+
+            [sessions]
+            ttl_secs = 600
+
+            [rate_limit]
+            challenges = 5
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.listen, "0.0.0.0:50051");
+        assert_eq!(config.sessions.ttl_secs, Some(600));
+        assert_eq!(config.rate_limit.window_secs, 60);
+        assert_eq!(config.key_cache, KeyCacheConfig::default());
+
+        let env: HashMap<&str, &str> = [
+            ("ZKP_LISTEN", "127.0.0.1:6000"),
+            ("ZKP_RATE_LIMIT_CHALLENGES", "10"),
+            ("ZKP_MAX_SESSIONS_PER_USER", "3"),
+            ("ZKP_TLS_CERT", "cert.pem"),
+            ("ZKP_TLS_KEY", "key.pem"),
+        ]
+        .into();
+        config
+            .apply_env(|name| env.get(name).map(|value| value.to_string()))
+            .unwrap();
+        assert_eq!(config.listen, "127.0.0.1:6000");
+        assert_eq!(config.parameter_set, "rfc3526-3072");
+        assert_eq!(config.rate_limit.challenges, Some(10));
+        assert_eq!(config.sessions.max_per_user, Some(3));
+        assert_eq!(config.tls.unwrap().cert, PathBuf::from("cert.pem"));
+
+        let mut bad = ServerConfig::default();
+        assert!(bad
+            .apply_env(|name| (name == "ZKP_SESSION_TTL_SECS").then(|| "soon".to_string()))
+            .is_err());
+    }
+
+    #[test]
+    fn test_invalid_configs_are_rejected() {
+        assert!(ServerConfig::from_toml("listen = 50051").is_err());
+        assert!(ServerConfig::from_toml("lisen = \"0.0.0.0:1\"").is_err());
+
+        let config = ServerConfig::from_toml("parameter_set = \"rfc0000\"").unwrap();
+        assert_eq!(
+            config.verifier().err(),
+            Some(Error::UnknownParameterSet("rfc0000".to_string()))
+        );
+
+        let config = ServerConfig::from_toml("admin_token = \"hunter2\"").unwrap();
+        assert!(config.admin_token().is_err());
+        assert_eq!(ServerConfig::default().admin_token(), Ok(None));
+
+        // the token in the clear only on loopback
+        let token = "a".repeat(MIN_ADMIN_TOKEN_LEN);
+        let config = ServerConfig::from_toml(&format!("admin_token = \"{}\"", token)).unwrap();
+        assert_eq!(config.admin_token(), Ok(Some(token.as_str())));
+        let exposed = ServerConfig {
+            listen: "0.0.0.0:50051".to_string(),
+            ..config.clone()
+        };
+        assert!(exposed.admin_token().is_err());
+        let with_tls = ServerConfig {
+            tls: Some(TlsConfig {
+                cert: "server.pem".into(),
+                key: "server.key".into(),
+                client_ca: None,
+            }),
+            ..exposed
+        };
+        assert_eq!(with_tls.admin_token(), Ok(Some(token.as_str())));
+    }
+
+    #[test]
+    fn test_verifier_follows_the_config() {
+        let config = ServerConfig::from_toml(
+            r#"
+            parameter_set = "rfc5114-1024-160"
+            accepted_parameter_sets = []
+            "#,
+        )
+        .unwrap();
+        let verifier = config.verifier().unwrap();
+        assert_eq!(verifier.default_parameter_set(), "rfc5114-1024-160");
+        assert_eq!(verifier.parameter_set_ids(), vec!["rfc5114-1024-160"]);
+    }
+
+    // CA, server and client certificates written to a fresh directory
+    fn certificates() -> PathBuf {
+        use rcgen::{BasicConstraints, Certificate as Cert, CertificateParams, IsCa};
+
+        let dir = std::env::temp_dir().join(format!("zkp-tls-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut ca_params = CertificateParams::new(vec![]);
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = Cert::from_params(ca_params).unwrap();
+        std::fs::write(dir.join("ca.pem"), ca.serialize_pem().unwrap()).unwrap();
+        for name in ["server", "client"] {
+            let cert =
+                Cert::from_params(CertificateParams::new(vec!["localhost".to_string()])).unwrap();
+            let pem = cert.serialize_pem_with_signer(&ca).unwrap();
+            std::fs::write(dir.join(format!("{}.pem", name)), pem).unwrap();
+            let key = cert.serialize_private_key_pem();
+            std::fs::write(dir.join(format!("{}.key", name)), key).unwrap();
+        }
+        dir
+    }
+
+    #[tokio::test]
+    async fn test_mutual_tls() {
+        use tonic_health::pb::{health_client::HealthClient, HealthCheckRequest};
+
+        let dir = certificates();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let server_tls = TlsConfig {
+            cert: dir.join("server.pem"),
+            key: dir.join("server.key"),
+            client_ca: Some(dir.join("ca.pem")),
+        };
+        let (_, health_service) = tonic_health::server::health_reporter();
+        let server = tonic::transport::Server::builder()
+            .tls_config(server_tls.server_tls().unwrap())
+            .unwrap()
+            .add_service(health_service)
+            .serve(addr);
+        tokio::spawn(server);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let ping = |config: ClientConfig| async move {
+            let channel = config
+                .endpoint()?
+                .connect()
+                .await
+                .map_err(|e| Error::Transport(e.to_string()))?;
+            HealthClient::new(channel)
+                .check(HealthCheckRequest::default())
+                .await
+                .map_err(|e| Error::Transport(e.to_string()))
+        };
+        let mut config = ClientConfig {
+            server: format!("https://{}", addr),
+            tls: Some(ClientTls {
+                ca: Some(dir.join("ca.pem")),
+                domain: Some("localhost".to_string()),
+                cert: Some(dir.join("client.pem")),
+                key: Some(dir.join("client.key")),
+            }),
+            ..ClientConfig::default()
+        };
+        assert!(ping(config.clone()).await.is_ok());
+
+        // no client certificate, no connection
+        let tls = config.tls.as_mut().unwrap();
+        tls.cert = None;
+        tls.key = None;
+        assert!(ping(config.clone()).await.is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_client_config() {
+        let mut config = ClientConfig::from_toml("server = \"https://auth:50051\"").unwrap();
+        assert_eq!(config.tls, None);
+        let env: HashMap<&str, &str> = [("ZKP_TLS_CA", "ca.pem")].into();
+        config
+            .apply_env(|name| env.get(name).map(|value| value.to_string()))
+            .unwrap();
+        assert_eq!(config.server, "https://auth:50051");
+        assert_eq!(config.retry.policy(), RetryPolicy::default());
+        assert_eq!(
+            config.tls.as_ref().unwrap().ca,
+            Some(PathBuf::from("ca.pem"))
+        );
+
+        let env: HashMap<&str, &str> =
+            [("ZKP_RETRY_ATTEMPTS", "0"), ("ZKP_TIMEOUT_MS", "250")].into();
+        config
+            .apply_env(|name| env.get(name).map(|value| value.to_string()))
+            .unwrap();
+        let policy = config.retry.policy();
+        assert_eq!(policy.attempts, 1);
+        assert_eq!(policy.timeout, Some(Duration::from_millis(250)));
+
+        config.tls.as_mut().unwrap().cert = Some("client.pem".into());
+        assert!(matches!(
+            config.endpoint(),
+            Err(Error::InvalidParameters(_))
+        ));
+    }
+}
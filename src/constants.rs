@@ -0,0 +1,31 @@
+// What `fixed` shares with the num-bigint code, kept apart so it builds
+// without std.
+
+// Domain separation for the Fiat-Shamir challenge. v1 didn't bind the group,
+// see `ZKP::hash_to_scalar`.
+pub(crate) const CHALLENGE_LABEL: &[u8] = b"zkp-chaum-pedersen/fiat-shamir/v2";
+
+// RFC 5114 1024-bit MODP group with 160-bit prime order subgroup, beta =
+// alpha^BETA_EXPONENT
+pub(crate) const RFC5114_1024_P: &str = concat!(
+    "B10B8F96A080E01DDE92DE5EAE5D54EC52C99FBCFB06A3C69A6A9DCA52D23B616073E28675A23D189838EF1E2EE652C0",
+    "13ECB4AEA906112324975C3CD49B83BFACCBDD7D90C4BD7098488E9C219A73724EFFD6FAE5644738FAA31A4FF55BCCC0",
+    "A151AF5F0DC8B4BD45BF37DF365C1A65E68CFDA76D4DA708DF1FB2BC2E4A4371",
+);
+pub(crate) const RFC5114_1024_Q: &str = "F518AA8781A8DF278ABA4E7D64B7CB9D49462353";
+pub(crate) const RFC5114_1024_ALPHA: &str = concat!(
+    "A4D1CBD5C3FD34126765A442EFB99905F8104DD258AC507FD6406CFF14266D31266FEA1E5C41564B777E690F5504F213",
+    "160217B4B01B886A5E91547F9E2749F4D7FBD7D3B9A92EE1909D0D2263F80A76A6A24C087A091F531DBF0A0169B6A28A",
+    "D662A4D18E73AFA32D779D5918D08BC8858F4DCEF97C2A24855E6EEB22B3B2E5",
+);
+pub(crate) const RFC5114_1024_BETA_EXPONENT: &str = "266FEA1E5C41564B777E69";
+
+// RFC 3526 group, 2048-bit MODP
+pub(crate) const MODP_2048_P: &str = concat!(
+    "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74020BBEA63B139B22514A08798E3404DD",
+    "EF9519B3CD3A431B302B0A6DF25F14374FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED",
+    "EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF0598DA48361C55D39A69163FA8FD24CF5F",
+    "83655D23DCA3AD961C62F356208552BB9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B",
+    "E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF6955817183995497CEA956AE515D2261898FA0510",
+    "15728E5A8AACAA68FFFFFFFFFFFFFFFF",
+);
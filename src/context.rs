@@ -0,0 +1,101 @@
+use std::io::{self, Read, Write};
+
+use sha2::{Digest, Sha256};
+
+use crate::Error;
+
+// The action context only goes into a challenge through its SHA-256, so it
+// can be hashed as it streams by: a multi-megabyte document is bound into a
+// proof without holding it in memory. Every `_digest` API takes the
+// `ContextDigest` in place of the bytes and gives the same challenges as
+// the one taking the bytes:
+//
+//     let digest = ContextDigest::from_reader(File::open(path)?)?;
+//     let proof = zkp.prove_compact_action_digest(&secret, &public_key, &digest);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ContextDigest(pub [u8; 32]);
+
+// Incremental, also an `io::Write` for `io::copy` and the like.
+#[derive(Debug, Clone, Default)]
+pub struct ContextHasher(Sha256);
+
+impl ContextDigest {
+    pub fn of(context: &[u8]) -> Self {
+        ContextDigest(Sha256::digest(context).into())
+    }
+
+    // reads to the end, in blocks
+    pub fn from_reader(mut reader: impl Read) -> Result<Self, Error> {
+        let mut hasher = ContextHasher::new();
+        io::copy(&mut reader, &mut hasher).map_err(|e| Error::Io(e.to_string()))?;
+        Ok(hasher.finish())
+    }
+}
+
+impl ContextHasher {
+    pub fn new() -> Self {
+        ContextHasher::default()
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    pub fn finish(self) -> ContextDigest {
+        ContextDigest(self.0.finalize().into())
+    }
+}
+
+impl Write for ContextHasher {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.update(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+    use crate::proof::Secret;
+
+    // a document that is never in memory at once
+    struct Zeros(usize);
+
+    impl Read for Zeros {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(self.0);
+            buf[..n].fill(0);
+            self.0 -= n;
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_streamed_digest_matches_the_bytes() {
+        let document = vec![0u8; 3 << 20];
+        let digest = ContextDigest::from_reader(Zeros(document.len())).unwrap();
+        assert_eq!(digest, ContextDigest::of(&document));
+
+        let mut hasher = ContextHasher::new();
+        for chunk in document.chunks(1000) {
+            hasher.update(chunk);
+        }
+        assert_eq!(hasher.finish(), digest);
+
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let secret = Secret::random(&zkp);
+        let public_key = zkp.public_key(&secret);
+        let proof = zkp.prove_compact_action_digest(&secret, &public_key, &digest);
+        assert!(zkp.verify_compact_action(&public_key, &proof, &document));
+        assert!(!zkp.verify_compact_action_digest(
+            &public_key,
+            &proof,
+            &ContextDigest::of(b"another file")
+        ));
+    }
+}
@@ -0,0 +1,103 @@
+use num_bigint::BigUint;
+
+use crate::ZKP;
+
+const CHALLENGE_LABEL: &[u8] = b"zkp-chaum-pedersen/ddh-tuple/v1";
+
+// Proof that (g, h, g^x, h^x) is a Diffie-Hellman tuple, in (c, s) form.
+// It's the Chaum-Pedersen statement with alpha = g, beta = h, y1 = g^x and
+// y2 = h^x, phrased the way most papers state it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DdhProof {
+    pub c: BigUint,
+    pub s: BigUint,
+}
+
+impl ZKP {
+    // Only p and q of `self` are used, the four group elements are explicit.
+    pub fn prove_ddh_tuple(
+        &self,
+        g: &BigUint,
+        h: &BigUint,
+        g_x: &BigUint,
+        h_x: &BigUint,
+        x: &BigUint,
+    ) -> DdhProof {
+        let k = ZKP::generate_random_nonzero_below(&self.q);
+        let a = self.pow(g, &k);
+        let b = self.pow(h, &k);
+
+        let c = self.hash_to_scalar(CHALLENGE_LABEL, &[g, h, g_x, h_x, &a, &b]);
+        let s = self.solve(&k, &c, x);
+
+        DdhProof { c, s }
+    }
+
+    // a = g^s * (g^x)^c, b = h^s * (h^x)^c, accept if H(g, h, g^x, h^x, a, b) = c
+    pub fn verify_ddh_tuple(
+        &self,
+        g: &BigUint,
+        h: &BigUint,
+        g_x: &BigUint,
+        h_x: &BigUint,
+        proof: &DdhProof,
+    ) -> bool {
+        if ![g, h, g_x, h_x].iter().all(|e| self.is_subgroup_element(e)) {
+            return false;
+        }
+        if proof.s >= self.q || proof.c >= self.q {
+            return false;
+        }
+
+        let a = self.pow2(g, &proof.s, g_x, &proof.c);
+        let b = self.pow2(h, &proof.s, h_x, &proof.c);
+
+        proof.c == self.hash_to_scalar(CHALLENGE_LABEL, &[g, h, g_x, h_x, &a, &b])
+    }
+
+    // 1 < e < p and e^q = 1 mod p
+    pub fn is_subgroup_element(&self, e: &BigUint) -> bool {
+        let one = BigUint::from(1u32);
+        *e > one && *e < self.p && self.pow(e, &self.q) == one
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+
+    #[test]
+    fn test_ddh_tuple() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let (g, h) = (&zkp.alpha, &zkp.beta);
+
+        let x = ZKP::generate_random_below(&zkp.q);
+        let g_x = ZKP::exponetiate(g, &x, &zkp.p);
+        let h_x = ZKP::exponetiate(h, &x, &zkp.p);
+
+        let proof = zkp.prove_ddh_tuple(g, h, &g_x, &h_x, &x);
+        assert!(zkp.verify_ddh_tuple(g, h, &g_x, &h_x, &proof));
+
+        // swapping the roles of the elements is a different statement
+        assert!(!zkp.verify_ddh_tuple(h, g, &h_x, &g_x, &proof));
+    }
+
+    #[test]
+    fn test_non_ddh_tuple_is_rejected() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let (g, h) = (&zkp.alpha, &zkp.beta);
+
+        let x = ZKP::generate_random_below(&zkp.q);
+        let g_x = ZKP::exponetiate(g, &x, &zkp.p);
+        let h_y = ZKP::exponetiate(h, &(&x + 1u32), &zkp.p);
+
+        let proof = zkp.prove_ddh_tuple(g, h, &g_x, &h_y, &x);
+        assert!(!zkp.verify_ddh_tuple(g, h, &g_x, &h_y, &proof));
+
+        // elements outside the subgroup
+        let one = BigUint::from(1u32);
+        let proof = zkp.prove_ddh_tuple(&one, &one, &one, &one, &x);
+        assert!(!zkp.verify_ddh_tuple(&one, &one, &one, &one, &proof));
+    }
+}
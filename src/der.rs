@@ -0,0 +1,262 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use num_bigint::BigUint;
+
+use crate::{Error, ZKP};
+
+// ASN.1 layout of the parameters. It follows PKCS#3 / X9.42 DH parameters
+// (p, g, q) and appends the second generator:
+//
+// ZkpParameters ::= SEQUENCE {
+//     prime         INTEGER, -- p
+//     base          INTEGER, -- alpha
+//     subgroupOrder INTEGER, -- q
+//     secondBase    INTEGER  -- beta
+// }
+pub const PEM_LABEL: &str = "ZKP PARAMETERS";
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_SEQUENCE: u8 = 0x30;
+
+impl ZKP {
+    pub fn to_der(&self) -> Vec<u8> {
+        let mut content = Vec::new();
+        for n in [&self.p, &self.alpha, &self.q, &self.beta] {
+            write_integer(&mut content, n);
+        }
+
+        let mut der = Vec::new();
+        write_tlv(&mut der, TAG_SEQUENCE, &content);
+        der
+    }
+
+    pub fn from_der(der: &[u8]) -> Result<ZKP, Error> {
+        let mut outer = DerReader::new(der);
+        let mut seq = outer.read_sequence()?;
+        outer.finish()?;
+
+        let p = seq.read_integer()?;
+        let alpha = seq.read_integer()?;
+        let q = seq.read_integer()?;
+        let beta = seq.read_integer()?;
+        seq.finish()?;
+
+        Ok(ZKP::new(p, q, alpha, beta))
+    }
+
+    pub fn to_pem(&self) -> String {
+        pem_encode(PEM_LABEL, &self.to_der())
+    }
+
+    pub fn from_pem(pem: &str) -> Result<ZKP, Error> {
+        ZKP::from_der(&pem_decode(pem, PEM_LABEL)?)
+    }
+}
+
+pub(crate) fn pem_encode(label: &str, der: &[u8]) -> String {
+    let body = STANDARD.encode(der);
+
+    let mut pem = format!("-----BEGIN {}-----\n", label);
+    for line in body.as_bytes().chunks(64) {
+        pem.push_str(std::str::from_utf8(line).unwrap());
+        pem.push('\n');
+    }
+    pem.push_str(&format!("-----END {}-----\n", label));
+    pem
+}
+
+// Takes the first block with the given label, anything around it is ignored.
+pub(crate) fn pem_decode(pem: &str, label: &str) -> Result<Vec<u8>, Error> {
+    let begin = format!("-----BEGIN {}-----", label);
+    let end = format!("-----END {}-----", label);
+
+    let start = pem
+        .find(&begin)
+        .ok_or_else(|| Error::InvalidEncoding(format!("missing \"{}\"", begin)))?
+        + begin.len();
+    let stop = pem[start..]
+        .find(&end)
+        .ok_or_else(|| Error::InvalidEncoding(format!("missing \"{}\"", end)))?
+        + start;
+
+    let body: String = pem[start..stop]
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    STANDARD
+        .decode(body)
+        .map_err(|e| Error::InvalidEncoding(format!("bad base64 in PEM: {}", e)))
+}
+
+pub(crate) fn write_integer(out: &mut Vec<u8>, n: &BigUint) {
+    let mut bytes = n.to_bytes_be();
+    // INTEGER is two's complement, keep positive values positive
+    if bytes[0] & 0x80 != 0 {
+        bytes.insert(0, 0);
+    }
+    write_tlv(out, TAG_INTEGER, &bytes);
+}
+
+pub(crate) fn write_tlv(out: &mut Vec<u8>, tag: u8, content: &[u8]) {
+    out.push(tag);
+
+    let len = content.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let len_bytes: Vec<u8> = len
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|b| *b == 0)
+            .collect();
+        out.push(0x80 | len_bytes.len() as u8);
+        out.extend_from_slice(&len_bytes);
+    }
+
+    out.extend_from_slice(content);
+}
+
+pub(crate) struct DerReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> DerReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        DerReader { data }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub(crate) fn finish(&self) -> Result<(), Error> {
+        if !self.is_empty() {
+            return Err(Error::InvalidEncoding(
+                "trailing data after DER value".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn read_sequence(&mut self) -> Result<DerReader<'a>, Error> {
+        Ok(DerReader::new(self.read_tlv(TAG_SEQUENCE)?))
+    }
+
+    pub(crate) fn read_integer(&mut self) -> Result<BigUint, Error> {
+        let content = self.read_tlv(TAG_INTEGER)?;
+
+        match content {
+            [] => Err(Error::InvalidEncoding("empty INTEGER".to_string())),
+            [b, ..] if b & 0x80 != 0 => Err(Error::InvalidEncoding("negative INTEGER".to_string())),
+            [0, b, ..] if b & 0x80 == 0 => {
+                Err(Error::InvalidEncoding("non-minimal INTEGER".to_string()))
+            }
+            _ => Ok(BigUint::from_bytes_be(content)),
+        }
+    }
+
+    fn read_tlv(&mut self, expected_tag: u8) -> Result<&'a [u8], Error> {
+        let truncated = || Error::InvalidEncoding("truncated DER value".to_string());
+
+        let (&tag, rest) = self.data.split_first().ok_or_else(truncated)?;
+        if tag != expected_tag {
+            return Err(Error::InvalidEncoding(format!(
+                "expected tag {:#04x}, found {:#04x}",
+                expected_tag, tag
+            )));
+        }
+
+        let (&first, mut rest) = rest.split_first().ok_or_else(truncated)?;
+        let len = if first < 0x80 {
+            first as usize
+        } else {
+            let n = (first & 0x7f) as usize;
+            if n == 0 || n > std::mem::size_of::<usize>() || rest.len() < n {
+                return Err(Error::InvalidEncoding("bad DER length".to_string()));
+            }
+            let (len_bytes, after) = rest.split_at(n);
+            rest = after;
+            if len_bytes[0] == 0 {
+                return Err(Error::InvalidEncoding("non-minimal DER length".to_string()));
+            }
+            let len = len_bytes
+                .iter()
+                .fold(0usize, |acc, b| (acc << 8) | *b as usize);
+            if len < 0x80 {
+                return Err(Error::InvalidEncoding("non-minimal DER length".to_string()));
+            }
+            len
+        };
+
+        if rest.len() < len {
+            return Err(truncated());
+        }
+        let (content, after) = rest.split_at(len);
+        self.data = after;
+        Ok(content)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+
+    #[test]
+    fn test_der_round_trip() {
+        for set in ParameterSet::ALL {
+            let zkp = set.zkp();
+            let decoded = ZKP::from_der(&zkp.to_der()).unwrap();
+
+            assert_eq!(decoded.p, zkp.p);
+            assert_eq!(decoded.q, zkp.q);
+            assert_eq!(decoded.alpha, zkp.alpha);
+            assert_eq!(decoded.beta, zkp.beta);
+        }
+    }
+
+    #[test]
+    fn test_small_values_encoding() {
+        let zkp = ZKP::new(
+            BigUint::from(23u32),
+            BigUint::from(11u32),
+            BigUint::from(4u32),
+            BigUint::from(0x89u32),
+        );
+
+        // 0x89 has the high bit set and needs a leading zero
+        assert_eq!(
+            zkp.to_der(),
+            vec![
+                0x30, 0x0d, 0x02, 0x01, 0x17, 0x02, 0x01, 0x04, 0x02, 0x01, 0x0b, 0x02, 0x02, 0x00,
+                0x89
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pem_round_trip() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let pem = zkp.to_pem();
+        assert!(pem.starts_with("-----BEGIN ZKP PARAMETERS-----\n"));
+
+        let decoded = ZKP::from_pem(&format!("pinned params\n{}", pem)).unwrap();
+        assert_eq!(decoded.to_der(), zkp.to_der());
+    }
+
+    #[test]
+    fn test_rejects_malformed_der() {
+        let der = ParameterSet::Rfc5114_1024_160.zkp().to_der();
+
+        let mut trailing = der.clone();
+        trailing.push(0);
+        assert!(ZKP::from_der(&trailing).is_err());
+        assert!(ZKP::from_der(&der[..der.len() - 1]).is_err());
+
+        // negative p
+        assert!(ZKP::from_der(&[
+            0x30, 0x0c, 0x02, 0x01, 0x97, 0x02, 0x01, 0x04, 0x02, 0x01, 0x0b, 0x02, 0x01, 0x09
+        ])
+        .is_err());
+        assert!(ZKP::from_pem("-----BEGIN DH PARAMETERS-----").is_err());
+    }
+}
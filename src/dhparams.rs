@@ -0,0 +1,245 @@
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+use crate::der::{pem_decode, DerReader};
+use crate::encoding::to_bytes_be_padded;
+use crate::prime::is_probable_prime;
+use crate::{Error, ZKP};
+
+const PKCS3_PEM_LABEL: &str = "DH PARAMETERS";
+const X942_PEM_LABEL: &str = "X9.42 DH PARAMETERS";
+
+const MIN_P_BITS: u64 = 1024;
+const MIN_Q_BITS: u64 = 160;
+const PRIMALITY_ROUNDS: usize = 40;
+
+// Domain label used to derive beta from (p, q, alpha).
+const BETA_LABEL: &[u8] = b"zkp-chaum-pedersen/openssl-dhparams/beta";
+
+impl ZKP {
+    // Accepts the output of `openssl dhparam` (PKCS#3, safe prime, q is
+    // derived as (p - 1) / 2) or `openssl genpkey -genparam -algorithm DHX`
+    // (X9.42, q is given). alpha is the provided generator projected onto the
+    // order q subgroup and beta is derived by hashing, so nobody knows
+    // log_alpha(beta).
+    pub fn from_openssl_dhparams(pem: &str) -> Result<ZKP, Error> {
+        let one = BigUint::from(1u32);
+
+        let (p, g, q) = if pem.contains(X942_PEM_LABEL) {
+            let der = pem_decode(pem, X942_PEM_LABEL)?;
+            let mut outer = DerReader::new(&der);
+            let mut seq = outer.read_sequence()?;
+            outer.finish()?;
+            // j and validationParms may follow, they are not needed here
+            (
+                seq.read_integer()?,
+                seq.read_integer()?,
+                seq.read_integer()?,
+            )
+        } else {
+            let der = pem_decode(pem, PKCS3_PEM_LABEL)?;
+            let mut outer = DerReader::new(&der);
+            let mut seq = outer.read_sequence()?;
+            outer.finish()?;
+            let p = seq.read_integer()?;
+            let g = seq.read_integer()?;
+            // privateValueLength is optional and meaningless for us
+            if !seq.is_empty() {
+                seq.read_integer()?;
+            }
+            seq.finish()?;
+            let q = (&p - &one) >> 1;
+            (p, g, q)
+        };
+
+        if p.bits() < MIN_P_BITS {
+            return Err(Error::InvalidParameters(format!(
+                "p has {} bits, at least {} are required",
+                p.bits(),
+                MIN_P_BITS
+            )));
+        }
+        if q.bits() < MIN_Q_BITS {
+            return Err(Error::InvalidParameters(format!(
+                "q has {} bits, at least {} are required",
+                q.bits(),
+                MIN_Q_BITS
+            )));
+        }
+        if !is_probable_prime(&p, PRIMALITY_ROUNDS) {
+            return Err(Error::InvalidParameters("p is not prime".to_string()));
+        }
+        if !is_probable_prime(&q, PRIMALITY_ROUNDS) {
+            // for PKCS#3 input this means p is not a safe prime
+            return Err(Error::InvalidParameters("q is not prime".to_string()));
+        }
+        let p_minus_one = &p - &one;
+        if (&p_minus_one % &q) != BigUint::from(0u32) {
+            return Err(Error::InvalidParameters(
+                "q does not divide p - 1".to_string(),
+            ));
+        }
+        if g <= one || g >= p_minus_one {
+            return Err(Error::InvalidParameters(
+                "generator out of range".to_string(),
+            ));
+        }
+
+        let cofactor = &p_minus_one / &q;
+        let alpha = g.modpow(&cofactor, &p);
+        if alpha == one {
+            return Err(Error::InvalidParameters(
+                "generator has no component in the order q subgroup".to_string(),
+            ));
+        }
+
+        Ok(ZKP::with_derived_beta(p, q, alpha, BETA_LABEL))
+    }
+}
+
+// Hashes (label, p, q, inputs, counter) to an integer mod p and raises it to
+// the cofactor, retrying with the next counter until it lands on an element
+// different from 1. The result has order q and no known discrete log.
+pub(crate) fn hash_to_subgroup(
+    p: &BigUint,
+    q: &BigUint,
+    label: &[u8],
+    inputs: &[&BigUint],
+) -> BigUint {
+    let one = BigUint::from(1u32);
+    let cofactor = (p - &one) / q;
+    // 128 extra bits make the bias of the reduction mod p negligible
+    let width = p.bits().div_ceil(8) as usize;
+    let wanted = width + 16;
+
+    for counter in 0u32.. {
+        let mut bytes = Vec::with_capacity(wanted + 32);
+        for block in 0u32.. {
+            if bytes.len() >= wanted {
+                break;
+            }
+            let mut hasher = Sha256::new();
+            hasher.update((label.len() as u32).to_be_bytes());
+            hasher.update(label);
+            for n in [p, q].into_iter().chain(inputs.iter().copied()) {
+                let n = to_bytes_be_padded(n, width);
+                hasher.update((n.len() as u32).to_be_bytes());
+                hasher.update(&n);
+            }
+            hasher.update(counter.to_be_bytes());
+            hasher.update(block.to_be_bytes());
+            bytes.extend_from_slice(&hasher.finalize());
+        }
+        bytes.truncate(wanted);
+
+        let h = BigUint::from_bytes_be(&bytes) % p;
+        let candidate = h.modpow(&cofactor, p);
+        if candidate > one {
+            return candidate;
+        }
+    }
+
+    unreachable!("the counter space can't be exhausted")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::der::{pem_encode, write_integer, write_tlv};
+    use crate::params::ParameterSet;
+
+    // openssl dhparam 1024
+    const OPENSSL_DHPARAM_1024: &str = "-----BEGIN DH PARAMETERS-----
+MIGHAoGBAP8lxdFGiux7+Jb3QffyhTWP6FRqEKU9PUTtcBjO/Ac1CdJnnwOMWB/2
+4AZtHoj0DAOUQugFUzbgil0mRd9BuCx03/nVriDF39G4Lku9zOOZvO8qnHj845IB
+Vp7RNuZjCIIFT4FQA52AiZpTcx+36RxmuO0Kazqps3SN6Uo6RLnnAgEC
+-----END DH PARAMETERS-----
+";
+
+    // openssl genpkey -genparam -algorithm DHX -pkeyopt dh_paramgen_prime_len:1024
+    //     -pkeyopt dh_paramgen_subprime_len:160
+    const OPENSSL_DHX_1024_160: &str = "-----BEGIN X9.42 DH PARAMETERS-----
+MIIBOgKBgQCGnKsp1mm5bMmrBf3ydXCGXRsgOu4pxprHnOFjgteDEtzXzky4wH+2
+zrzc/u+PkdF3Yg9UgX6E5WUY2RhpkKJX8UVt2aEXF5WjvjezlBCpVYng3on5gNHP
+dlrBsBPerWP4yQCA/nctuuUDml5QWseHSLYnWxXjStodiCcv1bq2KwKBgDpceKbv
+4bWfEWzty/vZhihZZ9efsrYWeuDAEUqqj44Jwkyal2WIxUFI+8fa3wm3TNL/gQTO
+dWtZeHBJH26fT2VZnl23BvLi5PPqnTFAVaDkA7hA0raGIk6B/QCSmKNjIdLSKzP+
+qS8ng2FAEMRiz9j2qqc0DzpL0M60QyTjktsYAhUA624wslvWNk0c8kvEops/kd5v
+0mcwGgMVAGySt04b051puQD8luO6kjv6agFjAgEB
+-----END X9.42 DH PARAMETERS-----
+";
+
+    fn pkcs3_pem(p: &BigUint, g: &BigUint) -> String {
+        let mut content = Vec::new();
+        write_integer(&mut content, p);
+        write_integer(&mut content, g);
+        let mut der = Vec::new();
+        write_tlv(&mut der, 0x30, &content);
+        pem_encode(PKCS3_PEM_LABEL, &der)
+    }
+
+    fn assert_usable(zkp: &ZKP) {
+        let one = BigUint::from(1u32);
+        assert_eq!(zkp.alpha.modpow(&zkp.q, &zkp.p), one);
+        assert_eq!(zkp.beta.modpow(&zkp.q, &zkp.p), one);
+        assert_ne!(zkp.alpha, zkp.beta);
+
+        let x = ZKP::generate_random_below(&zkp.q);
+        let k = ZKP::generate_random_below(&zkp.q);
+        let c = ZKP::generate_random_below(&zkp.q);
+        let y1 = ZKP::exponetiate(&zkp.alpha, &x, &zkp.p);
+        let y2 = ZKP::exponetiate(&zkp.beta, &x, &zkp.p);
+        let r1 = ZKP::exponetiate(&zkp.alpha, &k, &zkp.p);
+        let r2 = ZKP::exponetiate(&zkp.beta, &k, &zkp.p);
+        let s = zkp.solve(&k, &c, &x);
+        assert!(zkp.verify(&r1, &r2, &y1, &y2, &s, &c));
+    }
+
+    #[test]
+    fn test_openssl_dhparam_output() {
+        let zkp = ZKP::from_openssl_dhparams(OPENSSL_DHPARAM_1024).unwrap();
+        assert_eq!(zkp.q, (&zkp.p - 1u32) >> 1);
+        assert_usable(&zkp);
+
+        // derivation is deterministic
+        let again = ZKP::from_openssl_dhparams(OPENSSL_DHPARAM_1024).unwrap();
+        assert_eq!(again.beta, zkp.beta);
+    }
+
+    #[test]
+    fn test_openssl_x942_output() {
+        let zkp = ZKP::from_openssl_dhparams(OPENSSL_DHX_1024_160).unwrap();
+        assert_eq!(zkp.q.bits(), 160);
+        assert_usable(&zkp);
+    }
+
+    #[test]
+    fn test_rfc3526_group_as_pkcs3() {
+        let modp = ParameterSet::Rfc3526_2048.zkp();
+        let zkp = ZKP::from_openssl_dhparams(&pkcs3_pem(&modp.p, &BigUint::from(2u32))).unwrap();
+        assert_eq!(zkp.q, modp.q);
+        assert_eq!(zkp.alpha, modp.alpha);
+    }
+
+    #[test]
+    fn test_rejects_bad_parameters() {
+        let modp = ParameterSet::Rfc3526_2048.zkp();
+
+        // p is not prime
+        let pem = pkcs3_pem(&(&modp.p + 2u32), &BigUint::from(2u32));
+        assert!(ZKP::from_openssl_dhparams(&pem).is_err());
+
+        // RFC 5114 p is prime but not a safe prime
+        let rfc5114 = ParameterSet::Rfc5114_1024_160.zkp();
+        let pem = pkcs3_pem(&rfc5114.p, &rfc5114.alpha);
+        assert!(ZKP::from_openssl_dhparams(&pem).is_err());
+
+        // generator 1
+        let pem = pkcs3_pem(&modp.p, &BigUint::from(1u32));
+        assert!(ZKP::from_openssl_dhparams(&pem).is_err());
+
+        // too small
+        let pem = pkcs3_pem(&BigUint::from(23u32), &BigUint::from(2u32));
+        assert!(ZKP::from_openssl_dhparams(&pem).is_err());
+    }
+}
@@ -0,0 +1,187 @@
+use num_bigint::BigUint;
+
+use crate::modmath::{mod_inv_prime, mod_mul, mod_sub};
+use crate::proof::{Proof, Secret};
+use crate::ZKP;
+
+const ENCRYPTION_LABEL: &[u8] = b"zkp-chaum-pedersen/elgamal/encryption";
+const DECRYPTION_LABEL: &[u8] = b"zkp-chaum-pedersen/elgamal/decryption";
+
+// ElGamal over the order q subgroup generated by alpha:
+// h = alpha^x, c1 = alpha^r, c2 = m * h^r mod p
+// The plaintext m has to be an element of the subgroup, see `encode`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ciphertext {
+    pub c1: BigUint,
+    pub c2: BigUint,
+}
+
+impl ZKP {
+    pub fn elgamal_public_key(&self, secret: &Secret) -> BigUint {
+        self.pow(&self.alpha, secret.x())
+    }
+
+    // Exponential encoding m => alpha^m, only small values can be decoded
+    // back (see `decode`).
+    pub fn encode(&self, m: &BigUint) -> BigUint {
+        self.pow(&self.alpha, m)
+    }
+
+    // Brute force search of m < bound such that alpha^m = encoded.
+    pub fn decode(&self, encoded: &BigUint, bound: u64) -> Option<u64> {
+        let mut acc = BigUint::from(1u32);
+        for m in 0..bound {
+            if acc == *encoded {
+                return Some(m);
+            }
+            acc = mod_mul(&acc, &self.alpha, &self.p);
+        }
+        None
+    }
+
+    // output => (ciphertext, r), r is only needed to prove the encryption
+    pub fn encrypt(&self, h: &BigUint, m: &BigUint) -> (Ciphertext, Secret) {
+        let r = Secret::random(self);
+        (self.encrypt_with(h, m, &r), r)
+    }
+
+    pub fn encrypt_with(&self, h: &BigUint, m: &BigUint, r: &Secret) -> Ciphertext {
+        let c1 = self.pow(&self.alpha, r.x());
+        let c2 = mod_mul(m, &self.pow(h, r.x()), &self.p);
+
+        Ciphertext { c1, c2 }
+    }
+
+    // m = c2 * c1^-x mod p, c1 has order q so c1^-x = c1^(q - x)
+    pub fn decrypt(&self, secret: &Secret, ciphertext: &Ciphertext) -> BigUint {
+        let minus_x = mod_sub(&BigUint::from(0u32), secret.x(), &self.q);
+        let shared_inv = self.pow(&ciphertext.c1, &minus_x);
+
+        mod_mul(&ciphertext.c2, &shared_inv, &self.p)
+    }
+
+    // Proof, by whoever encrypted, that `ciphertext` encrypts m under h:
+    // log_alpha(c1) = log_h(c2 / m) = r
+    pub fn prove_encryption(
+        &self,
+        h: &BigUint,
+        m: &BigUint,
+        ciphertext: &Ciphertext,
+        r: &Secret,
+    ) -> Proof {
+        let group = self.with_beta(h);
+        group.prove_dleq(
+            ENCRYPTION_LABEL,
+            r.x(),
+            &ciphertext.c1,
+            &self.divide(&ciphertext.c2, m),
+        )
+    }
+
+    pub fn verify_encryption(
+        &self,
+        h: &BigUint,
+        m: &BigUint,
+        ciphertext: &Ciphertext,
+        proof: &Proof,
+    ) -> bool {
+        let group = self.with_beta(h);
+        group.verify_dleq(
+            ENCRYPTION_LABEL,
+            &ciphertext.c1,
+            &self.divide(&ciphertext.c2, m),
+            proof,
+        )
+    }
+
+    // Proof, by the key holder, that `ciphertext` decrypts to m:
+    // log_alpha(h) = log_c1(c2 / m) = x
+    pub fn prove_decryption(&self, secret: &Secret, m: &BigUint, ciphertext: &Ciphertext) -> Proof {
+        let group = self.with_beta(&ciphertext.c1);
+        group.prove_dleq(
+            DECRYPTION_LABEL,
+            secret.x(),
+            &self.elgamal_public_key(secret),
+            &self.divide(&ciphertext.c2, m),
+        )
+    }
+
+    pub fn verify_decryption(
+        &self,
+        h: &BigUint,
+        m: &BigUint,
+        ciphertext: &Ciphertext,
+        proof: &Proof,
+    ) -> bool {
+        let group = self.with_beta(&ciphertext.c1);
+        group.verify_dleq(DECRYPTION_LABEL, h, &self.divide(&ciphertext.c2, m), proof)
+    }
+
+    // a / b mod p
+    pub(crate) fn divide(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        mod_mul(a, &mod_inv_prime(b, &self.p), &self.p)
+    }
+
+    pub(crate) fn with_beta(&self, beta: &BigUint) -> ZKP {
+        ZKP::new(
+            self.p.clone(),
+            self.q.clone(),
+            self.alpha.clone(),
+            beta.clone(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let secret = Secret::random(&zkp);
+        let h = zkp.elgamal_public_key(&secret);
+
+        let m = zkp.encode(&BigUint::from(42u32));
+        let (ciphertext, _) = zkp.encrypt(&h, &m);
+        let decrypted = zkp.decrypt(&secret, &ciphertext);
+
+        assert_eq!(decrypted, m);
+        assert_eq!(zkp.decode(&decrypted, 100), Some(42));
+    }
+
+    #[test]
+    fn test_encryption_proof() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let h = zkp.elgamal_public_key(&Secret::random(&zkp));
+
+        let m = zkp.encode(&BigUint::from(1u32));
+        let (ciphertext, r) = zkp.encrypt(&h, &m);
+        let proof = zkp.prove_encryption(&h, &m, &ciphertext, &r);
+        assert!(zkp.verify_encryption(&h, &m, &ciphertext, &proof));
+
+        // claims about another plaintext or key are rejected
+        let other_m = zkp.encode(&BigUint::from(0u32));
+        assert!(!zkp.verify_encryption(&h, &other_m, &ciphertext, &proof));
+        let other_h = zkp.elgamal_public_key(&Secret::random(&zkp));
+        assert!(!zkp.verify_encryption(&other_h, &m, &ciphertext, &proof));
+
+        let proof = zkp.prove_encryption(&h, &other_m, &ciphertext, &r);
+        assert!(!zkp.verify_encryption(&h, &other_m, &ciphertext, &proof));
+    }
+
+    #[test]
+    fn test_decryption_proof() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let secret = Secret::random(&zkp);
+        let h = zkp.elgamal_public_key(&secret);
+
+        let m = zkp.encode(&BigUint::from(7u32));
+        let (ciphertext, _) = zkp.encrypt(&h, &m);
+        let proof = zkp.prove_decryption(&secret, &m, &ciphertext);
+
+        assert!(zkp.verify_decryption(&h, &m, &ciphertext, &proof));
+        assert!(!zkp.verify_decryption(&h, &zkp.encode(&BigUint::from(8u32)), &ciphertext, &proof));
+    }
+}
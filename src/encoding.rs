@@ -0,0 +1,108 @@
+use num_bigint::BigUint;
+
+use crate::{Error, ZKP};
+
+// Fixed-width big-endian encoding of protocol values. Group elements (y1, y2,
+// r1, r2, ...) take the byte length of p and scalars (c, s, x) the byte
+// length of q, so the same value always has the same bytes regardless of
+// leading zeros.
+
+// Left pads n with zeros to len bytes. Values wider than len are written
+// as is, they can only come from a peer and fail the range checks anyway.
+pub fn to_bytes_be_padded(n: &BigUint, len: usize) -> Vec<u8> {
+    let bytes = n.to_bytes_be();
+    if bytes.len() >= len {
+        return bytes;
+    }
+
+    let mut padded = vec![0u8; len - bytes.len()];
+    padded.extend_from_slice(&bytes);
+    padded
+}
+
+pub fn from_bytes_be_exact(bytes: &[u8], len: usize) -> Result<BigUint, Error> {
+    if bytes.len() != len {
+        return Err(Error::InvalidEncoding(format!(
+            "expected {} bytes, got {}",
+            len,
+            bytes.len()
+        )));
+    }
+    Ok(BigUint::from_bytes_be(bytes))
+}
+
+impl ZKP {
+    pub fn element_len(&self) -> usize {
+        self.p.bits().div_ceil(8) as usize
+    }
+
+    pub fn scalar_len(&self) -> usize {
+        self.q.bits().div_ceil(8) as usize
+    }
+
+    pub fn element_to_bytes(&self, e: &BigUint) -> Vec<u8> {
+        to_bytes_be_padded(e, self.element_len())
+    }
+
+    pub fn scalar_to_bytes(&self, n: &BigUint) -> Vec<u8> {
+        to_bytes_be_padded(n, self.scalar_len())
+    }
+
+    // exactly element_len bytes and below p
+    pub fn element_from_bytes(&self, bytes: &[u8]) -> Result<BigUint, Error> {
+        let e = from_bytes_be_exact(bytes, self.element_len())?;
+        if e >= self.p {
+            return Err(Error::InvalidEncoding("element is not below p".to_string()));
+        }
+        Ok(e)
+    }
+
+    // exactly scalar_len bytes and below q
+    pub fn scalar_from_bytes(&self, bytes: &[u8]) -> Result<BigUint, Error> {
+        let n = from_bytes_be_exact(bytes, self.scalar_len())?;
+        if n >= self.q {
+            return Err(Error::InvalidEncoding("scalar is not below q".to_string()));
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+
+    #[test]
+    fn test_padding() {
+        let n = BigUint::from(0x0102u32);
+        assert_eq!(to_bytes_be_padded(&n, 4), vec![0, 0, 1, 2]);
+        assert_eq!(to_bytes_be_padded(&n, 1), vec![1, 2]);
+        assert_eq!(to_bytes_be_padded(&BigUint::from(0u32), 2), vec![0, 0]);
+
+        assert_eq!(from_bytes_be_exact(&[0, 0, 1, 2], 4), Ok(n));
+        assert!(from_bytes_be_exact(&[1, 2], 4).is_err());
+    }
+
+    #[test]
+    fn test_elements_and_scalars() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        assert_eq!(zkp.element_len(), 128);
+        assert_eq!(zkp.scalar_len(), 20);
+
+        let small = BigUint::from(5u32);
+        let bytes = zkp.element_to_bytes(&small);
+        assert_eq!(bytes.len(), 128);
+        assert_eq!(zkp.element_from_bytes(&bytes), Ok(small.clone()));
+        assert_eq!(
+            zkp.scalar_from_bytes(&zkp.scalar_to_bytes(&small)),
+            Ok(small)
+        );
+
+        // minimal encodings and values out of range are rejected
+        assert!(zkp.element_from_bytes(&[5]).is_err());
+        assert!(zkp
+            .element_from_bytes(&zkp.element_to_bytes(&zkp.p))
+            .is_err());
+        assert!(zkp.scalar_from_bytes(&zkp.scalar_to_bytes(&zkp.q)).is_err());
+    }
+}
@@ -0,0 +1,100 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    UserNotFound(String),
+    ChallengeNotFound(String),
+    InvalidProof,
+    UnknownParameterSet(String),
+    InvalidEncoding(String),
+    InvalidParameters(String),
+    NoPendingCommitment,
+    Transport(String),
+    InvalidPublicKey,
+    ReplayedCommitment,
+    WrongPassphrase,
+    Io(String),
+    UnsupportedVersion(u32),
+    ActionMismatch,
+    RateLimited,
+    Expired,
+    UserDisabled(String),
+    RotationRequired(String),
+    UnknownRecoveryKey(String),
+    Declined,
+    StepUpRequired(String),
+    SessionNotFound,
+    AlreadyRegistered(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UserNotFound(user) => write!(f, "user {} not found", user),
+            Error::ChallengeNotFound(auth_id) => write!(f, "challenge {} not found", auth_id),
+            Error::InvalidProof => write!(f, "bad solution to the challenge"),
+            Error::UnknownParameterSet(id) => write!(f, "unknown parameter set {}", id),
+            Error::InvalidEncoding(reason) => write!(f, "invalid encoding: {}", reason),
+            Error::InvalidParameters(reason) => write!(f, "invalid parameters: {}", reason),
+            Error::NoPendingCommitment => write!(f, "no commitment waiting for a challenge"),
+            Error::Transport(reason) => write!(f, "transport error: {}", reason),
+            Error::InvalidPublicKey => write!(f, "public key is not in the order q subgroup"),
+            Error::ReplayedCommitment => write!(f, "commitment was already used"),
+            Error::WrongPassphrase => write!(f, "wrong passphrase or corrupted keystore"),
+            Error::Io(reason) => write!(f, "i/o error: {}", reason),
+            Error::UnsupportedVersion(version) => {
+                write!(f, "unsupported protocol version {}", version)
+            }
+            Error::ActionMismatch => write!(f, "proof was made for another action"),
+            Error::RateLimited => write!(f, "too many challenges, try again later"),
+            Error::Expired => write!(f, "proof is too old or from the future"),
+            Error::UserDisabled(user) => write!(f, "user {} is disabled", user),
+            Error::RotationRequired(user) => {
+                write!(f, "user {} has to rotate their key first", user)
+            }
+            Error::UnknownRecoveryKey(user) => {
+                write!(f, "no such recovery key enrolled for user {}", user)
+            }
+            Error::Declined => write!(f, "the prover declined to answer"),
+            Error::StepUpRequired(operation) => {
+                write!(f, "operation {} needs a stronger proof", operation)
+            }
+            Error::SessionNotFound => write!(f, "no such session, or it expired"),
+            Error::AlreadyRegistered(user) => write!(f, "user {} is already registered", user),
+        }
+    }
+}
+
+impl Error {
+    // Stable identifier for scripts and other machine readers, the Display
+    // text may change.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::UserNotFound(_) => "user_not_found",
+            Error::ChallengeNotFound(_) => "challenge_not_found",
+            Error::InvalidProof => "invalid_proof",
+            Error::UnknownParameterSet(_) => "unknown_parameter_set",
+            Error::InvalidEncoding(_) => "invalid_encoding",
+            Error::InvalidParameters(_) => "invalid_parameters",
+            Error::NoPendingCommitment => "no_pending_commitment",
+            Error::Transport(_) => "transport",
+            Error::InvalidPublicKey => "invalid_public_key",
+            Error::ReplayedCommitment => "replayed_commitment",
+            Error::WrongPassphrase => "wrong_passphrase",
+            Error::Io(_) => "io",
+            Error::UnsupportedVersion(_) => "unsupported_version",
+            Error::ActionMismatch => "action_mismatch",
+            Error::RateLimited => "rate_limited",
+            Error::Expired => "expired",
+            Error::UserDisabled(_) => "user_disabled",
+            Error::RotationRequired(_) => "rotation_required",
+            Error::UnknownRecoveryKey(_) => "unknown_recovery_key",
+            Error::Declined => "declined",
+            Error::StepUpRequired(_) => "step_up_required",
+            Error::SessionNotFound => "session_not_found",
+            Error::AlreadyRegistered(_) => "already_registered",
+        }
+    }
+}
+
+impl std::error::Error for Error {}
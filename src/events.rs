@@ -0,0 +1,51 @@
+use num_bigint::BigUint;
+
+use crate::Error;
+
+// Progress of the rounds a `Prover` drives (`login`, `authenticate`,
+// `rotate`), for host applications that show what is going on or ask the
+// user before answering: a mobile wrapper, a hardware wallet screen, a log.
+// Rounds run with `commit` and `respond` by hand don't report anything.
+//
+//     on_commit              (r1, r2) is made, before it is sent
+//     on_challenge_received  c came back; an error here declines the round
+//                            and the nonce is never used
+//     on_response_sent       s is computed and handed to the transport
+//     on_result              the round ended, however it ended
+//
+// Every method does nothing by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Round {
+    Login,
+    Rotation,
+}
+
+pub trait ProverEvents {
+    fn on_commit(&mut self, _user_name: &str, _round: Round, _r1: &BigUint, _r2: &BigUint) {}
+
+    fn on_challenge_received(
+        &mut self,
+        _user_name: &str,
+        _round: Round,
+        _auth_id: &str,
+        _c: &BigUint,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn on_response_sent(&mut self, _user_name: &str, _round: Round, _auth_id: &str) {}
+
+    fn on_result(&mut self, _user_name: &str, _round: Round, _result: Result<(), &Error>) {}
+}
+
+// the events of a prover without hooks
+impl ProverEvents for () {}
+
+impl Round {
+    pub fn id(&self) -> &'static str {
+        match self {
+            Round::Login => "login",
+            Round::Rotation => "rotation",
+        }
+    }
+}
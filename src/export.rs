@@ -0,0 +1,204 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+use crate::context::ContextDigest;
+use crate::params::ParameterSet;
+use crate::proof::{CompactProof, PublicKey, Secret};
+use crate::{Error, ZKP};
+
+// Offline authentication: a phone shows a proof as a QR code, an air-gapped
+// verifier scans it. The proof is compact (only c and s) and bound to a
+// context, the nonce or action the verifier displayed, so a scanned code
+// can't be replayed for something else.
+//
+//     "ZKP1:" base64url(version u8 = 2 | id length u8 | parameter set id |
+//                       c | s | context)
+//
+// c and s take the byte length of q each, so the size depends on the group:
+// about 80 characters plus the context on rfc5114-1024-160, 700 on the
+// RFC 3526 groups (still within a QR code, but a dense one).
+//
+// Version 1 hashed the challenge without the group. Codes live for one scan,
+// so it isn't read any more rather than kept around as a forgery risk.
+const PREFIX: &str = "ZKP1:";
+const VERSION: u8 = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactExport {
+    pub parameter_set: ParameterSet,
+    pub proof: CompactProof,
+    pub context: Vec<u8>,
+}
+
+impl CompactExport {
+    pub fn prove(
+        parameter_set: ParameterSet,
+        secret: &Secret,
+        public_key: &PublicKey,
+        context: &[u8],
+    ) -> Self {
+        let zkp = parameter_set.zkp();
+        CompactExport {
+            parameter_set,
+            proof: zkp.prove_compact_action(secret, public_key, context),
+            context: context.to_vec(),
+        }
+    }
+
+    pub fn export_compact(&self) -> String {
+        let zkp = self.parameter_set.zkp();
+        let id = self.parameter_set.id().as_bytes();
+
+        let mut bytes = vec![VERSION, id.len() as u8];
+        bytes.extend_from_slice(id);
+        bytes.extend_from_slice(&zkp.scalar_to_bytes(&self.proof.c));
+        bytes.extend_from_slice(&zkp.scalar_to_bytes(&self.proof.s));
+        bytes.extend_from_slice(&self.context);
+
+        format!("{}{}", PREFIX, URL_SAFE_NO_PAD.encode(bytes))
+    }
+
+    pub fn import_compact(text: &str) -> Result<Self, Error> {
+        let encoded = text
+            .trim()
+            .strip_prefix(PREFIX)
+            .ok_or_else(|| Error::InvalidEncoding("not a compact proof".to_string()))?;
+        let bytes = URL_SAFE_NO_PAD
+            .decode(encoded)
+            .map_err(|e| Error::InvalidEncoding(format!("bad base64url: {}", e)))?;
+
+        let truncated = || Error::InvalidEncoding("truncated compact proof".to_string());
+        let (&version, rest) = bytes.split_first().ok_or_else(truncated)?;
+        if version != VERSION {
+            return Err(Error::InvalidEncoding(format!(
+                "unknown compact proof version {}",
+                version
+            )));
+        }
+        let (&id_len, rest) = rest.split_first().ok_or_else(truncated)?;
+        if rest.len() < id_len as usize {
+            return Err(truncated());
+        }
+        let (id, rest) = rest.split_at(id_len as usize);
+        let parameter_set: ParameterSet = std::str::from_utf8(id)
+            .map_err(|_| Error::InvalidEncoding("parameter set id is not utf-8".to_string()))?
+            .parse()?;
+
+        let zkp = parameter_set.zkp();
+        let scalar_len = zkp.scalar_len();
+        if rest.len() < 2 * scalar_len {
+            return Err(truncated());
+        }
+        let (c, rest) = rest.split_at(scalar_len);
+        let (s, context) = rest.split_at(scalar_len);
+
+        Ok(CompactExport {
+            parameter_set,
+            proof: CompactProof {
+                c: zkp.scalar_from_bytes(c)?,
+                s: zkp.scalar_from_bytes(s)?,
+            },
+            context: context.to_vec(),
+        })
+    }
+
+    // The caller still has to check the context is the one it asked for.
+    pub fn verify(&self, public_key: &PublicKey) -> bool {
+        self.parameter_set
+            .zkp()
+            .verify_compact_action(public_key, &self.proof, &self.context)
+    }
+}
+
+impl ZKP {
+    // Non-interactive proof with c = `action_challenge(context)`, see
+    // `Verifier::create_action_challenge` for the interactive one.
+    pub fn prove_compact_action(
+        &self,
+        secret: &Secret,
+        public_key: &PublicKey,
+        context: &[u8],
+    ) -> CompactProof {
+        self.prove_compact_action_digest(secret, public_key, &ContextDigest::of(context))
+    }
+
+    pub fn prove_compact_action_digest(
+        &self,
+        secret: &Secret,
+        public_key: &PublicKey,
+        digest: &ContextDigest,
+    ) -> CompactProof {
+        let k = ZKP::generate_random_nonzero_below(&self.q);
+        let r1 = self.pow(&self.alpha, &k);
+        let r2 = self.pow(&self.beta, &k);
+        let c = self.action_challenge_digest(public_key, digest, &r1, &r2);
+
+        CompactProof {
+            s: self.solve(&k, &c, secret.x()),
+            c,
+        }
+    }
+
+    pub fn verify_compact_action(
+        &self,
+        public_key: &PublicKey,
+        proof: &CompactProof,
+        context: &[u8],
+    ) -> bool {
+        self.verify_compact_action_digest(public_key, proof, &ContextDigest::of(context))
+    }
+
+    pub fn verify_compact_action_digest(
+        &self,
+        public_key: &PublicKey,
+        proof: &CompactProof,
+        digest: &ContextDigest,
+    ) -> bool {
+        self.expand_checked(public_key, proof)
+            .is_some_and(|expanded| {
+                proof.c
+                    == self.action_challenge_digest(public_key, digest, &expanded.r1, &expanded.r2)
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_export_roundtrip() {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let zkp = set.zkp();
+        let secret = Secret::random(&zkp);
+        let public_key = zkp.public_key(&secret);
+
+        let export = CompactExport::prove(set, &secret, &public_key, b"nonce=8f2c41");
+        let text = export.export_compact();
+        assert!(text.len() < 120, "{} characters", text.len());
+        assert!(text
+            .chars()
+            .skip(PREFIX.len())
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_'));
+
+        let imported = CompactExport::import_compact(&text).unwrap();
+        assert_eq!(imported, export);
+        assert!(imported.verify(&public_key));
+        assert!(!imported.verify(&zkp.public_key(&Secret::random(&zkp))));
+    }
+
+    #[test]
+    fn test_context_is_bound() {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let zkp = set.zkp();
+        let secret = Secret::random(&zkp);
+        let public_key = zkp.public_key(&secret);
+
+        let mut export = CompactExport::prove(set, &secret, &public_key, b"nonce=1");
+        export.context = b"nonce=2".to_vec();
+        let imported = CompactExport::import_compact(&export.export_compact()).unwrap();
+        assert!(!imported.verify(&public_key));
+
+        assert!(CompactExport::import_compact("ZKP1:AQ").is_err());
+        assert!(CompactExport::import_compact("hello").is_err());
+    }
+}
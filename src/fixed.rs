@@ -0,0 +1,333 @@
+use crypto_bigint::modular::runtime_mod::{DynResidue, DynResidueParams};
+use crypto_bigint::rand_core::CryptoRngCore;
+use crypto_bigint::{Integer, Limb, NonZero, RandomMod, Uint, Word, U1024, U192, U2048};
+use sha2::{Digest, Sha256};
+
+use crate::constants::{
+    CHALLENGE_LABEL, MODP_2048_P, RFC5114_1024_ALPHA, RFC5114_1024_BETA_EXPONENT, RFC5114_1024_P,
+    RFC5114_1024_Q,
+};
+
+// The protocol with every value a fixed-width `Uint` sized at compile time,
+// P limbs for elements mod p and Q limbs for scalars mod q, for provers on
+// microcontrollers: with default-features = false it builds without std,
+// nothing in here allocates, the groups are built from constants and the
+// randomness comes from the caller's RNG (the device's TRNG). The challenge
+// is the one of `ZKP::challenge`, byte for byte, so proofs made here verify
+// against the regular verifier and the other way round.
+//
+// rfc3526-2048 takes 3 KiB for the group, 512 bytes per public key and 768
+// per proof, rfc5114-1024-160 1 KiB, 256 and 280; add crypto-bigint's stack
+// use during pow.
+#[derive(Debug, Clone, Copy)]
+pub struct FixedGroup<const P: usize, const Q: usize> {
+    pub p: Uint<P>,
+    pub q: Uint<Q>,
+    pub alpha: Uint<P>,
+    pub beta: Uint<P>,
+    p_params: DynResidueParams<P>,
+    q_params: DynResidueParams<Q>,
+}
+
+// rfc5114-1024-160, q in the next size up
+pub type Rfc5114Group = FixedGroup<{ U1024::LIMBS }, { U192::LIMBS }>;
+// rfc3526-2048, q = (p - 1) / 2 as wide as p
+pub type Rfc3526_2048Group = FixedGroup<{ U2048::LIMBS }, { U2048::LIMBS }>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedPublicKey<const P: usize> {
+    pub y1: Uint<P>,
+    pub y2: Uint<P>,
+}
+
+// Non-interactive proof (r1, r2, s) as in `Proof`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedProof<const P: usize, const Q: usize> {
+    pub r1: Uint<P>,
+    pub r2: Uint<P>,
+    pub s: Uint<Q>,
+}
+
+impl Rfc5114Group {
+    pub fn rfc5114_1024_160() -> Self {
+        let p = from_hex(RFC5114_1024_P);
+        let alpha = from_hex(RFC5114_1024_ALPHA);
+        let exponent: Uint<{ U1024::LIMBS }> = from_hex(RFC5114_1024_BETA_EXPONENT);
+        let beta = DynResidue::new(&alpha, DynResidueParams::new(&p))
+            .pow(&exponent)
+            .retrieve();
+        FixedGroup::new(p, from_hex(RFC5114_1024_Q), alpha, beta).expect("valid constants")
+    }
+}
+
+impl Rfc3526_2048Group {
+    pub fn rfc3526_2048() -> Self {
+        let p: U2048 = from_hex(MODP_2048_P);
+        let q = p.wrapping_sub(&U2048::ONE).shr_vartime(1);
+        FixedGroup::new(p, q, U2048::from_u8(4), U2048::from_u8(9)).expect("valid constants")
+    }
+}
+
+impl<const P: usize, const Q: usize> FixedGroup<P, Q> {
+    // None unless p and q are odd, q is no wider than p, alpha and beta are
+    // in (1, p) and p takes up all P limbs: the challenge pads every value to
+    // the byte length of p, which has to be the width of `Uint<P>`.
+    pub fn new(p: Uint<P>, q: Uint<Q>, alpha: Uint<P>, beta: Uint<P>) -> Option<Self> {
+        let bytes = P * Limb::BYTES;
+        let usable = Q <= P
+            && bytes >= 32
+            && p.bits() > (bytes - 1) * 8
+            && bool::from(p.is_odd())
+            && bool::from(q.is_odd())
+            && q > Uint::ONE
+            && [alpha, beta].iter().all(|e| *e > Uint::ONE && *e < p);
+        if !usable {
+            return None;
+        }
+        Some(FixedGroup {
+            p,
+            q,
+            alpha,
+            beta,
+            p_params: DynResidueParams::new(&p),
+            q_params: DynResidueParams::new(&q),
+        })
+    }
+
+    // x in [1, q)
+    pub fn public_key(&self, x: &Uint<Q>) -> FixedPublicKey<P> {
+        FixedPublicKey {
+            y1: self.pow(&self.alpha, x),
+            y2: self.pow(&self.beta, x),
+        }
+    }
+
+    pub fn random_nonzero_scalar(&self, rng: &mut impl CryptoRngCore) -> Uint<Q> {
+        let modulus = NonZero::new(self.q).unwrap();
+        loop {
+            let n = Uint::random_mod(rng, &modulus);
+            if n != Uint::ZERO {
+                return n;
+            }
+        }
+    }
+
+    pub fn prove(
+        &self,
+        x: &Uint<Q>,
+        public_key: &FixedPublicKey<P>,
+        rng: &mut impl CryptoRngCore,
+    ) -> FixedProof<P, Q> {
+        let k = self.random_nonzero_scalar(rng);
+        let r1 = self.pow(&self.alpha, &k);
+        let r2 = self.pow(&self.beta, &k);
+        let c = self.challenge(public_key, &r1, &r2);
+        let scalar = |n: &Uint<Q>| DynResidue::new(n, self.q_params);
+        FixedProof {
+            r1,
+            r2,
+            s: (scalar(&k) - scalar(&c) * scalar(x)).retrieve(),
+        }
+    }
+
+    // `ZKP::verify` with the Fiat-Shamir challenge, same range checks, and
+    // y^q = 1 for the key as in `ZKP::is_subgroup_key`
+    pub fn verify(&self, public_key: &FixedPublicKey<P>, proof: &FixedProof<P, Q>) -> bool {
+        let in_range = |e: &Uint<P>| *e > Uint::ONE && *e < self.p;
+        let elements = [&proof.r1, &proof.r2, &public_key.y1, &public_key.y2];
+        if proof.s >= self.q || !elements.into_iter().all(in_range) {
+            return false;
+        }
+        let in_subgroup = |y: &Uint<P>| self.pow(y, &self.q) == Uint::ONE;
+        if !in_subgroup(&public_key.y1) || !in_subgroup(&public_key.y2) {
+            return false;
+        }
+
+        let c = self.challenge(public_key, &proof.r1, &proof.r2);
+        let check = |base: &Uint<P>, y: &Uint<P>| {
+            let element = |n: &Uint<P>| DynResidue::new(n, self.p_params);
+            (element(base).pow(&proof.s) * element(y).pow(&c)).retrieve()
+        };
+        check(&self.alpha, &public_key.y1) == proof.r1
+            && check(&self.beta, &public_key.y2) == proof.r2
+    }
+
+    // H(label, p, q, alpha, beta, y1, y2, r1, r2) mod q with the encoding of
+    // `ZKP::hash_to_scalar`: each value length-prefixed and padded to the
+    // byte length of p. Streamed into the hasher, no buffer needed.
+    pub fn challenge(&self, public_key: &FixedPublicKey<P>, r1: &Uint<P>, r2: &Uint<P>) -> Uint<Q> {
+        let len = P * Limb::BYTES;
+        let mut hasher = Sha256::new();
+        hasher.update(CHALLENGE_LABEL);
+        update_padded(&mut hasher, &self.p, len);
+        update_padded(&mut hasher, &self.q, len);
+        for e in [
+            &self.alpha,
+            &self.beta,
+            &public_key.y1,
+            &public_key.y2,
+            r1,
+            r2,
+        ] {
+            update_padded(&mut hasher, e, len);
+        }
+
+        // the digest is wider than a 160-bit q, so reduce it at the width of p
+        let digest: Uint<P> = from_be_bytes(&hasher.finalize());
+        let q = NonZero::new(self.q.resize::<P>()).unwrap();
+        digest.rem(&q).resize()
+    }
+
+    fn pow(&self, base: &Uint<P>, exponent: &Uint<Q>) -> Uint<P> {
+        DynResidue::new(base, self.p_params)
+            .pow(exponent)
+            .retrieve()
+    }
+}
+
+fn update_padded<const L: usize>(hasher: &mut Sha256, n: &Uint<L>, len: usize) {
+    hasher.update((len as u32).to_be_bytes());
+    for _ in L * Limb::BYTES..len {
+        hasher.update([0u8]);
+    }
+    for word in n.as_words().iter().rev() {
+        hasher.update(word.to_be_bytes());
+    }
+}
+
+// big-endian bytes into the low end of a Uint, at most L words of them
+fn from_be_bytes<const L: usize>(bytes: &[u8]) -> Uint<L> {
+    let mut words = [0 as Word; L];
+    for (i, byte) in bytes.iter().rev().enumerate() {
+        words[i / Limb::BYTES] |= (*byte as Word) << (8 * (i % Limb::BYTES));
+    }
+    Uint::from_words(words)
+}
+
+// the parameter constants, any length up to L words
+fn from_hex<const L: usize>(hex: &str) -> Uint<L> {
+    let mut words = [0 as Word; L];
+    for (i, digit) in hex.chars().rev().enumerate() {
+        let nibble = digit.to_digit(16).expect("hex constant") as Word;
+        words[i / (2 * Limb::BYTES)] |= nibble << (4 * (i % (2 * Limb::BYTES)));
+    }
+    Uint::from_words(words)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+    use crate::proof::{Proof, PublicKey};
+    use num_bigint::BigUint;
+
+    fn int<const L: usize>(n: &Uint<L>) -> BigUint {
+        let bytes: Vec<u8> = n
+            .as_words()
+            .iter()
+            .rev()
+            .flat_map(|word| word.to_be_bytes())
+            .collect();
+        BigUint::from_bytes_be(&bytes)
+    }
+
+    fn uint<const L: usize>(n: &BigUint) -> Uint<L> {
+        from_be_bytes(&n.to_bytes_be())
+    }
+
+    fn interoperates<const P: usize, const Q: usize>(group: FixedGroup<P, Q>, set: ParameterSet) {
+        let zkp = set.zkp();
+        assert_eq!(
+            (
+                int(&group.p),
+                int(&group.q),
+                int(&group.alpha),
+                int(&group.beta)
+            ),
+            (
+                zkp.p.clone(),
+                zkp.q.clone(),
+                zkp.alpha.clone(),
+                zkp.beta.clone()
+            )
+        );
+
+        let mut rng = rand::thread_rng();
+        let x = group.random_nonzero_scalar(&mut rng);
+        let fixed_key = group.public_key(&x);
+        let public_key = PublicKey {
+            y1: int(&fixed_key.y1),
+            y2: int(&fixed_key.y2),
+        };
+
+        // made here, checked by the regular verifier
+        let proof = group.prove(&x, &fixed_key, &mut rng);
+        assert!(group.verify(&fixed_key, &proof));
+        let regular = Proof {
+            r1: int(&proof.r1),
+            r2: int(&proof.r2),
+            s: int(&proof.s),
+        };
+        assert!(zkp.verify_proof_without_expiry(&public_key, regular));
+
+        // and the other way round
+        let secret = crate::proof::Secret::new(int(&x));
+        let regular = zkp.prove(&secret, &public_key);
+        let proof = FixedProof {
+            r1: uint(&regular.r1),
+            r2: uint(&regular.r2),
+            s: uint(&regular.s),
+        };
+        assert!(group.verify(&fixed_key, &proof));
+
+        let other = group.public_key(&group.random_nonzero_scalar(&mut rng));
+        assert!(!group.verify(&other, &proof));
+    }
+
+    #[test]
+    fn test_rfc5114_matches_the_regular_protocol() {
+        interoperates(
+            Rfc5114Group::rfc5114_1024_160(),
+            ParameterSet::Rfc5114_1024_160,
+        );
+    }
+
+    #[test]
+    fn test_rfc3526_2048_matches_the_regular_protocol() {
+        interoperates(
+            Rfc3526_2048Group::rfc3526_2048(),
+            ParameterSet::Rfc3526_2048,
+        );
+    }
+
+    #[test]
+    fn test_rejects_keys_outside_the_subgroup() {
+        let group = Rfc5114Group::rfc5114_1024_160();
+        let mut rng = rand::thread_rng();
+        let x = group.random_nonzero_scalar(&mut rng);
+        let public_key = group.public_key(&x);
+        // -y has a component of order 2, which an even c cancels
+        let twisted = FixedPublicKey {
+            y1: group.p.wrapping_sub(&public_key.y1),
+            y2: group.p.wrapping_sub(&public_key.y2),
+        };
+        let proof = loop {
+            let proof = group.prove(&x, &twisted, &mut rng);
+            let c = group.challenge(&twisted, &proof.r1, &proof.r2);
+            if bool::from(c.is_even()) {
+                break proof;
+            }
+        };
+        assert!(!group.verify(&twisted, &proof));
+    }
+
+    #[test]
+    fn test_rejects_unusable_parameters() {
+        let group = Rfc5114Group::rfc5114_1024_160();
+        // p narrower than its Uint, the padded hash input would be off
+        let narrow: U1024 = group.p.shr_vartime(8);
+        assert!(FixedGroup::new(narrow | U1024::ONE, group.q, group.alpha, group.beta).is_none());
+        assert!(FixedGroup::new(group.p, group.q, U1024::ONE, group.beta).is_none());
+        assert!(FixedGroup::new(group.p, U192::from_u8(4), group.alpha, group.beta).is_none());
+    }
+}
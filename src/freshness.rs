@@ -0,0 +1,237 @@
+use std::time::Duration;
+
+use num_bigint::BigUint;
+
+use crate::bundle::unix_now;
+use crate::proof::{AnyProof, CompactProof, PublicKey, Secret};
+use crate::{Error, ZKP};
+
+// Non-interactive proofs that say when they were made and until when they
+// count. Both times go into the challenge
+//     c = H(p, q, alpha, beta, issued_at, expires_at, y1, y2, r1, r2) mod q
+// so a stored proof can't be given a new lifetime without x.
+//
+// `verify_proof` checks them against the system clock with the default
+// policy, `verify_fresh_proof_at` against the caller's clock and policy, and
+// the policy caps how long a proof may claim to live. Proofs without claims
+// fail `verify_proof`, so no proof is valid forever by default;
+// `verify_proof_without_expiry` is the explicit way round that.
+const FRESHNESS_LABEL: &[u8] = b"zkp-chaum-pedersen/fresh/v1";
+
+// how long `prove_fresh` proofs live unless told otherwise
+pub const DEFAULT_LIFETIME: Duration = Duration::from_secs(5 * 60);
+
+// unix seconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Validity {
+    pub issued_at: u64,
+    pub expires_at: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FreshProof {
+    pub validity: Validity,
+    pub proof: CompactProof,
+}
+
+// How far the prover's clock may be off from the verifier's, either way, and
+// the longest lifetime a proof may claim.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FreshnessPolicy {
+    pub max_clock_skew: Duration,
+    pub max_lifetime: Duration,
+}
+
+impl Default for FreshnessPolicy {
+    fn default() -> Self {
+        FreshnessPolicy {
+            max_clock_skew: Duration::from_secs(60),
+            max_lifetime: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+}
+
+impl Validity {
+    pub fn starting_now(lifetime: Duration) -> Self {
+        let issued_at = unix_now();
+        Validity {
+            issued_at,
+            expires_at: issued_at.saturating_add(lifetime.as_secs()),
+        }
+    }
+
+    pub fn check_at(&self, now: u64, policy: &FreshnessPolicy) -> Result<(), Error> {
+        let skew = policy.max_clock_skew.as_secs();
+        if self.expires_at < self.issued_at
+            || self.expires_at - self.issued_at > policy.max_lifetime.as_secs()
+            || self.issued_at > now.saturating_add(skew)
+            || self.expires_at.saturating_add(skew) < now
+        {
+            return Err(Error::Expired);
+        }
+        Ok(())
+    }
+}
+
+impl From<FreshProof> for AnyProof {
+    fn from(proof: FreshProof) -> Self {
+        AnyProof::Fresh(proof)
+    }
+}
+
+impl ZKP {
+    pub fn fresh_challenge(
+        &self,
+        public_key: &PublicKey,
+        validity: &Validity,
+        r1: &BigUint,
+        r2: &BigUint,
+    ) -> BigUint {
+        let issued_at = BigUint::from(validity.issued_at);
+        let expires_at = BigUint::from(validity.expires_at);
+        self.hash_to_scalar(
+            FRESHNESS_LABEL,
+            &[
+                &issued_at,
+                &expires_at,
+                &public_key.y1,
+                &public_key.y2,
+                r1,
+                r2,
+            ],
+        )
+    }
+
+    // valid from now for `lifetime`
+    pub fn prove_fresh(
+        &self,
+        secret: &Secret,
+        public_key: &PublicKey,
+        lifetime: Duration,
+    ) -> FreshProof {
+        self.prove_fresh_with(secret, public_key, Validity::starting_now(lifetime))
+    }
+
+    pub fn prove_fresh_with(
+        &self,
+        secret: &Secret,
+        public_key: &PublicKey,
+        validity: Validity,
+    ) -> FreshProof {
+        let k = ZKP::generate_random_nonzero_below(&self.q);
+        let r1 = self.pow(&self.alpha, &k);
+        let r2 = self.pow(&self.beta, &k);
+
+        let c = self.fresh_challenge(public_key, &validity, &r1, &r2);
+        let s = self.solve(&k, &c, secret.x());
+
+        FreshProof {
+            validity,
+            proof: CompactProof { c, s },
+        }
+    }
+
+    // Proofs without claims are never fresh.
+    pub fn verify_fresh_proof_at(
+        &self,
+        public_key: &PublicKey,
+        proof: impl Into<AnyProof>,
+        now: u64,
+        policy: &FreshnessPolicy,
+    ) -> Result<(), Error> {
+        let proof = match proof.into() {
+            AnyProof::Fresh(proof) => proof,
+            _ => return Err(Error::Expired),
+        };
+        proof.validity.check_at(now, policy)?;
+        if !self.verify_fresh_signature(public_key, &proof) {
+            return Err(Error::InvalidProof);
+        }
+        Ok(())
+    }
+
+    // the proof alone, whatever the clock says
+    fn verify_fresh_signature(&self, public_key: &PublicKey, proof: &FreshProof) -> bool {
+        self.expand_checked(public_key, &proof.proof)
+            .is_some_and(|expanded| {
+                proof.proof.c
+                    == self.fresh_challenge(public_key, &proof.validity, &expanded.r1, &expanded.r2)
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+
+    #[test]
+    fn test_validity_window() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let secret = Secret::random(&zkp);
+        let public_key = zkp.public_key(&secret);
+        let validity = Validity {
+            issued_at: 1_000_000,
+            expires_at: 1_000_300,
+        };
+        let proof = zkp.prove_fresh_with(&secret, &public_key, validity);
+        let policy = FreshnessPolicy::default();
+        let check = |now| zkp.verify_fresh_proof_at(&public_key, proof.clone(), now, &policy);
+
+        assert_eq!(check(1_000_100), Ok(()));
+        // within the skew on either side
+        assert_eq!(check(1_000_000 - 30), Ok(()));
+        assert_eq!(check(1_000_300 + 30), Ok(()));
+        assert_eq!(check(1_000_000 - 120), Err(Error::Expired));
+        assert_eq!(check(1_000_300 + 120), Err(Error::Expired));
+
+        // claims longer than the policy allows
+        let forever = Validity {
+            issued_at: 1_000_000,
+            expires_at: u64::MAX,
+        };
+        let proof = zkp.prove_fresh_with(&secret, &public_key, forever);
+        assert_eq!(
+            zkp.verify_fresh_proof_at(&public_key, proof, 1_000_100, &policy),
+            Err(Error::Expired)
+        );
+    }
+
+    #[test]
+    fn test_claims_are_bound() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let secret = Secret::random(&zkp);
+        let public_key = zkp.public_key(&secret);
+        let proof = zkp.prove_fresh(&secret, &public_key, DEFAULT_LIFETIME);
+        assert!(zkp.verify_proof(&public_key, proof.clone()));
+
+        let mut extended = proof.clone();
+        extended.validity.expires_at += 60;
+        assert!(!zkp.verify_proof(&public_key, extended.clone()));
+        assert_eq!(
+            zkp.verify_fresh_proof_at(
+                &public_key,
+                extended,
+                proof.validity.issued_at,
+                &FreshnessPolicy::default()
+            ),
+            Err(Error::InvalidProof)
+        );
+
+        // neither an untimed proof nor a stale one
+        let untimed = zkp.prove_compact(&secret, &public_key);
+        assert_eq!(
+            zkp.verify_fresh_proof_at(&public_key, untimed, 0, &FreshnessPolicy::default()),
+            Err(Error::Expired)
+        );
+        let stale = zkp.prove_fresh_with(
+            &secret,
+            &public_key,
+            Validity {
+                issued_at: 1_000_000,
+                expires_at: 1_000_300,
+            },
+        );
+        assert!(!zkp.verify_proof(&public_key, stale));
+    }
+}
@@ -0,0 +1,90 @@
+use num_bigint::BigUint;
+
+use crate::dhparams::hash_to_subgroup;
+use crate::ZKP;
+
+// Generators nobody chose. The Chaum-Pedersen proof itself doesn't care
+// who knows log_alpha(beta): two answers to one commitment give an x with
+// y1 = alpha^x and y2 = beta^x whatever it is. A generator hashed out of
+// (p, q, alpha, label) is one anyone can redo the derivation of, so a
+// published beta is seen not to be picked, and it has no known discrete
+// log to alpha, which is what needs it: the binding of a Pedersen
+// commitment, see `crate::pedersen`.
+//
+// The hash is reduced mod p and raised to the cofactor, and rejected and
+// redone with the next counter while it lands on 1, see `hash_to_subgroup`.
+// Different labels give independent generators.
+impl ZKP {
+    pub fn derive_generator(&self, label: &[u8]) -> BigUint {
+        hash_to_subgroup(&self.p, &self.q, label, &[&self.alpha])
+    }
+
+    // n more generators for `VectorZKP::from_zkp`, the i-th hashed with i
+    // after the label
+    pub fn derive_generators(&self, label: &[u8], n: usize) -> Vec<BigUint> {
+        (0..n)
+            .map(|i| {
+                let index = BigUint::from(i as u64);
+                hash_to_subgroup(&self.p, &self.q, label, &[&self.alpha, &index])
+            })
+            .collect()
+    }
+
+    // The group of (p, q, alpha) with beta derived from label.
+    pub fn with_derived_beta(p: BigUint, q: BigUint, alpha: BigUint, label: &[u8]) -> ZKP {
+        let beta = hash_to_subgroup(&p, &q, label, &[&alpha]);
+        ZKP::new(p, q, alpha, beta)
+    }
+
+    // whether beta is the one derived from label
+    pub fn beta_is_derived(&self, label: &[u8]) -> bool {
+        self.beta == self.derive_generator(label)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+    use crate::proof::Secret;
+    use crate::vector::VectorZKP;
+
+    #[test]
+    fn test_derived_generators_have_order_q() {
+        let group = ParameterSet::Rfc5114_1024_160.zkp();
+        let zkp = ZKP::with_derived_beta(
+            group.p.clone(),
+            group.q.clone(),
+            group.alpha.clone(),
+            b"example/beta",
+        );
+        let one = BigUint::from(1u32);
+        assert_eq!(zkp.beta.modpow(&zkp.q, &zkp.p), one);
+        assert!(zkp.beta != one && zkp.beta != zkp.alpha);
+
+        assert!(zkp.beta_is_derived(b"example/beta"));
+        assert!(!zkp.beta_is_derived(b"example/other"));
+        assert!(!group.beta_is_derived(b"example/beta"));
+
+        let secret = Secret::random(&zkp);
+        let public_key = zkp.public_key(&secret);
+        assert!(zkp.verify_proof_without_expiry(&public_key, zkp.prove(&secret, &public_key)));
+    }
+
+    #[test]
+    fn test_derivation_is_deterministic() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let extra = zkp.derive_generators(b"example/vector", 3);
+        assert_eq!(extra, zkp.derive_generators(b"example/vector", 3));
+        assert_eq!(extra[..2], zkp.derive_generators(b"example/vector", 2)[..]);
+        assert!(extra[0] != extra[1] && extra[1] != extra[2]);
+        assert_ne!(extra[0], zkp.derive_generator(b"example/vector"));
+
+        let vector = VectorZKP::from_zkp(&zkp, &extra);
+        let one = BigUint::from(1u32);
+        assert!(vector
+            .generators
+            .iter()
+            .all(|g| g.modpow(&zkp.q, &zkp.p) == one));
+    }
+}
@@ -0,0 +1,208 @@
+use num_bigint::BigUint;
+use tonic::transport::Channel;
+use tonic::{Code, Status};
+
+use crate::protocol::ProtocolVersion;
+use crate::retry::RetryPolicy;
+use crate::transport::AsyncTransport;
+use crate::zkp_auth::auth_client::AuthClient;
+use crate::zkp_auth::{
+    AuthenticationAnswerRequest, AuthenticationChallengeRequest, KeyRotationAnswerRequest,
+    KeyRotationChallengeRequest, NegotiateRequest, RegisterRequest,
+};
+use crate::{Error, ZKP};
+
+// `AsyncTransport` over the gRPC service, for one parameter set. Every call
+// goes through the retry policy, but commitments are sent once: the nonce
+// is the caller's, and a retry of a commitment that landed is refused as
+// replayed. The client is cloned per call, so the transport can be shared
+// by concurrent logins.
+#[derive(Debug, Clone)]
+pub struct GrpcTransport {
+    client: AuthClient<Channel>,
+    zkp: ZKP,
+    version: ProtocolVersion,
+    policy: RetryPolicy,
+}
+
+impl GrpcTransport {
+    // Negotiates the protocol version, servers from before that step only
+    // speak V1.
+    pub async fn connect(channel: Channel, zkp: ZKP, policy: RetryPolicy) -> Result<Self, Error> {
+        let client = AuthClient::new(channel);
+        let request = NegotiateRequest {
+            versions: ProtocolVersion::ALL.iter().map(|v| v.number()).collect(),
+        };
+        let version = match call(&policy, &client, request, |mut c, r| async move {
+            c.negotiate(r).await
+        })
+        .await
+        {
+            Ok(response) => ProtocolVersion::from_number(response.version)?,
+            Err(status) if status.code() == Code::Unimplemented => ProtocolVersion::V1,
+            Err(status) => return Err(Error::Transport(status.to_string())),
+        };
+
+        Ok(GrpcTransport {
+            client,
+            zkp,
+            version,
+            policy,
+        })
+    }
+
+    pub fn version(&self) -> ProtocolVersion {
+        self.version
+    }
+
+    fn element(&self, e: &BigUint) -> Vec<u8> {
+        self.version.encode_element(&self.zkp, e)
+    }
+
+    fn scalar(&self, n: &BigUint) -> Vec<u8> {
+        self.version.encode_scalar(&self.zkp, n)
+    }
+}
+
+#[tonic::async_trait]
+impl AsyncTransport for GrpcTransport {
+    async fn register(
+        &self,
+        user_name: &str,
+        parameter_set: &str,
+        y1: &BigUint,
+        y2: &BigUint,
+    ) -> Result<(), Error> {
+        let request = RegisterRequest {
+            user: user_name.to_string(),
+            y1: self.element(y1),
+            y2: self.element(y2),
+            parameter_set: parameter_set.to_string(),
+            version: self.version.number(),
+            blinding_proof: Vec::new(),
+        };
+        // a retry of a registration that landed finds the name taken
+        call(&self.policy, &self.client, request, |mut c, r| async move {
+            c.register(r).await
+        })
+        .await
+        .map_err(|status| match status.code() {
+            Code::AlreadyExists => Error::AlreadyRegistered(user_name.to_string()),
+            _ => to_error(status, || Error::UserNotFound(user_name.to_string())),
+        })?;
+        Ok(())
+    }
+
+    async fn create_challenge(
+        &self,
+        user_name: &str,
+        r1: &BigUint,
+        r2: &BigUint,
+    ) -> Result<(String, BigUint), Error> {
+        let request = AuthenticationChallengeRequest {
+            user: user_name.to_string(),
+            r1: self.element(r1),
+            r2: self.element(r2),
+            version: self.version.number(),
+        };
+        let response = call(
+            &self.policy.once(),
+            &self.client,
+            request,
+            |mut c, r| async move { c.create_authentication_challenge(r).await },
+        )
+        .await
+        .map_err(|status| to_error(status, || Error::UserNotFound(user_name.to_string())))?;
+        let c = self.version.decode_scalar(&self.zkp, &response.c)?;
+        Ok((response.auth_id, c))
+    }
+
+    // Not idempotent: an answer that landed consumed its challenge, so a
+    // retry after a lost reply fails with `ChallengeNotFound` and the login
+    // has to start over.
+    async fn verify(&self, auth_id: &str, s: &BigUint) -> Result<String, Error> {
+        let request = AuthenticationAnswerRequest {
+            auth_id: auth_id.to_string(),
+            s: self.scalar(s),
+            version: self.version.number(),
+        };
+        let response = call(&self.policy, &self.client, request, |mut c, r| async move {
+            c.verify_authentication(r).await
+        })
+        .await
+        .map_err(|status| to_error(status, || Error::ChallengeNotFound(auth_id.to_string())))?;
+        Ok(response.session_id)
+    }
+
+    async fn create_rotation_challenge(
+        &self,
+        user_name: &str,
+        r1: &BigUint,
+        r2: &BigUint,
+        new_y1: &BigUint,
+        new_y2: &BigUint,
+    ) -> Result<(String, BigUint), Error> {
+        let request = KeyRotationChallengeRequest {
+            user: user_name.to_string(),
+            r1: self.element(r1),
+            r2: self.element(r2),
+            new_y1: self.element(new_y1),
+            new_y2: self.element(new_y2),
+            version: self.version.number(),
+        };
+        let response = call(
+            &self.policy.once(),
+            &self.client,
+            request,
+            |mut c, r| async move { c.create_key_rotation_challenge(r).await },
+        )
+        .await
+        .map_err(|status| to_error(status, || Error::UserNotFound(user_name.to_string())))?;
+        let c = self.version.decode_scalar(&self.zkp, &response.c)?;
+        Ok((response.auth_id, c))
+    }
+
+    async fn verify_rotation(&self, auth_id: &str, s: &BigUint) -> Result<(), Error> {
+        let request = KeyRotationAnswerRequest {
+            auth_id: auth_id.to_string(),
+            s: self.scalar(s),
+            version: self.version.number(),
+        };
+        call(&self.policy, &self.client, request, |mut c, r| async move {
+            c.verify_key_rotation(r).await
+        })
+        .await
+        .map_err(|status| to_error(status, || Error::ChallengeNotFound(auth_id.to_string())))?;
+        Ok(())
+    }
+}
+
+// One rpc under the policy, with a fresh client handle and request per try.
+async fn call<Req, Resp, F, Fut>(
+    policy: &RetryPolicy,
+    client: &AuthClient<Channel>,
+    request: Req,
+    rpc: F,
+) -> Result<Resp, Status>
+where
+    Req: Clone,
+    F: Fn(AuthClient<Channel>, Req) -> Fut,
+    Fut: std::future::Future<Output = Result<tonic::Response<Resp>, Status>>,
+{
+    policy
+        .run(|| rpc(client.clone(), request.clone()))
+        .await
+        .map(tonic::Response::into_inner)
+}
+
+// The server's `to_status` the other way round, as far as the code tells.
+fn to_error(status: Status, not_found: impl FnOnce() -> Error) -> Error {
+    match status.code() {
+        Code::NotFound => not_found(),
+        Code::PermissionDenied => Error::InvalidProof,
+        Code::ResourceExhausted => Error::RateLimited,
+        Code::FailedPrecondition => Error::NoPendingCommitment,
+        Code::InvalidArgument => Error::InvalidParameters(status.message().to_string()),
+        _ => Error::Transport(status.to_string()),
+    }
+}
@@ -0,0 +1,123 @@
+use num_bigint::BigUint;
+
+use crate::proof::{PublicKey, Secret};
+use crate::{Error, ZKP};
+
+// Whatever holds x: memory, an HSM, a TPM or a remote signer. The nonce k is
+// drawn and kept by the holder as well, since k, c and s = k - c * x
+// together give x away.
+pub trait SecretHolder {
+    // (y1, y2) = (alpha^x, beta^x)
+    fn public_key(&self, zkp: &ZKP) -> Result<PublicKey, Error>;
+
+    // output => (r1, r2) for a fresh nonce, replaces any pending one
+    fn commit(&mut self, zkp: &ZKP) -> Result<(BigUint, BigUint), Error>;
+
+    // output => s = k - c * x mod q, consumes the pending nonce
+    fn compute_response(&mut self, zkp: &ZKP, c: &BigUint) -> Result<BigUint, Error>;
+}
+
+// x kept in process memory.
+#[derive(Debug)]
+pub struct LocalHolder {
+    secret: Secret,
+    k: Option<BigUint>,
+}
+
+impl LocalHolder {
+    pub fn new(secret: Secret) -> Self {
+        LocalHolder { secret, k: None }
+    }
+}
+
+impl SecretHolder for LocalHolder {
+    fn public_key(&self, zkp: &ZKP) -> Result<PublicKey, Error> {
+        Ok(zkp.public_key(&self.secret))
+    }
+
+    fn commit(&mut self, zkp: &ZKP) -> Result<(BigUint, BigUint), Error> {
+        let k = ZKP::generate_random_nonzero_below(&zkp.q);
+        let r1 = zkp.pow(&zkp.alpha, &k);
+        let r2 = zkp.pow(&zkp.beta, &k);
+        self.k = Some(k);
+
+        Ok((r1, r2))
+    }
+
+    // Answering two challenges with the same k would reveal x.
+    fn compute_response(&mut self, zkp: &ZKP, c: &BigUint) -> Result<BigUint, Error> {
+        let k = self.k.take().ok_or(Error::NoPendingCommitment)?;
+        Ok(zkp.solve(&k, c, self.secret.x()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+    use crate::prover::Prover;
+    use crate::simulation::Simulation;
+
+    // stands in for a device that signs a limited number of times
+    struct CountingHolder {
+        inner: LocalHolder,
+        responses_left: u32,
+    }
+
+    impl SecretHolder for CountingHolder {
+        fn public_key(&self, zkp: &ZKP) -> Result<PublicKey, Error> {
+            self.inner.public_key(zkp)
+        }
+
+        fn commit(&mut self, zkp: &ZKP) -> Result<(BigUint, BigUint), Error> {
+            self.inner.commit(zkp)
+        }
+
+        fn compute_response(&mut self, zkp: &ZKP, c: &BigUint) -> Result<BigUint, Error> {
+            if self.responses_left == 0 {
+                return Err(Error::Transport("device locked".to_string()));
+            }
+            self.responses_left -= 1;
+            self.inner.compute_response(zkp, c)
+        }
+    }
+
+    #[test]
+    fn test_local_holder_consumes_the_nonce() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let mut holder = LocalHolder::new(Secret::random(&zkp));
+        let c = BigUint::from(3u32);
+
+        assert_eq!(
+            holder.compute_response(&zkp, &c),
+            Err(Error::NoPendingCommitment)
+        );
+        let (r1, r2) = holder.commit(&zkp).unwrap();
+        let s = holder.compute_response(&zkp, &c).unwrap();
+        let public_key = holder.public_key(&zkp).unwrap();
+        assert!(zkp.verify(&r1, &r2, &public_key.y1, &public_key.y2, &s, &c));
+        assert_eq!(
+            holder.compute_response(&zkp, &c),
+            Err(Error::NoPendingCommitment)
+        );
+    }
+
+    #[test]
+    fn test_prover_with_external_holder() {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let zkp = set.zkp();
+        let holder = CountingHolder {
+            inner: LocalHolder::new(Secret::random(&zkp)),
+            responses_left: 1,
+        };
+        let mut prover = Prover::with_holder(zkp, set.id(), "alice", holder);
+
+        let mut sim = Simulation::new(set);
+        prover.register(sim.transport()).unwrap();
+        assert!(sim.login(&mut prover).is_ok());
+        assert_eq!(
+            sim.login(&mut prover),
+            Err(Error::Transport("device locked".to_string()))
+        );
+    }
+}
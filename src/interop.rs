@@ -0,0 +1,361 @@
+use std::str::FromStr;
+
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::proof::{Proof, PublicKey, Secret};
+use crate::{Error, ZKP};
+
+// Byte encodings and challenge hashes for our Chaum-Pedersen services in Go
+// and Python, so proofs can be checked across languages. No third-party
+// package is mirrored here: each profile is what the standard library
+// prover in interop/ (reference.go, reference.py) does, and a service
+// interoperates if its output matches that program's. The arithmetic is
+// the same everywhere (s = k - c * x mod q), only the bytes on the wire and
+// the input to the hash differ:
+//
+// Native  r1 | r2 | s, padded to the byte length of p, p and q.
+//         c = `ZKP::challenge`, the group bound and every value
+//         length-prefixed.
+// Go      encoding/json of {R1, R2, S string} as written by json.Marshal,
+//         each value big.Int.Text(16): {"r1":"6f1","r2":"70d","s":"13f"}.
+//         c = SHA-256(alpha.Bytes() || beta.Bytes() || y1 || y2 || r1 || r2)
+//         mod q, each value big.Int.Bytes() (minimal big-endian) with no
+//         length in between.
+// Python  json.dumps of {"r1": r1, "r2": r2, "s": s}, plain ints:
+//         {"r1": 1777, "r2": 1805, "s": 839}.
+//         c = int(sha256(",".join(str(v) for v in (alpha, beta, y1, y2, r1,
+//         r2)).encode()).hexdigest(), 16) % q.
+//
+// The Go and Python hashes bind the bases but not p and q, which are fixed
+// by the parameter set both sides agreed on; use Native between our own
+// services.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum InteropProfile {
+    #[default]
+    Native,
+    Go,
+    Python,
+}
+
+// field names as json.Marshal writes them with `json:"r1"` tags
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GoProof {
+    r1: String,
+    r2: String,
+    s: String,
+}
+
+impl InteropProfile {
+    pub const ALL: [InteropProfile; 3] = [
+        InteropProfile::Native,
+        InteropProfile::Go,
+        InteropProfile::Python,
+    ];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            InteropProfile::Native => "native",
+            InteropProfile::Go => "go",
+            InteropProfile::Python => "python",
+        }
+    }
+
+    pub fn challenge(
+        &self,
+        zkp: &ZKP,
+        public_key: &PublicKey,
+        r1: &BigUint,
+        r2: &BigUint,
+    ) -> BigUint {
+        let inputs = [
+            &zkp.alpha,
+            &zkp.beta,
+            &public_key.y1,
+            &public_key.y2,
+            r1,
+            r2,
+        ];
+        match self {
+            InteropProfile::Native => zkp.challenge(public_key, r1, r2),
+            InteropProfile::Go => {
+                let mut hasher = Sha256::new();
+                for n in inputs {
+                    hasher.update(go_bytes(n));
+                }
+                BigUint::from_bytes_be(&hasher.finalize()) % &zkp.q
+            }
+            InteropProfile::Python => {
+                let joined: Vec<String> = inputs.iter().map(|n| n.to_str_radix(10)).collect();
+                let digest = Sha256::digest(joined.join(",").as_bytes());
+                BigUint::from_bytes_be(&digest) % &zkp.q
+            }
+        }
+    }
+
+    pub fn prove(&self, zkp: &ZKP, secret: &Secret, public_key: &PublicKey) -> Proof {
+        let k = ZKP::generate_random_nonzero_below(&zkp.q);
+        self.prove_with_nonce(zkp, secret, public_key, &k)
+    }
+
+    fn prove_with_nonce(
+        &self,
+        zkp: &ZKP,
+        secret: &Secret,
+        public_key: &PublicKey,
+        k: &BigUint,
+    ) -> Proof {
+        let r1 = zkp.pow(&zkp.alpha, k);
+        let r2 = zkp.pow(&zkp.beta, k);
+        let c = self.challenge(zkp, public_key, &r1, &r2);
+        Proof {
+            s: zkp.solve(k, &c, secret.x()),
+            r1,
+            r2,
+        }
+    }
+
+    pub fn verify(&self, zkp: &ZKP, public_key: &PublicKey, proof: &Proof) -> bool {
+        let c = self.challenge(zkp, public_key, &proof.r1, &proof.r2);
+        zkp.verify(
+            &proof.r1,
+            &proof.r2,
+            &public_key.y1,
+            &public_key.y2,
+            &proof.s,
+            &c,
+        )
+    }
+
+    pub fn encode_proof(&self, zkp: &ZKP, proof: &Proof) -> Vec<u8> {
+        match self {
+            InteropProfile::Native => [
+                zkp.element_to_bytes(&proof.r1),
+                zkp.element_to_bytes(&proof.r2),
+                zkp.scalar_to_bytes(&proof.s),
+            ]
+            .concat(),
+            InteropProfile::Go => serde_json::to_vec(&GoProof {
+                r1: proof.r1.to_str_radix(16),
+                r2: proof.r2.to_str_radix(16),
+                s: proof.s.to_str_radix(16),
+            })
+            .expect("strings always serialize"),
+            InteropProfile::Python => format!(
+                "{{\"r1\": {}, \"r2\": {}, \"s\": {}}}",
+                proof.r1, proof.r2, proof.s
+            )
+            .into_bytes(),
+        }
+    }
+
+    pub fn decode_proof(&self, zkp: &ZKP, bytes: &[u8]) -> Result<Proof, Error> {
+        match self {
+            InteropProfile::Native => {
+                let (element_len, scalar_len) = (zkp.element_len(), zkp.scalar_len());
+                if bytes.len() != 2 * element_len + scalar_len {
+                    return Err(Error::InvalidEncoding(format!(
+                        "expected {} bytes, got {}",
+                        2 * element_len + scalar_len,
+                        bytes.len()
+                    )));
+                }
+                let (r1, rest) = bytes.split_at(element_len);
+                let (r2, s) = rest.split_at(element_len);
+                Ok(Proof {
+                    r1: zkp.element_from_bytes(r1)?,
+                    r2: zkp.element_from_bytes(r2)?,
+                    s: zkp.scalar_from_bytes(s)?,
+                })
+            }
+            InteropProfile::Go => {
+                let go: GoProof = serde_json::from_slice(bytes)
+                    .map_err(|e| Error::InvalidEncoding(e.to_string()))?;
+                Ok(Proof {
+                    r1: parse_integer(&go.r1, 16)?,
+                    r2: parse_integer(&go.r2, 16)?,
+                    s: parse_integer(&go.s, 16)?,
+                })
+            }
+            InteropProfile::Python => parse_python_proof(bytes),
+        }
+    }
+}
+
+impl FromStr for InteropProfile {
+    type Err = Error;
+
+    fn from_str(id: &str) -> Result<Self, Error> {
+        InteropProfile::ALL
+            .into_iter()
+            .find(|profile| profile.id() == id)
+            .ok_or_else(|| Error::InvalidParameters(format!("unknown interop profile {}", id)))
+    }
+}
+
+// big.Int.Bytes(): minimal big-endian, nothing at all for zero
+fn go_bytes(n: &BigUint) -> Vec<u8> {
+    if *n == BigUint::from(0u32) {
+        return Vec::new();
+    }
+    n.to_bytes_be()
+}
+
+fn parse_integer(text: &str, radix: u32) -> Result<BigUint, Error> {
+    if text.is_empty() || !text.chars().all(|ch| ch.is_digit(radix)) {
+        return Err(Error::InvalidEncoding(format!("bad integer {:?}", text)));
+    }
+    BigUint::parse_bytes(text.as_bytes(), radix)
+        .ok_or_else(|| Error::InvalidEncoding(format!("bad integer {:?}", text)))
+}
+
+// A flat object of the three ints in any order and spacing. serde_json would
+// need its arbitrary_precision feature (for the whole crate) to read them.
+fn parse_python_proof(bytes: &[u8]) -> Result<Proof, Error> {
+    let malformed = || Error::InvalidEncoding("malformed python proof".to_string());
+    let text = std::str::from_utf8(bytes).map_err(|_| malformed())?;
+    let body = text
+        .trim()
+        .strip_prefix('{')
+        .and_then(|rest| rest.strip_suffix('}'))
+        .ok_or_else(malformed)?;
+
+    let (mut r1, mut r2, mut s) = (None, None, None);
+    for field in body.split(',') {
+        let (key, value) = field.split_once(':').ok_or_else(malformed)?;
+        let slot = match key.trim() {
+            "\"r1\"" => &mut r1,
+            "\"r2\"" => &mut r2,
+            "\"s\"" => &mut s,
+            _ => return Err(malformed()),
+        };
+        if slot.replace(parse_integer(value.trim(), 10)?).is_some() {
+            return Err(malformed());
+        }
+    }
+    Ok(Proof {
+        r1: r1.ok_or_else(malformed)?,
+        r2: r2.ok_or_else(malformed)?,
+        s: s.ok_or_else(malformed)?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+
+    // p = 2039 = 2 * 1019 + 1, x = 123, k = 456, the "toy" case of the
+    // reference programs
+    fn toy() -> (ZKP, Secret, PublicKey) {
+        let n = |v: u32| BigUint::from(v);
+        let zkp = ZKP::new(n(2039), n(1019), n(4), n(9));
+        let secret = Secret::new(n(123));
+        let public_key = zkp.public_key(&secret);
+        (zkp, secret, public_key)
+    }
+
+    // The Python answers are the output of interop/reference.py (Python
+    // 3.11). The Go ones come from this module and still have to be checked
+    // against `go run interop/reference.go`.
+    #[test]
+    fn test_known_answers() {
+        let (zkp, secret, public_key) = toy();
+        assert_eq!(public_key.y1, BigUint::from(572u32));
+        assert_eq!(public_key.y2, BigUint::from(1014u32));
+        let k = BigUint::from(456u32);
+
+        let go = InteropProfile::Go.prove_with_nonce(&zkp, &secret, &public_key, &k);
+        assert_eq!(go.s, BigUint::from(319u32));
+        assert_eq!(
+            InteropProfile::Go.encode_proof(&zkp, &go),
+            br#"{"r1":"6f1","r2":"70d","s":"13f"}"#
+        );
+
+        let python = InteropProfile::Python.prove_with_nonce(&zkp, &secret, &public_key, &k);
+        assert_eq!(python.s, BigUint::from(839u32));
+        assert_eq!(
+            InteropProfile::Python.encode_proof(&zkp, &python),
+            br#"{"r1": 1777, "r2": 1805, "s": 839}"#
+        );
+    }
+
+    #[test]
+    fn test_python_reference_output() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let n = |v: u64| BigUint::from(v);
+        let secret = Secret::new(n(0x1234567890ABCDEF));
+        let public_key = zkp.public_key(&secret);
+        let k = n(0xFEDCBA0987654321);
+        let reference = concat!(
+            r#"{"r1": 97671161202121049026041300528744038056255178827629691503707633835030771926"#,
+            r#"060468632619219764811878653860691364586242110587798802576710825577297669757522648"#,
+            r#"162684463670490620908389213761399769864968662908139353531516184731055313312438453"#,
+            r#"395707308774260365715193187534343522625902604951087585939022018505493899, "r2": 1"#,
+            r#"181039626058843580286173065981012628530129564705735816225931539928775661096546439"#,
+            r#"767194673998370001541133660539630805724391524232335689572716883201037771684887975"#,
+            r#"723012793114974138097666266375230509109169447134585657448862816871772313290856597"#,
+            r#"87378745972784720255199670671705988148838098299563402400477896964, "s": 1223066269"#,
+            r#"138688484290455274083737387153074757633}"#,
+        );
+
+        let python = InteropProfile::Python;
+        let proof = python.decode_proof(&zkp, reference.as_bytes()).unwrap();
+        assert!(python.verify(&zkp, &public_key, &proof));
+        assert_eq!(
+            python.challenge(&zkp, &public_key, &proof.r1, &proof.r2),
+            "991558753406851477651011843973795579888051362599"
+                .parse::<BigUint>()
+                .unwrap()
+        );
+        let ours = python.prove_with_nonce(&zkp, &secret, &public_key, &k);
+        assert_eq!(python.encode_proof(&zkp, &ours), reference.as_bytes());
+    }
+
+    #[test]
+    fn test_profiles_roundtrip_and_stay_apart() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let secret = Secret::random(&zkp);
+        let public_key = zkp.public_key(&secret);
+
+        for profile in InteropProfile::ALL {
+            let proof = profile.prove(&zkp, &secret, &public_key);
+            let decoded = profile
+                .decode_proof(&zkp, &profile.encode_proof(&zkp, &proof))
+                .unwrap();
+            assert_eq!(decoded, proof);
+            assert!(profile.verify(&zkp, &public_key, &decoded));
+            for other in InteropProfile::ALL.into_iter().filter(|&p| p != profile) {
+                assert!(!other.verify(&zkp, &public_key, &proof), "{:?}", other);
+            }
+            assert_eq!(profile.id().parse::<InteropProfile>().unwrap(), profile);
+        }
+        assert!(zkp.verify_proof_without_expiry(
+            &public_key,
+            InteropProfile::Native.prove(&zkp, &secret, &public_key)
+        ));
+    }
+
+    #[test]
+    fn test_decoding_is_strict() {
+        let (zkp, _, _) = toy();
+        let python = InteropProfile::Python;
+        assert!(python
+            .decode_proof(&zkp, br#"{"s":839,"r2":1805,"r1":1777}"#)
+            .is_ok());
+        for bad in [
+            &br#"{"r1": 1777, "r2": 1805}"#[..],
+            br#"{"r1": 1777, "r1": 1777, "r2": 1805, "s": 839}"#,
+            br#"{"r1": -1, "r2": 1805, "s": 839}"#,
+            br#"{"r1": 1.5, "r2": 1805, "s": 839}"#,
+        ] {
+            assert!(python.decode_proof(&zkp, bad).is_err());
+        }
+        assert!(InteropProfile::Go
+            .decode_proof(&zkp, br#"{"r1":"6f1","r2":"70d","s":"-1"}"#)
+            .is_err());
+        assert!("rust".parse::<InteropProfile>().is_err());
+    }
+}
@@ -0,0 +1,333 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use num_bigint::BigUint;
+use rand::RngCore;
+use zeroize::Zeroize;
+
+use crate::der::{pem_decode, pem_encode};
+use crate::proof::Secret;
+use crate::Error;
+
+// Passphrase protected file holding the prover's secret x and the id of the
+// parameter set it belongs to. The key is derived with Argon2id and the
+// content sealed with XChaCha20-Poly1305; the header is authenticated as
+// associated data so the KDF parameters cannot be swapped.
+//
+// Layout inside the PEM block:
+//     version  u8 (1)
+//     m_cost   u32, t_cost u32, p_cost u32
+//     salt     [u8; 16]
+//     nonce    [u8; 24]
+//     sealed   u16 id length, id, x (big-endian, rest of the plaintext)
+pub const PEM_LABEL: &str = "ZKP KEYSTORE";
+
+const VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+pub(crate) const HEADER_LEN: usize = 1 + 12 + SALT_LEN + NONCE_LEN;
+
+// Largest costs a file may ask for (1 GiB, 64 passes, 16 lanes), checked
+// before deriving anything so a crafted header can't make opening it take
+// all the memory or hours.
+pub const MAX_M_COST: u32 = 1 << 20;
+pub const MAX_T_COST: u32 = 64;
+pub const MAX_P_COST: u32 = 16;
+
+// Argon2id cost parameters, m_cost in KiB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+// The argon2 crate defaults (19 MiB, 2 passes, 1 lane), as recommended by OWASP.
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams {
+            m_cost: Params::DEFAULT_M_COST,
+            t_cost: Params::DEFAULT_T_COST,
+            p_cost: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Keystore {
+    pub parameter_set: String,
+    pub secret: Secret,
+}
+
+impl Keystore {
+    pub fn new(parameter_set: &str, secret: Secret) -> Self {
+        Keystore {
+            parameter_set: parameter_set.to_string(),
+            secret,
+        }
+    }
+
+    pub fn encrypt(&self, passphrase: &str, params: KdfParams) -> Result<String, Error> {
+        let id = self.parameter_set.as_bytes();
+        if id.len() > u16::MAX as usize {
+            return Err(Error::InvalidEncoding(
+                "parameter set id too long".to_string(),
+            ));
+        }
+
+        let mut plaintext = Vec::new();
+        plaintext.extend_from_slice(&(id.len() as u16).to_be_bytes());
+        plaintext.extend_from_slice(id);
+        plaintext.extend_from_slice(&self.secret.x().to_bytes_be());
+
+        let sealed = seal(VERSION, passphrase, params, &plaintext);
+        plaintext.zeroize();
+        Ok(pem_encode(PEM_LABEL, &sealed?))
+    }
+
+    pub fn decrypt(pem: &str, passphrase: &str) -> Result<Keystore, Error> {
+        let bytes = pem_decode(pem, PEM_LABEL)?;
+        let mut plaintext = open(&bytes, VERSION, "keystore", passphrase)?;
+
+        let keystore = parse_plaintext(&plaintext);
+        plaintext.zeroize();
+        keystore
+    }
+
+    // Writes to a temporary file next to path and renames it over, so a
+    // crash never leaves a half written keystore behind.
+    pub fn save(&self, path: &Path, passphrase: &str, params: KdfParams) -> Result<(), Error> {
+        let pem = self.encrypt(passphrase, params)?;
+
+        let tmp = path.with_extension("tmp");
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&tmp).map_err(io_error)?;
+        file.write_all(pem.as_bytes()).map_err(io_error)?;
+        file.sync_all().map_err(io_error)?;
+        fs::rename(&tmp, path).map_err(io_error)
+    }
+
+    pub fn load(path: &Path, passphrase: &str) -> Result<Keystore, Error> {
+        let pem = fs::read_to_string(path).map_err(io_error)?;
+        Keystore::decrypt(&pem, passphrase)
+    }
+
+    // Re-encrypts the file under a new passphrase with a fresh salt and nonce.
+    pub fn rotate_passphrase(
+        path: &Path,
+        old_passphrase: &str,
+        new_passphrase: &str,
+        params: KdfParams,
+    ) -> Result<(), Error> {
+        Keystore::load(path, old_passphrase)?.save(path, new_passphrase, params)
+    }
+}
+
+// output => header || ciphertext, in the layout above. The backup format
+// shares it, with a version of its own.
+pub(crate) fn seal(
+    version: u8,
+    passphrase: &str,
+    params: KdfParams,
+    plaintext: &[u8],
+) -> Result<Vec<u8>, Error> {
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.push(version);
+    for n in [params.m_cost, params.t_cost, params.p_cost] {
+        header.extend_from_slice(&n.to_be_bytes());
+    }
+    header.extend_from_slice(&salt);
+    header.extend_from_slice(&nonce);
+
+    let sealed = cipher(passphrase, &salt, params)?
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: &header,
+            },
+        )
+        .map_err(|_| Error::InvalidEncoding("encryption failed".to_string()))?;
+    header.extend_from_slice(&sealed);
+    Ok(header)
+}
+
+// Reverse of `seal`, `what` names the format in errors. The caller zeroizes
+// the plaintext.
+pub(crate) fn open(
+    bytes: &[u8],
+    version: u8,
+    what: &str,
+    passphrase: &str,
+) -> Result<Vec<u8>, Error> {
+    if bytes.len() < HEADER_LEN {
+        return Err(Error::InvalidEncoding(format!("truncated {}", what)));
+    }
+    if bytes[0] != version {
+        return Err(Error::InvalidEncoding(format!(
+            "unsupported {} version {}",
+            what, bytes[0]
+        )));
+    }
+
+    let (header, sealed) = bytes.split_at(HEADER_LEN);
+    let u32_at = |i: usize| u32::from_be_bytes(header[i..i + 4].try_into().unwrap());
+    let params = KdfParams {
+        m_cost: u32_at(1),
+        t_cost: u32_at(5),
+        p_cost: u32_at(9),
+    };
+    let salt = &header[13..13 + SALT_LEN];
+    let nonce = &header[13 + SALT_LEN..];
+
+    cipher(passphrase, salt, params)?
+        .decrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: sealed,
+                aad: header,
+            },
+        )
+        .map_err(|_| Error::WrongPassphrase)
+}
+
+fn cipher(passphrase: &str, salt: &[u8], params: KdfParams) -> Result<XChaCha20Poly1305, Error> {
+    if params.m_cost > MAX_M_COST || params.t_cost > MAX_T_COST || params.p_cost > MAX_P_COST {
+        return Err(Error::InvalidParameters(format!(
+            "argon2 costs above the limits ({} KiB, {} passes, {} lanes)",
+            MAX_M_COST, MAX_T_COST, MAX_P_COST
+        )));
+    }
+    let params = Params::new(params.m_cost, params.t_cost, params.p_cost, Some(32))
+        .map_err(|e| Error::InvalidParameters(format!("argon2: {}", e)))?;
+
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| Error::InvalidParameters(format!("argon2: {}", e)))?;
+    let cipher = XChaCha20Poly1305::new(&key.into());
+    key.zeroize();
+    Ok(cipher)
+}
+
+fn parse_plaintext(plaintext: &[u8]) -> Result<Keystore, Error> {
+    let malformed = || Error::InvalidEncoding("malformed keystore content".to_string());
+
+    let id_len = u16::from_be_bytes(
+        plaintext
+            .get(..2)
+            .ok_or_else(malformed)?
+            .try_into()
+            .unwrap(),
+    );
+    let id = plaintext
+        .get(2..2 + id_len as usize)
+        .ok_or_else(malformed)?;
+    let parameter_set = std::str::from_utf8(id).map_err(|_| malformed())?;
+    let x = BigUint::from_bytes_be(&plaintext[2 + id_len as usize..]);
+
+    Ok(Keystore::new(parameter_set, Secret::new(x)))
+}
+
+fn io_error(error: std::io::Error) -> Error {
+    Error::Io(error.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // the defaults take seconds in debug builds
+    const WEAK: KdfParams = KdfParams {
+        m_cost: 64,
+        t_cost: 1,
+        p_cost: 1,
+    };
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let keystore = Keystore::new("rfc3526-2048", Secret::new(BigUint::from(123456789u32)));
+        let pem = keystore.encrypt("correct horse", WEAK).unwrap();
+        assert!(pem.starts_with("-----BEGIN ZKP KEYSTORE-----"));
+
+        let decrypted = Keystore::decrypt(&pem, "correct horse").unwrap();
+        assert_eq!(decrypted.parameter_set, "rfc3526-2048");
+        assert_eq!(decrypted.secret.x(), keystore.secret.x());
+
+        assert_eq!(
+            Keystore::decrypt(&pem, "battery staple").unwrap_err(),
+            Error::WrongPassphrase
+        );
+    }
+
+    #[test]
+    fn test_tampered_header_is_rejected() {
+        let keystore = Keystore::new("rfc5114-1024-160", Secret::new(BigUint::from(42u32)));
+        let pem = keystore.encrypt("passphrase", WEAK).unwrap();
+
+        // one more argon2 pass changes the key, and the header is authenticated
+        let mut bytes = pem_decode(&pem, PEM_LABEL).unwrap();
+        bytes[8] += 1;
+        let tampered = pem_encode(PEM_LABEL, &bytes);
+        assert_eq!(
+            Keystore::decrypt(&tampered, "passphrase").unwrap_err(),
+            Error::WrongPassphrase
+        );
+
+        // a huge m_cost is refused before argon2 tries to allocate it
+        bytes[1..5].copy_from_slice(&u32::MAX.to_be_bytes());
+        let greedy = pem_encode(PEM_LABEL, &bytes);
+        assert!(matches!(
+            Keystore::decrypt(&greedy, "passphrase"),
+            Err(Error::InvalidParameters(_))
+        ));
+        let too_slow = KdfParams {
+            t_cost: MAX_T_COST + 1,
+            ..WEAK
+        };
+        assert!(keystore.encrypt("passphrase", too_slow).is_err());
+    }
+
+    #[test]
+    fn test_save_load_rotate() {
+        let dir = std::env::temp_dir().join(format!(
+            "zkp-keystore-{}",
+            crate::ZKP::generate_random_string(8)
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("alice.pem");
+
+        let keystore = Keystore::new("rfc3526-2048", Secret::new(BigUint::from(7u32)));
+        keystore.save(&path, "old", WEAK).unwrap();
+        assert_eq!(
+            Keystore::load(&path, "old").unwrap().secret.x(),
+            keystore.secret.x()
+        );
+
+        Keystore::rotate_passphrase(&path, "old", "new", WEAK).unwrap();
+        assert_eq!(
+            Keystore::load(&path, "old").unwrap_err(),
+            Error::WrongPassphrase
+        );
+        let loaded = Keystore::load(&path, "new").unwrap();
+        assert_eq!(loaded.parameter_set, "rfc3526-2048");
+        assert_eq!(loaded.secret.x(), keystore.secret.x());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,5 +1,146 @@
-use num_bigint::{BigUint, RandBigInt};
+// Without the default "std" feature only `fixed` is left, no heap and no
+// std, for provers on microcontrollers.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+use std::sync::Arc;
 
+#[cfg(feature = "std")]
+use num_bigint::{BigUint, RandBigInt};
+#[cfg(feature = "std")]
+use rand::Rng;
+
+#[cfg(feature = "std")]
+#[macro_use]
+mod trace;
+
+#[cfg(feature = "std")]
+pub mod admin;
+#[cfg(feature = "std")]
+pub mod aggregate;
+#[cfg(feature = "std")]
+pub mod arith;
+#[cfg(feature = "std")]
+pub mod audit;
+#[cfg(feature = "std")]
+pub mod backup;
+#[cfg(feature = "std")]
+pub mod batch;
+#[cfg(feature = "std")]
+pub mod blind;
+#[cfg(feature = "bls12-381")]
+pub mod bls12;
+#[cfg(feature = "std")]
+pub mod bundle;
+#[cfg(feature = "std")]
+pub mod cache;
+#[cfg(feature = "std")]
+pub mod config;
+#[cfg(any(feature = "std", feature = "heapless"))]
+mod constants;
+#[cfg(feature = "std")]
+pub mod context;
+#[cfg(feature = "std")]
+pub mod ddh;
+#[cfg(feature = "std")]
+pub mod der;
+#[cfg(feature = "std")]
+pub mod dhparams;
+#[cfg(feature = "std")]
+pub mod elgamal;
+#[cfg(feature = "std")]
+pub mod encoding;
+#[cfg(feature = "std")]
+pub mod error;
+#[cfg(feature = "std")]
+pub mod events;
+#[cfg(feature = "std")]
+pub mod export;
+#[cfg(feature = "heapless")]
+pub mod fixed;
+#[cfg(feature = "std")]
+pub mod freshness;
+#[cfg(feature = "std")]
+pub mod generators;
+#[cfg(feature = "std")]
+pub mod grpc;
+#[cfg(feature = "std")]
+pub mod holder;
+#[cfg(feature = "std")]
+pub mod interop;
+#[cfg(feature = "std")]
+pub mod keystore;
+#[cfg(feature = "tower")]
+pub mod middleware;
+#[cfg(feature = "std")]
+pub mod migration;
+#[cfg(feature = "std")]
+pub mod modmath;
+#[cfg(feature = "std")]
+pub mod montgomery;
+#[cfg(feature = "std")]
+pub mod params;
+#[cfg(feature = "std")]
+pub mod pedersen;
+#[cfg(feature = "std")]
+pub mod pet;
+#[cfg(feature = "std")]
+pub mod policy;
+#[cfg(feature = "std")]
+pub mod prime;
+#[cfg(feature = "std")]
+pub mod proof;
+#[cfg(feature = "std")]
+pub mod protocol;
+#[cfg(feature = "std")]
+pub mod prover;
+#[cfg(feature = "std")]
+pub mod ratelimit;
+#[cfg(feature = "std")]
+pub mod recovery;
+#[cfg(feature = "std")]
+pub mod repetition;
+#[cfg(feature = "std")]
+pub mod retry;
+#[cfg(feature = "std")]
+pub mod sessions;
+#[cfg(feature = "std")]
+pub mod simulation;
+#[cfg(feature = "std")]
+pub mod simulator;
+#[cfg(feature = "std")]
+pub mod split;
+#[cfg(feature = "std")]
+pub mod stats;
+#[cfg(feature = "std")]
+pub mod store;
+#[cfg(all(feature = "std", any(test, feature = "test-utils")))]
+pub mod testing;
+#[cfg(feature = "std")]
+pub mod testvectors;
+#[cfg(feature = "std")]
+pub mod transcript;
+#[cfg(feature = "std")]
+pub mod transport;
+#[cfg(feature = "std")]
+pub mod vector;
+#[cfg(feature = "std")]
+pub mod verifier;
+#[cfg(feature = "std")]
+pub mod versioned;
+// generated by build.rs
+#[cfg(feature = "std")]
+#[rustfmt::skip]
+pub mod zkp_auth;
+
+#[cfg(feature = "std")]
+pub use error::Error;
+
+#[cfg(feature = "std")]
+use montgomery::GroupContext;
+
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
 pub struct ZKP {
     pub p: BigUint,
     pub q: BigUint,
@@ -7,7 +148,43 @@ pub struct ZKP {
     pub beta: BigUint,
 }
 
+#[cfg(feature = "std")]
 impl ZKP {
+    pub fn new(p: BigUint, q: BigUint, alpha: BigUint, beta: BigUint) -> Self {
+        ZKP { p, q, alpha, beta }
+    }
+
+    // The reduction contexts for the current p and q, see
+    // `GroupContext::cached`. None for moduli Montgomery can't handle (even
+    // p), callers fall back to `modpow`.
+    pub fn context(&self) -> Option<Arc<GroupContext>> {
+        GroupContext::cached(&self.p, &self.q)
+    }
+
+    // output => base^exponent mod p
+    pub fn pow(&self, base: &BigUint, exponent: &BigUint) -> BigUint {
+        match self.context() {
+            Some(context) => context.p.pow(base, exponent),
+            None => base.modpow(exponent, &self.p),
+        }
+    }
+
+    // output => a^e * b^f mod p
+    pub fn pow2(&self, a: &BigUint, e: &BigUint, b: &BigUint, f: &BigUint) -> BigUint {
+        match self.context() {
+            Some(context) => context.p.pow2(a, e, b, f),
+            None => (a.modpow(e, &self.p) * b.modpow(f, &self.p)) % &self.p,
+        }
+    }
+
+    // output => n mod q
+    pub fn reduce_q(&self, n: &BigUint) -> BigUint {
+        match self.context() {
+            Some(context) => context.q.reduce(n),
+            None => modmath::mod_reduce(n, &self.q),
+        }
+    }
+
     // output => n^exponet mod p
     pub fn exponetiate(n: &BigUint, exponent: &BigUint, p: &BigUint) -> BigUint {
         n.modpow(exponent, p)
@@ -15,11 +192,7 @@ impl ZKP {
 
     // output => s = k - c * x mod q
     pub fn solve(&self, k: &BigUint, c: &BigUint, x: &BigUint) -> BigUint {
-        if *k >= c * x {
-            return (k - c * x).modpow(&BigUint::from(1u32), &self.q);
-        }
-
-        return &self.q - (c * x - k).modpow(&BigUint::from(1u32), &self.q);
+        modmath::mod_sub(k, &self.reduce_q(&(c * x)), &self.q)
     }
 
     // cond1: r1 = alpha^s * y1^c mod p
@@ -33,23 +206,61 @@ impl ZKP {
         s: &BigUint,
         c: &BigUint,
     ) -> bool {
-        let cond1 = *r1
-            == (&self.alpha.modpow(s, &self.p) * y1.modpow(c, &self.p))
-                .modpow(&BigUint::from(1u32), &self.p);
-        let cond2 = *r2
-            == (&self.beta.modpow(s, &self.p) * y2.modpow(c, &self.p))
-                .modpow(&BigUint::from(1u32), &self.p);
+        if !self.in_range(s, &[r1, r2, y1, y2]) {
+            return false;
+        }
+
+        let cond1 = *r1 == self.pow2(&self.alpha, s, y1, c);
+        let cond2 = *r2 == self.pow2(&self.beta, s, y2, c);
 
         cond1 && cond2
     }
 
+    // s + q would pass the equations as well, and identity elements make
+    // them trivially true (y = 1 means x = 0, r = 1 means k = 0), so every
+    // proof check wants s < q and the elements in (1, p).
+    pub(crate) fn in_range(&self, s: &BigUint, elements: &[&BigUint]) -> bool {
+        let one = BigUint::from(1u32);
+        *s < self.q && elements.iter().all(|e| **e > one && **e < self.p)
+    }
+
     pub fn generate_random_below(bound: &BigUint) -> BigUint {
         let mut rng = rand::thread_rng();
         rng.gen_biguint_below(bound)
     }
+
+    // Uniform in [1, bound), for secrets and nonces: x = 0 makes y the
+    // identity and k = 0 sends r1 = r2 = 1 and s = -c * x, which gives x
+    // away. Draw c and other public values with `generate_random_below`.
+    pub fn generate_random_nonzero_below(bound: &BigUint) -> BigUint {
+        let mut rng = rand::thread_rng();
+        rng.gen_biguint_range(&BigUint::from(1u32), bound)
+    }
+
+    pub fn generate_random_string(size: usize) -> String {
+        rand::thread_rng()
+            .sample_iter(rand::distributions::Alphanumeric)
+            .take(size)
+            .map(char::from)
+            .collect()
+    }
+
+    // Reference: https://www.rfc-editor.org/rfc/rfc5114#page-15
+    // output => (alpha, beta, p, q)
+    pub fn get_constants() -> (BigUint, BigUint, BigUint, BigUint) {
+        let int = |hex_str: &str| BigUint::from_bytes_be(&hex::decode(hex_str).unwrap());
+        let p = int(constants::RFC5114_1024_P);
+        let q = int(constants::RFC5114_1024_Q);
+        let alpha = int(constants::RFC5114_1024_ALPHA);
+
+        // beta = alpha^i is also a generator
+        let beta = alpha.modpow(&int(constants::RFC5114_1024_BETA_EXPONENT), &p);
+
+        (alpha, beta, p, q)
+    }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod test {
     use super::*;
 
@@ -111,7 +322,8 @@ mod test {
         };
 
         let x = BigUint::from(6u32);
-        let k = ZKP::generate_random_below(&q);
+        // k = 0 gives the identity commitment, which is rejected
+        let k = ZKP::generate_random_nonzero_below(&q);
 
         let c: BigUint = ZKP::generate_random_below(&q);
 
@@ -190,4 +402,16 @@ mod test {
         let result = zkp.verify(&r1, &r2, &y1, &y2, &s, &c);
         assert!(result);
     }
+
+    #[test]
+    fn test_random_nonzero_below() {
+        let q = BigUint::from(11u32);
+        assert!((0..32).all(|_| {
+            let k = ZKP::generate_random_nonzero_below(&q);
+            k > BigUint::from(0u32) && k < q
+        }));
+
+        let two = BigUint::from(2u32);
+        assert!((0..32).all(|_| ZKP::generate_random_nonzero_below(&two) == BigUint::from(1u32)));
+    }
 }
@@ -0,0 +1,342 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use http::{header, Request, Response, StatusCode};
+use num_bigint::BigUint;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::policy::Policy;
+use crate::store::{InMemoryUserStore, UserStore};
+use crate::verifier::Verifier;
+use crate::Error;
+
+// Protects a tower service (an axum route, a hyper service...) with the
+// sessions a verifier grants after a successful proof:
+//
+//     let verifier = Arc::new(Mutex::new(verifier));
+//     let app = Router::new()
+//         .route("/account", get(account))
+//         .layer(ZkpAuthLayer::new(verifier.clone()));
+//
+// Requests must carry `Authorization: Bearer <session_id>`. The user the
+// session belongs to is put in the request extensions as
+// `AuthenticatedUser`, anything else is answered with 401 without reaching
+// the inner service. Sessions are looked up on every request, so one
+// revoked with `Verifier::revoke_session` fails the next.
+//
+// With a policy the operation of a request is "METHOD path" ("POST
+// /payments/send"), and a session that is not enough for it is answered
+// with 401 and `error="insufficient_user_authentication"` (RFC 9470): the
+// client logs in again, or proves for the request itself if the policy asks
+// for a fresh proof. That proof is the answer to an action challenge whose
+// context is the operation, sent instead of the session:
+//
+//     Authorization: ZKP-Action <auth_id> <s in hex>
+pub struct ZkpAuthLayer<S = InMemoryUserStore> {
+    verifier: Arc<Mutex<Verifier<S>>>,
+    policy: Option<Arc<Policy>>,
+}
+
+impl<S> ZkpAuthLayer<S> {
+    pub fn new(verifier: Arc<Mutex<Verifier<S>>>) -> Self {
+        ZkpAuthLayer {
+            verifier,
+            policy: None,
+        }
+    }
+
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.policy = Some(Arc::new(policy));
+        self
+    }
+}
+
+impl<S> Clone for ZkpAuthLayer<S> {
+    fn clone(&self) -> Self {
+        ZkpAuthLayer {
+            verifier: self.verifier.clone(),
+            policy: self.policy.clone(),
+        }
+    }
+}
+
+impl<I, S> Layer<I> for ZkpAuthLayer<S> {
+    type Service = ZkpAuth<I, S>;
+
+    fn layer(&self, inner: I) -> Self::Service {
+        ZkpAuth {
+            inner,
+            verifier: self.verifier.clone(),
+            policy: self.policy.clone(),
+        }
+    }
+}
+
+// The user name behind the session of the request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthenticatedUser(pub String);
+
+pub struct ZkpAuth<I, S = InMemoryUserStore> {
+    inner: I,
+    verifier: Arc<Mutex<Verifier<S>>>,
+    policy: Option<Arc<Policy>>,
+}
+
+impl<I: Clone, S> Clone for ZkpAuth<I, S> {
+    fn clone(&self) -> Self {
+        ZkpAuth {
+            inner: self.inner.clone(),
+            verifier: self.verifier.clone(),
+            policy: self.policy.clone(),
+        }
+    }
+}
+
+impl<I, S> ZkpAuth<I, S>
+where
+    S: UserStore,
+{
+    fn authenticate<B>(&self, request: &Request<B>) -> Result<AuthenticatedUser, Error> {
+        let authorization = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .ok_or(Error::SessionNotFound)?;
+        let operation = format!("{} {}", request.method(), request.uri().path());
+
+        let mut verifier = self.verifier.lock().map_err(|_| Error::SessionNotFound)?;
+        if let Some(answer) = authorization.strip_prefix("ZKP-Action ") {
+            let (auth_id, s) = action_answer(answer)?;
+            let user_name = match &self.policy {
+                Some(policy) => {
+                    verifier
+                        .authorize_action(policy, &operation, auth_id, &s)?
+                        .0
+                }
+                None => verifier.verify_action(auth_id, &s, operation.as_bytes())?,
+            };
+            return Ok(AuthenticatedUser(user_name));
+        }
+
+        let session_id = authorization
+            .strip_prefix("Bearer ")
+            .ok_or(Error::SessionNotFound)?
+            .trim();
+        let user_name = match &self.policy {
+            Some(policy) => verifier.authorize(policy, &operation, session_id)?,
+            None => verifier
+                .session_user(session_id)
+                .ok_or(Error::SessionNotFound)?
+                .to_string(),
+        };
+        Ok(AuthenticatedUser(user_name))
+    }
+}
+
+// "<auth_id> <s in hex>"
+fn action_answer(answer: &str) -> Result<(&str, BigUint), Error> {
+    let invalid = || Error::InvalidEncoding("expected ZKP-Action <auth_id> <s>".to_string());
+    let (auth_id, s) = answer.trim().split_once(' ').ok_or_else(invalid)?;
+    let s = BigUint::parse_bytes(s.trim().as_bytes(), 16).ok_or_else(invalid)?;
+    Ok((auth_id, s))
+}
+
+impl<I, S, ReqBody, ResBody> Service<Request<ReqBody>> for ZkpAuth<I, S>
+where
+    I: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S: UserStore,
+    ResBody: Default,
+{
+    type Response = Response<ResBody>;
+    type Error = I::Error;
+    type Future = ResponseFuture<I::Future, ResBody>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<ReqBody>) -> Self::Future {
+        match self.authenticate(&request) {
+            Ok(user) => {
+                request.extensions_mut().insert(user);
+                ResponseFuture::Authorized {
+                    future: self.inner.call(request),
+                }
+            }
+            Err(error) => {
+                trace_debug!(%error, "request refused");
+                ResponseFuture::Unauthorized {
+                    response: Some(unauthorized(&error)),
+                }
+            }
+        }
+    }
+}
+
+fn unauthorized<B: Default>(error: &Error) -> Response<B> {
+    let challenge = match error {
+        Error::StepUpRequired(_) => r#"Bearer error="insufficient_user_authentication""#,
+        _ => "Bearer",
+    };
+    let mut response = Response::new(B::default());
+    *response.status_mut() = StatusCode::UNAUTHORIZED;
+    response.headers_mut().insert(
+        header::WWW_AUTHENTICATE,
+        header::HeaderValue::from_static(challenge),
+    );
+    response
+}
+
+pin_project_lite::pin_project! {
+    #[project = ResponseFutureProj]
+    pub enum ResponseFuture<F, B> {
+        Authorized { #[pin] future: F },
+        Unauthorized { response: Option<Response<B>> },
+    }
+}
+
+impl<F, B, E> Future for ResponseFuture<F, B>
+where
+    F: Future<Output = Result<Response<B>, E>>,
+{
+    type Output = Result<Response<B>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            ResponseFutureProj::Authorized { future } => future.poll(cx),
+            ResponseFutureProj::Unauthorized { response } => Poll::Ready(Ok(response
+                .take()
+                .expect("ResponseFuture polled after completion"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+    use crate::policy::Requirement;
+    use crate::proof::Secret;
+    use crate::prover::Prover;
+    use crate::ZKP;
+    use std::convert::Infallible;
+    use std::future::{ready, Ready};
+
+    // answers with the authenticated user, or "" if the layer let an
+    // anonymous request through
+    struct Echo;
+
+    impl Service<Request<()>> for Echo {
+        type Response = Response<String>;
+        type Error = Infallible;
+        type Future = Ready<Result<Response<String>, Infallible>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: Request<()>) -> Self::Future {
+            let user = request
+                .extensions()
+                .get::<AuthenticatedUser>()
+                .map(|user| user.0.clone())
+                .unwrap_or_default();
+            ready(Ok(Response::new(user)))
+        }
+    }
+
+    fn request(authorization: Option<&str>) -> Request<()> {
+        request_to("/account", authorization)
+    }
+
+    fn request_to(path: &str, authorization: Option<&str>) -> Request<()> {
+        let mut builder = Request::builder().uri(path);
+        if let Some(value) = authorization {
+            builder = builder.header(header::AUTHORIZATION, value);
+        }
+        builder.body(()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_layer_checks_sessions() {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let zkp = set.zkp();
+        let mut verifier = Verifier::with_parameter_sets(set, &[set]);
+        let mut prover = Prover::new(zkp.clone(), set.id(), "alice", Secret::random(&zkp));
+        prover.register(&mut verifier).unwrap();
+        let session_id = prover.login(&mut verifier).unwrap();
+
+        let verifier = Arc::new(Mutex::new(verifier));
+        let mut service = ZkpAuthLayer::new(verifier.clone()).layer(Echo);
+
+        let bearer = format!("Bearer {}", session_id);
+        let response = service.call(request(Some(&bearer))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), "alice");
+
+        for authorization in [None, Some("Bearer nope"), Some(session_id.as_str())] {
+            let response = service.call(request(authorization)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            assert_eq!(response.headers()[header::WWW_AUTHENTICATE], "Bearer");
+        }
+
+        verifier.lock().unwrap().revoke_all_for_user("alice");
+        let response = service.call(request(Some(&bearer))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_layer_applies_the_policy() {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let zkp = set.zkp();
+        let mut verifier = Verifier::with_parameter_sets(set, &[set]);
+        let secret = Secret::random(&zkp);
+        let mut prover = Prover::new(zkp.clone(), set.id(), "alice", secret.clone());
+        prover.register(&mut verifier).unwrap();
+        let bearer = format!("Bearer {}", prover.login(&mut verifier).unwrap());
+
+        let mut policy = Policy::new(Requirement::Session);
+        policy.require("GET /payments", Requirement::FreshProof);
+        let verifier = Arc::new(Mutex::new(verifier));
+        let mut service = ZkpAuthLayer::new(verifier.clone())
+            .with_policy(policy)
+            .layer(Echo);
+
+        let response = service.call(request(Some(&bearer))).await.unwrap();
+        assert_eq!(response.body(), "alice");
+        let response = service
+            .call(request_to("/payments/42", Some(&bearer)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.headers()[header::WWW_AUTHENTICATE],
+            r#"Bearer error="insufficient_user_authentication""#
+        );
+
+        // proving for the request itself, once
+        let k = ZKP::generate_random_nonzero_below(&zkp.q);
+        let (r1, r2) = (zkp.pow(&zkp.alpha, &k), zkp.pow(&zkp.beta, &k));
+        let (auth_id, c) = verifier
+            .lock()
+            .unwrap()
+            .create_action_challenge("alice", r1, r2, b"GET /payments/42")
+            .unwrap();
+        let s = zkp.solve(&k, &c, secret.x());
+        let answer = format!("ZKP-Action {} {}", auth_id, s.to_str_radix(16));
+        let response = service
+            .call(request_to("/payments/42", Some(&answer)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), "alice");
+        let response = service
+            .call(request_to("/payments/42", Some(&answer)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,148 @@
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+use crate::proof::{Proof, PublicKey, Secret};
+use crate::ZKP;
+
+const MIGRATION_LABEL: &[u8] = b"zkp-chaum-pedersen/migration/v1";
+
+// Moves a user from one parameter set to another, e.g. off the 1024-bit
+// group: one proof for the old key and one for the new, both answering the
+// same challenge over the whole statement (user, both sets, both keys, all
+// four commitments). Neither half can be replayed alone or moved onto
+// another new key, so whoever holds the old secret is the one choosing the
+// new key.
+//
+// The new secret is a fresh one. The groups have different orders, so
+// proving the very same x in both would need a range proof on top, and
+// carrying a 160-bit x into a 2048-bit group would keep its old strength.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationRequest {
+    pub user_name: String,
+    pub from_set: String,
+    pub to_set: String,
+    pub new_key: PublicKey,
+    pub old_proof: Proof,
+    pub new_proof: Proof,
+}
+
+impl MigrationRequest {
+    pub fn prove(
+        user_name: &str,
+        (from_set, old_zkp): (&str, &ZKP),
+        old_secret: &Secret,
+        (to_set, new_zkp): (&str, &ZKP),
+        new_secret: &Secret,
+    ) -> Self {
+        let old_key = old_zkp.public_key(old_secret);
+        let new_key = new_zkp.public_key(new_secret);
+
+        let old_k = ZKP::generate_random_nonzero_below(&old_zkp.q);
+        let new_k = ZKP::generate_random_nonzero_below(&new_zkp.q);
+        let mut request = MigrationRequest {
+            user_name: user_name.to_string(),
+            from_set: from_set.to_string(),
+            to_set: to_set.to_string(),
+            new_key,
+            old_proof: Proof {
+                r1: old_zkp.pow(&old_zkp.alpha, &old_k),
+                r2: old_zkp.pow(&old_zkp.beta, &old_k),
+                s: BigUint::from(0u32),
+            },
+            new_proof: Proof {
+                r1: new_zkp.pow(&new_zkp.alpha, &new_k),
+                r2: new_zkp.pow(&new_zkp.beta, &new_k),
+                s: BigUint::from(0u32),
+            },
+        };
+
+        let (old_c, new_c) = request.challenges(old_zkp, &old_key, new_zkp);
+        request.old_proof.s = old_zkp.solve(&old_k, &old_c, old_secret.x());
+        request.new_proof.s = new_zkp.solve(&new_k, &new_c, new_secret.x());
+        request
+    }
+
+    // Both halves against the old key stored for the user.
+    pub fn verify(&self, old_zkp: &ZKP, old_key: &PublicKey, new_zkp: &ZKP) -> bool {
+        let (old_c, new_c) = self.challenges(old_zkp, old_key, new_zkp);
+        let old = &self.old_proof;
+        let new = &self.new_proof;
+
+        old_zkp.verify(&old.r1, &old.r2, &old_key.y1, &old_key.y2, &old.s, &old_c)
+            && new_zkp.verify(
+                &new.r1,
+                &new.r2,
+                &self.new_key.y1,
+                &self.new_key.y2,
+                &new.s,
+                &new_c,
+            )
+    }
+
+    // one digest, reduced mod each q
+    fn challenges(&self, old_zkp: &ZKP, old_key: &PublicKey, new_zkp: &ZKP) -> (BigUint, BigUint) {
+        let mut hasher = Sha256::new();
+        hasher.update(MIGRATION_LABEL);
+        for field in [&self.user_name, &self.from_set, &self.to_set] {
+            hasher.update((field.len() as u32).to_be_bytes());
+            hasher.update(field.as_bytes());
+        }
+        let old = [
+            &old_key.y1,
+            &old_key.y2,
+            &self.old_proof.r1,
+            &self.old_proof.r2,
+        ];
+        let new = [
+            &self.new_key.y1,
+            &self.new_key.y2,
+            &self.new_proof.r1,
+            &self.new_proof.r2,
+        ];
+        for (zkp, values) in [(old_zkp, old), (new_zkp, new)] {
+            for n in values {
+                let bytes = zkp.element_to_bytes(n);
+                hasher.update((bytes.len() as u32).to_be_bytes());
+                hasher.update(&bytes);
+            }
+        }
+
+        let digest = BigUint::from_bytes_be(&hasher.finalize());
+        (old_zkp.reduce_q(&digest), new_zkp.reduce_q(&digest))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+
+    #[test]
+    fn test_migration_request() {
+        let (from, to) = (ParameterSet::Rfc5114_1024_160, ParameterSet::Rfc3526_2048);
+        let (old_zkp, new_zkp) = (from.zkp(), to.zkp());
+        let old_secret = Secret::random(&old_zkp);
+        let old_key = old_zkp.public_key(&old_secret);
+        let new_secret = Secret::random(&new_zkp);
+
+        let request = MigrationRequest::prove(
+            "alice",
+            (from.id(), &old_zkp),
+            &old_secret,
+            (to.id(), &new_zkp),
+            &new_secret,
+        );
+        assert_eq!(request.new_key, new_zkp.public_key(&new_secret));
+        assert!(request.verify(&old_zkp, &old_key, &new_zkp));
+
+        // the halves are bound to each other and to the user
+        let other_key = old_zkp.public_key(&Secret::random(&old_zkp));
+        assert!(!request.verify(&old_zkp, &other_key, &new_zkp));
+        let mut moved = request.clone();
+        moved.new_key = new_zkp.public_key(&Secret::random(&new_zkp));
+        assert!(!moved.verify(&old_zkp, &old_key, &new_zkp));
+        let mut renamed = request;
+        renamed.user_name = "bob".to_string();
+        assert!(!renamed.verify(&old_zkp, &old_key, &new_zkp));
+    }
+}
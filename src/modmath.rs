@@ -0,0 +1,79 @@
+use num_bigint::BigUint;
+
+// Modular arithmetic on BigUint, the one place the rest of the crate does
+// it. Operands may be anything, including values >= m; results are always
+// in [0, m). m must not be 0.
+
+// output => n mod m
+pub fn mod_reduce(n: &BigUint, m: &BigUint) -> BigUint {
+    n % m
+}
+
+// output => a - b mod m, without underflowing when b > a
+pub fn mod_sub(a: &BigUint, b: &BigUint, m: &BigUint) -> BigUint {
+    let (a, b) = (a % m, b % m);
+    if a >= b {
+        a - b
+    } else {
+        m - (b - a)
+    }
+}
+
+// output => a * b mod m
+pub fn mod_mul(a: &BigUint, b: &BigUint, m: &BigUint) -> BigUint {
+    (a * b) % m
+}
+
+// output => base^exponent mod m
+pub fn mod_exp(base: &BigUint, exponent: &BigUint, m: &BigUint) -> BigUint {
+    base.modpow(exponent, m)
+}
+
+// output => a^-1 mod m for a prime m (Fermat: a^(m - 2)), 0 for a = 0 mod m
+pub fn mod_inv_prime(a: &BigUint, m: &BigUint) -> BigUint {
+    if *m < BigUint::from(2u32) {
+        return BigUint::from(0u32);
+    }
+    mod_exp(a, &(m - 2u32), m)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn n(value: u32) -> BigUint {
+        BigUint::from(value)
+    }
+
+    #[test]
+    fn test_operands_at_or_above_the_modulus() {
+        let m = n(11);
+        assert_eq!(mod_reduce(&n(11), &m), n(0));
+        assert_eq!(mod_reduce(&n(25), &m), n(3));
+
+        assert_eq!(mod_sub(&n(3), &n(5), &m), n(9));
+        assert_eq!(mod_sub(&n(5), &n(5), &m), n(0));
+        // reduced first, 11 - 0 would not be
+        assert_eq!(mod_sub(&n(11), &n(0), &m), n(0));
+        assert_eq!(mod_sub(&n(0), &n(22), &m), n(0));
+        assert_eq!(mod_sub(&n(1), &n(23), &m), n(0));
+        assert_eq!(mod_sub(&n(14), &n(30), &m), n(6));
+
+        assert_eq!(mod_mul(&n(12), &n(13), &m), n(2));
+        assert_eq!(mod_mul(&n(11), &n(7), &m), n(0));
+
+        assert_eq!(mod_exp(&n(14), &n(2), &m), n(9));
+        assert_eq!(mod_exp(&n(5), &n(0), &m), n(1));
+        assert_eq!(mod_exp(&n(5), &n(0), &n(1)), n(0));
+    }
+
+    #[test]
+    fn test_inverse() {
+        let m = n(23);
+        for a in 1..23 {
+            assert_eq!(mod_mul(&n(a), &mod_inv_prime(&n(a), &m), &m), n(1));
+        }
+        assert_eq!(mod_inv_prime(&n(24), &m), n(1));
+        assert_eq!(mod_inv_prime(&n(0), &m), n(0));
+    }
+}
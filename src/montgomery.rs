@@ -0,0 +1,366 @@
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
+
+use num_bigint::BigUint;
+
+// Precomputed reduction contexts for one group: Montgomery for p, where all
+// the exponentiations happen, and Barrett for q, where the responses and
+// challenges are reduced. `BigUint::modpow` derives its Montgomery constants
+// (including R^2 mod p, a full division) on every call; these are derived
+// once per group and kept, see `GroupContext::cached`.
+#[derive(Debug, Clone)]
+pub struct GroupContext {
+    pub p: Montgomery,
+    pub q: Barrett,
+}
+
+// p => (q, context) for every group seen, None where p is even
+type ContextTable = HashMap<BigUint, Vec<(BigUint, Option<Arc<GroupContext>>)>>;
+
+// A process works in a handful of groups; past this many the table starts
+// over rather than growing with every custom group.
+const MAX_CACHED_GROUPS: usize = 64;
+
+impl GroupContext {
+    // None if p is even, Montgomery form needs an odd modulus.
+    pub fn new(p: &BigUint, q: &BigUint) -> Option<Self> {
+        Some(GroupContext {
+            p: Montgomery::new(p)?,
+            q: Barrett::new(q),
+        })
+    }
+
+    // The context for (p, q), built on first use and shared by every `ZKP`
+    // with these moduli. Kept in a table rather than in `ZKP` so the group
+    // stays a plain struct of four public fields.
+    pub fn cached(p: &BigUint, q: &BigUint) -> Option<Arc<GroupContext>> {
+        static TABLE: OnceLock<RwLock<ContextTable>> = OnceLock::new();
+        let table = TABLE.get_or_init(Default::default);
+        let find = |table: &ContextTable| {
+            let groups = table.get(p)?;
+            let (_, context) = groups.iter().find(|(cached_q, _)| cached_q == q)?;
+            Some(context.clone())
+        };
+
+        if let Some(context) = find(&table.read().unwrap_or_else(PoisonError::into_inner)) {
+            return context;
+        }
+        let mut table = table.write().unwrap_or_else(PoisonError::into_inner);
+        if let Some(context) = find(&table) {
+            return context;
+        }
+        if table.values().map(Vec::len).sum::<usize>() >= MAX_CACHED_GROUPS {
+            table.clear();
+        }
+        let context = GroupContext::new(p, q).map(Arc::new);
+        table
+            .entry(p.clone())
+            .or_default()
+            .push((q.clone(), context.clone()));
+        context
+    }
+}
+
+// Arithmetic mod an odd n on 64-bit limbs, with R = 2^(64 * limbs).
+#[derive(Debug, Clone)]
+pub struct Montgomery {
+    modulus: BigUint,
+    // little-endian limbs of n
+    n: Vec<u64>,
+    // -n^-1 mod 2^64
+    n0_inv: u64,
+    // R^2 mod n
+    r2: Vec<u64>,
+}
+
+impl Montgomery {
+    pub fn new(modulus: &BigUint) -> Option<Self> {
+        if !modulus.bit(0) || *modulus == BigUint::from(1u32) {
+            return None;
+        }
+        let n = modulus.to_u64_digits();
+
+        // Newton iteration doubles the correct low bits every round
+        let mut inv: u64 = 1;
+        for _ in 0..6 {
+            inv = inv.wrapping_mul(2u64.wrapping_sub(n[0].wrapping_mul(inv)));
+        }
+        let r2 = (BigUint::from(1u32) << (128 * n.len())) % modulus;
+
+        Some(Montgomery {
+            modulus: modulus.clone(),
+            r2: limbs(&r2, n.len()),
+            n0_inv: inv.wrapping_neg(),
+            n,
+        })
+    }
+
+    pub fn modulus(&self) -> &BigUint {
+        &self.modulus
+    }
+
+    // base^exponent mod n, 4-bit fixed window
+    pub fn pow(&self, base: &BigUint, exponent: &BigUint) -> BigUint {
+        let table = self.window_table(base);
+        let mut acc = self.one();
+        for nibble in nibbles(exponent) {
+            for _ in 0..4 {
+                acc = self.mul(&acc, &acc);
+            }
+            if nibble != 0 {
+                acc = self.mul(&acc, &table[nibble as usize]);
+            }
+        }
+        self.leave(&acc)
+    }
+
+    // a^e * b^f mod n with one shared chain of squarings (Shamir's trick),
+    // what every Chaum-Pedersen check computes
+    pub fn pow2(&self, a: &BigUint, e: &BigUint, b: &BigUint, f: &BigUint) -> BigUint {
+        let a = self.enter(a);
+        let b = self.enter(b);
+        let ab = self.mul(&a, &b);
+
+        let bits = e.bits().max(f.bits());
+        let mut acc = self.one();
+        for i in (0..bits).rev() {
+            acc = self.mul(&acc, &acc);
+            match (e.bit(i), f.bit(i)) {
+                (true, true) => acc = self.mul(&acc, &ab),
+                (true, false) => acc = self.mul(&acc, &a),
+                (false, true) => acc = self.mul(&acc, &b),
+                (false, false) => {}
+            }
+        }
+        self.leave(&acc)
+    }
+
+    fn window_table(&self, base: &BigUint) -> Vec<Vec<u64>> {
+        let mut table = Vec::with_capacity(16);
+        table.push(self.one());
+        table.push(self.enter(base));
+        for i in 2..16 {
+            let next = self.mul(&table[i - 1], &table[1]);
+            table.push(next);
+        }
+        table
+    }
+
+    // R mod n
+    fn one(&self) -> Vec<u64> {
+        let mut one = vec![0; self.n.len()];
+        one[0] = 1;
+        self.mul(&one, &self.r2)
+    }
+
+    fn enter(&self, a: &BigUint) -> Vec<u64> {
+        let a = if *a >= self.modulus {
+            a % &self.modulus
+        } else {
+            a.clone()
+        };
+        self.mul(&limbs(&a, self.n.len()), &self.r2)
+    }
+
+    fn leave(&self, a: &[u64]) -> BigUint {
+        let mut one = vec![0; self.n.len()];
+        one[0] = 1;
+        from_limbs(&self.mul(a, &one))
+    }
+
+    // a * b * R^-1 mod n, finely integrated operand scanning: the product
+    // row and the reduction row are added in the same pass. t stays below
+    // 2n, so it needs one limb more than n.
+    fn mul(&self, a: &[u64], b: &[u64]) -> Vec<u64> {
+        let len = self.n.len();
+        let (a, b, n) = (&a[..len], &b[..len], &self.n[..len]);
+        let mut t = vec![0u64; len + 1];
+
+        for &b_i in b {
+            let (u, mut carry_ab) = mac(t[0], a[0], b_i, 0);
+            let m = u.wrapping_mul(self.n0_inv);
+            let (_, mut carry_mn) = mac(u, m, n[0], 0);
+            for j in 1..len {
+                let (u, carry) = mac(t[j], a[j], b_i, carry_ab);
+                carry_ab = carry;
+                let (u, carry) = mac(u, m, n[j], carry_mn);
+                carry_mn = carry;
+                t[j - 1] = u;
+            }
+            let top = t[len] as u128 + carry_ab as u128 + carry_mn as u128;
+            t[len - 1] = top as u64;
+            t[len] = (top >> 64) as u64;
+        }
+
+        if t[len] != 0 || !less_than(&t[..len], n) {
+            let mut borrow = false;
+            for (t_j, &n_j) in t.iter_mut().zip(n) {
+                let (diff, b1) = t_j.overflowing_sub(n_j);
+                let (diff, b2) = diff.overflowing_sub(borrow as u64);
+                *t_j = diff;
+                borrow = b1 || b2;
+            }
+        }
+        t.truncate(len);
+        t
+    }
+}
+
+// Reduction mod q for values below q^2 (products of two scalars, hashes of
+// up to twice the size of q) with mu = floor(4^k / q) computed once.
+#[derive(Debug, Clone)]
+pub struct Barrett {
+    modulus: BigUint,
+    // bit length of q
+    k: u64,
+    mu: BigUint,
+}
+
+impl Barrett {
+    pub fn new(modulus: &BigUint) -> Self {
+        let k = modulus.bits();
+        Barrett {
+            modulus: modulus.clone(),
+            k,
+            mu: (BigUint::from(1u32) << (2 * k)) / modulus,
+        }
+    }
+
+    pub fn modulus(&self) -> &BigUint {
+        &self.modulus
+    }
+
+    pub fn reduce(&self, x: &BigUint) -> BigUint {
+        if x.bits() > 2 * self.k {
+            return x % &self.modulus;
+        }
+        let estimate = ((x >> (self.k - 1)) * &self.mu) >> (self.k + 1);
+        // the estimate is short by at most 2 * q
+        let mut r = x - estimate * &self.modulus;
+        while r >= self.modulus {
+            r -= &self.modulus;
+        }
+        r
+    }
+}
+
+// a + b * c + carry, never overflows 128 bits
+fn mac(a: u64, b: u64, c: u64, carry: u64) -> (u64, u64) {
+    let wide = a as u128 + (b as u128) * (c as u128) + carry as u128;
+    (wide as u64, (wide >> 64) as u64)
+}
+
+fn less_than(a: &[u64], b: &[u64]) -> bool {
+    for (x, y) in a.iter().zip(b).rev() {
+        if x != y {
+            return x < y;
+        }
+    }
+    false
+}
+
+fn limbs(n: &BigUint, len: usize) -> Vec<u64> {
+    let mut digits = n.to_u64_digits();
+    digits.resize(len, 0);
+    digits
+}
+
+fn from_limbs(limbs: &[u64]) -> BigUint {
+    let digits: Vec<u32> = limbs
+        .iter()
+        .flat_map(|limb| [*limb as u32, (*limb >> 32) as u32])
+        .collect();
+    BigUint::new(digits)
+}
+
+// most significant first
+fn nibbles(n: &BigUint) -> impl Iterator<Item = u8> {
+    let bytes = n.to_bytes_be();
+    let mut nibbles = Vec::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        nibbles.push(byte >> 4);
+        nibbles.push(byte & 0x0f);
+    }
+    nibbles.into_iter()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+    use crate::ZKP;
+
+    #[test]
+    fn test_montgomery_matches_modpow() {
+        for set in [ParameterSet::Rfc5114_1024_160, ParameterSet::Rfc3526_2048] {
+            let zkp = set.zkp();
+            let ctx = Montgomery::new(&zkp.p).unwrap();
+            let e = ZKP::generate_random_below(&zkp.q);
+            let f = ZKP::generate_random_below(&zkp.q);
+
+            assert_eq!(ctx.pow(&zkp.alpha, &e), zkp.alpha.modpow(&e, &zkp.p));
+            assert_eq!(
+                ctx.pow2(&zkp.alpha, &e, &zkp.beta, &f),
+                (zkp.alpha.modpow(&e, &zkp.p) * zkp.beta.modpow(&f, &zkp.p)) % &zkp.p
+            );
+            // edge cases: zero exponent, base above the modulus
+            assert_eq!(
+                ctx.pow(&zkp.alpha, &BigUint::from(0u32)),
+                BigUint::from(1u32)
+            );
+            let above = &zkp.p + 5u32;
+            assert_eq!(ctx.pow(&above, &e), above.modpow(&e, &zkp.p));
+        }
+
+        let toy = Montgomery::new(&BigUint::from(23u32)).unwrap();
+        assert_eq!(
+            toy.pow(&BigUint::from(4u32), &BigUint::from(6u32)),
+            BigUint::from(2u32)
+        );
+        assert!(Montgomery::new(&BigUint::from(24u32)).is_none());
+    }
+
+    #[test]
+    fn test_context_follows_the_group() {
+        let mut zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        assert!(zkp.context().is_some());
+        let e = ZKP::generate_random_below(&zkp.q);
+        assert_eq!(zkp.pow(&zkp.alpha, &e), zkp.alpha.modpow(&e, &zkp.p));
+
+        // looked up by the current moduli, never stale
+        zkp.p = BigUint::from(23u32);
+        zkp.q = BigUint::from(11u32);
+        assert_eq!(*zkp.context().unwrap().p.modulus(), zkp.p);
+        assert_eq!(
+            zkp.pow(&BigUint::from(4u32), &BigUint::from(6u32)),
+            BigUint::from(2u32)
+        );
+
+        // a group written as a plain struct gets one too
+        let set = ParameterSet::Rfc5114_1024_160.zkp();
+        let literal = ZKP {
+            p: set.p.clone(),
+            q: set.q.clone(),
+            alpha: set.alpha.clone(),
+            beta: set.beta.clone(),
+        };
+        assert_eq!(*literal.context().unwrap().q.modulus(), set.q);
+        zkp.p = BigUint::from(24u32);
+        assert!(zkp.context().is_none());
+    }
+
+    #[test]
+    fn test_barrett_matches_remainder() {
+        for set in [ParameterSet::Rfc5114_1024_160, ParameterSet::Rfc3526_2048] {
+            let zkp = set.zkp();
+            let ctx = Barrett::new(&zkp.q);
+            for _ in 0..20 {
+                let a = ZKP::generate_random_below(&zkp.q);
+                let b = ZKP::generate_random_below(&zkp.q);
+                assert_eq!(ctx.reduce(&(&a * &b)), (&a * &b) % &zkp.q);
+            }
+            let wide = &zkp.q * &zkp.q * 3u32;
+            assert_eq!(ctx.reduce(&wide), BigUint::from(0u32));
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use std::fmt;
+use std::str::FromStr;
+
+use num_bigint::BigUint;
+
+use crate::constants::MODP_2048_P;
+use crate::ZKP;
+
+// Named group parameters a user can register under. The id is what goes on
+// the wire and in the verifier's records, so it must never change once
+// published.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParameterSet {
+    // RFC 5114 1024-bit MODP group with 160-bit prime order subgroup (legacy)
+    Rfc5114_1024_160,
+    // RFC 3526 2048-bit MODP group (safe prime, q = (p - 1) / 2)
+    Rfc3526_2048,
+    // RFC 3526 3072-bit MODP group (safe prime, q = (p - 1) / 2)
+    Rfc3526_3072,
+    // RFC 3526 8192-bit MODP group (safe prime, q = (p - 1) / 2)
+    Rfc3526_8192,
+}
+
+// Symmetric-equivalent strength as in NIST SP 800-57 part 1, table 2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SecurityLevel {
+    Bits112,
+    Bits128,
+    Bits192,
+}
+
+impl SecurityLevel {
+    pub fn bits(&self) -> u32 {
+        match self {
+            SecurityLevel::Bits112 => 112,
+            SecurityLevel::Bits128 => 128,
+            SecurityLevel::Bits192 => 192,
+        }
+    }
+
+    // Smallest built-in parameter set reaching the level: 2048, 3072 and
+    // 7680 bits of p are needed respectively, there is no 7680-bit MODP
+    // group so 192 bits maps to the 8192-bit one.
+    pub fn parameter_set(&self) -> ParameterSet {
+        match self {
+            SecurityLevel::Bits112 => ParameterSet::Rfc3526_2048,
+            SecurityLevel::Bits128 => ParameterSet::Rfc3526_3072,
+            SecurityLevel::Bits192 => ParameterSet::Rfc3526_8192,
+        }
+    }
+}
+
+impl ParameterSet {
+    pub const ALL: [ParameterSet; 4] = [
+        ParameterSet::Rfc5114_1024_160,
+        ParameterSet::Rfc3526_2048,
+        ParameterSet::Rfc3526_3072,
+        ParameterSet::Rfc3526_8192,
+    ];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            ParameterSet::Rfc5114_1024_160 => "rfc5114-1024-160",
+            ParameterSet::Rfc3526_2048 => "rfc3526-2048",
+            ParameterSet::Rfc3526_3072 => "rfc3526-3072",
+            ParameterSet::Rfc3526_8192 => "rfc3526-8192",
+        }
+    }
+
+    // Estimated strength in bits, the 1024-bit group only gives about 80.
+    pub fn security_bits(&self) -> u32 {
+        match self {
+            ParameterSet::Rfc5114_1024_160 => 80,
+            ParameterSet::Rfc3526_2048 => 112,
+            ParameterSet::Rfc3526_3072 => 128,
+            ParameterSet::Rfc3526_8192 => 200,
+        }
+    }
+
+    pub fn zkp(&self) -> ZKP {
+        match self {
+            ParameterSet::Rfc5114_1024_160 => {
+                let (alpha, beta, p, q) = ZKP::get_constants();
+                ZKP::new(p, q, alpha, beta)
+            }
+            ParameterSet::Rfc3526_2048 => safe_prime_group(MODP_2048_P),
+            ParameterSet::Rfc3526_3072 => safe_prime_group(MODP_3072_P),
+            ParameterSet::Rfc3526_8192 => safe_prime_group(MODP_8192_P),
+        }
+    }
+}
+
+impl fmt::Display for ParameterSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+impl FromStr for ParameterSet {
+    type Err = crate::Error;
+
+    fn from_str(id: &str) -> Result<Self, Self::Err> {
+        ParameterSet::ALL
+            .iter()
+            .find(|set| set.id() == id)
+            .copied()
+            .ok_or_else(|| crate::Error::UnknownParameterSet(id.to_string()))
+    }
+}
+
+impl ZKP {
+    pub fn for_security_level(level: SecurityLevel) -> ZKP {
+        level.parameter_set().zkp()
+    }
+}
+
+// For a safe prime p = 2q + 1 the squares form the subgroup of order q, so
+// alpha = 2^2 and beta = 3^2 are generators with no known relation between
+// them.
+fn safe_prime_group(p_hex: &str) -> ZKP {
+    let p = BigUint::from_bytes_be(&hex::decode(p_hex).unwrap());
+    let q = (&p - 1u32) >> 1;
+
+    ZKP::new(p, q, BigUint::from(4u32), BigUint::from(9u32))
+}
+
+// RFC 3526 group, 3072-bit MODP
+const MODP_3072_P: &str = concat!(
+    "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74020BBEA63B139B22514A08798E3404DD",
+    "EF9519B3CD3A431B302B0A6DF25F14374FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED",
+    "EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF0598DA48361C55D39A69163FA8FD24CF5F",
+    "83655D23DCA3AD961C62F356208552BB9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B",
+    "E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF6955817183995497CEA956AE515D2261898FA0510",
+    "15728E5A8AAAC42DAD33170D04507A33A85521ABDF1CBA64ECFB850458DBEF0A8AEA71575D060C7DB3970F85A6E1E4C7",
+    "ABF5AE8CDB0933D71E8C94E04A25619DCEE3D2261AD2EE6BF12FFA06D98A0864D87602733EC86A64521F2B18177B200C",
+    "BBE117577A615D6C770988C0BAD946E208E24FA074E5AB3143DB5BFCE0FD108E4B82D120A93AD2CAFFFFFFFFFFFFFFFF",
+);
+
+// RFC 3526 group, 8192-bit MODP
+const MODP_8192_P: &str = concat!(
+    "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74020BBEA63B139B22514A08798E3404DD",
+    "EF9519B3CD3A431B302B0A6DF25F14374FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED",
+    "EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF0598DA48361C55D39A69163FA8FD24CF5F",
+    "83655D23DCA3AD961C62F356208552BB9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B",
+    "E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF6955817183995497CEA956AE515D2261898FA0510",
+    "15728E5A8AAAC42DAD33170D04507A33A85521ABDF1CBA64ECFB850458DBEF0A8AEA71575D060C7DB3970F85A6E1E4C7",
+    "ABF5AE8CDB0933D71E8C94E04A25619DCEE3D2261AD2EE6BF12FFA06D98A0864D87602733EC86A64521F2B18177B200C",
+    "BBE117577A615D6C770988C0BAD946E208E24FA074E5AB3143DB5BFCE0FD108E4B82D120A92108011A723C12A787E6D7",
+    "88719A10BDBA5B2699C327186AF4E23C1A946834B6150BDA2583E9CA2AD44CE8DBBBC2DB04DE8EF92E8EFC141FBECAA6",
+    "287C59474E6BC05D99B2964FA090C3A2233BA186515BE7ED1F612970CEE2D7AFB81BDD762170481CD0069127D5B05AA9",
+    "93B4EA988D8FDDC186FFB7DC90A6C08F4DF435C93402849236C3FAB4D27C7026C1D4DCB2602646DEC9751E763DBA37BD",
+    "F8FF9406AD9E530EE5DB382F413001AEB06A53ED9027D831179727B0865A8918DA3EDBEBCF9B14ED44CE6CBACED4BB1B",
+    "DB7F1447E6CC254B332051512BD7AF426FB8F401378CD2BF5983CA01C64B92ECF032EA15D1721D03F482D7CE6E74FEF6",
+    "D55E702F46980C82B5A84031900B1C9E59E7C97FBEC7E8F323A97A7E36CC88BE0F1D45B7FF585AC54BD407B22B4154AA",
+    "CC8F6D7EBF48E1D814CC5ED20F8037E0A79715EEF29BE32806A1D58BB7C5DA76F550AA3D8A1FBFF0EB19CCB1A313D55C",
+    "DA56C9EC2EF29632387FE8D76E3C0468043E8F663F4860EE12BF2D5B0B7474D6E694F91E6DBE115974A3926F12FEE5E4",
+    "38777CB6A932DF8CD8BEC4D073B931BA3BC832B68D9DD300741FA7BF8AFC47ED2576F6936BA424663AAB639C5AE4F568",
+    "3423B4742BF1C978238F16CBE39D652DE3FDB8BEFC848AD922222E04A4037C0713EB57A81A23F0C73473FC646CEA306B",
+    "4BCBC8862F8385DDFA9D4B7FA2C087E879683303ED5BDD3A062B3CF5B3A278A66D2A13F83F44F82DDF310EE074AB6A36",
+    "4597E899A0255DC164F31CC50846851DF9AB48195DED7EA1B1D510BD7EE74D73FAF36BC31ECFA268359046F4EB879F92",
+    "4009438B481C6CD7889A002ED5EE382BC9190DA6FC026E479558E4475677E9AA9E3050E2765694DFC81F56E880B96E71",
+    "60C980DD98EDD3DFFFFFFFFFFFFFFFFF",
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ids_round_trip() {
+        for set in ParameterSet::ALL {
+            assert_eq!(set.id().parse::<ParameterSet>().unwrap(), set);
+        }
+        assert!("rfc5114-512".parse::<ParameterSet>().is_err());
+    }
+
+    #[test]
+    fn test_security_levels() {
+        for level in [
+            SecurityLevel::Bits112,
+            SecurityLevel::Bits128,
+            SecurityLevel::Bits192,
+        ] {
+            let set = level.parameter_set();
+            assert!(set.security_bits() >= level.bits());
+            assert_eq!(ZKP::for_security_level(level).p, set.zkp().p);
+        }
+        assert!(ParameterSet::Rfc5114_1024_160.security_bits() < SecurityLevel::Bits112.bits());
+        assert_eq!(
+            ZKP::for_security_level(SecurityLevel::Bits128).p.bits(),
+            3072
+        );
+    }
+
+    #[test]
+    fn test_generators_have_order_q() {
+        for set in ParameterSet::ALL {
+            let zkp = set.zkp();
+            let one = BigUint::from(1u32);
+
+            assert_eq!(zkp.alpha.modpow(&zkp.q, &zkp.p), one);
+            assert_eq!(zkp.beta.modpow(&zkp.q, &zkp.p), one);
+            assert_ne!(zkp.alpha, one);
+            assert_ne!(zkp.beta, one);
+        }
+    }
+}
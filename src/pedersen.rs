@@ -0,0 +1,185 @@
+use num_bigint::BigUint;
+
+use crate::dhparams::hash_to_subgroup;
+use crate::proof::{PublicKey, Secret};
+use crate::ZKP;
+
+const GENERATOR_LABEL: &[u8] = b"zkp-chaum-pedersen/pedersen/generator";
+const CHALLENGE_LABEL: &[u8] = b"zkp-chaum-pedersen/pedersen/committed-key";
+
+// Pedersen commitment to m < q over the same subgroup:
+//     C = alpha^m * h^r mod p
+// hiding for a random r and binding as long as nobody knows log_alpha(h),
+// which is why h is hashed into the group rather than picked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Commitment {
+    pub c: BigUint,
+}
+
+// Proof that a commitment hides the secret of a public key:
+//     C = alpha^x * h^r, y1 = alpha^x, y2 = beta^x
+// so a credential can carry y committed rather than revealed and still be
+// tied to the one logging in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommittedKeyProof {
+    pub t1: BigUint,
+    pub t2: BigUint,
+    pub t3: BigUint,
+    pub s_x: BigUint,
+    pub s_r: BigUint,
+}
+
+impl ZKP {
+    // A subgroup element nobody knows the log of, hashed from the group.
+    pub fn pedersen_generator(&self) -> BigUint {
+        hash_to_subgroup(
+            &self.p,
+            &self.q,
+            GENERATOR_LABEL,
+            &[&self.alpha, &self.beta],
+        )
+    }
+
+    // output => (commitment, r), r is the opening
+    pub fn commit_value(&self, m: &BigUint) -> (Commitment, Secret) {
+        let r = Secret::random(self);
+        (self.commit_value_with(m, &r), r)
+    }
+
+    pub fn commit_value_with(&self, m: &BigUint, r: &Secret) -> Commitment {
+        let h = self.pedersen_generator();
+        Commitment {
+            c: self.pow2(&self.alpha, m, &h, r.x()),
+        }
+    }
+
+    pub fn open_commitment(&self, commitment: &Commitment, m: &BigUint, r: &Secret) -> bool {
+        *m < self.q && self.commit_value_with(m, r) == *commitment
+    }
+
+    pub fn prove_committed_key(
+        &self,
+        secret: &Secret,
+        public_key: &PublicKey,
+        commitment: &Commitment,
+        r: &Secret,
+    ) -> CommittedKeyProof {
+        let h = self.pedersen_generator();
+        let k_x = ZKP::generate_random_nonzero_below(&self.q);
+        let k_r = ZKP::generate_random_nonzero_below(&self.q);
+
+        let t1 = self.pow2(&self.alpha, &k_x, &h, &k_r);
+        let t2 = self.pow(&self.alpha, &k_x);
+        let t3 = self.pow(&self.beta, &k_x);
+
+        let c = self.committed_key_challenge(&h, public_key, commitment, &t1, &t2, &t3);
+        let s_x = self.solve(&k_x, &c, secret.x());
+        let s_r = self.solve(&k_r, &c, r.x());
+
+        CommittedKeyProof {
+            t1,
+            t2,
+            t3,
+            s_x,
+            s_r,
+        }
+    }
+
+    // cond1: t1 = alpha^s_x * h^s_r * C^c
+    // cond2: t2 = alpha^s_x * y1^c
+    // cond3: t3 = beta^s_x * y2^c
+    pub fn verify_committed_key(
+        &self,
+        public_key: &PublicKey,
+        commitment: &Commitment,
+        proof: &CommittedKeyProof,
+    ) -> bool {
+        if proof.s_x >= self.q || proof.s_r >= self.q {
+            return false;
+        }
+        let elements = [&commitment.c, &public_key.y1, &public_key.y2];
+        if !elements.into_iter().all(|e| self.is_subgroup_element(e)) {
+            return false;
+        }
+
+        let h = self.pedersen_generator();
+        let c = self
+            .committed_key_challenge(&h, public_key, commitment, &proof.t1, &proof.t2, &proof.t3);
+
+        let cond1 = proof.t1
+            == (self.pow2(&self.alpha, &proof.s_x, &h, &proof.s_r) * self.pow(&commitment.c, &c))
+                % &self.p;
+        let cond2 = proof.t2 == self.pow2(&self.alpha, &proof.s_x, &public_key.y1, &c);
+        let cond3 = proof.t3 == self.pow2(&self.beta, &proof.s_x, &public_key.y2, &c);
+
+        cond1 && cond2 && cond3
+    }
+
+    fn committed_key_challenge(
+        &self,
+        h: &BigUint,
+        public_key: &PublicKey,
+        commitment: &Commitment,
+        t1: &BigUint,
+        t2: &BigUint,
+        t3: &BigUint,
+    ) -> BigUint {
+        self.hash_to_scalar(
+            CHALLENGE_LABEL,
+            &[
+                &self.alpha,
+                &self.beta,
+                h,
+                &public_key.y1,
+                &public_key.y2,
+                &commitment.c,
+                t1,
+                t2,
+                t3,
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+
+    #[test]
+    fn test_commit_and_open() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let h = zkp.pedersen_generator();
+        assert!(zkp.is_subgroup_element(&h));
+        assert_ne!(h, zkp.alpha);
+        assert_eq!(h, zkp.pedersen_generator());
+
+        let m = BigUint::from(42u32);
+        let (commitment, r) = zkp.commit_value(&m);
+        assert!(zkp.open_commitment(&commitment, &m, &r));
+        assert!(!zkp.open_commitment(&commitment, &BigUint::from(43u32), &r));
+
+        // hiding: the same value commits differently every time
+        let (again, _) = zkp.commit_value(&m);
+        assert_ne!(again, commitment);
+    }
+
+    #[test]
+    fn test_committed_key_proof() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let secret = Secret::random(&zkp);
+        let public_key = zkp.public_key(&secret);
+        let (commitment, r) = zkp.commit_value(secret.x());
+
+        let proof = zkp.prove_committed_key(&secret, &public_key, &commitment, &r);
+        assert!(zkp.verify_committed_key(&public_key, &commitment, &proof));
+
+        // another key, or a commitment to something else, doesn't verify
+        let other = zkp.public_key(&Secret::random(&zkp));
+        assert!(!zkp.verify_committed_key(&other, &commitment, &proof));
+        let (elsewhere, r) = zkp.commit_value(&BigUint::from(7u32));
+        assert!(!zkp.verify_committed_key(&public_key, &elsewhere, &proof));
+        let proof = zkp.prove_committed_key(&secret, &public_key, &elsewhere, &r);
+        assert!(!zkp.verify_committed_key(&public_key, &elsewhere, &proof));
+    }
+}
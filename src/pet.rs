@@ -0,0 +1,233 @@
+use num_bigint::BigUint;
+
+use crate::ddh::DdhProof;
+use crate::elgamal::Ciphertext;
+use crate::modmath::{mod_mul, mod_sub};
+use crate::proof::Secret;
+use crate::ZKP;
+
+const CHALLENGE_LABEL: &[u8] = b"zkp-chaum-pedersen/plaintext-equivalence/v1";
+
+// Proof that two ElGamal ciphertexts, possibly under different keys h1 and
+// h2, hide the same plaintext. Made by whoever knows both randomizers (the
+// encrypting voter or the mix server that re-encrypted):
+//     a1 = g^r1, a2 = g^r2, b1 / b2 = h1^r1 * h2^-r2
+// which is a Chaum-Pedersen style proof with two secrets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlaintextEquivalenceProof {
+    pub t1: BigUint,
+    pub t2: BigUint,
+    pub t3: BigUint,
+    pub s1: BigUint,
+    pub s2: BigUint,
+}
+
+impl ZKP {
+    pub fn prove_plaintext_equivalence(
+        &self,
+        h1: &BigUint,
+        ciphertext1: &Ciphertext,
+        r1: &Secret,
+        h2: &BigUint,
+        ciphertext2: &Ciphertext,
+        r2: &Secret,
+    ) -> PlaintextEquivalenceProof {
+        let k1 = ZKP::generate_random_nonzero_below(&self.q);
+        let k2 = ZKP::generate_random_nonzero_below(&self.q);
+
+        let t1 = self.pow(&self.alpha, &k1);
+        let t2 = self.pow(&self.alpha, &k2);
+        let t3 = self.h1_over_h2(h1, &k1, h2, &k2);
+
+        let c =
+            self.plaintext_equivalence_challenge(h1, ciphertext1, h2, ciphertext2, &t1, &t2, &t3);
+        let s1 = self.solve(&k1, &c, r1.x());
+        let s2 = self.solve(&k2, &c, r2.x());
+
+        PlaintextEquivalenceProof { t1, t2, t3, s1, s2 }
+    }
+
+    // cond1: t1 = g^s1 * a1^c
+    // cond2: t2 = g^s2 * a2^c
+    // cond3: t3 = h1^s1 * h2^-s2 * (b1 / b2)^c
+    pub fn verify_plaintext_equivalence(
+        &self,
+        h1: &BigUint,
+        ciphertext1: &Ciphertext,
+        h2: &BigUint,
+        ciphertext2: &Ciphertext,
+        proof: &PlaintextEquivalenceProof,
+    ) -> bool {
+        let elements = [
+            h1,
+            &ciphertext1.c1,
+            &ciphertext1.c2,
+            h2,
+            &ciphertext2.c1,
+            &ciphertext2.c2,
+            &proof.t1,
+            &proof.t2,
+            &proof.t3,
+        ];
+        if !elements.iter().all(|e| self.is_subgroup_element(e)) {
+            return false;
+        }
+        if proof.s1 >= self.q || proof.s2 >= self.q {
+            return false;
+        }
+
+        let c = self.plaintext_equivalence_challenge(
+            h1,
+            ciphertext1,
+            h2,
+            ciphertext2,
+            &proof.t1,
+            &proof.t2,
+            &proof.t3,
+        );
+
+        let cond1 = proof.t1 == self.pow2(&self.alpha, &proof.s1, &ciphertext1.c1, &c);
+        let cond2 = proof.t2 == self.pow2(&self.alpha, &proof.s2, &ciphertext2.c1, &c);
+
+        let b_ratio = self.divide(&ciphertext1.c2, &ciphertext2.c2);
+        let cond3 = proof.t3
+            == mod_mul(
+                &self.h1_over_h2(h1, &proof.s1, h2, &proof.s2),
+                &self.pow(&b_ratio, &c),
+                &self.p,
+            );
+
+        cond1 && cond2 && cond3
+    }
+
+    // Same-key variant made by the key holder, e.g. during a tally: with
+    // a = a1 / a2 and b = b1 / b2, (g, a, h, b) is a DH tuple with exponent
+    // x exactly when both ciphertexts hide the same plaintext.
+    pub fn prove_plaintext_equivalence_by_key(
+        &self,
+        secret: &Secret,
+        ciphertext1: &Ciphertext,
+        ciphertext2: &Ciphertext,
+    ) -> DdhProof {
+        let h = self.elgamal_public_key(secret);
+        let a = self.divide(&ciphertext1.c1, &ciphertext2.c1);
+        let b = self.divide(&ciphertext1.c2, &ciphertext2.c2);
+
+        self.prove_ddh_tuple(&self.alpha, &a, &h, &b, secret.x())
+    }
+
+    pub fn verify_plaintext_equivalence_by_key(
+        &self,
+        h: &BigUint,
+        ciphertext1: &Ciphertext,
+        ciphertext2: &Ciphertext,
+        proof: &DdhProof,
+    ) -> bool {
+        let a = self.divide(&ciphertext1.c1, &ciphertext2.c1);
+        let b = self.divide(&ciphertext1.c2, &ciphertext2.c2);
+
+        self.verify_ddh_tuple(&self.alpha, &a, h, &b, proof)
+    }
+
+    // h1^e1 * h2^-e2 mod p, elements have order q so h2^-e2 = h2^(q - e2)
+    fn h1_over_h2(&self, h1: &BigUint, e1: &BigUint, h2: &BigUint, e2: &BigUint) -> BigUint {
+        let e2_neg = mod_sub(&BigUint::from(0u32), e2, &self.q);
+        self.pow2(h1, e1, h2, &e2_neg)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn plaintext_equivalence_challenge(
+        &self,
+        h1: &BigUint,
+        ciphertext1: &Ciphertext,
+        h2: &BigUint,
+        ciphertext2: &Ciphertext,
+        t1: &BigUint,
+        t2: &BigUint,
+        t3: &BigUint,
+    ) -> BigUint {
+        self.hash_to_scalar(
+            CHALLENGE_LABEL,
+            &[
+                &self.alpha,
+                h1,
+                &ciphertext1.c1,
+                &ciphertext1.c2,
+                h2,
+                &ciphertext2.c1,
+                &ciphertext2.c2,
+                t1,
+                t2,
+                t3,
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+
+    #[test]
+    fn test_equivalence_under_different_keys() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let h1 = zkp.elgamal_public_key(&Secret::random(&zkp));
+        let h2 = zkp.elgamal_public_key(&Secret::random(&zkp));
+
+        let vote = zkp.encode(&BigUint::from(3u32));
+        let (ct1, r1) = zkp.encrypt(&h1, &vote);
+        let (ct2, r2) = zkp.encrypt(&h2, &vote);
+
+        let proof = zkp.prove_plaintext_equivalence(&h1, &ct1, &r1, &h2, &ct2, &r2);
+        assert!(zkp.verify_plaintext_equivalence(&h1, &ct1, &h2, &ct2, &proof));
+
+        // different plaintexts
+        let other_vote = zkp.encode(&BigUint::from(4u32));
+        let (ct3, r3) = zkp.encrypt(&h2, &other_vote);
+        let proof = zkp.prove_plaintext_equivalence(&h1, &ct1, &r1, &h2, &ct3, &r3);
+        assert!(!zkp.verify_plaintext_equivalence(&h1, &ct1, &h2, &ct3, &proof));
+    }
+
+    #[test]
+    fn test_elements_outside_the_group_are_refused() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let h1 = zkp.elgamal_public_key(&Secret::random(&zkp));
+        let h2 = zkp.elgamal_public_key(&Secret::random(&zkp));
+        let (ct1, r1) = zkp.encrypt(&h1, &zkp.encode(&BigUint::from(3u32)));
+        let (mut ct2, r2) = zkp.encrypt(&h2, &zkp.encode(&BigUint::from(4u32)));
+
+        // b2 = 0 makes b1 / b2 = 0, and t3 = 0 then holds for any c
+        ct2.c2 = BigUint::from(0u32);
+        let (k1, k2) = (BigUint::from(5u32), BigUint::from(7u32));
+        let t1 = zkp.pow(&zkp.alpha, &k1);
+        let t2 = zkp.pow(&zkp.alpha, &k2);
+        let t3 = BigUint::from(0u32);
+        let c = zkp.plaintext_equivalence_challenge(&h1, &ct1, &h2, &ct2, &t1, &t2, &t3);
+        let proof = PlaintextEquivalenceProof {
+            s1: zkp.solve(&k1, &c, r1.x()),
+            s2: zkp.solve(&k2, &c, r2.x()),
+            t1,
+            t2,
+            t3,
+        };
+        assert!(!zkp.verify_plaintext_equivalence(&h1, &ct1, &h2, &ct2, &proof));
+    }
+
+    #[test]
+    fn test_equivalence_by_key_holder() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let secret = Secret::random(&zkp);
+        let h = zkp.elgamal_public_key(&secret);
+
+        let vote = zkp.encode(&BigUint::from(1u32));
+        let (ct1, _) = zkp.encrypt(&h, &vote);
+        let (ct2, _) = zkp.encrypt(&h, &vote);
+        let proof = zkp.prove_plaintext_equivalence_by_key(&secret, &ct1, &ct2);
+        assert!(zkp.verify_plaintext_equivalence_by_key(&h, &ct1, &ct2, &proof));
+
+        let (ct3, _) = zkp.encrypt(&h, &zkp.encode(&BigUint::from(2u32)));
+        let proof = zkp.prove_plaintext_equivalence_by_key(&secret, &ct1, &ct3);
+        assert!(!zkp.verify_plaintext_equivalence_by_key(&h, &ct1, &ct3, &proof));
+    }
+}
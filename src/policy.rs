@@ -0,0 +1,228 @@
+use std::time::Duration;
+
+use num_bigint::BigUint;
+
+use crate::store::UserStore;
+use crate::verifier::Verifier;
+use crate::Error;
+
+// Step-up authentication: how much proof an operation needs, checked after
+// the proof itself. Operations are whatever names the application gives
+// them ("GET /account", "payments/transfer"), the rule with the longest
+// matching prefix applies and `default` when none does. A prefix matches
+// whole segments: "GET /payments" covers "GET /payments/42" but not
+// "GET /paymentsX":
+//
+//     let mut policy = Policy::new(Requirement::Session);
+//     policy.require("payments/", Requirement::FreshProof);
+//     policy.require("settings/", Requirement::RecentLogin(Duration::from_secs(300)));
+//
+// Session        an open session is enough
+// RecentLogin    the session was opened at most that long ago, an older one
+//                has to log in again
+// FreshProof     a proof made for this very request: the answer to an action
+//                challenge whose context is the operation; no session is
+//                enough
+//
+// `Verifier::authorize` checks a session against the policy,
+// `Verifier::authorize_action` an action answer, and
+// `ZkpAuthLayer::with_policy` does either for every request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Requirement {
+    Session,
+    RecentLogin(Duration),
+    FreshProof,
+}
+
+// What the request was verified with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Assurance {
+    Session { age: Duration },
+    FreshProof,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Policy {
+    pub default: Requirement,
+    // (operation prefix, requirement)
+    pub rules: Vec<(String, Requirement)>,
+}
+
+impl Requirement {
+    pub fn is_met_by(&self, assurance: &Assurance) -> bool {
+        match (self, assurance) {
+            (_, Assurance::FreshProof) => true,
+            (Requirement::Session, Assurance::Session { .. }) => true,
+            (Requirement::RecentLogin(max_age), Assurance::Session { age }) => age <= max_age,
+            (Requirement::FreshProof, Assurance::Session { .. }) => false,
+        }
+    }
+}
+
+impl Policy {
+    pub fn new(default: Requirement) -> Self {
+        Policy {
+            default,
+            rules: Vec::new(),
+        }
+    }
+
+    pub fn require(&mut self, prefix: &str, requirement: Requirement) {
+        self.rules.push((prefix.to_string(), requirement));
+    }
+
+    pub fn requirement(&self, operation: &str) -> Requirement {
+        self.rules
+            .iter()
+            .filter(|(prefix, _)| covers(prefix, operation))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.default, |(_, requirement)| *requirement)
+    }
+
+    pub fn evaluate(&self, operation: &str, assurance: &Assurance) -> Result<(), Error> {
+        if !self.requirement(operation).is_met_by(assurance) {
+            trace_warn!(operation, "operation needs a stronger proof");
+            return Err(Error::StepUpRequired(operation.to_string()));
+        }
+        Ok(())
+    }
+}
+
+impl<S: UserStore> Verifier<S> {
+    // how long ago the session was opened, None once it is gone
+    pub fn session_age(&self, session_id: &str) -> Option<Duration> {
+        self.session_user(session_id)?;
+        self.sessions
+            .get(session_id)
+            .map(|(_, opened)| opened.elapsed())
+    }
+
+    // The user behind session_id if the session is enough for operation.
+    pub fn authorize(
+        &self,
+        policy: &Policy,
+        operation: &str,
+        session_id: &str,
+    ) -> Result<String, Error> {
+        let user_name = self
+            .session_user(session_id)
+            .ok_or(Error::SessionNotFound)?
+            .to_string();
+        let age = self.session_age(session_id).unwrap_or_default();
+        policy.evaluate(operation, &Assurance::Session { age })?;
+        Ok(user_name)
+    }
+
+    // The user who answered an action challenge over operation, which is
+    // enough for any requirement.
+    pub fn authorize_action(
+        &mut self,
+        policy: &Policy,
+        operation: &str,
+        auth_id: &str,
+        s: &BigUint,
+    ) -> Result<(String, Assurance), Error> {
+        let user_name = self.verify_action(auth_id, s, operation.as_bytes())?;
+        let assurance = Assurance::FreshProof;
+        policy.evaluate(operation, &assurance)?;
+        Ok((user_name, assurance))
+    }
+}
+
+// prefix ending where a segment of operation does
+fn covers(prefix: &str, operation: &str) -> bool {
+    match operation.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || prefix.ends_with('/') || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+    use crate::proof::Secret;
+    use crate::prover::Prover;
+    use crate::ZKP;
+
+    fn policy() -> Policy {
+        let mut policy = Policy::new(Requirement::Session);
+        policy.require("payments/", Requirement::FreshProof);
+        policy.require(
+            "settings/",
+            Requirement::RecentLogin(Duration::from_secs(300)),
+        );
+        policy.require("settings/theme", Requirement::Session);
+        policy
+    }
+
+    #[test]
+    fn test_longest_prefix_wins() {
+        let policy = policy();
+        assert_eq!(policy.requirement("account"), Requirement::Session);
+        assert_eq!(policy.requirement("payments/send"), Requirement::FreshProof);
+        assert_eq!(
+            policy.requirement("settings/email"),
+            Requirement::RecentLogin(Duration::from_secs(300))
+        );
+        assert_eq!(policy.requirement("settings/theme"), Requirement::Session);
+        assert_eq!(
+            policy.requirement("settings/theme/dark"),
+            Requirement::Session
+        );
+        assert_eq!(
+            policy.requirement("settings/themes"),
+            Requirement::RecentLogin(Duration::from_secs(300))
+        );
+
+        let old = Assurance::Session {
+            age: Duration::from_secs(3600),
+        };
+        assert_eq!(policy.evaluate("settings/theme", &old), Ok(()));
+        assert_eq!(
+            policy.evaluate("settings/email", &old),
+            Err(Error::StepUpRequired("settings/email".to_string()))
+        );
+        assert_eq!(
+            policy.evaluate("payments/send", &Assurance::FreshProof),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_sessions_against_the_policy() {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let zkp = set.zkp();
+        let mut verifier = Verifier::with_parameter_sets(set, &[]);
+        let secret = Secret::random(&zkp);
+        let mut prover = Prover::new(zkp.clone(), set.id(), "alice", secret.clone());
+        prover.register(&mut verifier).unwrap();
+        let session_id = prover.login(&mut verifier).unwrap();
+
+        let policy = policy();
+        assert_eq!(
+            verifier.authorize(&policy, "settings/email", &session_id),
+            Ok("alice".to_string())
+        );
+        assert_eq!(
+            verifier.authorize(&policy, "payments/send", &session_id),
+            Err(Error::StepUpRequired("payments/send".to_string()))
+        );
+        assert_eq!(
+            verifier.authorize(&policy, "account", "nope"),
+            Err(Error::SessionNotFound)
+        );
+
+        // a proof for the operation itself
+        let k = ZKP::generate_random_nonzero_below(&zkp.q);
+        let (r1, r2) = (zkp.pow(&zkp.alpha, &k), zkp.pow(&zkp.beta, &k));
+        let (auth_id, c) = verifier
+            .create_action_challenge("alice", r1, r2, b"payments/send")
+            .unwrap();
+        let s = zkp.solve(&k, &c, secret.x());
+        assert_eq!(
+            verifier.authorize_action(&policy, "payments/send", &auth_id, &s),
+            Ok(("alice".to_string(), Assurance::FreshProof))
+        );
+    }
+}
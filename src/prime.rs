@@ -0,0 +1,70 @@
+use num_bigint::{BigUint, RandBigInt};
+
+const SMALL_PRIMES: [u32; 25] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+];
+
+// Miller-Rabin with random bases, the error probability is at most 4^-rounds.
+pub fn is_probable_prime(n: &BigUint, rounds: usize) -> bool {
+    for small in SMALL_PRIMES {
+        let small = BigUint::from(small);
+        if *n == small {
+            return true;
+        }
+        if (n % &small) == BigUint::from(0u32) {
+            return false;
+        }
+    }
+    if *n < BigUint::from(2u32) {
+        return false;
+    }
+
+    let one = BigUint::from(1u32);
+    let n_minus_one = n - &one;
+    let shift = n_minus_one.trailing_zeros().unwrap();
+    let d = &n_minus_one >> shift;
+
+    let mut rng = rand::thread_rng();
+    'witness: for _ in 0..rounds {
+        let a = rng.gen_biguint_range(&BigUint::from(2u32), &n_minus_one);
+        let mut x = a.modpow(&d, n);
+        if x == one || x == n_minus_one {
+            continue;
+        }
+        for _ in 1..shift {
+            x = x.modpow(&BigUint::from(2u32), n);
+            if x == n_minus_one {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_small_numbers() {
+        let primes: Vec<u32> = (0..200u32)
+            .filter(|n| is_probable_prime(&BigUint::from(*n), 10))
+            .collect();
+        assert_eq!(primes.len(), 46);
+        assert_eq!(&primes[..6], &[2, 3, 5, 7, 11, 13]);
+        assert_eq!(*primes.last().unwrap(), 199);
+    }
+
+    #[test]
+    fn test_known_values() {
+        // 2^127 - 1 is prime, 2^128 + 1 is not (Fermat F7)
+        let m127 = (BigUint::from(1u32) << 127) - 1u32;
+        let f7 = (BigUint::from(1u32) << 128) + 1u32;
+        assert!(is_probable_prime(&m127, 20));
+        assert!(!is_probable_prime(&f7, 20));
+        // Carmichael number
+        assert!(!is_probable_prime(&BigUint::from(561u32), 20));
+    }
+}
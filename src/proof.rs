@@ -0,0 +1,477 @@
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+use crate::bundle::unix_now;
+use crate::constants::CHALLENGE_LABEL;
+use crate::context::ContextDigest;
+use crate::freshness::{FreshProof, FreshnessPolicy};
+use crate::ZKP;
+
+const ACTION_LABEL: &[u8] = b"zkp-chaum-pedersen/action/v2";
+
+// The prover's secret x.
+#[derive(Clone)]
+pub struct Secret(BigUint);
+
+impl Secret {
+    pub fn new(x: BigUint) -> Self {
+        Secret(x)
+    }
+
+    pub fn random(zkp: &ZKP) -> Self {
+        Secret(ZKP::generate_random_nonzero_below(&zkp.q))
+    }
+
+    pub fn x(&self) -> &BigUint {
+        &self.0
+    }
+
+    // x + r mod q, the secret behind `PublicKey::rerandomize(r)`
+    pub fn rerandomize(&self, zkp: &ZKP, r: &Secret) -> Secret {
+        Secret((&self.0 + &r.0) % &zkp.q)
+    }
+}
+
+// Never print the secret by accident.
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Secret(..)")
+    }
+}
+
+// y1 = alpha^x mod p
+// y2 = beta^x mod p
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicKey {
+    pub y1: BigUint,
+    pub y2: BigUint,
+}
+
+impl PublicKey {
+    // (y1 * alpha^r, y2 * beta^r), the key of x + r. Uniformly distributed
+    // for a random r, so presentations of the same credential to different
+    // verifiers can't be linked by the key; only the holder of x and r can
+    // prove for it.
+    pub fn rerandomize(&self, zkp: &ZKP, r: &Secret) -> PublicKey {
+        PublicKey {
+            y1: (&self.y1 * zkp.pow(&zkp.alpha, r.x())) % &zkp.p,
+            y2: (&self.y2 * zkp.pow(&zkp.beta, r.x())) % &zkp.p,
+        }
+    }
+}
+
+// Non-interactive proof carrying the commitments: (r1, r2, s).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proof {
+    pub r1: BigUint,
+    pub r2: BigUint,
+    pub s: BigUint,
+}
+
+// Non-interactive proof carrying the challenge instead: (c, s). The verifier
+// recomputes the commitments, so it's about half the size of `Proof` (c and s
+// are mod q, r1 and r2 are mod p).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactProof {
+    pub c: BigUint,
+    pub s: BigUint,
+}
+
+// Any representation, so `verify_proof` takes them all.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnyProof {
+    Full(Proof),
+    Compact(CompactProof),
+    // with a validity period, see `crate::freshness`
+    Fresh(FreshProof),
+}
+
+impl From<Proof> for AnyProof {
+    fn from(proof: Proof) -> Self {
+        AnyProof::Full(proof)
+    }
+}
+
+impl From<CompactProof> for AnyProof {
+    fn from(proof: CompactProof) -> Self {
+        AnyProof::Compact(proof)
+    }
+}
+
+impl ZKP {
+    pub fn public_key(&self, secret: &Secret) -> PublicKey {
+        PublicKey {
+            y1: self.pow(&self.alpha, secret.x()),
+            y2: self.pow(&self.beta, secret.x()),
+        }
+    }
+
+    // c = H(p, q, alpha, beta, y1, y2, r1, r2) mod q
+    pub fn challenge(&self, public_key: &PublicKey, r1: &BigUint, r2: &BigUint) -> BigUint {
+        self.hash_to_scalar(CHALLENGE_LABEL, &[&public_key.y1, &public_key.y2, r1, r2])
+    }
+
+    // c = H(p, q, alpha, beta, H(context), y1, y2, r1, r2) mod q, the
+    // challenge of a proof that authorizes one action. context is whatever
+    // identifies the action (method and path, a transaction hash, a nonce of
+    // the relying party), the prover recomputes c to check what it is about
+    // to approve.
+    pub fn action_challenge(
+        &self,
+        public_key: &PublicKey,
+        context: &[u8],
+        r1: &BigUint,
+        r2: &BigUint,
+    ) -> BigUint {
+        self.action_challenge_digest(public_key, &ContextDigest::of(context), r1, r2)
+    }
+
+    // the same with H(context) already computed, see `crate::context`
+    pub fn action_challenge_digest(
+        &self,
+        public_key: &PublicKey,
+        digest: &ContextDigest,
+        r1: &BigUint,
+        r2: &BigUint,
+    ) -> BigUint {
+        let context = BigUint::from_bytes_be(&digest.0);
+        self.hash_to_scalar(
+            ACTION_LABEL,
+            &[&context, &public_key.y1, &public_key.y2, r1, r2],
+        )
+    }
+
+    // H(label, p, q, alpha, beta, values...) mod q, every value is padded to
+    // the byte length of p and length-prefixed so the encoding is
+    // unambiguous. The values alone are not enough: with the group left out
+    // of the hash ("weak" Fiat-Shamir) a forger who gets to pick the group,
+    // or the bases, can solve for a statement that fits a proof made up in
+    // advance.
+    pub(crate) fn hash_to_scalar(&self, label: &[u8], values: &[&BigUint]) -> BigUint {
+        let group = [&self.p, &self.q, &self.alpha, &self.beta];
+        let values: Vec<&BigUint> = group.into_iter().chain(values.iter().copied()).collect();
+        self.hash_values(label, &values)
+    }
+
+    // the statement-free hash, only for checking weak legacy proofs
+    pub(crate) fn hash_values(&self, label: &[u8], values: &[&BigUint]) -> BigUint {
+        let mut hasher = Sha256::new();
+        hasher.update(label);
+        for n in values {
+            let bytes = self.element_to_bytes(n);
+            hasher.update((bytes.len() as u32).to_be_bytes());
+            hasher.update(&bytes);
+        }
+
+        self.reduce_q(&BigUint::from_bytes_be(&hasher.finalize()))
+    }
+
+    // Fiat-Shamir proof that log_alpha(y1) = log_beta(y2) = x with the bases
+    // bound into the challenge, for the constructions where alpha and beta
+    // are not fixed group parameters (ElGamal, DDH tuples...).
+    pub(crate) fn prove_dleq(
+        &self,
+        label: &[u8],
+        x: &BigUint,
+        y1: &BigUint,
+        y2: &BigUint,
+    ) -> Proof {
+        let k = ZKP::generate_random_nonzero_below(&self.q);
+        let r1 = self.pow(&self.alpha, &k);
+        let r2 = self.pow(&self.beta, &k);
+
+        let c = self.hash_to_scalar(label, &[&self.alpha, &self.beta, y1, y2, &r1, &r2]);
+        let s = self.solve(&k, &c, x);
+
+        Proof { r1, r2, s }
+    }
+
+    pub(crate) fn verify_dleq(
+        &self,
+        label: &[u8],
+        y1: &BigUint,
+        y2: &BigUint,
+        proof: &Proof,
+    ) -> bool {
+        let c = self.hash_to_scalar(
+            label,
+            &[&self.alpha, &self.beta, y1, y2, &proof.r1, &proof.r2],
+        );
+        self.verify(&proof.r1, &proof.r2, y1, y2, &proof.s, &c)
+    }
+
+    // A fresh unlinkable (secret, public key) pair for the same credential,
+    // prove with the returned secret as usual.
+    pub fn rerandomize(&self, secret: &Secret, public_key: &PublicKey) -> (Secret, PublicKey) {
+        let r = Secret::random(self);
+        (
+            secret.rerandomize(self, &r),
+            public_key.rerandomize(self, &r),
+        )
+    }
+
+    pub fn prove(&self, secret: &Secret, public_key: &PublicKey) -> Proof {
+        let k = ZKP::generate_random_nonzero_below(&self.q);
+        let r1 = self.pow(&self.alpha, &k);
+        let r2 = self.pow(&self.beta, &k);
+
+        let c = self.challenge(public_key, &r1, &r2);
+        let s = self.solve(&k, &c, secret.x());
+
+        Proof { r1, r2, s }
+    }
+
+    pub fn prove_compact(&self, secret: &Secret, public_key: &PublicKey) -> CompactProof {
+        let proof = self.prove(secret, public_key);
+        self.compact(public_key, &proof)
+    }
+
+    pub fn compact(&self, public_key: &PublicKey, proof: &Proof) -> CompactProof {
+        CompactProof {
+            c: self.challenge(public_key, &proof.r1, &proof.r2),
+            s: proof.s.clone(),
+        }
+    }
+
+    // r1 = alpha^s * y1^c mod p
+    // r2 = beta^s * y2^c mod p
+    pub fn expand(&self, public_key: &PublicKey, proof: &CompactProof) -> Proof {
+        let r1 = self.pow2(&self.alpha, &proof.s, &public_key.y1, &proof.c);
+        let r2 = self.pow2(&self.beta, &proof.s, &public_key.y2, &proof.c);
+
+        Proof {
+            r1,
+            r2,
+            s: proof.s.clone(),
+        }
+    }
+
+    // `expand` for checking a compact proof: None when s or the commitments
+    // are out of range, see `ZKP::in_range`, or the key is not in the
+    // subgroup.
+    pub(crate) fn expand_checked(
+        &self,
+        public_key: &PublicKey,
+        proof: &CompactProof,
+    ) -> Option<Proof> {
+        if proof.s >= self.q || !self.is_subgroup_key(public_key) {
+            return None;
+        }
+        let expanded = self.expand(public_key, proof);
+        self.in_range(&expanded.s, &[&expanded.r1, &expanded.r2])
+            .then_some(expanded)
+    }
+
+    // A key with a component of small order passes the equations whenever
+    // c is a multiple of that order, which a prover can grind for, so keys
+    // are checked to be in the subgroup and not just in range.
+    pub(crate) fn is_subgroup_key(&self, public_key: &PublicKey) -> bool {
+        self.is_subgroup_element(&public_key.y1) && self.is_subgroup_element(&public_key.y2)
+    }
+
+    // Only fresh proofs pass, checked against the system clock with the
+    // default `FreshnessPolicy`: a `Proof` or `CompactProof` says nothing
+    // about when it was made and would be valid forever.
+    pub fn verify_proof(&self, public_key: &PublicKey, proof: impl Into<AnyProof>) -> bool {
+        match proof.into() {
+            proof @ AnyProof::Fresh(_) => self.verify_proof_without_expiry(public_key, proof),
+            AnyProof::Full(_) | AnyProof::Compact(_) => false,
+        }
+    }
+
+    // `verify_proof` that also accepts proofs without claims, for callers
+    // that bound their lifetime some other way (a challenge, a signed
+    // bundle) or mean to keep them valid. Fresh proofs still have theirs
+    // checked.
+    pub fn verify_proof_without_expiry(
+        &self,
+        public_key: &PublicKey,
+        proof: impl Into<AnyProof>,
+    ) -> bool {
+        match proof.into() {
+            AnyProof::Full(proof) => {
+                if !self.is_subgroup_key(public_key) {
+                    return false;
+                }
+                let c = self.challenge(public_key, &proof.r1, &proof.r2);
+                self.verify(
+                    &proof.r1,
+                    &proof.r2,
+                    &public_key.y1,
+                    &public_key.y2,
+                    &proof.s,
+                    &c,
+                )
+            }
+            AnyProof::Compact(proof) => {
+                self.expand_checked(public_key, &proof)
+                    .is_some_and(|expanded| {
+                        proof.c == self.challenge(public_key, &expanded.r1, &expanded.r2)
+                    })
+            }
+            proof @ AnyProof::Fresh(_) => self
+                .verify_fresh_proof_at(public_key, proof, unix_now(), &FreshnessPolicy::default())
+                .is_ok(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::modmath::mod_inv_prime;
+    use crate::params::ParameterSet;
+
+    #[test]
+    fn test_full_and_compact_proofs() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let secret = Secret::random(&zkp);
+        let public_key = zkp.public_key(&secret);
+
+        let proof = zkp.prove(&secret, &public_key);
+        assert!(zkp.verify_proof_without_expiry(&public_key, proof.clone()));
+
+        let compact = zkp.compact(&public_key, &proof);
+        assert!(zkp.verify_proof_without_expiry(&public_key, compact.clone()));
+        assert_eq!(zkp.expand(&public_key, &compact), proof);
+
+        let compact = zkp.prove_compact(&secret, &public_key);
+        assert!(zkp.verify_proof_without_expiry(&public_key, compact.clone()));
+
+        // a stored proof without claims doesn't pass by default
+        assert!(!zkp.verify_proof(&public_key, proof));
+        assert!(!zkp.verify_proof(&public_key, compact));
+        let fresh = zkp.prove_fresh(&secret, &public_key, crate::freshness::DEFAULT_LIFETIME);
+        assert!(zkp.verify_proof(&public_key, fresh));
+    }
+
+    #[test]
+    fn test_proofs_for_another_key_fail() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let secret = Secret::random(&zkp);
+        let public_key = zkp.public_key(&secret);
+        let other_key = zkp.public_key(&Secret::random(&zkp));
+
+        let proof = zkp.prove(&secret, &public_key);
+        let compact = zkp.compact(&public_key, &proof);
+        assert!(!zkp.verify_proof_without_expiry(&other_key, proof.clone()));
+        assert!(!zkp.verify_proof_without_expiry(&other_key, compact.clone()));
+
+        let mut tampered = compact;
+        tampered.s += 1u32;
+        assert!(!zkp.verify_proof_without_expiry(&public_key, tampered));
+    }
+
+    #[test]
+    fn test_keys_outside_the_subgroup_fail() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let secret = Secret::random(&zkp);
+        let public_key = zkp.public_key(&secret);
+        // -y has a component of order 2, which an even c cancels
+        let minus_one = &zkp.p - 1u32;
+        let twisted = PublicKey {
+            y1: (&public_key.y1 * &minus_one) % &zkp.p,
+            y2: (&public_key.y2 * &minus_one) % &zkp.p,
+        };
+        let compact = loop {
+            let compact = zkp.prove_compact(&secret, &twisted);
+            if !compact.c.bit(0) {
+                break compact;
+            }
+        };
+        assert_eq!(
+            zkp.pow2(&zkp.alpha, &compact.s, &twisted.y1, &compact.c),
+            zkp.pow2(&zkp.alpha, &compact.s, &public_key.y1, &compact.c)
+        );
+        let proof = zkp.expand(&twisted, &compact);
+
+        assert!(!zkp.verify_proof_without_expiry(&twisted, compact));
+        assert!(!zkp.verify_proof_without_expiry(&twisted, proof));
+    }
+
+    #[test]
+    fn test_compact_proofs_are_range_checked() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let secret = Secret::random(&zkp);
+        let public_key = zkp.public_key(&secret);
+
+        // (c, s + q) expands to the same commitments
+        let mut shifted = zkp.prove_compact(&secret, &public_key);
+        shifted.s += &zkp.q;
+        assert!(!zkp.verify_proof_without_expiry(&public_key, shifted.clone()));
+        let mut action = zkp.prove_compact_action(&secret, &public_key, b"pay 10");
+        action.s += &zkp.q;
+        assert!(!zkp.verify_compact_action(&public_key, &action, b"pay 10"));
+        let mut fresh = zkp.prove_fresh(&secret, &public_key, crate::freshness::DEFAULT_LIFETIME);
+        fresh.proof.s += &zkp.q;
+        assert!(!zkp.verify_proof(&public_key, fresh));
+
+        // anyone can prove for the identity key, x = 0
+        let one = BigUint::from(1u32);
+        let identity = PublicKey {
+            y1: one.clone(),
+            y2: one.clone(),
+        };
+        let k = ZKP::generate_random_nonzero_below(&zkp.q);
+        let (r1, r2) = (zkp.pow(&zkp.alpha, &k), zkp.pow(&zkp.beta, &k));
+        let c = zkp.challenge(&identity, &r1, &r2);
+        let forged = CompactProof { c, s: k };
+        assert!(!zkp.verify_proof_without_expiry(&identity, forged));
+    }
+
+    #[test]
+    fn test_rerandomized_keys() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let secret = Secret::random(&zkp);
+        let public_key = zkp.public_key(&secret);
+
+        let r = Secret::random(&zkp);
+        let moved = public_key.rerandomize(&zkp, &r);
+        assert_eq!(moved, zkp.public_key(&secret.rerandomize(&zkp, &r)));
+        assert_eq!(
+            public_key.rerandomize(&zkp, &Secret::new(BigUint::from(0u32))),
+            public_key
+        );
+
+        let (fresh_secret, fresh_key) = zkp.rerandomize(&secret, &public_key);
+        assert_ne!(fresh_key, public_key);
+        let proof = zkp.prove(&fresh_secret, &fresh_key);
+        assert!(zkp.verify_proof_without_expiry(&fresh_key, proof));
+
+        // the original secret no longer fits
+        let proof = zkp.prove(&secret, &fresh_key);
+        assert!(!zkp.verify_proof_without_expiry(&fresh_key, proof));
+    }
+
+    #[test]
+    fn test_challenge_binds_the_group() {
+        // A forger free to pick beta after the challenge: with y1 = alpha^x
+        // and an unrelated y2, r2 and s, solving for
+        //     beta = (r2 * y2^-c)^(1/s)
+        // passes both equations, a "proof" that log_alpha(y1) = log_beta(y2)
+        // when they differ. The challenge a weak verifier hashes stays put
+        // when beta changes, ours doesn't.
+        let group = ParameterSet::Rfc5114_1024_160.zkp();
+        let (p, q) = (&group.p, &group.q);
+        let pow = |base: &BigUint, e: &BigUint| base.modpow(e, p);
+
+        let x = ZKP::generate_random_below(q);
+        let k = ZKP::generate_random_below(q);
+        let public_key = PublicKey {
+            y1: pow(&group.alpha, &x),
+            y2: pow(&group.alpha, &ZKP::generate_random_below(q)),
+        };
+        let r1 = pow(&group.alpha, &k);
+        let r2 = pow(&group.alpha, &ZKP::generate_random_below(q));
+
+        let c = group.hash_values(b"weak", &[&public_key.y1, &public_key.y2, &r1, &r2]);
+        let s = group.solve(&k, &c, &x);
+        let s_inverse = mod_inv_prime(&s, q);
+        let beta = pow(&((&r2 * pow(&public_key.y2, &(q - &c))) % p), &s_inverse);
+        let zkp = ZKP::new(p.clone(), q.clone(), group.alpha.clone(), beta);
+
+        assert!(zkp.verify(&r1, &r2, &public_key.y1, &public_key.y2, &s, &c));
+        let proof = Proof { r1, r2, s };
+        assert!(!zkp.verify_proof_without_expiry(&public_key, proof));
+    }
+}
@@ -0,0 +1,119 @@
+use num_bigint::BigUint;
+
+use crate::{Error, ZKP};
+
+// Versions of the wire protocol. A client asks the server which versions it
+// speaks (the Negotiate rpc), takes the highest one both know and sends it
+// with every request; requests without a version are V1.
+//
+// V1: interactive, values in any big-endian encoding
+// V2: interactive, canonical fixed-width encodings (see `encoding`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ProtocolVersion {
+    V1,
+    V2,
+}
+
+impl ProtocolVersion {
+    pub const ALL: [ProtocolVersion; 2] = [ProtocolVersion::V1, ProtocolVersion::V2];
+
+    pub const LATEST: ProtocolVersion = ProtocolVersion::V2;
+
+    pub fn number(&self) -> u32 {
+        match self {
+            ProtocolVersion::V1 => 1,
+            ProtocolVersion::V2 => 2,
+        }
+    }
+
+    // 0 is what proto3 sends for a field old clients don't know about
+    pub fn from_number(number: u32) -> Result<ProtocolVersion, Error> {
+        match number {
+            0 | 1 => Ok(ProtocolVersion::V1),
+            2 => Ok(ProtocolVersion::V2),
+            _ => Err(Error::UnsupportedVersion(number)),
+        }
+    }
+
+    // Highest version in both lists, numbers we don't know are skipped.
+    pub fn negotiate(ours: &[ProtocolVersion], theirs: &[u32]) -> Option<ProtocolVersion> {
+        theirs
+            .iter()
+            .filter_map(|number| ProtocolVersion::from_number(*number).ok())
+            .filter(|version| ours.contains(version))
+            .max()
+    }
+
+    pub fn encode_element(&self, zkp: &ZKP, e: &BigUint) -> Vec<u8> {
+        match self {
+            ProtocolVersion::V1 => e.to_bytes_be(),
+            ProtocolVersion::V2 => zkp.element_to_bytes(e),
+        }
+    }
+
+    pub fn encode_scalar(&self, zkp: &ZKP, n: &BigUint) -> Vec<u8> {
+        match self {
+            ProtocolVersion::V1 => n.to_bytes_be(),
+            ProtocolVersion::V2 => zkp.scalar_to_bytes(n),
+        }
+    }
+
+    pub fn decode_element(&self, zkp: &ZKP, bytes: &[u8]) -> Result<BigUint, Error> {
+        match self {
+            ProtocolVersion::V1 => Ok(BigUint::from_bytes_be(bytes)),
+            ProtocolVersion::V2 => zkp.element_from_bytes(bytes),
+        }
+    }
+
+    pub fn decode_scalar(&self, zkp: &ZKP, bytes: &[u8]) -> Result<BigUint, Error> {
+        match self {
+            ProtocolVersion::V1 => Ok(BigUint::from_bytes_be(bytes)),
+            ProtocolVersion::V2 => zkp.scalar_from_bytes(bytes),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+
+    #[test]
+    fn test_negotiate() {
+        let all = &ProtocolVersion::ALL;
+        assert_eq!(
+            ProtocolVersion::negotiate(all, &[1, 2, 7]),
+            Some(ProtocolVersion::V2)
+        );
+        assert_eq!(
+            ProtocolVersion::negotiate(&[ProtocolVersion::V1], &[1, 2]),
+            Some(ProtocolVersion::V1)
+        );
+        assert_eq!(ProtocolVersion::negotiate(all, &[3]), None);
+
+        assert_eq!(ProtocolVersion::from_number(0), Ok(ProtocolVersion::V1));
+        assert_eq!(
+            ProtocolVersion::from_number(3),
+            Err(Error::UnsupportedVersion(3))
+        );
+    }
+
+    #[test]
+    fn test_encodings_by_version() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let n = BigUint::from(258u32);
+
+        let v1 = ProtocolVersion::V1.encode_element(&zkp, &n);
+        let v2 = ProtocolVersion::V2.encode_element(&zkp, &n);
+        assert_eq!(v1, vec![1, 2]);
+        assert_eq!(v2.len(), zkp.element_len());
+
+        // V1 accepts both, V2 only the canonical width
+        assert_eq!(ProtocolVersion::V1.decode_element(&zkp, &v2), Ok(n.clone()));
+        assert_eq!(ProtocolVersion::V2.decode_element(&zkp, &v2), Ok(n.clone()));
+        assert!(ProtocolVersion::V2.decode_element(&zkp, &v1).is_err());
+        assert!(ProtocolVersion::V2
+            .decode_scalar(&zkp, &ProtocolVersion::V1.encode_scalar(&zkp, &n))
+            .is_err());
+    }
+}
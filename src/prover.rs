@@ -0,0 +1,435 @@
+#[cfg(debug_assertions)]
+use std::collections::HashSet;
+
+use num_bigint::BigUint;
+#[cfg(debug_assertions)]
+use sha2::{Digest, Sha256};
+
+use crate::events::{ProverEvents, Round};
+use crate::holder::{LocalHolder, SecretHolder};
+use crate::proof::{PublicKey, Secret};
+use crate::transport::{AsyncTransport, Transport};
+use crate::{Error, ZKP};
+
+// Client side of the protocol for one user. `commit` and `respond` are the
+// two halves of an interactive round, `register` and `login` drive them
+// over a transport. The secret itself stays with the holder.
+#[derive(Debug)]
+pub struct Prover<H = LocalHolder> {
+    zkp: ZKP,
+    parameter_set: String,
+    user_name: String,
+    holder: H,
+    events: Events,
+    // debug builds: fingerprints of every commitment made, see `commit`
+    #[cfg(debug_assertions)]
+    commitments: HashSet<[u8; 32]>,
+}
+
+struct Events(Box<dyn ProverEvents + Send + Sync>);
+
+impl std::fmt::Debug for Events {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Events(..)")
+    }
+}
+
+impl Prover<LocalHolder> {
+    pub fn new(zkp: ZKP, parameter_set: &str, user_name: &str, secret: Secret) -> Self {
+        Prover::with_holder(zkp, parameter_set, user_name, LocalHolder::new(secret))
+    }
+}
+
+impl<H: SecretHolder> Prover<H> {
+    pub fn with_holder(zkp: ZKP, parameter_set: &str, user_name: &str, holder: H) -> Self {
+        Prover {
+            zkp,
+            parameter_set: parameter_set.to_string(),
+            user_name: user_name.to_string(),
+            holder,
+            events: Events(Box::new(())),
+            #[cfg(debug_assertions)]
+            commitments: HashSet::new(),
+        }
+    }
+
+    pub fn user_name(&self) -> &str {
+        &self.user_name
+    }
+
+    pub fn parameter_set(&self) -> &str {
+        &self.parameter_set
+    }
+
+    pub fn zkp(&self) -> &ZKP {
+        &self.zkp
+    }
+
+    pub fn holder(&self) -> &H {
+        &self.holder
+    }
+
+    pub fn public_key(&self) -> Result<PublicKey, Error> {
+        self.holder.public_key(&self.zkp)
+    }
+
+    // Reports the progress of every round from now on, see `crate::events`.
+    pub fn set_events(&mut self, events: impl ProverEvents + Send + Sync + 'static) {
+        self.events = Events(Box::new(events));
+    }
+
+    // output => (r1, r2), replaces any commitment still waiting
+    //
+    // Debug builds panic on a commitment seen before: the same (r1, r2) is
+    // the same k, and two answers for one k give x away. A holder with a
+    // broken RNG (or a deterministic one) must not get past testing.
+    pub fn commit(&mut self) -> Result<(BigUint, BigUint), Error> {
+        let (r1, r2) = self.holder.commit(&self.zkp)?;
+        #[cfg(debug_assertions)]
+        {
+            let mut hasher = Sha256::new();
+            hasher.update(self.zkp.element_to_bytes(&r1));
+            hasher.update(self.zkp.element_to_bytes(&r2));
+            if !self.commitments.insert(hasher.finalize().into()) {
+                panic!("nonce reuse: the holder committed to the same k twice");
+            }
+        }
+        Ok((r1, r2))
+    }
+
+    // The nonce is consumed: answering two challenges with the same k would
+    // reveal x.
+    pub fn respond(&mut self, c: &BigUint) -> Result<BigUint, Error> {
+        self.holder.compute_response(&self.zkp, c)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "prover.register", skip_all, fields(user = %self.user_name))
+    )]
+    pub fn register<T: Transport>(&self, transport: &mut T) -> Result<(), Error> {
+        let public_key = self.public_key()?;
+        transport.register(
+            &self.user_name,
+            &self.parameter_set,
+            &public_key.y1,
+            &public_key.y2,
+        )
+    }
+
+    // output => session_id
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "prover.login", skip_all, fields(user = %self.user_name))
+    )]
+    pub fn login<T: Transport>(&mut self, transport: &mut T) -> Result<String, Error> {
+        let round = Round::Login;
+        let result = (|| {
+            let (r1, r2) = self.commit_in(round)?;
+            let (auth_id, c) = transport.create_challenge(&self.user_name, &r1, &r2)?;
+            let s = self.respond_in(round, &auth_id, &c)?;
+            transport.verify(&auth_id, &s)
+        })();
+        self.report(round, &result);
+        result
+    }
+
+    // Proves knowledge of the current secret and registers the public key of
+    // `new_holder` in the same round. The prover keeps the old holder if the
+    // verifier refuses.
+    // output => the old holder
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "prover.rotate", skip_all, fields(user = %self.user_name))
+    )]
+    pub fn rotate<T: Transport>(&mut self, new_holder: H, transport: &mut T) -> Result<H, Error> {
+        let round = Round::Rotation;
+        let result = (|| {
+            let new_key = new_holder.public_key(&self.zkp)?;
+            let (r1, r2) = self.commit_in(round)?;
+            let (auth_id, c) = transport.create_rotation_challenge(
+                &self.user_name,
+                &r1,
+                &r2,
+                &new_key.y1,
+                &new_key.y2,
+            )?;
+            let s = self.respond_in(round, &auth_id, &c)?;
+            transport.verify_rotation(&auth_id, &s)
+        })();
+        self.report(round, &result);
+        result?;
+
+        Ok(std::mem::replace(&mut self.holder, new_holder))
+    }
+
+    // `register` and `login` for async applications, any number of provers
+    // can share one transport. Cancellation safe: the nonce stays with the
+    // holder until the answer is computed, a future dropped halfway leaves
+    // at most an unanswered challenge at the verifier and the next round
+    // commits afresh.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "prover.register", skip_all, fields(user = %self.user_name))
+    )]
+    pub async fn register_async<T: AsyncTransport + ?Sized>(
+        &self,
+        transport: &T,
+    ) -> Result<(), Error> {
+        let public_key = self.public_key()?;
+        transport
+            .register(
+                &self.user_name,
+                &self.parameter_set,
+                &public_key.y1,
+                &public_key.y2,
+            )
+            .await
+    }
+
+    // output => session_id
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "prover.authenticate", skip_all, fields(user = %self.user_name))
+    )]
+    pub async fn authenticate<T: AsyncTransport + ?Sized>(
+        &mut self,
+        transport: &T,
+    ) -> Result<String, Error> {
+        let round = Round::Login;
+        let result = async {
+            let (r1, r2) = self.commit_in(round)?;
+            let (auth_id, c) = transport
+                .create_challenge(&self.user_name, &r1, &r2)
+                .await?;
+            let s = self.respond_in(round, &auth_id, &c)?;
+            transport.verify(&auth_id, &s).await
+        }
+        .await;
+        self.report(round, &result);
+        result
+    }
+
+    fn commit_in(&mut self, round: Round) -> Result<(BigUint, BigUint), Error> {
+        let (r1, r2) = self.commit()?;
+        self.events.0.on_commit(&self.user_name, round, &r1, &r2);
+        Ok((r1, r2))
+    }
+
+    fn respond_in(&mut self, round: Round, auth_id: &str, c: &BigUint) -> Result<BigUint, Error> {
+        self.events
+            .0
+            .on_challenge_received(&self.user_name, round, auth_id, c)?;
+        let s = self.respond(c)?;
+        self.events
+            .0
+            .on_response_sent(&self.user_name, round, auth_id);
+        Ok(s)
+    }
+
+    fn report<T>(&mut self, round: Round, result: &Result<T, Error>) {
+        let result = result.as_ref().map(|_| ());
+        self.events.0.on_result(&self.user_name, round, result);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+    use crate::verifier::Verifier;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::Mutex;
+
+    // a holder whose nonce never changes
+    struct FixedNonceHolder {
+        secret: Secret,
+    }
+
+    impl SecretHolder for FixedNonceHolder {
+        fn public_key(&self, zkp: &ZKP) -> Result<PublicKey, Error> {
+            Ok(zkp.public_key(&self.secret))
+        }
+
+        fn commit(&mut self, zkp: &ZKP) -> Result<(BigUint, BigUint), Error> {
+            let k = BigUint::from(7u32);
+            Ok((zkp.pow(&zkp.alpha, &k), zkp.pow(&zkp.beta, &k)))
+        }
+
+        fn compute_response(&mut self, zkp: &ZKP, c: &BigUint) -> Result<BigUint, Error> {
+            Ok(zkp.solve(&BigUint::from(7u32), c, self.secret.x()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_async_logins() {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let zkp = set.zkp();
+        let transport = Arc::new(Mutex::new(Verifier::with_parameter_sets(set, &[])));
+
+        let logins = (0..8).map(|i| {
+            let (zkp, transport) = (zkp.clone(), transport.clone());
+            tokio::spawn(async move {
+                let user = format!("user{}", i);
+                let mut prover = Prover::new(zkp.clone(), set.id(), &user, Secret::random(&zkp));
+                prover.register_async(transport.as_ref()).await?;
+                prover.authenticate(transport.as_ref()).await
+            })
+        });
+        let mut sessions = Vec::new();
+        for login in logins.collect::<Vec<_>>() {
+            sessions.push(login.await.unwrap().unwrap());
+        }
+        sessions.sort();
+        sessions.dedup();
+        assert_eq!(sessions.len(), 8);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_login_can_be_retried() {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let zkp = set.zkp();
+        let transport = Mutex::new(Verifier::with_parameter_sets(set, &[]));
+        let mut prover = Prover::new(zkp.clone(), set.id(), "alice", Secret::random(&zkp));
+        prover.register_async(&transport).await.unwrap();
+
+        // committed, then dropped while waiting for the challenge
+        let busy = transport.lock().await;
+        let login = prover.authenticate(&transport);
+        assert!(tokio::time::timeout(Duration::from_millis(20), login)
+            .await
+            .is_err());
+        drop(busy);
+
+        assert!(prover.authenticate(&transport).await.is_ok());
+    }
+
+    // what a host application would show, one line per event
+    #[derive(Clone, Default)]
+    struct Recorder {
+        lines: Arc<std::sync::Mutex<Vec<String>>>,
+        decline: bool,
+    }
+
+    impl ProverEvents for Recorder {
+        fn on_commit(&mut self, user_name: &str, round: Round, _r1: &BigUint, _r2: &BigUint) {
+            self.push(format!("commit {} {}", round.id(), user_name));
+        }
+
+        fn on_challenge_received(
+            &mut self,
+            _user_name: &str,
+            round: Round,
+            _auth_id: &str,
+            _c: &BigUint,
+        ) -> Result<(), Error> {
+            self.push(format!("challenge {}", round.id()));
+            if self.decline {
+                return Err(Error::Declined);
+            }
+            Ok(())
+        }
+
+        fn on_response_sent(&mut self, _user_name: &str, round: Round, _auth_id: &str) {
+            self.push(format!("response {}", round.id()));
+        }
+
+        fn on_result(&mut self, _user_name: &str, round: Round, result: Result<(), &Error>) {
+            let code = result.err().map_or("ok", |error| error.code());
+            self.push(format!("result {} {}", round.id(), code));
+        }
+    }
+
+    impl Recorder {
+        fn push(&self, line: String) {
+            self.lines.lock().unwrap().push(line);
+        }
+
+        fn take(&self) -> Vec<String> {
+            std::mem::take(&mut self.lines.lock().unwrap())
+        }
+    }
+
+    #[test]
+    fn test_events_follow_the_rounds() {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let zkp = set.zkp();
+        let mut verifier = Verifier::with_parameter_sets(set, &[]);
+        let mut prover = Prover::new(zkp.clone(), set.id(), "alice", Secret::random(&zkp));
+        let recorder = Recorder::default();
+        prover.set_events(recorder.clone());
+        prover.register(&mut verifier).unwrap();
+        prover.login(&mut verifier).unwrap();
+        assert_eq!(
+            recorder.take(),
+            [
+                "commit login alice",
+                "challenge login",
+                "response login",
+                "result login ok"
+            ]
+        );
+
+        let new_holder = LocalHolder::new(Secret::random(&zkp));
+        prover.rotate(new_holder, &mut verifier).unwrap();
+        assert_eq!(recorder.take().last().unwrap(), "result rotation ok");
+
+        // failures are reported too, a stranger gets no challenge
+        let mut stranger = Prover::new(zkp.clone(), set.id(), "bob", Secret::random(&zkp));
+        stranger.set_events(recorder.clone());
+        assert!(stranger.login(&mut verifier).is_err());
+        assert_eq!(
+            recorder.take(),
+            ["commit login bob", "result login user_not_found"]
+        );
+    }
+
+    #[test]
+    fn test_declined_challenge_is_not_answered() {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let zkp = set.zkp();
+        let mut verifier = Verifier::with_parameter_sets(set, &[]);
+        let mut prover = Prover::new(zkp.clone(), set.id(), "alice", Secret::random(&zkp));
+        prover.register(&mut verifier).unwrap();
+        let recorder = Recorder {
+            decline: true,
+            ..Recorder::default()
+        };
+        prover.set_events(recorder.clone());
+
+        assert_eq!(prover.login(&mut verifier), Err(Error::Declined));
+        assert_eq!(
+            recorder.take(),
+            [
+                "commit login alice",
+                "challenge login",
+                "result login declined"
+            ]
+        );
+        assert_eq!(verifier.pending_challenges().len(), 1);
+    }
+
+    #[test]
+    fn test_fresh_nonces() {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let zkp = set.zkp();
+        let mut prover = Prover::new(zkp.clone(), set.id(), "alice", Secret::random(&zkp));
+        let first = prover.commit().unwrap();
+        assert_ne!(prover.commit().unwrap(), first);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "nonce reuse")]
+    fn test_reused_nonce_panics() {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let zkp = set.zkp();
+        let holder = FixedNonceHolder {
+            secret: Secret::random(&zkp),
+        };
+        let mut prover = Prover::with_holder(zkp, set.id(), "alice", holder);
+        prover.commit().unwrap();
+        let _ = prover.commit();
+    }
+}
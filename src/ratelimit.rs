@@ -0,0 +1,79 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+// Caps the challenges issued to one user in a sliding window. A proof can't
+// be guessed, but every challenge costs the verifier a pending entry and the
+// answer two exponentiations, so an unlimited stream of them is a cheap way
+// to load it.
+#[derive(Debug)]
+pub struct RateLimiter {
+    // user_name => when the challenges in the window were issued
+    issued: HashMap<String, VecDeque<Instant>>,
+    max_challenges: usize,
+    window: Duration,
+}
+
+impl RateLimiter {
+    pub fn new(max_challenges: usize, window: Duration) -> Self {
+        RateLimiter {
+            issued: HashMap::new(),
+            max_challenges,
+            window,
+        }
+    }
+
+    // no limit at all
+    pub fn unlimited() -> Self {
+        RateLimiter::new(usize::MAX, Duration::ZERO)
+    }
+
+    // Counts the attempt if it's allowed.
+    pub fn allow(&mut self, user_name: &str) -> bool {
+        if self.max_challenges == usize::MAX {
+            return true;
+        }
+        let window = self.window;
+        let issued = self.issued.entry(user_name.to_string()).or_default();
+        while issued.front().is_some_and(|at| at.elapsed() >= window) {
+            issued.pop_front();
+        }
+        if issued.len() >= self.max_challenges {
+            return false;
+        }
+        issued.push_back(Instant::now());
+
+        // users who went quiet don't need an entry
+        if self.issued.len() > 1024 {
+            self.issued
+                .retain(|_, issued| issued.back().is_some_and(|at| at.elapsed() < window));
+        }
+        true
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        RateLimiter::unlimited()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_limit_per_user() {
+        let mut limiter = RateLimiter::new(2, Duration::from_secs(60));
+        assert!(limiter.allow("alice"));
+        assert!(limiter.allow("alice"));
+        assert!(!limiter.allow("alice"));
+        assert!(limiter.allow("bob"));
+
+        let mut expired = RateLimiter::new(1, Duration::ZERO);
+        assert!(expired.allow("alice"));
+        assert!(expired.allow("alice"));
+
+        let mut unlimited = RateLimiter::default();
+        assert!((0..100).all(|_| unlimited.allow("alice")));
+    }
+}
@@ -0,0 +1,528 @@
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+use crate::audit::AuditEvent;
+use crate::context::ContextDigest;
+use crate::modmath::mod_reduce;
+use crate::proof::{CompactProof, PublicKey, Secret};
+use crate::store::{UserInfo, UserStore};
+use crate::verifier::{Purpose, Verifier};
+use crate::{Error, ZKP};
+
+// Account recovery: next to their key a user enrolls recovery keys, the
+// public keys of secrets kept somewhere else (a second device, or one-time
+// recovery codes written down, each hashed into a secret), and proves
+// knowledge of one of them to install a new key once theirs is lost.
+//
+// enroll   a proof by the current key over the user, the recovery keys
+//          enrolled so far and the new ones, which replace them; a recorded
+//          enrollment stops verifying as soon as the set changes
+// recover  a rotation round (r1, r2, new key) answered with the recovery
+//          secret instead of x; on success the new key is installed, the
+//          recovery key is used up, the user's sessions end and a forced
+//          rotation counts as done
+//
+// Disabled users can do neither. Users asked to rotate can recover but not
+// enroll, their current key may be what leaked.
+const ENROLLMENT_LABEL: &[u8] = b"zkp-chaum-pedersen/recovery-enrollment/v1";
+const RECOVERY_CODE_LABEL: &[u8] = b"zkp-chaum-pedersen/recovery-code/v1";
+
+pub const MAX_RECOVERY_KEYS: usize = 16;
+// alphanumeric, about 119 bits
+pub const RECOVERY_CODE_LEN: usize = 20;
+
+impl Secret {
+    // The secret of a recovery code, bound to the user so one code gives
+    // different keys on different accounts. Expanded past the length of q
+    // and reduced, as in `blinding_factor`.
+    pub fn from_recovery_code(zkp: &ZKP, user_name: &str, code: &str) -> Secret {
+        let code = code.trim();
+        let mut wide = Vec::new();
+        let mut counter = 0u32;
+        while wide.len() < zkp.scalar_len() + 16 {
+            let mut hasher = Sha256::new();
+            hasher.update(RECOVERY_CODE_LABEL);
+            hasher.update(counter.to_be_bytes());
+            hasher.update((user_name.len() as u32).to_be_bytes());
+            hasher.update(user_name.as_bytes());
+            hasher.update(code.as_bytes());
+            wide.extend_from_slice(&hasher.finalize());
+            counter += 1;
+        }
+        Secret::new(mod_reduce(&BigUint::from_bytes_be(&wide), &zkp.q))
+    }
+}
+
+// n fresh codes to show the user once, and the keys to enroll for them
+pub fn generate_recovery_codes(
+    zkp: &ZKP,
+    user_name: &str,
+    n: usize,
+) -> (Vec<String>, Vec<PublicKey>) {
+    (0..n)
+        .map(|_| {
+            let code = ZKP::generate_random_string(RECOVERY_CODE_LEN);
+            let key = zkp.public_key(&Secret::from_recovery_code(zkp, user_name, &code));
+            (code, key)
+        })
+        .unzip()
+}
+
+impl ZKP {
+    // `current` are the keys enrolled now, empty the first time
+    pub fn prove_recovery_enrollment(
+        &self,
+        secret: &Secret,
+        public_key: &PublicKey,
+        user_name: &str,
+        current: &[PublicKey],
+        keys: &[PublicKey],
+    ) -> CompactProof {
+        let context = self.enrollment_context(user_name, current, keys);
+        self.prove_compact_action(secret, public_key, &context)
+    }
+
+    pub fn verify_recovery_enrollment(
+        &self,
+        public_key: &PublicKey,
+        user_name: &str,
+        current: &[PublicKey],
+        keys: &[PublicKey],
+        proof: &CompactProof,
+    ) -> bool {
+        let context = self.enrollment_context(user_name, current, keys);
+        self.verify_compact_action(public_key, proof, &context)
+    }
+
+    // c || s, each padded to the byte length of q
+    pub fn enrollment_proof_to_bytes(&self, proof: &CompactProof) -> Vec<u8> {
+        [
+            self.scalar_to_bytes(&proof.c),
+            self.scalar_to_bytes(&proof.s),
+        ]
+        .concat()
+    }
+
+    pub fn enrollment_proof_from_bytes(&self, bytes: &[u8]) -> Result<CompactProof, Error> {
+        if bytes.len() != 2 * self.scalar_len() {
+            return Err(Error::InvalidEncoding(format!(
+                "enrollment proof must be {} bytes, got {}",
+                2 * self.scalar_len(),
+                bytes.len()
+            )));
+        }
+        let (c, s) = bytes.split_at(self.scalar_len());
+        Ok(CompactProof {
+            c: self.scalar_from_bytes(c)?,
+            s: self.scalar_from_bytes(s)?,
+        })
+    }
+
+    fn enrollment_context(
+        &self,
+        user_name: &str,
+        current: &[PublicKey],
+        keys: &[PublicKey],
+    ) -> Vec<u8> {
+        let mut context = ENROLLMENT_LABEL.to_vec();
+        context.extend_from_slice(&(user_name.len() as u32).to_be_bytes());
+        context.extend_from_slice(user_name.as_bytes());
+        for set in [current, keys] {
+            context.extend_from_slice(&(set.len() as u32).to_be_bytes());
+            for key in set {
+                context.extend_from_slice(&self.element_to_bytes(&key.y1));
+                context.extend_from_slice(&self.element_to_bytes(&key.y2));
+            }
+        }
+        context
+    }
+}
+
+impl<S: UserStore> Verifier<S> {
+    pub fn recovery_keys(&self, user_name: &str) -> Vec<PublicKey> {
+        self.users.recovery_keys(user_name)
+    }
+
+    // Replaces the user's recovery keys with `keys`, none removes them all.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(user = %user_name))
+    )]
+    pub fn enroll_recovery_keys(
+        &mut self,
+        user_name: &str,
+        keys: Vec<PublicKey>,
+        proof: &CompactProof,
+    ) -> Result<(), Error> {
+        let user_info = self
+            .users
+            .get(user_name)
+            .ok_or_else(|| Error::UserNotFound(user_name.to_string()))?;
+        self.check_status(
+            user_name,
+            &Purpose::Action(ContextDigest::of(ENROLLMENT_LABEL)),
+        )?;
+        self.check_stored_key(&user_info)?;
+        if keys.len() > MAX_RECOVERY_KEYS {
+            return Err(Error::InvalidParameters(format!(
+                "at most {} recovery keys",
+                MAX_RECOVERY_KEYS
+            )));
+        }
+
+        let public_key = PublicKey {
+            y1: user_info.y1.clone(),
+            y2: user_info.y2.clone(),
+        };
+        let current = self.users.recovery_keys(user_name);
+        let zkp = self
+            .parameter_set(&user_info.parameter_set)
+            .ok_or_else(|| Error::UnknownParameterSet(user_info.parameter_set.clone()))?;
+        if keys
+            .iter()
+            .any(|key| !zkp.is_subgroup_element(&key.y1) || !zkp.is_subgroup_element(&key.y2))
+        {
+            trace_warn!("recovery key is not in the subgroup");
+            return Err(Error::InvalidPublicKey);
+        }
+        if !zkp.verify_recovery_enrollment(&public_key, user_name, &current, &keys, proof) {
+            trace_warn!(outcome = "rejected", "recovery enrollment proof failed");
+            return Err(Error::InvalidProof);
+        }
+        if !self
+            .seen_commitments
+            .insert(enrollment_fingerprint(user_name, proof))
+        {
+            trace_warn!("enrollment replayed");
+            return Err(Error::ReplayedCommitment);
+        }
+
+        let count = keys.len();
+        if !self.users.set_recovery_keys(user_name, keys)? {
+            return Err(Error::InvalidParameters(
+                "the user store can't keep recovery keys".to_string(),
+            ));
+        }
+        trace_info!(keys = count, "recovery keys enrolled");
+        self.audit(AuditEvent::RecoveryKeysEnrolled {
+            user: user_name.to_string(),
+            keys: count,
+        });
+
+        Ok(())
+    }
+
+    // The prover commits with the secret of `recovery_key`, one of the
+    // user's enrolled keys.
+    // output => (auth_id, c)
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(user = %user_name, auth_id))
+    )]
+    pub fn create_recovery_challenge(
+        &mut self,
+        user_name: &str,
+        recovery_key: PublicKey,
+        r1: BigUint,
+        r2: BigUint,
+        new_key: PublicKey,
+    ) -> Result<(String, BigUint), Error> {
+        if let Some(user_info) = self.users.get(user_name) {
+            if !self.users.recovery_keys(user_name).contains(&recovery_key) {
+                trace_warn!("recovery with a key that is not enrolled");
+                return Err(Error::UnknownRecoveryKey(user_name.to_string()));
+            }
+            let zkp = self
+                .parameter_set(&user_info.parameter_set)
+                .ok_or_else(|| Error::UnknownParameterSet(user_info.parameter_set.clone()))?;
+            if !zkp.is_subgroup_element(&new_key.y1) || !zkp.is_subgroup_element(&new_key.y2) {
+                trace_warn!("recovery to an invalid public key");
+                return Err(Error::InvalidPublicKey);
+            }
+        }
+
+        let purpose = Purpose::Recovery {
+            recovery_key,
+            new_key,
+        };
+        self.issue_challenge(user_name, r1, r2, purpose, None)
+    }
+
+    // Installs the new key of a recovery challenge answered with the
+    // recovery secret.
+    // output => the number of recovery keys left
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(auth_id = %auth_id, user))
+    )]
+    pub fn verify_recovery(&mut self, auth_id: &str, s: &BigUint) -> Result<usize, Error> {
+        let (challenge, user_info) = self.check_answer(auth_id, s, |purpose| {
+            matches!(purpose, Purpose::Recovery { .. })
+        })?;
+        let (recovery_key, new_key) = match challenge.purpose {
+            Purpose::Recovery {
+                recovery_key,
+                new_key,
+            } => (recovery_key, new_key),
+            _ => unreachable!("checked by check_answer"),
+        };
+
+        // the key may have been revoked by a re-enrollment while the
+        // challenge was pending
+        if !self
+            .users
+            .recovery_keys(&challenge.user_name)
+            .contains(&recovery_key)
+        {
+            trace_warn!("recovery key revoked since the challenge");
+            return Err(Error::UnknownRecoveryKey(challenge.user_name));
+        }
+
+        // Swapped before the recovery key is used up: of two recoveries
+        // racing with one key only the first finds the record unchanged.
+        let recovered = UserInfo {
+            y1: new_key.y1,
+            y2: new_key.y2,
+            ..user_info.clone()
+        };
+        if !self.users.compare_and_swap(&user_info, recovered.clone())? {
+            trace_warn!("user record changed during recovery");
+            return Err(Error::InvalidProof);
+        }
+        let mut left = self.users.recovery_keys(&challenge.user_name);
+        left.retain(|key| *key != recovery_key);
+        let recovery_keys_left = left.len();
+        self.key_cache.insert(&recovered);
+        self.sessions
+            .retain(|_, (user_name, _)| *user_name != challenge.user_name);
+        self.users.set_recovery_keys(&challenge.user_name, left)?;
+        self.clear_rotation_required(&challenge.user_name)?;
+        trace_info!(outcome = "accepted", "account recovered");
+        self.audit(AuditEvent::Recovered {
+            user: challenge.user_name,
+            parameter_set: user_info.parameter_set,
+            recovery_keys_left,
+        });
+
+        Ok(recovery_keys_left)
+    }
+}
+
+fn enrollment_fingerprint(user_name: &str, proof: &CompactProof) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(ENROLLMENT_LABEL);
+    hasher.update((user_name.len() as u32).to_be_bytes());
+    hasher.update(user_name.as_bytes());
+    for n in [&proof.c, &proof.s] {
+        let bytes = n.to_bytes_be();
+        hasher.update((bytes.len() as u32).to_be_bytes());
+        hasher.update(&bytes);
+    }
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+
+    struct Account {
+        verifier: Verifier,
+        zkp: ZKP,
+        secret: Secret,
+        codes: Vec<String>,
+        // the recorded enrollment
+        enrollment: (Vec<PublicKey>, CompactProof),
+    }
+
+    // alice registered with two recovery codes enrolled
+    fn account() -> Account {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let zkp = set.zkp();
+        let secret = Secret::random(&zkp);
+        let public_key = zkp.public_key(&secret);
+        let mut verifier = Verifier::with_parameter_sets(set, &[]);
+        verifier
+            .register("alice", public_key.y1.clone(), public_key.y2.clone())
+            .unwrap();
+
+        let (codes, keys) = generate_recovery_codes(&zkp, "alice", 2);
+        let proof = zkp.prove_recovery_enrollment(&secret, &public_key, "alice", &[], &keys);
+        verifier
+            .enroll_recovery_keys("alice", keys.clone(), &proof)
+            .unwrap();
+        Account {
+            verifier,
+            zkp,
+            secret,
+            codes,
+            enrollment: (keys, proof),
+        }
+    }
+
+    // output => the recovery keys left
+    fn recover(account: &mut Account, code: &str, new_key: PublicKey) -> Result<usize, Error> {
+        let zkp = &account.zkp;
+        let recovery_secret = Secret::from_recovery_code(zkp, "alice", code);
+        let recovery_key = zkp.public_key(&recovery_secret);
+        let k = ZKP::generate_random_nonzero_below(&zkp.q);
+        let (r1, r2) = (zkp.pow(&zkp.alpha, &k), zkp.pow(&zkp.beta, &k));
+        let (auth_id, c) =
+            account
+                .verifier
+                .create_recovery_challenge("alice", recovery_key, r1, r2, new_key)?;
+        let s = zkp.solve(&k, &c, recovery_secret.x());
+        account.verifier.verify_recovery(&auth_id, &s)
+    }
+
+    #[test]
+    fn test_recovery_codes_replace_the_key_once() {
+        let mut account = account();
+        let zkp = account.zkp.clone();
+        account.verifier.force_key_rotation("alice").unwrap();
+
+        let new_secret = Secret::random(&zkp);
+        let new_key = zkp.public_key(&new_secret);
+        let code = account.codes[0].clone();
+        assert_eq!(recover(&mut account, &code, new_key.clone()), Ok(1));
+        let user_info = account.verifier.user("alice").unwrap();
+        assert_eq!((user_info.y1, user_info.y2), (new_key.y1, new_key.y2));
+        assert!(!account.verifier.store().status("alice").rotation_required);
+
+        // used up
+        let other = zkp.public_key(&Secret::random(&zkp));
+        assert_eq!(
+            recover(&mut account, &code, other.clone()),
+            Err(Error::UnknownRecoveryKey("alice".to_string()))
+        );
+        let code = account.codes[1].clone();
+        assert_eq!(recover(&mut account, &format!(" {}\n", code), other), Ok(0));
+    }
+
+    #[test]
+    fn test_recovery_needs_the_recovery_secret() {
+        let mut account = account();
+        let zkp = account.zkp.clone();
+        let before = account.verifier.user("alice").unwrap();
+
+        // the answer made with x instead of the code's secret
+        let recovery_key = account.verifier.recovery_keys("alice")[0].clone();
+        let k = ZKP::generate_random_nonzero_below(&zkp.q);
+        let (r1, r2) = (zkp.pow(&zkp.alpha, &k), zkp.pow(&zkp.beta, &k));
+        let new_key = zkp.public_key(&Secret::random(&zkp));
+        let (auth_id, c) = account
+            .verifier
+            .create_recovery_challenge("alice", recovery_key, r1, r2, new_key)
+            .unwrap();
+        let s = zkp.solve(&k, &c, account.secret.x());
+        assert_eq!(
+            account.verifier.verify_recovery(&auth_id, &s),
+            Err(Error::InvalidProof)
+        );
+        assert_eq!(account.verifier.user("alice"), Some(before));
+        assert_eq!(account.verifier.recovery_keys("alice").len(), 2);
+
+        account.verifier.disable_user("alice").unwrap();
+        let code = account.codes[0].clone();
+        let new_key = zkp.public_key(&Secret::random(&zkp));
+        assert_eq!(
+            recover(&mut account, &code, new_key),
+            Err(Error::UserDisabled("alice".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_revoking_a_key_ends_its_pending_recovery() {
+        let mut account = account();
+        let zkp = account.zkp.clone();
+        let before = account.verifier.user("alice").unwrap();
+
+        let recovery_secret = Secret::from_recovery_code(&zkp, "alice", &account.codes[0]);
+        let recovery_key = zkp.public_key(&recovery_secret);
+        let k = ZKP::generate_random_nonzero_below(&zkp.q);
+        let (r1, r2) = (zkp.pow(&zkp.alpha, &k), zkp.pow(&zkp.beta, &k));
+        let new_key = zkp.public_key(&Secret::random(&zkp));
+        let (auth_id, c) = account
+            .verifier
+            .create_recovery_challenge("alice", recovery_key, r1, r2, new_key)
+            .unwrap();
+
+        // alice drops the leaked code before the answer comes in
+        let public_key = zkp.public_key(&account.secret);
+        let current = account.verifier.recovery_keys("alice");
+        let kept = vec![current[1].clone()];
+        let proof =
+            zkp.prove_recovery_enrollment(&account.secret, &public_key, "alice", &current, &kept);
+        account
+            .verifier
+            .enroll_recovery_keys("alice", kept, &proof)
+            .unwrap();
+
+        let s = zkp.solve(&k, &c, recovery_secret.x());
+        assert_eq!(
+            account.verifier.verify_recovery(&auth_id, &s),
+            Err(Error::UnknownRecoveryKey("alice".to_string()))
+        );
+        assert_eq!(account.verifier.user("alice"), Some(before));
+    }
+
+    #[test]
+    fn test_enrollment_is_bound_to_the_current_set() {
+        let mut account = account();
+        let zkp = account.zkp.clone();
+        let public_key = zkp.public_key(&account.secret);
+        let current = account.verifier.recovery_keys("alice");
+
+        // an enrollment by another key
+        let (_, keys) = generate_recovery_codes(&zkp, "alice", 1);
+        let mallory = Secret::random(&zkp);
+        let forged = zkp.prove_recovery_enrollment(&mallory, &public_key, "alice", &current, &keys);
+        assert_eq!(
+            account
+                .verifier
+                .enroll_recovery_keys("alice", keys.clone(), &forged),
+            Err(Error::InvalidProof)
+        );
+
+        // removing them all, then the recorded enrollment again: it was
+        // made over the same empty set
+        let proof =
+            zkp.prove_recovery_enrollment(&account.secret, &public_key, "alice", &current, &[]);
+        let wire = zkp.enrollment_proof_to_bytes(&proof);
+        let proof = zkp.enrollment_proof_from_bytes(&wire).unwrap();
+        account
+            .verifier
+            .enroll_recovery_keys("alice", Vec::new(), &proof)
+            .unwrap();
+        assert!(account.verifier.recovery_keys("alice").is_empty());
+        let (keys, first) = account.enrollment.clone();
+        assert_eq!(
+            account
+                .verifier
+                .enroll_recovery_keys("alice", keys.clone(), &first),
+            Err(Error::ReplayedCommitment)
+        );
+        // and the removal, once the set is not the one it was made over
+        let (_, other) = generate_recovery_codes(&zkp, "alice", 1);
+        let proof =
+            zkp.prove_recovery_enrollment(&account.secret, &public_key, "alice", &[], &other);
+        account
+            .verifier
+            .enroll_recovery_keys("alice", other, &proof)
+            .unwrap();
+        assert_eq!(
+            account.verifier.enroll_recovery_keys(
+                "alice",
+                Vec::new(),
+                &zkp.enrollment_proof_from_bytes(&wire).unwrap()
+            ),
+            Err(Error::InvalidProof)
+        );
+
+        account.verifier.force_key_rotation("alice").unwrap();
+        assert_eq!(
+            account.verifier.enroll_recovery_keys("alice", keys, &first),
+            Err(Error::RotationRequired("alice".to_string()))
+        );
+    }
+}
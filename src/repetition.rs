@@ -0,0 +1,278 @@
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+use crate::modmath::mod_sub;
+use crate::proof::{PublicKey, Secret};
+use crate::{Error, ZKP};
+
+// Parallel repetition with one-bit challenges, for provers that can't afford
+// a full-size challenge: with c in {0, 1} the verifier's y^c is free and the
+// prover's c * x is an addition at most. One round is cheated with
+// probability 1/2, t independent rounds with probability 2^-t, so t is the
+// soundness in bits and has to be picked for it (80 for interactive logins,
+// 128 for anything a forger can grind on offline, like the non-interactive
+// proofs here).
+const CHALLENGE_LABEL: &[u8] = b"zkp-chaum-pedersen/repetition/v1";
+
+// 2^16 rounds is far past any sensible soundness
+pub const MAX_ROUNDS: usize = u16::MAX as usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Repetition {
+    pub rounds: usize,
+}
+
+impl Repetition {
+    // t rounds for a soundness error of 2^-t
+    pub fn for_soundness_bits(bits: usize) -> Result<Self, Error> {
+        if bits == 0 || bits > MAX_ROUNDS {
+            return Err(Error::InvalidParameters(format!(
+                "rounds must be in 1..={}",
+                MAX_ROUNDS
+            )));
+        }
+        Ok(Repetition { rounds: bits })
+    }
+
+    pub fn soundness_bits(&self) -> usize {
+        self.rounds
+    }
+
+    // the probability a prover without x passes, 2^-t
+    pub fn soundness_error(&self) -> f64 {
+        0.5f64.powi(self.rounds as i32)
+    }
+}
+
+// t commitments (r1, r2) and their answers; the challenge bits are hashed
+// from the statement and the commitments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepeatedProof {
+    pub commitments: Vec<(BigUint, BigUint)>,
+    pub answers: Vec<BigUint>,
+}
+
+impl RepeatedProof {
+    pub fn repetition(&self) -> Repetition {
+        Repetition {
+            rounds: self.commitments.len(),
+        }
+    }
+
+    // rounds u16 | (r1 | r2 | s) per round, r1 and r2 padded to the byte
+    // length of p, s to that of q
+    pub fn to_bytes(&self, zkp: &ZKP) -> Vec<u8> {
+        let mut bytes = (self.commitments.len() as u16).to_be_bytes().to_vec();
+        for ((r1, r2), s) in self.commitments.iter().zip(&self.answers) {
+            bytes.extend_from_slice(&zkp.element_to_bytes(r1));
+            bytes.extend_from_slice(&zkp.element_to_bytes(r2));
+            bytes.extend_from_slice(&zkp.scalar_to_bytes(s));
+        }
+        bytes
+    }
+
+    pub fn from_bytes(zkp: &ZKP, bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() < 2 {
+            return Err(Error::InvalidEncoding(
+                "truncated repeated proof".to_string(),
+            ));
+        }
+        let rounds = u16::from_be_bytes([bytes[0], bytes[1]]) as usize;
+        let round_len = 2 * zkp.element_len() + zkp.scalar_len();
+        if rounds == 0 || bytes.len() != 2 + rounds * round_len {
+            return Err(Error::InvalidEncoding(format!(
+                "expected {} rounds of {} bytes",
+                rounds, round_len
+            )));
+        }
+
+        let mut proof = RepeatedProof {
+            commitments: Vec::with_capacity(rounds),
+            answers: Vec::with_capacity(rounds),
+        };
+        for round in bytes[2..].chunks(round_len) {
+            let (r1, rest) = round.split_at(zkp.element_len());
+            let (r2, s) = rest.split_at(zkp.element_len());
+            proof
+                .commitments
+                .push((zkp.element_from_bytes(r1)?, zkp.element_from_bytes(r2)?));
+            proof.answers.push(zkp.scalar_from_bytes(s)?);
+        }
+        Ok(proof)
+    }
+}
+
+impl ZKP {
+    // output = (k, (r1, r2)) per round, the ks stay with the prover
+    pub fn commit_repeated(
+        &self,
+        repetition: Repetition,
+    ) -> (Vec<BigUint>, Vec<(BigUint, BigUint)>) {
+        (0..repetition.rounds)
+            .map(|_| {
+                let k = ZKP::generate_random_nonzero_below(&self.q);
+                let r = (self.pow(&self.alpha, &k), self.pow(&self.beta, &k));
+                (k, r)
+            })
+            .unzip()
+    }
+
+    // output = s_i = k_i - c_i * x mod q, which is k_i or k_i - x
+    pub fn solve_repeated(&self, ks: &[BigUint], challenges: &[bool], x: &BigUint) -> Vec<BigUint> {
+        ks.iter()
+            .zip(challenges)
+            .map(|(k, &c)| match c {
+                true => mod_sub(k, x, &self.q),
+                false => k.clone(),
+            })
+            .collect()
+    }
+
+    // Every round must verify, and there must be as many answers and
+    // challenges as commitments.
+    pub fn verify_repeated(
+        &self,
+        public_key: &PublicKey,
+        commitments: &[(BigUint, BigUint)],
+        challenges: &[bool],
+        answers: &[BigUint],
+    ) -> bool {
+        if commitments.is_empty()
+            || commitments.len() != challenges.len()
+            || commitments.len() != answers.len()
+        {
+            return false;
+        }
+        let (zero, one) = (BigUint::from(0u32), BigUint::from(1u32));
+        commitments
+            .iter()
+            .zip(challenges)
+            .zip(answers)
+            .all(|(((r1, r2), &c), s)| {
+                let c = if c { &one } else { &zero };
+                self.verify(r1, r2, &public_key.y1, &public_key.y2, s, c)
+            })
+    }
+
+    // The t challenge bits from H(p, q, alpha, beta, y1, y2, r1_1, r2_1, ...),
+    // expanded with a counter past 256 rounds.
+    pub fn repeated_challenges(
+        &self,
+        public_key: &PublicKey,
+        commitments: &[(BigUint, BigUint)],
+    ) -> Vec<bool> {
+        let mut hasher = Sha256::new();
+        hasher.update(CHALLENGE_LABEL);
+        let statement = [
+            &self.p,
+            &self.q,
+            &self.alpha,
+            &self.beta,
+            &public_key.y1,
+            &public_key.y2,
+        ];
+        let rounds = commitments.iter().flat_map(|(r1, r2)| [r1, r2]);
+        for n in statement.into_iter().chain(rounds) {
+            let bytes = self.element_to_bytes(n);
+            hasher.update((bytes.len() as u32).to_be_bytes());
+            hasher.update(&bytes);
+        }
+        let seed = hasher.finalize();
+
+        let mut bits = Vec::with_capacity(commitments.len());
+        let mut counter = 0u32;
+        while bits.len() < commitments.len() {
+            let block = Sha256::new()
+                .chain_update(seed)
+                .chain_update(counter.to_be_bytes())
+                .finalize();
+            bits.extend(
+                block
+                    .iter()
+                    .flat_map(|byte| (0..8).map(move |i| byte >> i & 1 == 1)),
+            );
+            counter += 1;
+        }
+        bits.truncate(commitments.len());
+        bits
+    }
+
+    pub fn prove_repeated(
+        &self,
+        secret: &Secret,
+        public_key: &PublicKey,
+        repetition: Repetition,
+    ) -> RepeatedProof {
+        let (ks, commitments) = self.commit_repeated(repetition);
+        let challenges = self.repeated_challenges(public_key, &commitments);
+        RepeatedProof {
+            answers: self.solve_repeated(&ks, &challenges, secret.x()),
+            commitments,
+        }
+    }
+
+    // At least `required` rounds, so a prover can't pick a weaker proof than
+    // the verifier asked for.
+    pub fn verify_repeated_proof(
+        &self,
+        public_key: &PublicKey,
+        proof: &RepeatedProof,
+        required: Repetition,
+    ) -> bool {
+        if proof.repetition().rounds < required.rounds {
+            return false;
+        }
+        let challenges = self.repeated_challenges(public_key, &proof.commitments);
+        self.verify_repeated(public_key, &proof.commitments, &challenges, &proof.answers)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+
+    #[test]
+    fn test_interactive_rounds() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let secret = Secret::random(&zkp);
+        let public_key = zkp.public_key(&secret);
+        let repetition = Repetition::for_soundness_bits(40).unwrap();
+        assert_eq!(repetition.soundness_error(), 2f64.powi(-40));
+
+        let (ks, commitments) = zkp.commit_repeated(repetition);
+        let challenges: Vec<bool> = (0..40).map(|i| i % 3 == 0).collect();
+        let answers = zkp.solve_repeated(&ks, &challenges, secret.x());
+        assert!(zkp.verify_repeated(&public_key, &commitments, &challenges, &answers));
+
+        // a prover without x can answer one of the two challenges per round,
+        // here it guessed 0 everywhere and round 0 asks for 1
+        let other = zkp.public_key(&Secret::random(&zkp));
+        let guessed = zkp.solve_repeated(&ks, &[false; 40], &BigUint::from(0u32));
+        assert!(!zkp.verify_repeated(&other, &commitments, &challenges, &guessed));
+        assert!(!zkp.verify_repeated(&public_key, &commitments, &challenges[1..], &answers));
+
+        assert!(Repetition::for_soundness_bits(0).is_err());
+    }
+
+    #[test]
+    fn test_non_interactive_roundtrip() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let secret = Secret::random(&zkp);
+        let public_key = zkp.public_key(&secret);
+        let repetition = Repetition::for_soundness_bits(300).unwrap();
+
+        let proof = zkp.prove_repeated(&secret, &public_key, repetition);
+        let decoded = RepeatedProof::from_bytes(&zkp, &proof.to_bytes(&zkp)).unwrap();
+        assert_eq!(decoded, proof);
+        assert!(zkp.verify_repeated_proof(&public_key, &decoded, repetition));
+
+        let stronger = Repetition::for_soundness_bits(301).unwrap();
+        assert!(!zkp.verify_repeated_proof(&public_key, &decoded, stronger));
+        let other = zkp.public_key(&Secret::random(&zkp));
+        assert!(!zkp.verify_repeated_proof(&other, &decoded, repetition));
+
+        let bytes = proof.to_bytes(&zkp);
+        assert!(RepeatedProof::from_bytes(&zkp, &bytes[..bytes.len() - 1]).is_err());
+    }
+}
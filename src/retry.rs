@@ -0,0 +1,184 @@
+use std::future::Future;
+use std::time::Duration;
+
+use rand::Rng;
+use tonic::{Code, Status};
+
+// How a remote prover copes with a flaky network: every call gets a
+// deadline, and calls that failed for a reason that may go away are tried
+// again after an exponential, jittered backoff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    // tries per call, the first one included
+    pub attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    // per try, none if unset
+    pub timeout: Option<Duration>,
+}
+
+impl RetryPolicy {
+    // one try, no deadline
+    pub fn none() -> Self {
+        RetryPolicy {
+            attempts: 1,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+            timeout: None,
+        }
+    }
+
+    // Pause before try number `attempt` (the first retry is 1): full jitter
+    // over initial * 2^(attempt - 1), capped at max, so clients that failed
+    // together don't come back together.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16);
+        let ceiling = self
+            .initial_backoff
+            .saturating_mul(1 << exponent)
+            .min(self.max_backoff);
+        if ceiling.is_zero() {
+            return ceiling;
+        }
+        rand::thread_rng().gen_range(Duration::ZERO..=ceiling)
+    }
+
+    // Worth another try: the server was unreachable or too slow. Rejected
+    // proofs, unknown users, the rate limit and errors the server could not
+    // classify (Unknown) are final.
+    pub fn is_transient(status: &Status) -> bool {
+        matches!(
+            status.code(),
+            Code::Unavailable | Code::DeadlineExceeded | Code::Aborted
+        )
+    }
+
+    // Same deadline, a single try: for requests the server accepts once. A
+    // commitment is one of them, a retry of one that landed is refused as
+    // replayed, so the caller starts over with a fresh nonce instead.
+    pub fn once(&self) -> Self {
+        RetryPolicy {
+            attempts: 1,
+            ..self.clone()
+        }
+    }
+
+    // Runs `call` until it succeeds, fails for good or the tries run out.
+    pub async fn run<T, F, Fut>(&self, mut call: F) -> Result<T, Status>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Status>>,
+    {
+        let mut attempt = 0;
+        loop {
+            let result = match self.timeout {
+                Some(timeout) => match tokio::time::timeout(timeout, call()).await {
+                    Ok(result) => result,
+                    Err(_) => Err(Status::deadline_exceeded("call timed out")),
+                },
+                None => call().await,
+            };
+            attempt += 1;
+            match result {
+                Err(status) if attempt < self.attempts && RetryPolicy::is_transient(&status) => {
+                    trace_warn!(attempt, code = ?status.code(), "retrying call");
+                    tokio::time::sleep(self.backoff(attempt)).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+            timeout: Some(Duration::from_secs(10)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::cell::Cell;
+
+    #[tokio::test]
+    async fn test_retries_transient_failures_only() {
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+            ..RetryPolicy::default()
+        };
+
+        let tries = Cell::new(0);
+        let result = policy
+            .run(|| {
+                tries.set(tries.get() + 1);
+                let tried = tries.get();
+                async move {
+                    if tried < 3 {
+                        Err(Status::unavailable("down"))
+                    } else {
+                        Ok(tried)
+                    }
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 3);
+
+        tries.set(0);
+        let result: Result<(), _> = policy
+            .run(|| {
+                tries.set(tries.get() + 1);
+                async { Err(Status::permission_denied("bad proof")) }
+            })
+            .await;
+        assert_eq!(result.unwrap_err().code(), Code::PermissionDenied);
+        assert_eq!(tries.get(), 1);
+
+        tries.set(0);
+        let result: Result<(), _> = policy
+            .run(|| {
+                tries.set(tries.get() + 1);
+                async { Err(Status::unknown("handler panicked")) }
+            })
+            .await;
+        assert_eq!(result.unwrap_err().code(), Code::Unknown);
+        assert_eq!(tries.get(), 1);
+
+        tries.set(0);
+        let result: Result<(), _> = policy
+            .once()
+            .run(|| {
+                tries.set(tries.get() + 1);
+                async { Err(Status::unavailable("down")) }
+            })
+            .await;
+        assert_eq!(result.unwrap_err().code(), Code::Unavailable);
+        assert_eq!(tries.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_timeout_and_backoff() {
+        let policy = RetryPolicy {
+            attempts: 2,
+            timeout: Some(Duration::from_millis(10)),
+            ..RetryPolicy::default()
+        };
+        let result: Result<(), _> = policy
+            .run(|| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                Ok(())
+            })
+            .await;
+        assert_eq!(result.unwrap_err().code(), Code::DeadlineExceeded);
+
+        assert!(policy.backoff(1) <= policy.initial_backoff);
+        assert!(policy.backoff(30) <= policy.max_backoff);
+        assert_eq!(RetryPolicy::none().backoff(1), Duration::ZERO);
+    }
+}
@@ -1,9 +1,958 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use sha2::{Digest, Sha256};
+use tokio::sync::{mpsc, Notify};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{transport::Server, Code, Request, Response, Status, Streaming};
+use tonic_health::server::HealthReporter;
+
+use zkp_chaum_padersen::{
+    blind::BlindedRegistration,
+    config::ServerConfig,
+    proof::PublicKey,
+    protocol::ProtocolVersion,
+    store::UserStore,
+    transcript::{Entry, TranscriptWriter},
+    verifier::Verifier,
+    Error, ZKP,
+};
+
 pub mod zkp_auth {
     include!("./zkp_auth.rs");
 }
 
-use zkp_auth::auth_server;
+use zkp_auth::{
+    admin_server::{Admin, AdminServer},
+    auth_server::{Auth, AuthServer},
+    authentication_stream_request, authentication_stream_response, AuthenticationAnswerRequest,
+    AuthenticationAnswerResponse, AuthenticationChallengeRequest, AuthenticationChallengeResponse,
+    AuthenticationStreamRequest, AuthenticationStreamResponse, KeyRotationAnswerRequest,
+    KeyRotationAnswerResponse, KeyRotationChallengeRequest, NegotiateRequest, NegotiateResponse,
+    RegisterRequest, RegisterResponse,
+};
+use zkp_auth::{
+    AdminResponse, AdminUser, AdminUserRequest, ListPendingChallengesRequest,
+    ListPendingChallengesResponse, ListSessionsRequest, ListSessionsResponse, ListUsersRequest,
+    ListUsersResponse, ParameterSetStats, ParameterSetStatsRequest, ParameterSetStatsResponse,
+    PendingChallenge, RevokeSessionRequest, RevokeUserSessionsResponse, Session,
+};
+use zkp_auth::{
+    EnrollRecoveryKeysRequest, EnrollRecoveryKeysResponse, RecoveryAnswerRequest,
+    RecoveryAnswerResponse, RecoveryChallengeRequest, RecoveryKey,
+};
+
+// how often readiness pings the user store
+const READINESS_INTERVAL: Duration = Duration::from_secs(5);
+
+// the store is picked by the config
+type ServerVerifier = Verifier<Box<dyn UserStore + Send>>;
+
+// cloned into the task of every streaming login
+#[derive(Clone)]
+struct AuthImpl {
+    verifier: Arc<Mutex<ServerVerifier>>,
+    // request and reply, for the thread writing the transcript
+    transcript: Option<Sender<[Entry; 2]>>,
+}
+
+// the admin rpcs, behind `admin_auth`
+struct AdminImpl {
+    verifier: Arc<Mutex<ServerVerifier>>,
+}
+
+fn to_status(error: Error) -> Status {
+    let code = match error {
+        Error::UserNotFound(_) | Error::ChallengeNotFound(_) | Error::UnknownRecoveryKey(_) => {
+            Code::NotFound
+        }
+        Error::InvalidProof
+        | Error::ReplayedCommitment
+        | Error::ActionMismatch
+        | Error::Expired
+        | Error::UserDisabled(_) => Code::PermissionDenied,
+        Error::UnknownParameterSet(_)
+        | Error::InvalidEncoding(_)
+        | Error::InvalidParameters(_)
+        | Error::InvalidPublicKey
+        | Error::UnsupportedVersion(_) => Code::InvalidArgument,
+        Error::NoPendingCommitment | Error::RotationRequired(_) => Code::FailedPrecondition,
+        Error::Transport(_) => Code::Unavailable,
+        Error::RateLimited => Code::ResourceExhausted,
+        Error::Declined => Code::Cancelled,
+        Error::AlreadyRegistered(_) => Code::AlreadyExists,
+        Error::StepUpRequired(_) | Error::SessionNotFound => Code::Unauthenticated,
+        Error::WrongPassphrase | Error::Io(_) => Code::Internal,
+    };
+    Status::new(code, error.to_string())
+}
+
+#[tonic::async_trait]
+impl Auth for AuthImpl {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "rpc.negotiate", skip_all)
+    )]
+    async fn negotiate(
+        &self,
+        request: Request<NegotiateRequest>,
+    ) -> Result<Response<NegotiateResponse>, Status> {
+        let request = request.into_inner();
+
+        let version = ProtocolVersion::negotiate(&ProtocolVersion::ALL, &request.versions)
+            .ok_or_else(|| {
+                Status::failed_precondition("no protocol version in common with the server")
+            })?;
+
+        let verifier = self.verifier.lock().unwrap();
+        Ok(Response::new(NegotiateResponse {
+            version: version.number(),
+            supported_versions: ProtocolVersion::ALL.iter().map(|v| v.number()).collect(),
+            parameter_sets: verifier.parameter_set_ids(),
+            default_parameter_set: verifier.default_parameter_set().to_string(),
+        }))
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "rpc.register", skip_all)
+    )]
+    async fn register(
+        &self,
+        request: Request<RegisterRequest>,
+    ) -> Result<Response<RegisterResponse>, Status> {
+        let request = request.into_inner();
+        let version = ProtocolVersion::from_number(request.version).map_err(to_status)?;
+
+        let verifier = &mut self.verifier.lock().unwrap();
+        let parameter_set = match request.parameter_set.as_str() {
+            "" => verifier.default_parameter_set().to_string(),
+            id => id.to_string(),
+        };
+        let result = verifier
+            .parameter_set(&parameter_set)
+            .cloned()
+            .ok_or_else(|| Error::UnknownParameterSet(parameter_set.clone()))
+            .and_then(|zkp| {
+                let y1 = version.decode_element(&zkp, &request.y1)?;
+                let y2 = version.decode_element(&zkp, &request.y2)?;
+                if request.blinding_proof.is_empty() {
+                    return verifier.register_with_parameter_set(
+                        &request.user,
+                        &parameter_set,
+                        y1,
+                        y2,
+                    );
+                }
+                let registration = BlindedRegistration::from_parts(
+                    &zkp,
+                    PublicKey { y1, y2 },
+                    &request.blinding_proof,
+                )?;
+                verifier.register_blinded(&request.user, &parameter_set, registration)
+            });
+        self.record(
+            Entry::Register {
+                user: request.user,
+                parameter_set,
+                y1: request.y1,
+                y2: request.y2,
+            },
+            &result,
+            |_| Entry::Registered,
+        );
+        result.map_err(to_status)?;
+
+        Ok(Response::new(RegisterResponse {}))
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "rpc.create_authentication_challenge", skip_all)
+    )]
+    async fn create_authentication_challenge(
+        &self,
+        request: Request<AuthenticationChallengeRequest>,
+    ) -> Result<Response<AuthenticationChallengeResponse>, Status> {
+        let request = request.into_inner();
+        let version = ProtocolVersion::from_number(request.version).map_err(to_status)?;
+
+        let verifier = &mut self.verifier.lock().unwrap();
+        let result = user_zkp(verifier, &request.user).and_then(|zkp| {
+            let r1 = version.decode_element(&zkp, &request.r1)?;
+            let r2 = version.decode_element(&zkp, &request.r2)?;
+            let (auth_id, c) = verifier.create_challenge(&request.user, r1, r2)?;
+            Ok((auth_id, version.encode_scalar(&zkp, &c)))
+        });
+        self.record(
+            Entry::Commitment {
+                user: request.user,
+                r1: request.r1,
+                r2: request.r2,
+            },
+            &result,
+            challenge_entry,
+        );
+        let (auth_id, c) = result.map_err(to_status)?;
+
+        Ok(Response::new(AuthenticationChallengeResponse {
+            auth_id,
+            c,
+        }))
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "rpc.verify_authentication", skip_all)
+    )]
+    async fn verify_authentication(
+        &self,
+        request: Request<AuthenticationAnswerRequest>,
+    ) -> Result<Response<AuthenticationAnswerResponse>, Status> {
+        let request = request.into_inner();
+        let version = ProtocolVersion::from_number(request.version).map_err(to_status)?;
+
+        let verifier = &mut self.verifier.lock().unwrap();
+        let result = challenge_zkp(verifier, &request.auth_id).and_then(|zkp| {
+            let s = version.decode_scalar(&zkp, &request.s)?;
+            verifier.verify(&request.auth_id, &s)
+        });
+        self.record(
+            Entry::Response {
+                auth_id: request.auth_id,
+                s: request.s,
+            },
+            &result,
+            |session_id| Entry::session(session_id),
+        );
+        let session_id = result.map_err(to_status)?;
+
+        Ok(Response::new(AuthenticationAnswerResponse { session_id }))
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "rpc.create_key_rotation_challenge", skip_all)
+    )]
+    async fn create_key_rotation_challenge(
+        &self,
+        request: Request<KeyRotationChallengeRequest>,
+    ) -> Result<Response<AuthenticationChallengeResponse>, Status> {
+        let request = request.into_inner();
+        let version = ProtocolVersion::from_number(request.version).map_err(to_status)?;
+
+        let verifier = &mut self.verifier.lock().unwrap();
+        let result = user_zkp(verifier, &request.user).and_then(|zkp| {
+            let r1 = version.decode_element(&zkp, &request.r1)?;
+            let r2 = version.decode_element(&zkp, &request.r2)?;
+            let new_y1 = version.decode_element(&zkp, &request.new_y1)?;
+            let new_y2 = version.decode_element(&zkp, &request.new_y2)?;
+            let (auth_id, c) =
+                verifier.create_rotation_challenge(&request.user, r1, r2, new_y1, new_y2)?;
+            Ok((auth_id, version.encode_scalar(&zkp, &c)))
+        });
+        self.record(
+            Entry::RotationCommitment {
+                user: request.user,
+                r1: request.r1,
+                r2: request.r2,
+                new_y1: request.new_y1,
+                new_y2: request.new_y2,
+            },
+            &result,
+            challenge_entry,
+        );
+        let (auth_id, c) = result.map_err(to_status)?;
+
+        Ok(Response::new(AuthenticationChallengeResponse {
+            auth_id,
+            c,
+        }))
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "rpc.verify_key_rotation", skip_all)
+    )]
+    async fn verify_key_rotation(
+        &self,
+        request: Request<KeyRotationAnswerRequest>,
+    ) -> Result<Response<KeyRotationAnswerResponse>, Status> {
+        let request = request.into_inner();
+        let version = ProtocolVersion::from_number(request.version).map_err(to_status)?;
+
+        let verifier = &mut self.verifier.lock().unwrap();
+        let result = challenge_zkp(verifier, &request.auth_id).and_then(|zkp| {
+            let s = version.decode_scalar(&zkp, &request.s)?;
+            verifier.verify_rotation(&request.auth_id, &s)
+        });
+        self.record(
+            Entry::Response {
+                auth_id: request.auth_id,
+                s: request.s,
+            },
+            &result,
+            |_| Entry::Rotated,
+        );
+        result.map_err(to_status)?;
+
+        Ok(Response::new(KeyRotationAnswerResponse {}))
+    }
+
+    // The recovery rpcs are not written to the transcript, it has no
+    // entries for them.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "rpc.enroll_recovery_keys", skip_all)
+    )]
+    async fn enroll_recovery_keys(
+        &self,
+        request: Request<EnrollRecoveryKeysRequest>,
+    ) -> Result<Response<EnrollRecoveryKeysResponse>, Status> {
+        let request = request.into_inner();
+        let version = ProtocolVersion::from_number(request.version).map_err(to_status)?;
+
+        let verifier = &mut self.verifier.lock().unwrap();
+        user_zkp(verifier, &request.user)
+            .and_then(|zkp| {
+                let keys = request
+                    .keys
+                    .iter()
+                    .map(|key| decode_recovery_key(&zkp, version, key))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                let proof = zkp.enrollment_proof_from_bytes(&request.proof)?;
+                verifier.enroll_recovery_keys(&request.user, keys, &proof)
+            })
+            .map_err(to_status)?;
+
+        Ok(Response::new(EnrollRecoveryKeysResponse {}))
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "rpc.create_recovery_challenge", skip_all)
+    )]
+    async fn create_recovery_challenge(
+        &self,
+        request: Request<RecoveryChallengeRequest>,
+    ) -> Result<Response<AuthenticationChallengeResponse>, Status> {
+        let request = request.into_inner();
+        let version = ProtocolVersion::from_number(request.version).map_err(to_status)?;
+
+        let verifier = &mut self.verifier.lock().unwrap();
+        let (auth_id, c) = user_zkp(verifier, &request.user)
+            .and_then(|zkp| {
+                let recovery_key = request
+                    .recovery_key
+                    .as_ref()
+                    .ok_or_else(|| Error::InvalidEncoding("recovery_key is missing".to_string()))?;
+                let recovery_key = decode_recovery_key(&zkp, version, recovery_key)?;
+                let r1 = version.decode_element(&zkp, &request.r1)?;
+                let r2 = version.decode_element(&zkp, &request.r2)?;
+                let new_key = PublicKey {
+                    y1: version.decode_element(&zkp, &request.new_y1)?,
+                    y2: version.decode_element(&zkp, &request.new_y2)?,
+                };
+                let (auth_id, c) = verifier.create_recovery_challenge(
+                    &request.user,
+                    recovery_key,
+                    r1,
+                    r2,
+                    new_key,
+                )?;
+                Ok((auth_id, version.encode_scalar(&zkp, &c)))
+            })
+            .map_err(to_status)?;
+
+        Ok(Response::new(AuthenticationChallengeResponse {
+            auth_id,
+            c,
+        }))
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "rpc.verify_recovery", skip_all)
+    )]
+    async fn verify_recovery(
+        &self,
+        request: Request<RecoveryAnswerRequest>,
+    ) -> Result<Response<RecoveryAnswerResponse>, Status> {
+        let request = request.into_inner();
+        let version = ProtocolVersion::from_number(request.version).map_err(to_status)?;
+
+        let verifier = &mut self.verifier.lock().unwrap();
+        let left = challenge_zkp(verifier, &request.auth_id)
+            .and_then(|zkp| {
+                let s = version.decode_scalar(&zkp, &request.s)?;
+                verifier.verify_recovery(&request.auth_id, &s)
+            })
+            .map_err(to_status)?;
+
+        Ok(Response::new(RecoveryAnswerResponse {
+            recovery_keys_left: left as u32,
+        }))
+    }
+
+    type AuthenticateStream = ReceiverStream<Result<AuthenticationStreamResponse, Status>>;
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "rpc.authenticate", skip_all)
+    )]
+    async fn authenticate(
+        &self,
+        request: Request<Streaming<AuthenticationStreamRequest>>,
+    ) -> Result<Response<Self::AuthenticateStream>, Status> {
+        let mut inbound = request.into_inner();
+        let (outbound, receiver) = mpsc::channel(1);
+
+        let auth_impl = self.clone();
+        tokio::spawn(async move {
+            if let Err(status) = auth_impl.stream_login(&mut inbound, &outbound).await {
+                // the prover may be gone already, nothing left to tell then
+                let _ = outbound.send(Err(status)).await;
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+}
+
+#[tonic::async_trait]
+impl Admin for AdminImpl {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "rpc.admin.list_users", skip_all)
+    )]
+    async fn list_users(
+        &self,
+        _request: Request<ListUsersRequest>,
+    ) -> Result<Response<ListUsersResponse>, Status> {
+        let verifier = self.verifier.lock().unwrap();
+        let users = verifier
+            .list_users()
+            .into_iter()
+            .map(|summary| AdminUser {
+                user: summary.user_info.user_name,
+                parameter_set: summary.user_info.parameter_set,
+                disabled: summary.status.disabled,
+                rotation_required: summary.status.rotation_required,
+                sessions: summary.sessions as u32,
+            })
+            .collect();
+
+        Ok(Response::new(ListUsersResponse { users }))
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "rpc.admin.disable_user", skip_all)
+    )]
+    async fn disable_user(
+        &self,
+        request: Request<AdminUserRequest>,
+    ) -> Result<Response<AdminResponse>, Status> {
+        let user = request.into_inner().user;
+        let verifier = &mut self.verifier.lock().unwrap();
+        verifier.disable_user(&user).map_err(to_status)?;
+
+        Ok(Response::new(AdminResponse {}))
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "rpc.admin.enable_user", skip_all)
+    )]
+    async fn enable_user(
+        &self,
+        request: Request<AdminUserRequest>,
+    ) -> Result<Response<AdminResponse>, Status> {
+        let user = request.into_inner().user;
+        let verifier = &mut self.verifier.lock().unwrap();
+        verifier.enable_user(&user).map_err(to_status)?;
+
+        Ok(Response::new(AdminResponse {}))
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "rpc.admin.delete_user", skip_all)
+    )]
+    async fn delete_user(
+        &self,
+        request: Request<AdminUserRequest>,
+    ) -> Result<Response<AdminResponse>, Status> {
+        let user = request.into_inner().user;
+        let verifier = &mut self.verifier.lock().unwrap();
+        verifier.delete_user(&user).map_err(to_status)?;
+
+        Ok(Response::new(AdminResponse {}))
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "rpc.admin.force_key_rotation", skip_all)
+    )]
+    async fn force_key_rotation(
+        &self,
+        request: Request<AdminUserRequest>,
+    ) -> Result<Response<AdminResponse>, Status> {
+        let user = request.into_inner().user;
+        let verifier = &mut self.verifier.lock().unwrap();
+        verifier.force_key_rotation(&user).map_err(to_status)?;
+
+        Ok(Response::new(AdminResponse {}))
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "rpc.admin.list_pending_challenges", skip_all)
+    )]
+    async fn list_pending_challenges(
+        &self,
+        _request: Request<ListPendingChallengesRequest>,
+    ) -> Result<Response<ListPendingChallengesResponse>, Status> {
+        let verifier = self.verifier.lock().unwrap();
+        let challenges = verifier
+            .pending_challenges()
+            .into_iter()
+            .map(|pending| PendingChallenge {
+                auth_id: pending.auth_id,
+                user: pending.user_name,
+                purpose: pending.kind.id().to_string(),
+                age_ms: pending.age.as_millis() as u64,
+            })
+            .collect();
+
+        Ok(Response::new(ListPendingChallengesResponse { challenges }))
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "rpc.admin.get_parameter_set_stats", skip_all)
+    )]
+    async fn get_parameter_set_stats(
+        &self,
+        _request: Request<ParameterSetStatsRequest>,
+    ) -> Result<Response<ParameterSetStatsResponse>, Status> {
+        let verifier = self.verifier.lock().unwrap();
+        let parameter_sets = verifier
+            .parameter_set_stats()
+            .into_iter()
+            .map(|stats| ParameterSetStats {
+                id: stats.id,
+                is_default: stats.is_default,
+                users: stats.users as u32,
+                pending_challenges: stats.pending_challenges as u32,
+            })
+            .collect();
+
+        Ok(Response::new(ParameterSetStatsResponse { parameter_sets }))
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "rpc.admin.list_sessions", skip_all)
+    )]
+    async fn list_sessions(
+        &self,
+        request: Request<ListSessionsRequest>,
+    ) -> Result<Response<ListSessionsResponse>, Status> {
+        let user = request.into_inner().user;
+        let verifier = self.verifier.lock().unwrap();
+        let sessions = match user.as_str() {
+            "" => verifier.list_sessions(),
+            user => verifier.user_sessions(user),
+        };
+        let sessions = sessions
+            .into_iter()
+            .map(|session| Session {
+                session_id: session.session_id,
+                user: session.user_name,
+                age_ms: session.age.as_millis() as u64,
+            })
+            .collect();
+
+        Ok(Response::new(ListSessionsResponse { sessions }))
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "rpc.admin.revoke_session", skip_all)
+    )]
+    async fn revoke_session(
+        &self,
+        request: Request<RevokeSessionRequest>,
+    ) -> Result<Response<AdminResponse>, Status> {
+        let session_id = request.into_inner().session_id;
+        let verifier = &mut self.verifier.lock().unwrap();
+        verifier.revoke_session(&session_id).map_err(to_status)?;
+
+        Ok(Response::new(AdminResponse {}))
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "rpc.admin.revoke_user_sessions", skip_all)
+    )]
+    async fn revoke_user_sessions(
+        &self,
+        request: Request<AdminUserRequest>,
+    ) -> Result<Response<RevokeUserSessionsResponse>, Status> {
+        let user = request.into_inner().user;
+        let revoked = self.verifier.lock().unwrap().revoke_all_for_user(&user);
+
+        Ok(Response::new(RevokeUserSessionsResponse {
+            revoked: revoked as u32,
+        }))
+    }
+}
+
+// Lets through requests with "authorization: Bearer <token>". The digests
+// are compared rather than the tokens, so how long the comparison takes says
+// nothing about the token. Interceptors have to fail with a `Status`.
+#[allow(clippy::result_large_err)]
+fn admin_auth(token: &str) -> impl Fn(Request<()>) -> Result<Request<()>, Status> + Clone {
+    let expected = Sha256::digest(token.as_bytes());
+    move |request: Request<()>| {
+        let presented = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        match presented {
+            Some(presented) if Sha256::digest(presented.as_bytes()) == expected => Ok(request),
+            _ => Err(Status::unauthenticated("a valid admin token is required")),
+        }
+    }
+}
+
+impl AuthImpl {
+    // commitment -> challenge -> answer -> session on one stream. The
+    // challenge is held here and dropped with the stream if the prover never
+    // answers.
+    async fn stream_login(
+        &self,
+        inbound: &mut Streaming<AuthenticationStreamRequest>,
+        outbound: &mpsc::Sender<Result<AuthenticationStreamResponse, Status>>,
+    ) -> Result<(), Status> {
+        use authentication_stream_request::Step;
+        use authentication_stream_response::Step as Reply;
+
+        let request = match next_step(inbound).await? {
+            Step::Commitment(request) => request,
+            Step::S(_) => return Err(Status::failed_precondition("expected a commitment")),
+        };
+        let version = ProtocolVersion::from_number(request.version).map_err(to_status)?;
+
+        let (zkp, detached) = {
+            let verifier = &mut self.verifier.lock().unwrap();
+            let result = user_zkp(verifier, &request.user).and_then(|zkp| {
+                let r1 = version.decode_element(&zkp, &request.r1)?;
+                let r2 = version.decode_element(&zkp, &request.r2)?;
+                let detached = verifier.create_detached_challenge(&request.user, r1, r2)?;
+                Ok((zkp, detached))
+            });
+            self.record(
+                Entry::Commitment {
+                    user: request.user,
+                    r1: request.r1,
+                    r2: request.r2,
+                },
+                &result,
+                |(zkp, detached)| Entry::Challenge {
+                    auth_id: detached.auth_id.clone(),
+                    c: version.encode_scalar(zkp, detached.c()),
+                },
+            );
+            result.map_err(to_status)?
+        };
+
+        let challenge = AuthenticationChallengeResponse {
+            auth_id: detached.auth_id.clone(),
+            c: version.encode_scalar(&zkp, detached.c()),
+        };
+        send_step(outbound, Reply::Challenge(challenge)).await?;
+
+        let s = match next_step(inbound).await? {
+            Step::S(s) => s,
+            Step::Commitment(_) => return Err(Status::failed_precondition("expected an answer")),
+        };
+
+        let session_id = {
+            let verifier = &mut self.verifier.lock().unwrap();
+            let auth_id = detached.auth_id.clone();
+            let result = version
+                .decode_scalar(&zkp, &s)
+                .and_then(|s| verifier.verify_detached(detached, &s));
+            self.record(Entry::Response { auth_id, s }, &result, |session_id| {
+                Entry::session(session_id)
+            });
+            result.map_err(to_status)?
+        };
+
+        send_step(
+            outbound,
+            Reply::Session(AuthenticationAnswerResponse { session_id }),
+        )
+        .await
+    }
+
+    // Queues the request and its reply, with the bytes as received, for the
+    // transcript if one is kept. Called with the verifier locked so the
+    // pairs are queued in the order the verifier saw them; the file is
+    // written by `write_transcript`, outside the lock.
+    fn record<T>(
+        &self,
+        request: Entry,
+        result: &Result<T, Error>,
+        reply: impl FnOnce(&T) -> Entry,
+    ) {
+        let Some(transcript) = &self.transcript else {
+            return;
+        };
+        let reply = match result {
+            Ok(value) => reply(value),
+            Err(error) => Entry::Error {
+                message: error.to_string(),
+            },
+        };
+        // fails only once the writer thread is gone
+        let _ = transcript.send([request, reply]);
+    }
+}
+
+fn write_transcript(mut writer: TranscriptWriter, entries: Receiver<[Entry; 2]>) {
+    for pair in entries {
+        if let Err(_error) = writer.write(&pair) {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(error = %_error, "could not write the transcript");
+        }
+    }
+}
+
+async fn next_step(
+    inbound: &mut Streaming<AuthenticationStreamRequest>,
+) -> Result<authentication_stream_request::Step, Status> {
+    inbound
+        .message()
+        .await?
+        .and_then(|request| request.step)
+        .ok_or_else(|| Status::cancelled("stream ended before the exchange did"))
+}
+
+async fn send_step(
+    outbound: &mpsc::Sender<Result<AuthenticationStreamResponse, Status>>,
+    step: authentication_stream_response::Step,
+) -> Result<(), Status> {
+    let response = AuthenticationStreamResponse { step: Some(step) };
+    outbound
+        .send(Ok(response))
+        .await
+        .map_err(|_| Status::cancelled("prover went away"))
+}
+
+fn challenge_entry((auth_id, c): &(String, Vec<u8>)) -> Entry {
+    Entry::Challenge {
+        auth_id: auth_id.clone(),
+        c: c.clone(),
+    }
+}
+
+// group of the user's parameter set, the widths of the V2 encodings depend on it
+fn user_zkp(verifier: &ServerVerifier, user_name: &str) -> Result<ZKP, Error> {
+    let user_info = verifier
+        .user(user_name)
+        .ok_or_else(|| Error::UserNotFound(user_name.to_string()))?;
+    verifier
+        .parameter_set(&user_info.parameter_set)
+        .cloned()
+        .ok_or(Error::UnknownParameterSet(user_info.parameter_set))
+}
+
+fn decode_recovery_key(
+    zkp: &ZKP,
+    version: ProtocolVersion,
+    key: &RecoveryKey,
+) -> Result<PublicKey, Error> {
+    Ok(PublicKey {
+        y1: version.decode_element(zkp, &key.y1)?,
+        y2: version.decode_element(zkp, &key.y2)?,
+    })
+}
+
+fn challenge_zkp(verifier: &ServerVerifier, auth_id: &str) -> Result<ZKP, Error> {
+    let user_name = verifier
+        .challenge_user(auth_id)
+        .ok_or_else(|| Error::ChallengeNotFound(auth_id.to_string()))?;
+    user_zkp(verifier, user_name)
+}
+
+#[tokio::main]
+async fn main() {
+    #[cfg(feature = "tracing")]
+    tracing_subscriber::fmt::init();
+
+    // usage: server [config.toml], or ZKP_CONFIG=path, see `ServerConfig`
+    // for the file and the environment variables overriding it
+    let config_path = std::env::args()
+        .nth(1)
+        .or_else(|| std::env::var("ZKP_CONFIG").ok());
+    let config =
+        ServerConfig::load(config_path.as_deref().map(Path::new)).expect("invalid configuration");
+    let addr = config.listen.clone();
+
+    #[cfg(feature = "metrics")]
+    {
+        let metrics_addr: std::net::SocketAddr = config
+            .metrics_listen
+            .parse()
+            .expect("could not convert the metrics address");
+        metrics_exporter_prometheus::PrometheusBuilder::new()
+            .with_http_listener(metrics_addr)
+            .install()
+            .expect("could not install the prometheus exporter");
+        zkp_chaum_padersen::stats::describe();
+        println!("✅ Serving metrics in http://{}/metrics", metrics_addr);
+    }
+
+    println!("✅ Running the server in {}", addr);
+
+    // by default legacy users keep authenticating on the 1024-bit group, new
+    // users register on 2048 bits unless they ask for something else
+    let verifier = config.verifier().expect("could not set up the verifier");
+    if let Some(path) = &config.audit_log {
+        println!("✅ Writing the audit log to {}", path.display());
+    }
+    if let Some(path) = &config.storage.path {
+        println!("✅ Keeping users in {}", path.display());
+    }
+
+    // appends every message, as received and sent, for `Transcript::replay`
+    let transcript = config.transcript.as_ref().map(|path| {
+        let writer = TranscriptWriter::open(path).expect("could not open the transcript");
+        println!("✅ Recording the transcript to {}", path.display());
+        let (sender, receiver) = channel();
+        std::thread::spawn(move || write_transcript(writer, receiver));
+        sender
+    });
+
+    let verifier = Arc::new(Mutex::new(verifier));
+    let auth_impl = AuthImpl {
+        verifier: verifier.clone(),
+        transcript,
+    };
+    let admin_token = config.admin_token().expect("invalid admin token");
+    let admin = admin_token.map(|token| {
+        println!("✅ Serving the admin API");
+        let admin_impl = AdminImpl {
+            verifier: verifier.clone(),
+        };
+        AdminServer::with_interceptor(admin_impl, admin_auth(token))
+    });
+
+    // grpc.health.v1: "" is liveness, up as long as the process serves at
+    // all; "zkp_auth.Auth" is readiness, down while the user store fails its
+    // ping and from the moment shutdown starts
+    let (health_reporter, health_service) = tonic_health::server::health_reporter();
+    let draining = Arc::new(AtomicBool::new(false));
+    tokio::spawn(report_readiness(
+        health_reporter.clone(),
+        verifier,
+        draining.clone(),
+    ));
+
+    // On SIGTERM the listener closes and requests already in flight get
+    // `shutdown_grace_secs` to finish.
+    let signalled = Arc::new(Notify::new());
+    let shutdown = {
+        let signalled = signalled.clone();
+        let mut health_reporter = health_reporter.clone();
+        async move {
+            shutdown_signal().await;
+            println!("⏳ Shutting down, draining in-flight requests");
+            draining.store(true, Ordering::SeqCst);
+            health_reporter
+                .set_not_serving::<AuthServer<AuthImpl>>()
+                .await;
+            signalled.notify_one();
+        }
+    };
+    let grace = Duration::from_secs(config.shutdown_grace_secs);
+    let mut builder = Server::builder();
+    if let Some(tls) = &config.tls {
+        let server_tls = tls
+            .server_tls()
+            .expect("could not load the TLS certificate");
+        builder = builder
+            .tls_config(server_tls)
+            .expect("invalid TLS configuration");
+        match tls.client_ca {
+            Some(_) => println!("✅ TLS on, client certificates required"),
+            None => println!("✅ TLS on"),
+        }
+    }
+    let server = builder
+        .add_service(health_service)
+        .add_service(AuthServer::new(auth_impl))
+        .add_optional_service(admin)
+        .serve_with_shutdown(addr.parse().expect("could not convert address"), shutdown);
+
+    tokio::select! {
+        served = server => served.unwrap(),
+        _ = async {
+            signalled.notified().await;
+            tokio::time::sleep(grace).await;
+        } => println!("⚠️ Grace period over, dropping the remaining requests"),
+    }
+}
+
+async fn report_readiness(
+    mut health_reporter: HealthReporter,
+    verifier: Arc<Mutex<ServerVerifier>>,
+    draining: Arc<AtomicBool>,
+) {
+    let mut interval = tokio::time::interval(READINESS_INTERVAL);
+    let mut ready = None;
+    loop {
+        interval.tick().await;
+        if draining.load(Ordering::SeqCst) {
+            return;
+        }
+        let ping = verifier.lock().unwrap().store().ping();
+        if ready == Some(ping.is_ok()) {
+            continue;
+        }
+        match &ping {
+            Ok(()) => health_reporter.set_serving::<AuthServer<AuthImpl>>().await,
+            Err(error) => {
+                println!("⚠️ User store unavailable, not ready: {}", error);
+                health_reporter
+                    .set_not_serving::<AuthServer<AuthImpl>>()
+                    .await
+            }
+        }
+        ready = Some(ping.is_ok());
+    }
+}
 
-fn main() {
-    println!("Hi, I'm the server");
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let mut terminate =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+                .expect("could not listen for SIGTERM");
+        tokio::select! {
+            _ = terminate.recv() => {}
+            _ = tokio::signal::ctrl_c() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c()
+        .await
+        .expect("could not listen for ctrl-c");
 }
@@ -0,0 +1,180 @@
+use std::time::Duration;
+
+use crate::audit::AuditEvent;
+use crate::store::UserStore;
+use crate::verifier::Verifier;
+use crate::Error;
+
+// The sessions a verifier has granted, for the operator of a compromised
+// account: list them, end one, or end all of a user's. A revoked session is
+// gone from the verifier, so `ZkpAuthLayer` answers its next request with
+// 401 and nothing has to be told about it.
+//
+// `Verifier::set_max_sessions_per_user` bounds how many a user keeps open,
+// a login over the limit ends their oldest session.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionInfo {
+    pub session_id: String,
+    pub user_name: String,
+    pub age: Duration,
+}
+
+impl<S: UserStore> Verifier<S> {
+    // oldest first, expired sessions left out
+    pub fn list_sessions(&self) -> Vec<SessionInfo> {
+        let mut sessions: Vec<SessionInfo> = self
+            .sessions
+            .iter()
+            .filter(|(session_id, _)| self.session_user(session_id).is_some())
+            .map(|(session_id, (user_name, opened))| SessionInfo {
+                session_id: session_id.clone(),
+                user_name: user_name.clone(),
+                age: opened.elapsed(),
+            })
+            .collect();
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.age));
+        sessions
+    }
+
+    pub fn user_sessions(&self, user_name: &str) -> Vec<SessionInfo> {
+        self.list_sessions()
+            .into_iter()
+            .filter(|session| session.user_name == user_name)
+            .collect()
+    }
+
+    pub fn revoke_session(&mut self, session_id: &str) -> Result<(), Error> {
+        let (user_name, _) = self
+            .sessions
+            .remove(session_id)
+            .ok_or(Error::SessionNotFound)?;
+        trace_info!(user = %user_name, "session revoked");
+        self.audit(AuditEvent::SessionsRevoked {
+            user: user_name,
+            sessions: 1,
+        });
+        Ok(())
+    }
+
+    // output => how many sessions ended
+    pub fn revoke_all_for_user(&mut self, user_name: &str) -> usize {
+        let before = self.sessions.len();
+        self.sessions.retain(|_, (owner, _)| owner != user_name);
+        let revoked = before - self.sessions.len();
+        if revoked > 0 {
+            trace_info!(user = %user_name, revoked, "sessions revoked");
+            self.audit(AuditEvent::SessionsRevoked {
+                user: user_name.to_string(),
+                sessions: revoked,
+            });
+        }
+        revoked
+    }
+
+    // Ends the user's oldest sessions until at most `keep` are left.
+    pub(crate) fn keep_newest_sessions(&mut self, user_name: &str, keep: usize) {
+        let mut owned: Vec<(String, Duration)> = self
+            .sessions
+            .iter()
+            .filter(|(_, (owner, _))| owner == user_name)
+            .map(|(session_id, (_, opened))| (session_id.clone(), opened.elapsed()))
+            .collect();
+        if owned.len() <= keep {
+            return;
+        }
+        owned.sort_by_key(|(_, age)| std::cmp::Reverse(*age));
+        for (session_id, _) in owned.drain(..owned.len() - keep) {
+            self.sessions.remove(&session_id);
+            trace_info!(user = %user_name, "oldest session ended, over the limit");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::audit::{AuditRecord, AuditSink};
+    use crate::params::ParameterSet;
+    use crate::proof::Secret;
+    use crate::prover::Prover;
+
+    fn setup() -> (Verifier, Prover, Prover) {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let zkp = set.zkp();
+        let mut verifier = Verifier::with_parameter_sets(set, &[]);
+        let alice = Prover::new(zkp.clone(), set.id(), "alice", Secret::random(&zkp));
+        let bob = Prover::new(zkp.clone(), set.id(), "bob", Secret::random(&zkp));
+        alice.register(&mut verifier).unwrap();
+        bob.register(&mut verifier).unwrap();
+        (verifier, alice, bob)
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedSink(Arc<Mutex<Vec<AuditEvent>>>);
+
+    impl AuditSink for SharedSink {
+        fn record(&mut self, record: &AuditRecord) -> Result<(), Error> {
+            self.0.lock().unwrap().push(record.event.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_list_and_revoke() {
+        let (mut verifier, mut alice, mut bob) = setup();
+        let sink = SharedSink::default();
+        verifier.add_audit_sink(sink.clone());
+        let first = alice.login(&mut verifier).unwrap();
+        let second = alice.login(&mut verifier).unwrap();
+        let bobs = bob.login(&mut verifier).unwrap();
+
+        let sessions = verifier.user_sessions("alice");
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].session_id, first);
+        assert_eq!(verifier.list_sessions().len(), 3);
+
+        verifier.revoke_session(&first).unwrap();
+        assert_eq!(verifier.session_user(&first), None);
+        assert_eq!(verifier.session_user(&second), Some("alice"));
+        assert_eq!(verifier.revoke_session(&first), Err(Error::SessionNotFound));
+
+        alice.login(&mut verifier).unwrap();
+        assert_eq!(verifier.revoke_all_for_user("alice"), 2);
+        assert!(verifier.user_sessions("alice").is_empty());
+        assert_eq!(verifier.session_user(&bobs), Some("bob"));
+        let revocations = |sink: &SharedSink| {
+            let events = sink.0.lock().unwrap();
+            events
+                .iter()
+                .filter(|event| matches!(event, AuditEvent::SessionsRevoked { .. }))
+                .count()
+        };
+        let audited = revocations(&sink);
+        assert_eq!(verifier.revoke_all_for_user("alice"), 0);
+        // nothing was revoked, nothing to audit
+        assert_eq!(revocations(&sink), audited);
+    }
+
+    #[test]
+    fn test_limit_ends_the_oldest() {
+        let (mut verifier, mut alice, mut bob) = setup();
+        assert!(verifier.set_max_sessions_per_user(Some(0)).is_err());
+        verifier.set_max_sessions_per_user(Some(2)).unwrap();
+        let bobs = bob.login(&mut verifier).unwrap();
+        let first = alice.login(&mut verifier).unwrap();
+        let second = alice.login(&mut verifier).unwrap();
+        let third = alice.login(&mut verifier).unwrap();
+
+        assert_eq!(verifier.session_user(&first), None);
+        let left: Vec<String> = verifier
+            .user_sessions("alice")
+            .into_iter()
+            .map(|session| session.session_id)
+            .collect();
+        assert_eq!(left, vec![second, third]);
+        assert_eq!(verifier.session_user(&bobs), Some("bob"));
+    }
+}
@@ -0,0 +1,357 @@
+use num_bigint::BigUint;
+
+use crate::holder::SecretHolder;
+use crate::params::ParameterSet;
+use crate::proof::Secret;
+use crate::prover::Prover;
+use crate::store::{InMemoryUserStore, UserStore};
+use crate::transport::Transport;
+use crate::verifier::Verifier;
+use crate::Error;
+
+// Everything that crosses the in-memory wire, in both directions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Register {
+        user_name: String,
+        parameter_set: String,
+        y1: BigUint,
+        y2: BigUint,
+    },
+    Commitment {
+        user_name: String,
+        r1: BigUint,
+        r2: BigUint,
+    },
+    Challenge {
+        auth_id: String,
+        c: BigUint,
+    },
+    Response {
+        auth_id: String,
+        s: BigUint,
+    },
+    Session {
+        session_id: String,
+    },
+    RotationCommitment {
+        user_name: String,
+        r1: BigUint,
+        r2: BigUint,
+        new_y1: BigUint,
+        new_y2: BigUint,
+    },
+    Rotated,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    Deliver,
+    Drop,
+}
+
+type Interceptor = Box<dyn FnMut(&mut Message) -> Delivery>;
+
+// Transport to a verifier in the same process. Every message goes through
+// the interceptors, which can rewrite it or drop it (the caller then gets
+// `Error::Transport`), and is appended to the log as delivered.
+pub struct InMemoryTransport<S = InMemoryUserStore> {
+    verifier: Verifier<S>,
+    interceptors: Vec<Interceptor>,
+    log: Vec<Message>,
+}
+
+impl<S: UserStore> InMemoryTransport<S> {
+    pub fn new(verifier: Verifier<S>) -> Self {
+        InMemoryTransport {
+            verifier,
+            interceptors: Vec::new(),
+            log: Vec::new(),
+        }
+    }
+
+    pub fn verifier(&self) -> &Verifier<S> {
+        &self.verifier
+    }
+
+    pub fn verifier_mut(&mut self) -> &mut Verifier<S> {
+        &mut self.verifier
+    }
+
+    pub fn intercept(&mut self, interceptor: impl FnMut(&mut Message) -> Delivery + 'static) {
+        self.interceptors.push(Box::new(interceptor));
+    }
+
+    pub fn clear_interceptors(&mut self) {
+        self.interceptors.clear();
+    }
+
+    pub fn log(&self) -> &[Message] {
+        &self.log
+    }
+
+    fn send(&mut self, mut message: Message) -> Result<Message, Error> {
+        for interceptor in self.interceptors.iter_mut() {
+            if interceptor(&mut message) == Delivery::Drop {
+                return Err(Error::Transport("message dropped".to_string()));
+            }
+        }
+        self.log.push(message.clone());
+        Ok(message)
+    }
+}
+
+impl<S: UserStore> Transport for InMemoryTransport<S> {
+    fn register(
+        &mut self,
+        user_name: &str,
+        parameter_set: &str,
+        y1: &BigUint,
+        y2: &BigUint,
+    ) -> Result<(), Error> {
+        let message = self.send(Message::Register {
+            user_name: user_name.to_string(),
+            parameter_set: parameter_set.to_string(),
+            y1: y1.clone(),
+            y2: y2.clone(),
+        })?;
+        match message {
+            Message::Register {
+                user_name,
+                parameter_set,
+                y1,
+                y2,
+            } => self
+                .verifier
+                .register_with_parameter_set(&user_name, &parameter_set, y1, y2),
+            _ => Err(Error::Transport("unexpected message".to_string())),
+        }
+    }
+
+    fn create_challenge(
+        &mut self,
+        user_name: &str,
+        r1: &BigUint,
+        r2: &BigUint,
+    ) -> Result<(String, BigUint), Error> {
+        let message = self.send(Message::Commitment {
+            user_name: user_name.to_string(),
+            r1: r1.clone(),
+            r2: r2.clone(),
+        })?;
+        let (auth_id, c) = match message {
+            Message::Commitment { user_name, r1, r2 } => {
+                self.verifier.create_challenge(&user_name, r1, r2)?
+            }
+            _ => return Err(Error::Transport("unexpected message".to_string())),
+        };
+
+        match self.send(Message::Challenge { auth_id, c })? {
+            Message::Challenge { auth_id, c } => Ok((auth_id, c)),
+            _ => Err(Error::Transport("unexpected message".to_string())),
+        }
+    }
+
+    fn verify(&mut self, auth_id: &str, s: &BigUint) -> Result<String, Error> {
+        let message = self.send(Message::Response {
+            auth_id: auth_id.to_string(),
+            s: s.clone(),
+        })?;
+        let session_id = match message {
+            Message::Response { auth_id, s } => self.verifier.verify(&auth_id, &s)?,
+            _ => return Err(Error::Transport("unexpected message".to_string())),
+        };
+
+        match self.send(Message::Session { session_id })? {
+            Message::Session { session_id } => Ok(session_id),
+            _ => Err(Error::Transport("unexpected message".to_string())),
+        }
+    }
+
+    fn create_rotation_challenge(
+        &mut self,
+        user_name: &str,
+        r1: &BigUint,
+        r2: &BigUint,
+        new_y1: &BigUint,
+        new_y2: &BigUint,
+    ) -> Result<(String, BigUint), Error> {
+        let message = self.send(Message::RotationCommitment {
+            user_name: user_name.to_string(),
+            r1: r1.clone(),
+            r2: r2.clone(),
+            new_y1: new_y1.clone(),
+            new_y2: new_y2.clone(),
+        })?;
+        let (auth_id, c) = match message {
+            Message::RotationCommitment {
+                user_name,
+                r1,
+                r2,
+                new_y1,
+                new_y2,
+            } => self
+                .verifier
+                .create_rotation_challenge(&user_name, r1, r2, new_y1, new_y2)?,
+            _ => return Err(Error::Transport("unexpected message".to_string())),
+        };
+
+        match self.send(Message::Challenge { auth_id, c })? {
+            Message::Challenge { auth_id, c } => Ok((auth_id, c)),
+            _ => Err(Error::Transport("unexpected message".to_string())),
+        }
+    }
+
+    fn verify_rotation(&mut self, auth_id: &str, s: &BigUint) -> Result<(), Error> {
+        let message = self.send(Message::Response {
+            auth_id: auth_id.to_string(),
+            s: s.clone(),
+        })?;
+        match message {
+            Message::Response { auth_id, s } => self.verifier.verify_rotation(&auth_id, &s)?,
+            _ => return Err(Error::Transport("unexpected message".to_string())),
+        };
+
+        match self.send(Message::Rotated)? {
+            Message::Rotated => Ok(()),
+            _ => Err(Error::Transport("unexpected message".to_string())),
+        }
+    }
+}
+
+// A verifier with an in-memory store and transport, and provers created
+// against it:
+//
+//     let mut sim = Simulation::new(ParameterSet::Rfc3526_2048);
+//     let mut alice = sim.register("alice")?;
+//     let session_id = sim.login(&mut alice)?;
+pub struct Simulation {
+    parameter_set: ParameterSet,
+    transport: InMemoryTransport,
+}
+
+impl Simulation {
+    pub fn new(parameter_set: ParameterSet) -> Self {
+        Simulation {
+            parameter_set,
+            transport: InMemoryTransport::new(Verifier::with_parameter_sets(parameter_set, &[])),
+        }
+    }
+
+    pub fn transport(&mut self) -> &mut InMemoryTransport {
+        &mut self.transport
+    }
+
+    pub fn verifier(&self) -> &Verifier {
+        self.transport.verifier()
+    }
+
+    // Prover with a fresh random secret, already registered.
+    pub fn register(&mut self, user_name: &str) -> Result<Prover, Error> {
+        let zkp = self.parameter_set.zkp();
+        let secret = Secret::random(&zkp);
+        let prover = Prover::new(zkp, self.parameter_set.id(), user_name, secret);
+        prover.register(&mut self.transport)?;
+        Ok(prover)
+    }
+
+    pub fn login<H: SecretHolder>(&mut self, prover: &mut Prover<H>) -> Result<String, Error> {
+        prover.login(&mut self.transport)
+    }
+
+    pub fn intercept(&mut self, interceptor: impl FnMut(&mut Message) -> Delivery + 'static) {
+        self.transport.intercept(interceptor);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::holder::LocalHolder;
+
+    #[test]
+    fn test_full_flow() {
+        let mut sim = Simulation::new(ParameterSet::Rfc5114_1024_160);
+        let mut alice = sim.register("alice").unwrap();
+
+        let session_id = sim.login(&mut alice).unwrap();
+        assert_eq!(sim.verifier().session_user(&session_id), Some("alice"));
+
+        // register, commitment, challenge, response, session
+        assert_eq!(sim.transport().log().len(), 5);
+    }
+
+    #[test]
+    fn test_tampered_messages_are_rejected() {
+        let mut sim = Simulation::new(ParameterSet::Rfc5114_1024_160);
+        let mut alice = sim.register("alice").unwrap();
+
+        sim.intercept(|message| {
+            if let Message::Response { s, .. } = message {
+                *s += 1u32;
+            }
+            Delivery::Deliver
+        });
+        assert_eq!(sim.login(&mut alice), Err(Error::InvalidProof));
+
+        sim.transport().clear_interceptors();
+        sim.intercept(|message| {
+            if let Message::Challenge { c, .. } = message {
+                *c += 1u32;
+            }
+            Delivery::Deliver
+        });
+        assert_eq!(sim.login(&mut alice), Err(Error::InvalidProof));
+
+        sim.transport().clear_interceptors();
+        assert!(sim.login(&mut alice).is_ok());
+    }
+
+    #[test]
+    fn test_dropped_and_unexpected_messages() {
+        let mut sim = Simulation::new(ParameterSet::Rfc5114_1024_160);
+        let mut alice = sim.register("alice").unwrap();
+
+        sim.intercept(|message| match message {
+            Message::Session { .. } => Delivery::Drop,
+            _ => Delivery::Deliver,
+        });
+        assert!(matches!(sim.login(&mut alice), Err(Error::Transport(_))));
+
+        // answering without a commitment
+        assert_eq!(
+            alice.respond(&BigUint::from(1u32)),
+            Err(Error::NoPendingCommitment)
+        );
+
+        let mut mallory = Prover::new(
+            ParameterSet::Rfc5114_1024_160.zkp(),
+            "rfc5114-1024-160",
+            "mallory",
+            Secret::new(BigUint::from(1u32)),
+        );
+        assert_eq!(
+            sim.login(&mut mallory),
+            Err(Error::UserNotFound("mallory".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_rotate_key() {
+        let mut sim = Simulation::new(ParameterSet::Rfc5114_1024_160);
+        let mut alice = sim.register("alice").unwrap();
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+
+        let new_holder = LocalHolder::new(Secret::random(&zkp));
+        let new_key = new_holder.public_key(&zkp).unwrap();
+        let old_holder = alice.rotate(new_holder, sim.transport()).unwrap();
+
+        let user_info = sim.verifier().user("alice").unwrap();
+        assert_eq!((user_info.y1, user_info.y2), (new_key.y1, new_key.y2));
+        assert!(sim.login(&mut alice).is_ok());
+
+        // the old secret is gone
+        let mut stale = Prover::with_holder(zkp, "rfc5114-1024-160", "alice", old_holder);
+        assert_eq!(sim.login(&mut stale), Err(Error::InvalidProof));
+    }
+}
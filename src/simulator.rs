@@ -0,0 +1,104 @@
+use num_bigint::BigUint;
+
+use crate::proof::PublicKey;
+use crate::ZKP;
+
+// One round of the interactive protocol: commitment, challenge, answer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transcript {
+    pub r1: BigUint,
+    pub r2: BigUint,
+    pub c: BigUint,
+    pub s: BigUint,
+}
+
+impl ZKP {
+    // Honest-verifier zero knowledge: a transcript for any public key,
+    // without x. s and c are picked first and the commitment solved for,
+    //     r1 = alpha^s * y1^c, r2 = beta^s * y2^c
+    // For a c drawn uniformly (an honest verifier) the result is distributed
+    // exactly like a real round, where s is uniform because k is. It doesn't
+    // verify as a Fiat-Shamir proof, c isn't the hash of the commitment.
+    pub fn simulate(&self, public_key: &PublicKey) -> Transcript {
+        let c = ZKP::generate_random_below(&self.q);
+        self.simulate_with_challenge(public_key, &c)
+    }
+
+    // For a challenge fixed in advance, the simulated branches of an
+    // OR-composition.
+    pub fn simulate_with_challenge(&self, public_key: &PublicKey, c: &BigUint) -> Transcript {
+        let s = ZKP::generate_random_below(&self.q);
+        self.simulate_with(public_key, c, &s)
+    }
+
+    pub fn simulate_with(&self, public_key: &PublicKey, c: &BigUint, s: &BigUint) -> Transcript {
+        Transcript {
+            r1: self.pow2(&self.alpha, s, &public_key.y1, c),
+            r2: self.pow2(&self.beta, s, &public_key.y2, c),
+            c: c.clone(),
+            s: s.clone(),
+        }
+    }
+
+    pub fn verify_transcript(&self, public_key: &PublicKey, transcript: &Transcript) -> bool {
+        transcript.c < self.q
+            && self.verify(
+                &transcript.r1,
+                &transcript.r2,
+                &public_key.y1,
+                &public_key.y2,
+                &transcript.s,
+                &transcript.c,
+            )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+    use crate::proof::{Proof, Secret};
+
+    #[test]
+    fn test_simulated_transcripts_verify() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let public_key = zkp.public_key(&Secret::random(&zkp));
+
+        let transcript = zkp.simulate(&public_key);
+        assert!(zkp.verify_transcript(&public_key, &transcript));
+        assert!(zkp.is_subgroup_element(&transcript.r1));
+        assert!(zkp.is_subgroup_element(&transcript.r2));
+        assert_ne!(transcript, zkp.simulate(&public_key));
+
+        let c = BigUint::from(12345u32);
+        assert_eq!(zkp.simulate_with_challenge(&public_key, &c).c, c);
+
+        // the commitment isn't the one hashed into c
+        let proof = Proof {
+            r1: transcript.r1,
+            r2: transcript.r2,
+            s: transcript.s,
+        };
+        assert!(!zkp.verify_proof_without_expiry(&public_key, proof));
+    }
+
+    #[test]
+    fn test_simulation_matches_real_rounds() {
+        // (c, s) determines the commitment, so a real round and a simulated
+        // one with the same (c, s) are the same transcript: the simulator
+        // draws from the real distribution
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let secret = Secret::random(&zkp);
+        let public_key = zkp.public_key(&secret);
+
+        let k = ZKP::generate_random_below(&zkp.q);
+        let c = ZKP::generate_random_below(&zkp.q);
+        let real = Transcript {
+            r1: zkp.pow(&zkp.alpha, &k),
+            r2: zkp.pow(&zkp.beta, &k),
+            s: zkp.solve(&k, &c, secret.x()),
+            c,
+        };
+        assert_eq!(zkp.simulate_with(&public_key, &real.c, &real.s), real);
+    }
+}
@@ -0,0 +1,108 @@
+use num_bigint::BigUint;
+
+use crate::holder::SecretHolder;
+use crate::modmath::mod_sub;
+use crate::proof::{PublicKey, Secret};
+use crate::{Error, ZKP};
+
+// 2-of-2 proving: x = x_device + x_server mod q, each share kept by its own
+// holder (a phone and a backend, say). Every round each side draws its own
+// nonce, and since everything is linear in the exponent the pieces combine
+// into one ordinary proof:
+//
+//     (y1, y2) = (y1_device * y1_server, y2_device * y2_server)
+//     (r1, r2) = (r1_device * r1_server, r2_device * r2_server)
+//     s        = s_device + s_server mod q
+//
+// The verifier can't tell it apart from a single prover. Neither side ever
+// sees the other's share or nonce; c must only be requested once both
+// commitments are in, which `Prover` does.
+#[derive(Debug)]
+pub struct JointHolder<A, B> {
+    pub device: A,
+    pub server: B,
+}
+
+impl<A: SecretHolder, B: SecretHolder> JointHolder<A, B> {
+    pub fn new(device: A, server: B) -> Self {
+        JointHolder { device, server }
+    }
+}
+
+impl<A: SecretHolder, B: SecretHolder> SecretHolder for JointHolder<A, B> {
+    fn public_key(&self, zkp: &ZKP) -> Result<PublicKey, Error> {
+        let device = self.device.public_key(zkp)?;
+        let server = self.server.public_key(zkp)?;
+        Ok(PublicKey {
+            y1: (device.y1 * server.y1) % &zkp.p,
+            y2: (device.y2 * server.y2) % &zkp.p,
+        })
+    }
+
+    fn commit(&mut self, zkp: &ZKP) -> Result<(BigUint, BigUint), Error> {
+        let (device_r1, device_r2) = self.device.commit(zkp)?;
+        let (server_r1, server_r2) = self.server.commit(zkp)?;
+        Ok((
+            (device_r1 * server_r1) % &zkp.p,
+            (device_r2 * server_r2) % &zkp.p,
+        ))
+    }
+
+    fn compute_response(&mut self, zkp: &ZKP, c: &BigUint) -> Result<BigUint, Error> {
+        let device_s = self.device.compute_response(zkp, c)?;
+        let server_s = self.server.compute_response(zkp, c)?;
+        Ok((device_s + server_s) % &zkp.q)
+    }
+}
+
+// Splits an existing secret into two random shares. Shares can also be
+// drawn independently, the joint public key is then the product of the
+// partial ones.
+pub fn split_secret(zkp: &ZKP, secret: &Secret) -> (Secret, Secret) {
+    let device = ZKP::generate_random_nonzero_below(&zkp.q);
+    let server = mod_sub(secret.x(), &device, &zkp.q);
+    (Secret::new(device), Secret::new(server))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::holder::LocalHolder;
+    use crate::params::ParameterSet;
+    use crate::prover::Prover;
+    use crate::simulation::Simulation;
+
+    #[test]
+    fn test_shares_combine_to_the_secret() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let secret = Secret::random(&zkp);
+        let (device, server) = split_secret(&zkp, &secret);
+        assert_eq!((device.x() + server.x()) % &zkp.q, *secret.x());
+
+        let joint = JointHolder::new(LocalHolder::new(device), LocalHolder::new(server));
+        assert_eq!(joint.public_key(&zkp).unwrap(), zkp.public_key(&secret));
+    }
+
+    #[test]
+    fn test_joint_login_needs_both_shares() {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let zkp = set.zkp();
+        let secret = Secret::random(&zkp);
+        let (device, server) = split_secret(&zkp, &secret);
+
+        let mut sim = Simulation::new(set);
+        let joint = JointHolder::new(
+            LocalHolder::new(device.clone()),
+            LocalHolder::new(server.clone()),
+        );
+        let mut prover = Prover::with_holder(zkp.clone(), set.id(), "alice", joint);
+        prover.register(sim.transport()).unwrap();
+        assert!(sim.login(&mut prover).is_ok());
+
+        // one share alone is just some other secret
+        for share in [device, server] {
+            let mut alone = Prover::new(zkp.clone(), set.id(), "alice", share);
+            assert_eq!(sim.login(&mut alone), Err(Error::InvalidProof));
+        }
+    }
+}
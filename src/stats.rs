@@ -0,0 +1,54 @@
+// Metrics emitted by the verifier through the `metrics` facade. Without the
+// `metrics` feature every function here is a no-op, so the protocol code can
+// call them unconditionally.
+#[cfg(feature = "metrics")]
+use metrics::{counter, describe_counter, describe_histogram, histogram, Unit};
+use std::time::Duration;
+
+pub const REGISTRATIONS_TOTAL: &str = "zkp_registrations_total";
+pub const CHALLENGES_ISSUED_TOTAL: &str = "zkp_challenges_issued_total";
+pub const VERIFICATIONS_TOTAL: &str = "zkp_verifications_total";
+pub const VERIFY_DURATION_SECONDS: &str = "zkp_verify_duration_seconds";
+
+// Registers the metric descriptions in the installed recorder. Call it once,
+// after installing the exporter.
+pub fn describe() {
+    #[cfg(feature = "metrics")]
+    {
+        describe_counter!(REGISTRATIONS_TOTAL, "Number of registered public keys");
+        describe_counter!(
+            CHALLENGES_ISSUED_TOTAL,
+            "Number of challenges sent to provers"
+        );
+        describe_counter!(
+            VERIFICATIONS_TOTAL,
+            "Number of answered challenges, labelled by outcome (passed/failed)"
+        );
+        describe_histogram!(
+            VERIFY_DURATION_SECONDS,
+            Unit::Seconds,
+            "Time spent checking the prover's solution"
+        );
+    }
+}
+
+pub(crate) fn record_registration() {
+    #[cfg(feature = "metrics")]
+    counter!(REGISTRATIONS_TOTAL).increment(1);
+}
+
+pub(crate) fn record_challenge() {
+    #[cfg(feature = "metrics")]
+    counter!(CHALLENGES_ISSUED_TOTAL).increment(1);
+}
+
+pub(crate) fn record_verification(passed: bool, elapsed: Duration) {
+    #[cfg(feature = "metrics")]
+    {
+        let outcome = if passed { "passed" } else { "failed" };
+        counter!(VERIFICATIONS_TOTAL, "outcome" => outcome).increment(1);
+        histogram!(VERIFY_DURATION_SECONDS).record(elapsed.as_secs_f64());
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = (passed, elapsed);
+}
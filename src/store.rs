@@ -0,0 +1,499 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+
+use crate::proof::PublicKey;
+use crate::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserInfo {
+    pub user_name: String,
+    pub parameter_set: String,
+    pub y1: BigUint,
+    pub y2: BigUint,
+}
+
+// What an operator set on a user, see `crate::admin`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserStatus {
+    // no challenges are issued or answered
+    pub disabled: bool,
+    // only a key rotation is accepted, until it succeeds
+    pub rotation_required: bool,
+}
+
+// Where the verifier keeps the registered users. Records are returned by
+// value so implementations can sit on top of a database, and changes return
+// an error if they couldn't be made durable, `Error::Io` for a failed
+// write, with the store left as it was.
+pub trait UserStore {
+    fn get(&self, user_name: &str) -> Option<UserInfo>;
+
+    // inserts or replaces the record for `user_info.user_name`
+    fn insert(&mut self, user_info: UserInfo) -> Result<(), Error>;
+
+    fn remove(&mut self, user_name: &str) -> Result<Option<UserInfo>, Error>;
+
+    // Replaces the record only if it still equals `current`, returns whether
+    // it did. Stores shared between processes should override this with an
+    // atomic compare-and-swap.
+    fn compare_and_swap(&mut self, current: &UserInfo, new: UserInfo) -> Result<bool, Error> {
+        if self.get(&current.user_name).as_ref() != Some(current) {
+            return Ok(false);
+        }
+        self.insert(new)?;
+        Ok(true)
+    }
+
+    // Replaces `old` by `new`, the user's record under another parameter
+    // set, if `old` is still current, and keeps `old` marked superseded.
+    // Stores that keep history should override this and `superseded`.
+    fn supersede(&mut self, old: &UserInfo, new: UserInfo) -> Result<bool, Error> {
+        self.compare_and_swap(old, new)
+    }
+
+    // records replaced by `supersede`, oldest first
+    fn superseded(&self, _user_name: &str) -> Vec<UserInfo> {
+        Vec::new()
+    }
+
+    fn user_names(&self) -> Vec<String>;
+
+    fn status(&self, _user_name: &str) -> UserStatus {
+        UserStatus::default()
+    }
+
+    // Returns false if the user isn't registered or the store can't keep
+    // statuses, which stores that don't override this can't. The status goes
+    // with the record on `remove`.
+    fn set_status(&mut self, _user_name: &str, _status: UserStatus) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    // keys a user can replace their key with, see `crate::recovery`
+    fn recovery_keys(&self, _user_name: &str) -> Vec<PublicKey> {
+        Vec::new()
+    }
+
+    // Replaces them all, false as for `set_status`. They go with the record
+    // on `remove`.
+    fn set_recovery_keys(
+        &mut self,
+        _user_name: &str,
+        _keys: Vec<PublicKey>,
+    ) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    // Whether the backend can currently serve, for readiness probes.
+    fn ping(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct InMemoryUserStore {
+    users: HashMap<String, UserInfo>,
+    superseded: HashMap<String, Vec<UserInfo>>,
+    statuses: HashMap<String, UserStatus>,
+    recovery_keys: HashMap<String, Vec<PublicKey>>,
+}
+
+impl InMemoryUserStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl UserStore for InMemoryUserStore {
+    fn get(&self, user_name: &str) -> Option<UserInfo> {
+        self.users.get(user_name).cloned()
+    }
+
+    fn insert(&mut self, user_info: UserInfo) -> Result<(), Error> {
+        self.users.insert(user_info.user_name.clone(), user_info);
+        Ok(())
+    }
+
+    fn remove(&mut self, user_name: &str) -> Result<Option<UserInfo>, Error> {
+        self.statuses.remove(user_name);
+        self.recovery_keys.remove(user_name);
+        Ok(self.users.remove(user_name))
+    }
+
+    fn supersede(&mut self, old: &UserInfo, new: UserInfo) -> Result<bool, Error> {
+        if !self.compare_and_swap(old, new)? {
+            return Ok(false);
+        }
+        self.superseded
+            .entry(old.user_name.clone())
+            .or_default()
+            .push(old.clone());
+        Ok(true)
+    }
+
+    fn superseded(&self, user_name: &str) -> Vec<UserInfo> {
+        self.superseded.get(user_name).cloned().unwrap_or_default()
+    }
+
+    fn user_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.users.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    fn status(&self, user_name: &str) -> UserStatus {
+        self.statuses.get(user_name).copied().unwrap_or_default()
+    }
+
+    fn set_status(&mut self, user_name: &str, status: UserStatus) -> Result<bool, Error> {
+        if !self.users.contains_key(user_name) {
+            return Ok(false);
+        }
+        match status == UserStatus::default() {
+            true => self.statuses.remove(user_name),
+            false => self.statuses.insert(user_name.to_string(), status),
+        };
+        Ok(true)
+    }
+
+    fn recovery_keys(&self, user_name: &str) -> Vec<PublicKey> {
+        self.recovery_keys
+            .get(user_name)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn set_recovery_keys(&mut self, user_name: &str, keys: Vec<PublicKey>) -> Result<bool, Error> {
+        if !self.users.contains_key(user_name) {
+            return Ok(false);
+        }
+        match keys.is_empty() {
+            true => self.recovery_keys.remove(user_name),
+            false => self.recovery_keys.insert(user_name.to_string(), keys),
+        };
+        Ok(true)
+    }
+}
+
+impl<T: UserStore + ?Sized> UserStore for Box<T> {
+    fn get(&self, user_name: &str) -> Option<UserInfo> {
+        (**self).get(user_name)
+    }
+
+    fn insert(&mut self, user_info: UserInfo) -> Result<(), Error> {
+        (**self).insert(user_info)
+    }
+
+    fn remove(&mut self, user_name: &str) -> Result<Option<UserInfo>, Error> {
+        (**self).remove(user_name)
+    }
+
+    fn compare_and_swap(&mut self, current: &UserInfo, new: UserInfo) -> Result<bool, Error> {
+        (**self).compare_and_swap(current, new)
+    }
+
+    fn supersede(&mut self, old: &UserInfo, new: UserInfo) -> Result<bool, Error> {
+        (**self).supersede(old, new)
+    }
+
+    fn superseded(&self, user_name: &str) -> Vec<UserInfo> {
+        (**self).superseded(user_name)
+    }
+
+    fn user_names(&self) -> Vec<String> {
+        (**self).user_names()
+    }
+
+    fn status(&self, user_name: &str) -> UserStatus {
+        (**self).status(user_name)
+    }
+
+    fn set_status(&mut self, user_name: &str, status: UserStatus) -> Result<bool, Error> {
+        (**self).set_status(user_name, status)
+    }
+
+    fn recovery_keys(&self, user_name: &str) -> Vec<PublicKey> {
+        (**self).recovery_keys(user_name)
+    }
+
+    fn set_recovery_keys(&mut self, user_name: &str, keys: Vec<PublicKey>) -> Result<bool, Error> {
+        (**self).set_recovery_keys(user_name, keys)
+    }
+
+    fn ping(&self) -> Result<(), Error> {
+        (**self).ping()
+    }
+}
+
+// Users kept in one JSON file, rewritten (to a temporary file, then renamed
+// over the old one) on every change. For a single server process with a
+// moderate number of users, anything larger wants a database.
+#[derive(Debug)]
+pub struct JsonFileUserStore {
+    path: PathBuf,
+    memory: InMemoryUserStore,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoredUsers {
+    users: Vec<StoredUser>,
+    #[serde(default)]
+    superseded: Vec<StoredUser>,
+    // user name => status, only users with something set
+    #[serde(default)]
+    statuses: BTreeMap<String, UserStatus>,
+    // user name => recovery keys, hex as in `StoredUser`
+    #[serde(default)]
+    recovery_keys: BTreeMap<String, Vec<(String, String)>>,
+}
+
+// y1 and y2 in hex
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredUser {
+    user_name: String,
+    parameter_set: String,
+    y1: String,
+    y2: String,
+}
+
+impl JsonFileUserStore {
+    // Starts empty if the file doesn't exist yet.
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        let mut memory = InMemoryUserStore::new();
+        if path.exists() {
+            let json = std::fs::read_to_string(path).map_err(|e| Error::Io(e.to_string()))?;
+            let stored: StoredUsers =
+                serde_json::from_str(&json).map_err(|e| Error::InvalidEncoding(e.to_string()))?;
+            for user in stored.users {
+                let user_info = user.into_user_info()?;
+                memory.users.insert(user_info.user_name.clone(), user_info);
+            }
+            for user in stored.superseded {
+                let user_info = user.into_user_info()?;
+                memory
+                    .superseded
+                    .entry(user_info.user_name.clone())
+                    .or_default()
+                    .push(user_info);
+            }
+            memory.statuses.extend(stored.statuses);
+            for (user_name, keys) in stored.recovery_keys {
+                let keys = keys
+                    .iter()
+                    .map(|(y1, y2)| {
+                        Ok(PublicKey {
+                            y1: decode_hex(y1)?,
+                            y2: decode_hex(y2)?,
+                        })
+                    })
+                    .collect::<Result<_, Error>>()?;
+                memory.recovery_keys.insert(user_name, keys);
+            }
+        }
+
+        Ok(JsonFileUserStore {
+            path: path.to_path_buf(),
+            memory,
+        })
+    }
+
+    pub fn flush(&self) -> Result<(), Error> {
+        let mut stored = StoredUsers::default();
+        for user_name in self.memory.user_names() {
+            if let Some(user_info) = self.memory.get(&user_name) {
+                stored.users.push(StoredUser::from(&user_info));
+            }
+            for user_info in self.memory.superseded(&user_name) {
+                stored.superseded.push(StoredUser::from(&user_info));
+            }
+        }
+        stored.statuses.extend(self.memory.statuses.clone());
+        for (user_name, keys) in &self.memory.recovery_keys {
+            let keys = keys
+                .iter()
+                .map(|key| (encode_hex(&key.y1), encode_hex(&key.y2)))
+                .collect();
+            stored.recovery_keys.insert(user_name.clone(), keys);
+        }
+        let json = serde_json::to_vec_pretty(&stored).map_err(|e| Error::Io(e.to_string()))?;
+
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, json).map_err(|e| Error::Io(e.to_string()))?;
+        std::fs::rename(&tmp, &self.path).map_err(|e| Error::Io(e.to_string()))
+    }
+
+    // Applies `change` and writes the file if `changed` says it did
+    // anything. A failed write undoes the change, so memory never holds
+    // what a restart would lose.
+    fn persist<T>(
+        &mut self,
+        change: impl FnOnce(&mut InMemoryUserStore) -> Result<T, Error>,
+        changed: impl FnOnce(&T) -> bool,
+    ) -> Result<T, Error> {
+        let before = self.memory.clone();
+        let outcome = change(&mut self.memory)?;
+        if changed(&outcome) {
+            if let Err(error) = self.flush() {
+                self.memory = before;
+                return Err(error);
+            }
+        }
+        Ok(outcome)
+    }
+}
+
+impl UserStore for JsonFileUserStore {
+    fn get(&self, user_name: &str) -> Option<UserInfo> {
+        self.memory.get(user_name)
+    }
+
+    fn insert(&mut self, user_info: UserInfo) -> Result<(), Error> {
+        self.persist(|memory| memory.insert(user_info), |_| true)
+    }
+
+    fn remove(&mut self, user_name: &str) -> Result<Option<UserInfo>, Error> {
+        self.persist(|memory| memory.remove(user_name), Option::is_some)
+    }
+
+    fn supersede(&mut self, old: &UserInfo, new: UserInfo) -> Result<bool, Error> {
+        self.persist(|memory| memory.supersede(old, new), |done| *done)
+    }
+
+    fn superseded(&self, user_name: &str) -> Vec<UserInfo> {
+        self.memory.superseded(user_name)
+    }
+
+    fn user_names(&self) -> Vec<String> {
+        self.memory.user_names()
+    }
+
+    fn status(&self, user_name: &str) -> UserStatus {
+        self.memory.status(user_name)
+    }
+
+    fn set_status(&mut self, user_name: &str, status: UserStatus) -> Result<bool, Error> {
+        self.persist(|memory| memory.set_status(user_name, status), |done| *done)
+    }
+
+    fn recovery_keys(&self, user_name: &str) -> Vec<PublicKey> {
+        self.memory.recovery_keys(user_name)
+    }
+
+    fn set_recovery_keys(&mut self, user_name: &str, keys: Vec<PublicKey>) -> Result<bool, Error> {
+        self.persist(
+            |memory| memory.set_recovery_keys(user_name, keys),
+            |done| *done,
+        )
+    }
+
+    // the next write goes to a temporary file next to the store
+    fn ping(&self) -> Result<(), Error> {
+        let dir = match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let metadata = std::fs::metadata(dir).map_err(|e| Error::Io(e.to_string()))?;
+        if metadata.permissions().readonly() {
+            return Err(Error::Io(format!("{} is read-only", dir.display())));
+        }
+        Ok(())
+    }
+}
+
+impl From<&UserInfo> for StoredUser {
+    fn from(user_info: &UserInfo) -> Self {
+        StoredUser {
+            user_name: user_info.user_name.clone(),
+            parameter_set: user_info.parameter_set.clone(),
+            y1: encode_hex(&user_info.y1),
+            y2: encode_hex(&user_info.y2),
+        }
+    }
+}
+
+impl StoredUser {
+    fn into_user_info(self) -> Result<UserInfo, Error> {
+        Ok(UserInfo {
+            y1: decode_hex(&self.y1)?,
+            y2: decode_hex(&self.y2)?,
+            user_name: self.user_name,
+            parameter_set: self.parameter_set,
+        })
+    }
+}
+
+fn encode_hex(n: &BigUint) -> String {
+    hex::encode(n.to_bytes_be())
+}
+
+fn decode_hex(value: &str) -> Result<BigUint, Error> {
+    hex::decode(value)
+        .map(|bytes| BigUint::from_bytes_be(&bytes))
+        .map_err(|e| Error::InvalidEncoding(e.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn user(name: &str, parameter_set: &str) -> UserInfo {
+        UserInfo {
+            user_name: name.to_string(),
+            parameter_set: parameter_set.to_string(),
+            y1: BigUint::from(4u32),
+            y2: BigUint::from(9u32),
+        }
+    }
+
+    #[test]
+    fn test_json_file_store_survives_reopening() {
+        let path = std::env::temp_dir().join(format!("zkp-users-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut store = JsonFileUserStore::open(&path).unwrap();
+        store.insert(user("alice", "old")).unwrap();
+        store.insert(user("bob", "old")).unwrap();
+        assert!(store
+            .supersede(&user("alice", "old"), user("alice", "new"))
+            .unwrap());
+        let disabled = UserStatus {
+            disabled: true,
+            rotation_required: false,
+        };
+        assert!(store.set_status("bob", disabled).unwrap());
+        assert!(store.set_status("alice", disabled).unwrap());
+        let recovery_key = PublicKey {
+            y1: BigUint::from(3u32),
+            y2: BigUint::from(13u32),
+        };
+        assert!(store
+            .set_recovery_keys("alice", vec![recovery_key.clone()])
+            .unwrap());
+        store.remove("bob").unwrap();
+        assert!(!store.set_status("bob", disabled).unwrap());
+
+        let reopened = JsonFileUserStore::open(&path).unwrap();
+        assert_eq!(reopened.user_names(), vec!["alice".to_string()]);
+        assert_eq!(reopened.get("alice"), Some(user("alice", "new")));
+        assert_eq!(reopened.superseded("alice"), vec![user("alice", "old")]);
+        assert_eq!(reopened.status("alice"), disabled);
+        assert_eq!(reopened.recovery_keys("alice"), vec![recovery_key]);
+        assert_eq!(reopened.status("bob"), UserStatus::default());
+        assert!(reopened.ping().is_ok());
+        std::fs::remove_file(&path).unwrap();
+
+        let mut gone = JsonFileUserStore::open(Path::new("/nonexistent/users.json")).unwrap();
+        assert!(gone.ping().is_err());
+        // a failed write is reported and leaves nothing behind in memory
+        assert!(matches!(
+            gone.insert(user("carol", "old")),
+            Err(Error::Io(_))
+        ));
+        assert_eq!(gone.get("carol"), None);
+        assert!(gone.user_names().is_empty());
+    }
+}
@@ -0,0 +1,289 @@
+// Malicious provers for testing verifier implementations, and the soundness
+// extractor further down. Each `Attack` is a structured-but-invalid way of
+// going through the protocol; a correct verifier rejects all of them, at
+// registration or when checking the answer.
+//
+//     for attack in Attack::ALL {
+//         let mut transport = my_verifier();
+//         let result = attack.run(&zkp, "rfc3526-2048", "mallory", &mut transport);
+//         assert!(result.is_err(), "{:?} was accepted", attack);
+//     }
+use num_bigint::BigUint;
+
+use crate::arith::{Group, ModArith};
+use crate::modmath::{mod_inv_prime, mod_mul, mod_reduce, mod_sub};
+use crate::proof::{PublicKey, Secret};
+use crate::simulator::Transcript;
+use crate::transport::Transport;
+use crate::{Error, ZKP};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attack {
+    // answers with s computed from another secret than the registered one
+    WrongSecret,
+    // logs in honestly, then sends the same commitment and answer again
+    ReusedNonce,
+    // registers y1 = alpha^x, y2 = beta^x' with x != x'
+    MismatchedY2,
+    // answers with s + q, which satisfies the equations
+    ShiftedResponse,
+    // registers y1 = y2 = 1 (x = 0)
+    IdentityPublicKey,
+    // commits to r1 = r2 = 1 (k = 0)
+    IdentityCommitment,
+    // registers elements outside the order q subgroup (p - 1 has order 2)
+    OutOfSubgroupPublicKey,
+}
+
+impl Attack {
+    pub const ALL: [Attack; 7] = [
+        Attack::WrongSecret,
+        Attack::ReusedNonce,
+        Attack::MismatchedY2,
+        Attack::ShiftedResponse,
+        Attack::IdentityPublicKey,
+        Attack::IdentityCommitment,
+        Attack::OutOfSubgroupPublicKey,
+    ];
+
+    // Plays the attack as `user_name`. Returns the first error of the
+    // verifier, or the session id if it was fooled.
+    pub fn run<T: Transport>(
+        &self,
+        zkp: &ZKP,
+        parameter_set: &str,
+        user_name: &str,
+        transport: &mut T,
+    ) -> Result<String, Error> {
+        let one = BigUint::from(1u32);
+        let x = ZKP::generate_random_nonzero_below(&zkp.q);
+        let y1 = ZKP::exponetiate(&zkp.alpha, &x, &zkp.p);
+        let y2 = ZKP::exponetiate(&zkp.beta, &x, &zkp.p);
+
+        match self {
+            Attack::WrongSecret => {
+                transport.register(user_name, parameter_set, &y1, &y2)?;
+                let x_fake = mod_reduce(&(&x + 1u32), &zkp.q);
+                let k = nonce(zkp);
+                let (r1, r2) = commitment(zkp, &k);
+                let (auth_id, c) = transport.create_challenge(user_name, &r1, &r2)?;
+                transport.verify(&auth_id, &respond(zkp, &k, &c, &x_fake))
+            }
+            Attack::ReusedNonce => {
+                transport.register(user_name, parameter_set, &y1, &y2)?;
+                let k = nonce(zkp);
+                let (r1, r2) = commitment(zkp, &k);
+                let (auth_id, c) = transport.create_challenge(user_name, &r1, &r2)?;
+                transport.verify(&auth_id, &respond(zkp, &k, &c, &x))?;
+
+                let (auth_id, c) = transport.create_challenge(user_name, &r1, &r2)?;
+                transport.verify(&auth_id, &respond(zkp, &k, &c, &x))
+            }
+            Attack::MismatchedY2 => {
+                let y2 = ZKP::exponetiate(&zkp.beta, &(&x + 1u32), &zkp.p);
+                transport.register(user_name, parameter_set, &y1, &y2)?;
+                let k = nonce(zkp);
+                let (r1, r2) = commitment(zkp, &k);
+                let (auth_id, c) = transport.create_challenge(user_name, &r1, &r2)?;
+                transport.verify(&auth_id, &respond(zkp, &k, &c, &x))
+            }
+            Attack::ShiftedResponse => {
+                transport.register(user_name, parameter_set, &y1, &y2)?;
+                let k = nonce(zkp);
+                let (r1, r2) = commitment(zkp, &k);
+                let (auth_id, c) = transport.create_challenge(user_name, &r1, &r2)?;
+                let s = respond(zkp, &k, &c, &x) + &zkp.q;
+                transport.verify(&auth_id, &s)
+            }
+            Attack::IdentityPublicKey => {
+                transport.register(user_name, parameter_set, &one, &one)?;
+                let (auth_id, _) = transport.create_challenge(user_name, &one, &one)?;
+                transport.verify(&auth_id, &BigUint::from(0u32))
+            }
+            Attack::IdentityCommitment => {
+                transport.register(user_name, parameter_set, &y1, &y2)?;
+                let k = BigUint::from(0u32);
+                let (auth_id, c) = transport.create_challenge(user_name, &one, &one)?;
+                transport.verify(&auth_id, &respond(zkp, &k, &c, &x))
+            }
+            Attack::OutOfSubgroupPublicKey => {
+                let minus_one = &zkp.p - 1u32;
+                let y1 = mod_mul(&y1, &minus_one, &zkp.p);
+                let y2 = mod_mul(&y2, &minus_one, &zkp.p);
+                transport.register(user_name, parameter_set, &y1, &y2)?;
+                let k = nonce(zkp);
+                let (r1, r2) = commitment(zkp, &k);
+                let (auth_id, c) = transport.create_challenge(user_name, &r1, &r2)?;
+                transport.verify(&auth_id, &respond(zkp, &k, &c, &x))
+            }
+        }
+    }
+}
+
+// Special soundness, constructively: two accepting transcripts with the same
+// commitment and different challenges give away x,
+//     s1 - s2 = (c2 - c1) * x mod q
+// A prover that can answer two challenges for one commitment knows x, which
+// is what makes the protocol a proof of knowledge. None if the transcripts
+// don't qualify.
+pub fn extract(
+    zkp: &ZKP,
+    public_key: &PublicKey,
+    first: &Transcript,
+    second: &Transcript,
+) -> Option<Secret> {
+    let same_commitment = first.r1 == second.r1 && first.r2 == second.r2;
+    if !same_commitment
+        || first.c == second.c
+        || !zkp.verify_transcript(public_key, first)
+        || !zkp.verify_transcript(public_key, second)
+    {
+        return None;
+    }
+
+    let q = &zkp.q;
+    let ds = mod_sub(&first.s, &second.s, q);
+    let dc = mod_sub(&second.c, &first.c, q);
+    let x = mod_mul(&ds, &mod_inv_prime(&dc, q), q);
+    Some(Secret::new(x))
+}
+
+// Plays `rounds` rewound logins on backend A (one commitment, two
+// challenges) and checks that A's verifier accepts both answers and that
+// `extract` recovers the x they were computed with. Panics otherwise.
+pub fn check_special_soundness<A: ModArith>(zkp: &ZKP, rounds: usize) {
+    let group = Group::<A>::from_zkp(zkp).expect("backend supports the group");
+    let to_biguint = |bytes: Vec<u8>| BigUint::from_bytes_be(&bytes);
+
+    for _ in 0..rounds {
+        let x = group.q.random_below();
+        let (y1, y2) = group.public_key(&x);
+        let (k, r1, r2) = group.commit();
+        let (c1, c2) = (group.q.random_below(), group.q.random_below());
+        if c1 == c2 {
+            continue;
+        }
+        let (s1, s2) = (group.solve(&k, &c1, &x), group.solve(&k, &c2, &x));
+        assert!(group.verify((&r1, &r2), (&y1, &y2), &s1, &c1));
+        assert!(group.verify((&r1, &r2), (&y1, &y2), &s2, &c2));
+
+        let public_key = PublicKey {
+            y1: to_biguint(group.p.encode(&y1)),
+            y2: to_biguint(group.p.encode(&y2)),
+        };
+        let transcript = |c: &A::Int, s: &A::Int| Transcript {
+            r1: to_biguint(group.p.encode(&r1)),
+            r2: to_biguint(group.p.encode(&r2)),
+            c: to_biguint(group.q.encode(c)),
+            s: to_biguint(group.q.encode(s)),
+        };
+        let extracted = extract(
+            zkp,
+            &public_key,
+            &transcript(&c1, &s1),
+            &transcript(&c2, &s2),
+        )
+        .expect("two accepting transcripts extract");
+        assert_eq!(
+            extracted.x(),
+            &to_biguint(group.q.encode(&x)),
+            "extracted the wrong secret"
+        );
+    }
+}
+
+fn nonce(zkp: &ZKP) -> BigUint {
+    ZKP::generate_random_nonzero_below(&zkp.q)
+}
+
+fn commitment(zkp: &ZKP, k: &BigUint) -> (BigUint, BigUint) {
+    (
+        ZKP::exponetiate(&zkp.alpha, k, &zkp.p),
+        ZKP::exponetiate(&zkp.beta, k, &zkp.p),
+    )
+}
+
+fn respond(zkp: &ZKP, k: &BigUint, c: &BigUint, x: &BigUint) -> BigUint {
+    zkp.solve(k, c, x)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+    use crate::simulation::InMemoryTransport;
+    use crate::verifier::Verifier;
+
+    #[test]
+    fn test_verifier_rejects_every_attack() {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let zkp = set.zkp();
+
+        for attack in Attack::ALL {
+            let mut verifier = Verifier::with_parameter_sets(set, &[]);
+            let result = attack.run(&zkp, set.id(), "mallory", &mut verifier);
+            assert!(result.is_err(), "{:?} was accepted", attack);
+        }
+    }
+
+    #[test]
+    fn test_rejection_reasons() {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let zkp = set.zkp();
+        let run = |attack: Attack| {
+            let mut transport = InMemoryTransport::new(Verifier::with_parameter_sets(set, &[]));
+            attack.run(&zkp, set.id(), "mallory", &mut transport)
+        };
+
+        assert_eq!(run(Attack::WrongSecret), Err(Error::InvalidProof));
+        assert_eq!(run(Attack::ReusedNonce), Err(Error::ReplayedCommitment));
+        assert_eq!(run(Attack::MismatchedY2), Err(Error::InvalidProof));
+        assert_eq!(run(Attack::ShiftedResponse), Err(Error::InvalidProof));
+        assert_eq!(run(Attack::IdentityPublicKey), Err(Error::InvalidPublicKey));
+        assert_eq!(run(Attack::IdentityCommitment), Err(Error::InvalidProof));
+        assert_eq!(
+            run(Attack::OutOfSubgroupPublicKey),
+            Err(Error::InvalidPublicKey)
+        );
+    }
+
+    #[test]
+    fn test_extract() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let secret = Secret::random(&zkp);
+        let public_key = zkp.public_key(&secret);
+
+        let k = nonce(&zkp);
+        let (r1, r2) = commitment(&zkp, &k);
+        let answer = |c: u32| Transcript {
+            r1: r1.clone(),
+            r2: r2.clone(),
+            c: BigUint::from(c),
+            s: respond(&zkp, &k, &BigUint::from(c), secret.x()),
+        };
+        let extracted = extract(&zkp, &public_key, &answer(3), &answer(7)).unwrap();
+        assert_eq!(extracted.x(), secret.x());
+
+        // one challenge, or two commitments, give nothing away
+        assert!(extract(&zkp, &public_key, &answer(3), &answer(3)).is_none());
+        let other = zkp.simulate(&public_key);
+        assert!(extract(&zkp, &public_key, &answer(3), &other).is_none());
+    }
+
+    #[test]
+    fn test_special_soundness_for_every_backend() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        check_special_soundness::<crate::montgomery::Montgomery>(&zkp, 16);
+
+        #[cfg(feature = "crypto-bigint")]
+        {
+            use crate::arith::CryptoBigint;
+            use crypto_bigint::{U1024, U2048};
+            check_special_soundness::<CryptoBigint<{ U1024::LIMBS }>>(&zkp, 16);
+            check_special_soundness::<CryptoBigint<{ U2048::LIMBS }>>(
+                &ParameterSet::Rfc3526_2048.zkp(),
+                4,
+            );
+        }
+    }
+}
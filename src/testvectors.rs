@@ -0,0 +1,210 @@
+use num_bigint::BigUint;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::export::CompactExport;
+use crate::params::ParameterSet;
+use crate::proof::{CompactProof, Proof, PublicKey, Secret};
+use crate::ZKP;
+
+const SEED_LABEL: &[u8] = b"zkp-chaum-pedersen/test-vectors/v1";
+const ACTION_CONTEXT: &[u8] = b"test-vector action";
+
+// Reference values for the other implementations (the Go and TypeScript
+// ports) to check themselves against. Nothing is drawn at random: every
+// scalar is derived from the seed,
+//
+//     scalar(name) = SHA-256 blocks of (label, seed, parameter set id, name,
+//                    block) widened to the length of q + 16 bytes, mod q
+//
+// so a seed always gives the same file. Numbers are big-endian hex padded to
+// the length of p (elements) or q (scalars), as on the wire.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TestVectors {
+    pub seed: String,
+    pub vectors: Vec<TestVector>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TestVector {
+    pub parameter_set: String,
+    pub p: String,
+    pub q: String,
+    pub alpha: String,
+    pub beta: String,
+    pub x: String,
+    pub y1: String,
+    pub y2: String,
+    pub k: String,
+    pub r1: String,
+    pub r2: String,
+    // an interactive round with a verifier drawn c
+    pub interactive_c: String,
+    pub interactive_s: String,
+    // the same commitment made non-interactive, `ZKP::challenge`
+    pub c: String,
+    pub s: String,
+    // `ZKP::encode_proof`
+    pub proof: String,
+    // `CompactExport` of an action proof bound to `action_context`, with its
+    // own nonce k_action
+    pub action_context: String,
+    pub k_action: String,
+    pub action_c: String,
+    pub action_s: String,
+    pub compact_export: String,
+}
+
+impl TestVectors {
+    pub fn generate(seed: &[u8], parameter_sets: &[ParameterSet]) -> Self {
+        TestVectors {
+            seed: hex::encode(seed),
+            vectors: parameter_sets
+                .iter()
+                .map(|set| TestVector::generate(seed, *set))
+                .collect(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("test vectors always serialize")
+    }
+}
+
+impl TestVector {
+    pub fn generate(seed: &[u8], parameter_set: ParameterSet) -> Self {
+        let zkp = parameter_set.zkp();
+        let scalar = |name: &str| derive_scalar(&zkp, seed, parameter_set.id(), name);
+        let element = |e: &BigUint| hex::encode(zkp.element_to_bytes(e));
+        let scalar_hex = |n: &BigUint| hex::encode(zkp.scalar_to_bytes(n));
+
+        let x = scalar("x");
+        let public_key = zkp.public_key(&Secret::new(x.clone()));
+        let k = scalar("k");
+        let (r1, r2) = (zkp.pow(&zkp.alpha, &k), zkp.pow(&zkp.beta, &k));
+
+        let interactive_c = scalar("interactive_c");
+        let interactive_s = zkp.solve(&k, &interactive_c, &x);
+        let c = zkp.challenge(&public_key, &r1, &r2);
+        let s = zkp.solve(&k, &c, &x);
+        let proof = Proof {
+            r1: r1.clone(),
+            r2: r2.clone(),
+            s: s.clone(),
+        };
+
+        let k_action = scalar("k_action");
+        let action_c = zkp.action_challenge(
+            &public_key,
+            ACTION_CONTEXT,
+            &zkp.pow(&zkp.alpha, &k_action),
+            &zkp.pow(&zkp.beta, &k_action),
+        );
+        let action_s = zkp.solve(&k_action, &action_c, &x);
+        let export = CompactExport {
+            parameter_set,
+            proof: CompactProof {
+                c: action_c.clone(),
+                s: action_s.clone(),
+            },
+            context: ACTION_CONTEXT.to_vec(),
+        };
+
+        TestVector {
+            parameter_set: parameter_set.id().to_string(),
+            p: element(&zkp.p),
+            q: scalar_hex(&zkp.q),
+            alpha: element(&zkp.alpha),
+            beta: element(&zkp.beta),
+            x: scalar_hex(&x),
+            y1: element(&public_key.y1),
+            y2: element(&public_key.y2),
+            k: scalar_hex(&k),
+            r1: element(&r1),
+            r2: element(&r2),
+            interactive_c: scalar_hex(&interactive_c),
+            interactive_s: scalar_hex(&interactive_s),
+            c: scalar_hex(&c),
+            s: scalar_hex(&s),
+            proof: hex::encode(zkp.encode_proof(&proof)),
+            action_context: hex::encode(ACTION_CONTEXT),
+            k_action: scalar_hex(&k_action),
+            action_c: scalar_hex(&action_c),
+            action_s: scalar_hex(&action_s),
+            compact_export: export.export_compact(),
+        }
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey {
+            y1: from_hex(&self.y1),
+            y2: from_hex(&self.y2),
+        }
+    }
+}
+
+fn derive_scalar(zkp: &ZKP, seed: &[u8], parameter_set: &str, name: &str) -> BigUint {
+    let wanted = zkp.scalar_len() + 16;
+    let mut bytes = Vec::with_capacity(wanted + 32);
+    for block in 0u32.. {
+        if bytes.len() >= wanted {
+            break;
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(SEED_LABEL);
+        for field in [seed, parameter_set.as_bytes(), name.as_bytes()] {
+            hasher.update((field.len() as u32).to_be_bytes());
+            hasher.update(field);
+        }
+        hasher.update(block.to_be_bytes());
+        bytes.extend_from_slice(&hasher.finalize());
+    }
+    bytes.truncate(wanted);
+
+    BigUint::from_bytes_be(&bytes) % &zkp.q
+}
+
+fn from_hex(value: &str) -> BigUint {
+    BigUint::from_bytes_be(&hex::decode(value).expect("test vectors are hex"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_vectors_are_reproducible() {
+        let sets = [ParameterSet::Rfc5114_1024_160];
+        let vectors = TestVectors::generate(b"seed", &sets);
+        assert_eq!(
+            vectors.to_json(),
+            TestVectors::generate(b"seed", &sets).to_json()
+        );
+        assert_ne!(vectors, TestVectors::generate(b"other seed", &sets));
+    }
+
+    #[test]
+    fn test_vectors_verify() {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let zkp = set.zkp();
+        let vector = TestVector::generate(b"seed", set);
+        let public_key = vector.public_key();
+
+        let (r1, r2) = (from_hex(&vector.r1), from_hex(&vector.r2));
+        let (y1, y2) = (&public_key.y1, &public_key.y2);
+        let (c, s) = (
+            from_hex(&vector.interactive_c),
+            from_hex(&vector.interactive_s),
+        );
+        assert!(zkp.verify(&r1, &r2, y1, y2, &s, &c));
+
+        let proof = zkp
+            .decode_proof(&hex::decode(&vector.proof).unwrap())
+            .unwrap();
+        assert!(zkp.verify_versioned(&public_key, &proof));
+
+        let export = CompactExport::import_compact(&vector.compact_export).unwrap();
+        assert_eq!(export.context, ACTION_CONTEXT);
+        assert!(export.verify(&public_key));
+    }
+}
@@ -0,0 +1,23 @@
+// Thin wrappers around `tracing` so call sites don't need `#[cfg(feature = "tracing")]`.
+// Never pass secret material (x, k, s) to these macros.
+
+macro_rules! trace_info {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::info!($($arg)*);
+    };
+}
+
+macro_rules! trace_warn {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::warn!($($arg)*);
+    };
+}
+
+macro_rules! trace_debug {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)*);
+    };
+}
@@ -0,0 +1,460 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::proof::PublicKey;
+use crate::store::UserStore;
+use crate::transport::Transport;
+use crate::verifier::{Purpose, Verifier};
+use crate::Error;
+
+// Everything exchanged between a prover and a verifier, in order. Values are
+// kept as the bytes that went over the wire (hex in the JSON lines), so a
+// transcript captured in front of a foreign client shows its encoding as is.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Entry {
+    Register {
+        user: String,
+        parameter_set: String,
+        #[serde(with = "hex_bytes")]
+        y1: Vec<u8>,
+        #[serde(with = "hex_bytes")]
+        y2: Vec<u8>,
+    },
+    Registered,
+    Commitment {
+        user: String,
+        #[serde(with = "hex_bytes")]
+        r1: Vec<u8>,
+        #[serde(with = "hex_bytes")]
+        r2: Vec<u8>,
+    },
+    RotationCommitment {
+        user: String,
+        #[serde(with = "hex_bytes")]
+        r1: Vec<u8>,
+        #[serde(with = "hex_bytes")]
+        r2: Vec<u8>,
+        #[serde(with = "hex_bytes")]
+        new_y1: Vec<u8>,
+        #[serde(with = "hex_bytes")]
+        new_y2: Vec<u8>,
+    },
+    Challenge {
+        auth_id: String,
+        #[serde(with = "hex_bytes")]
+        c: Vec<u8>,
+    },
+    Response {
+        auth_id: String,
+        #[serde(with = "hex_bytes")]
+        s: Vec<u8>,
+    },
+    // SHA-256 of the session id, never the id: it is a bearer token
+    Session {
+        session_fingerprint: String,
+    },
+    Rotated,
+    Error {
+        message: String,
+    },
+}
+
+impl Entry {
+    // answers of the verifier, as opposed to requests of the prover
+    fn is_reply(&self) -> bool {
+        matches!(
+            self,
+            Entry::Registered
+                | Entry::Challenge { .. }
+                | Entry::Session { .. }
+                | Entry::Rotated
+                | Entry::Error { .. }
+        )
+    }
+
+    pub fn session(session_id: &str) -> Entry {
+        Entry::Session {
+            session_fingerprint: hex::encode(Sha256::digest(session_id.as_bytes())),
+        }
+    }
+
+    fn error<T>(result: &Result<T, Error>) -> Option<Entry> {
+        result.as_ref().err().map(|error| Entry::Error {
+            message: error.to_string(),
+        })
+    }
+}
+
+// Outcome of one request when replayed, next to the one recorded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayStep {
+    // index of the request in the transcript
+    pub index: usize,
+    // Err(message) if the recorded verifier refused the request
+    pub recorded: Result<(), String>,
+    pub replayed: Result<(), Error>,
+}
+
+impl ReplayStep {
+    pub fn matches(&self) -> bool {
+        self.recorded.is_ok() == self.replayed.is_ok()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Transcript {
+    pub entries: Vec<Entry>,
+}
+
+impl Transcript {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, entry: Entry) {
+        self.entries.push(entry);
+    }
+
+    pub fn to_jsonl(&self) -> String {
+        self.entries
+            .iter()
+            .map(|entry| serde_json::to_string(entry).unwrap() + "\n")
+            .collect()
+    }
+
+    pub fn from_jsonl(jsonl: &str) -> Result<Transcript, Error> {
+        let entries = jsonl
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                serde_json::from_str(line).map_err(|e| Error::InvalidEncoding(e.to_string()))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Transcript { entries })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        std::fs::write(path, self.to_jsonl()).map_err(|e| Error::Io(e.to_string()))
+    }
+
+    pub fn load(path: &Path) -> Result<Transcript, Error> {
+        let jsonl = std::fs::read_to_string(path).map_err(|e| Error::Io(e.to_string()))?;
+        Transcript::from_jsonl(&jsonl)
+    }
+
+    // Plays every request again against `verifier`, which should have the
+    // parameter sets of the recorded one and none of its users. Challenges
+    // are not drawn again, the recorded c is reused so the recorded answers
+    // can be checked.
+    pub fn replay<S: UserStore>(&self, verifier: &mut Verifier<S>) -> Vec<ReplayStep> {
+        let int = |bytes: &[u8]| BigUint::from_bytes_be(bytes);
+        // recorded auth_id => (replayed auth_id, rotation)
+        let mut auth_ids: HashMap<String, (String, bool)> = HashMap::new();
+        let mut steps = Vec::new();
+
+        for (index, entry) in self.entries.iter().enumerate() {
+            if entry.is_reply() {
+                continue;
+            }
+            let reply = self.entries.get(index + 1).filter(|reply| reply.is_reply());
+            let recorded = match reply {
+                Some(Entry::Error { message }) => Err(message.clone()),
+                Some(_) => Ok(()),
+                None => Err("no reply recorded".to_string()),
+            };
+            let recorded_challenge = match reply {
+                Some(Entry::Challenge { auth_id, c }) => Some((auth_id.clone(), int(c))),
+                _ => None,
+            };
+
+            let replayed = match entry {
+                Entry::Register {
+                    user,
+                    parameter_set,
+                    y1,
+                    y2,
+                } => verifier.register_with_parameter_set(user, parameter_set, int(y1), int(y2)),
+                Entry::Commitment { user, r1, r2 }
+                | Entry::RotationCommitment { user, r1, r2, .. } => {
+                    let rotation = matches!(entry, Entry::RotationCommitment { .. });
+                    let c = recorded_challenge.as_ref().map(|(_, c)| c.clone());
+                    let issued = match entry {
+                        Entry::RotationCommitment { new_y1, new_y2, .. } => {
+                            let new_key = PublicKey {
+                                y1: int(new_y1),
+                                y2: int(new_y2),
+                            };
+                            verifier.issue_rotation_challenge(user, int(r1), int(r2), new_key, c)
+                        }
+                        _ => verifier.issue_challenge(user, int(r1), int(r2), Purpose::Login, c),
+                    };
+                    issued.map(|(auth_id, _)| {
+                        if let Some((recorded_id, _)) = &recorded_challenge {
+                            auth_ids.insert(recorded_id.clone(), (auth_id, rotation));
+                        }
+                    })
+                }
+                Entry::Response { auth_id, s } => {
+                    let (auth_id, rotation) = auth_ids
+                        .get(auth_id)
+                        .cloned()
+                        .unwrap_or_else(|| (auth_id.clone(), false));
+                    if rotation {
+                        verifier.verify_rotation(&auth_id, &int(s))
+                    } else {
+                        verifier.verify(&auth_id, &int(s)).map(|_| ())
+                    }
+                }
+                _ => unreachable!("replies are skipped"),
+            };
+
+            steps.push(ReplayStep {
+                index,
+                recorded,
+                replayed,
+            });
+        }
+
+        steps
+    }
+}
+
+// Appends entries to a file as they happen, for long running processes.
+pub struct TranscriptWriter<W = File> {
+    writer: W,
+}
+
+impl TranscriptWriter<File> {
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| Error::Io(e.to_string()))?;
+        Ok(TranscriptWriter { writer: file })
+    }
+}
+
+impl<W: Write> TranscriptWriter<W> {
+    pub fn write(&mut self, entries: &[Entry]) -> Result<(), Error> {
+        let jsonl = Transcript {
+            entries: entries.to_vec(),
+        }
+        .to_jsonl();
+        self.writer
+            .write_all(jsonl.as_bytes())
+            .and_then(|_| self.writer.flush())
+            .map_err(|e| Error::Io(e.to_string()))
+    }
+}
+
+// Transport recording what goes through it before passing it on.
+pub struct RecordingTransport<T> {
+    inner: T,
+    transcript: Transcript,
+}
+
+impl<T: Transport> RecordingTransport<T> {
+    pub fn new(inner: T) -> Self {
+        RecordingTransport {
+            inner,
+            transcript: Transcript::new(),
+        }
+    }
+
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    pub fn transcript(&self) -> &Transcript {
+        &self.transcript
+    }
+
+    pub fn into_transcript(self) -> Transcript {
+        self.transcript
+    }
+}
+
+impl<T: Transport> Transport for RecordingTransport<T> {
+    fn register(
+        &mut self,
+        user_name: &str,
+        parameter_set: &str,
+        y1: &BigUint,
+        y2: &BigUint,
+    ) -> Result<(), Error> {
+        self.transcript.push(Entry::Register {
+            user: user_name.to_string(),
+            parameter_set: parameter_set.to_string(),
+            y1: y1.to_bytes_be(),
+            y2: y2.to_bytes_be(),
+        });
+        let result = self.inner.register(user_name, parameter_set, y1, y2);
+        self.transcript
+            .push(Entry::error(&result).unwrap_or(Entry::Registered));
+        result
+    }
+
+    fn create_challenge(
+        &mut self,
+        user_name: &str,
+        r1: &BigUint,
+        r2: &BigUint,
+    ) -> Result<(String, BigUint), Error> {
+        self.transcript.push(Entry::Commitment {
+            user: user_name.to_string(),
+            r1: r1.to_bytes_be(),
+            r2: r2.to_bytes_be(),
+        });
+        let result = self.inner.create_challenge(user_name, r1, r2);
+        self.push_challenge(&result);
+        result
+    }
+
+    fn verify(&mut self, auth_id: &str, s: &BigUint) -> Result<String, Error> {
+        self.push_response(auth_id, s);
+        let result = self.inner.verify(auth_id, s);
+        self.transcript.push(
+            Entry::error(&result).unwrap_or_else(|| Entry::session(result.as_ref().unwrap())),
+        );
+        result
+    }
+
+    fn create_rotation_challenge(
+        &mut self,
+        user_name: &str,
+        r1: &BigUint,
+        r2: &BigUint,
+        new_y1: &BigUint,
+        new_y2: &BigUint,
+    ) -> Result<(String, BigUint), Error> {
+        self.transcript.push(Entry::RotationCommitment {
+            user: user_name.to_string(),
+            r1: r1.to_bytes_be(),
+            r2: r2.to_bytes_be(),
+            new_y1: new_y1.to_bytes_be(),
+            new_y2: new_y2.to_bytes_be(),
+        });
+        let result = self
+            .inner
+            .create_rotation_challenge(user_name, r1, r2, new_y1, new_y2);
+        self.push_challenge(&result);
+        result
+    }
+
+    fn verify_rotation(&mut self, auth_id: &str, s: &BigUint) -> Result<(), Error> {
+        self.push_response(auth_id, s);
+        let result = self.inner.verify_rotation(auth_id, s);
+        self.transcript
+            .push(Entry::error(&result).unwrap_or(Entry::Rotated));
+        result
+    }
+}
+
+impl<T> RecordingTransport<T> {
+    fn push_challenge(&mut self, result: &Result<(String, BigUint), Error>) {
+        let entry = match result {
+            Ok((auth_id, c)) => Entry::Challenge {
+                auth_id: auth_id.clone(),
+                c: c.to_bytes_be(),
+            },
+            Err(error) => Entry::Error {
+                message: error.to_string(),
+            },
+        };
+        self.transcript.push(entry);
+    }
+
+    fn push_response(&mut self, auth_id: &str, s: &BigUint) {
+        self.transcript.push(Entry::Response {
+            auth_id: auth_id.to_string(),
+            s: s.to_bytes_be(),
+        });
+    }
+}
+
+mod hex_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        hex::decode(s).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+    use crate::proof::Secret;
+    use crate::prover::Prover;
+    use crate::testing::Attack;
+
+    fn recorded_session() -> Transcript {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let mut transport = RecordingTransport::new(Verifier::with_parameter_sets(set, &[]));
+
+        let zkp = set.zkp();
+        let mut alice = Prover::new(zkp.clone(), set.id(), "alice", Secret::random(&zkp));
+        alice.register(&mut transport).unwrap();
+        alice.login(&mut transport).unwrap();
+        assert!(Attack::WrongSecret
+            .run(&zkp, set.id(), "mallory", &mut transport)
+            .is_err());
+
+        transport.into_transcript()
+    }
+
+    #[test]
+    fn test_record_and_replay() {
+        let transcript = recorded_session();
+        // register, commitment, response, each with its reply, twice
+        assert_eq!(transcript.entries.len(), 12);
+        assert!(matches!(transcript.entries[11], Entry::Error { .. }));
+        // the session id itself is never written
+        match &transcript.entries[5] {
+            Entry::Session {
+                session_fingerprint,
+            } => assert_eq!(session_fingerprint.len(), 64),
+            entry => panic!("expected alice's session, got {:?}", entry),
+        }
+
+        let transcript = Transcript::from_jsonl(&transcript.to_jsonl()).unwrap();
+        let mut verifier = Verifier::with_parameter_sets(ParameterSet::Rfc5114_1024_160, &[]);
+        let steps = transcript.replay(&mut verifier);
+
+        assert_eq!(steps.len(), 6);
+        assert!(steps.iter().all(ReplayStep::matches));
+        assert_eq!(steps[5].replayed, Err(Error::InvalidProof));
+    }
+
+    #[test]
+    fn test_replay_spots_a_changed_answer() {
+        let mut transcript = recorded_session();
+        if let Entry::Response { s, .. } = &mut transcript.entries[4] {
+            s[0] ^= 1;
+        } else {
+            panic!("expected alice's response");
+        }
+
+        let mut verifier = Verifier::with_parameter_sets(ParameterSet::Rfc5114_1024_160, &[]);
+        let mismatches: Vec<usize> = transcript
+            .replay(&mut verifier)
+            .iter()
+            .filter(|step| !step.matches())
+            .map(|step| step.index)
+            .collect();
+        assert_eq!(mismatches, vec![4]);
+    }
+}
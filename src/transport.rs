@@ -0,0 +1,171 @@
+use num_bigint::BigUint;
+
+use crate::store::UserStore;
+use crate::verifier::Verifier;
+use crate::Error;
+
+// How the prover reaches the verifier: the three protocol round trips.
+// A `Verifier` is itself a transport for the prover living in the same
+// process; remote transports map the calls onto the wire.
+pub trait Transport {
+    fn register(
+        &mut self,
+        user_name: &str,
+        parameter_set: &str,
+        y1: &BigUint,
+        y2: &BigUint,
+    ) -> Result<(), Error>;
+
+    // output => (auth_id, c)
+    fn create_challenge(
+        &mut self,
+        user_name: &str,
+        r1: &BigUint,
+        r2: &BigUint,
+    ) -> Result<(String, BigUint), Error>;
+
+    // output => session_id
+    fn verify(&mut self, auth_id: &str, s: &BigUint) -> Result<String, Error>;
+
+    // key rotation: a login round whose answer installs (new_y1, new_y2)
+    // output => (auth_id, c)
+    fn create_rotation_challenge(
+        &mut self,
+        user_name: &str,
+        r1: &BigUint,
+        r2: &BigUint,
+        new_y1: &BigUint,
+        new_y2: &BigUint,
+    ) -> Result<(String, BigUint), Error>;
+
+    fn verify_rotation(&mut self, auth_id: &str, s: &BigUint) -> Result<(), Error>;
+}
+
+impl<S: UserStore> Transport for Verifier<S> {
+    fn register(
+        &mut self,
+        user_name: &str,
+        parameter_set: &str,
+        y1: &BigUint,
+        y2: &BigUint,
+    ) -> Result<(), Error> {
+        self.register_with_parameter_set(user_name, parameter_set, y1.clone(), y2.clone())
+    }
+
+    fn create_challenge(
+        &mut self,
+        user_name: &str,
+        r1: &BigUint,
+        r2: &BigUint,
+    ) -> Result<(String, BigUint), Error> {
+        Verifier::create_challenge(self, user_name, r1.clone(), r2.clone())
+    }
+
+    fn verify(&mut self, auth_id: &str, s: &BigUint) -> Result<String, Error> {
+        Verifier::verify(self, auth_id, s)
+    }
+
+    fn create_rotation_challenge(
+        &mut self,
+        user_name: &str,
+        r1: &BigUint,
+        r2: &BigUint,
+        new_y1: &BigUint,
+        new_y2: &BigUint,
+    ) -> Result<(String, BigUint), Error> {
+        Verifier::create_rotation_challenge(
+            self,
+            user_name,
+            r1.clone(),
+            r2.clone(),
+            new_y1.clone(),
+            new_y2.clone(),
+        )
+    }
+
+    fn verify_rotation(&mut self, auth_id: &str, s: &BigUint) -> Result<(), Error> {
+        Verifier::verify_rotation(self, auth_id, s)
+    }
+}
+
+// The same round trips for async applications. Methods take `&self` so one
+// transport (a gRPC channel, a shared verifier) serves concurrent logins,
+// each driven by its own `Prover`.
+#[tonic::async_trait]
+pub trait AsyncTransport: Send + Sync {
+    async fn register(
+        &self,
+        user_name: &str,
+        parameter_set: &str,
+        y1: &BigUint,
+        y2: &BigUint,
+    ) -> Result<(), Error>;
+
+    // output => (auth_id, c)
+    async fn create_challenge(
+        &self,
+        user_name: &str,
+        r1: &BigUint,
+        r2: &BigUint,
+    ) -> Result<(String, BigUint), Error>;
+
+    // output => session_id
+    async fn verify(&self, auth_id: &str, s: &BigUint) -> Result<String, Error>;
+
+    // output => (auth_id, c)
+    async fn create_rotation_challenge(
+        &self,
+        user_name: &str,
+        r1: &BigUint,
+        r2: &BigUint,
+        new_y1: &BigUint,
+        new_y2: &BigUint,
+    ) -> Result<(String, BigUint), Error>;
+
+    async fn verify_rotation(&self, auth_id: &str, s: &BigUint) -> Result<(), Error>;
+}
+
+// Any blocking transport behind a lock, an in-process verifier for example.
+// The lock is held for one call at a time, never across the prover's work.
+#[tonic::async_trait]
+impl<T: Transport + Send> AsyncTransport for tokio::sync::Mutex<T> {
+    async fn register(
+        &self,
+        user_name: &str,
+        parameter_set: &str,
+        y1: &BigUint,
+        y2: &BigUint,
+    ) -> Result<(), Error> {
+        self.lock().await.register(user_name, parameter_set, y1, y2)
+    }
+
+    async fn create_challenge(
+        &self,
+        user_name: &str,
+        r1: &BigUint,
+        r2: &BigUint,
+    ) -> Result<(String, BigUint), Error> {
+        self.lock().await.create_challenge(user_name, r1, r2)
+    }
+
+    async fn verify(&self, auth_id: &str, s: &BigUint) -> Result<String, Error> {
+        self.lock().await.verify(auth_id, s)
+    }
+
+    async fn create_rotation_challenge(
+        &self,
+        user_name: &str,
+        r1: &BigUint,
+        r2: &BigUint,
+        new_y1: &BigUint,
+        new_y2: &BigUint,
+    ) -> Result<(String, BigUint), Error> {
+        self.lock()
+            .await
+            .create_rotation_challenge(user_name, r1, r2, new_y1, new_y2)
+    }
+
+    async fn verify_rotation(&self, auth_id: &str, s: &BigUint) -> Result<(), Error> {
+        self.lock().await.verify_rotation(auth_id, s)
+    }
+}
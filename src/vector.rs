@@ -0,0 +1,140 @@
+use num_bigint::BigUint;
+
+use crate::ZKP;
+
+const CHALLENGE_LABEL: &[u8] = b"zkp-chaum-pedersen/vector/v1";
+
+// Chaum-Pedersen generalized to n bases: proves that y_i = g_i^x mod p for
+// every i with the same x. With two generators it is the usual protocol
+// with g_1 = alpha and g_2 = beta.
+#[derive(Debug, Clone)]
+pub struct VectorZKP {
+    pub p: BigUint,
+    pub q: BigUint,
+    pub generators: Vec<BigUint>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VectorProof {
+    pub r: Vec<BigUint>,
+    pub s: BigUint,
+}
+
+impl VectorZKP {
+    pub fn from_zkp(zkp: &ZKP, extra_generators: &[BigUint]) -> Self {
+        let mut generators = vec![zkp.alpha.clone(), zkp.beta.clone()];
+        generators.extend_from_slice(extra_generators);
+
+        VectorZKP {
+            p: zkp.p.clone(),
+            q: zkp.q.clone(),
+            generators,
+        }
+    }
+
+    // output => [g_1^e mod p, ..., g_n^e mod p]
+    pub fn exponetiate(&self, exponent: &BigUint) -> Vec<BigUint> {
+        let group = self.group();
+        self.generators
+            .iter()
+            .map(|g| group.pow(g, exponent))
+            .collect()
+    }
+
+    // output => s = k - c * x mod q
+    pub fn solve(&self, k: &BigUint, c: &BigUint, x: &BigUint) -> BigUint {
+        self.group().solve(k, c, x)
+    }
+
+    // cond_i: r_i = g_i^s * y_i^c mod p
+    pub fn verify(&self, r: &[BigUint], y: &[BigUint], s: &BigUint, c: &BigUint) -> bool {
+        if r.len() != self.generators.len() || y.len() != self.generators.len() {
+            return false;
+        }
+
+        let group = self.group();
+        self.generators
+            .iter()
+            .zip(r.iter().zip(y))
+            .all(|(g, (r_i, y_i))| *r_i == group.pow2(g, s, y_i, c))
+    }
+
+    // c = H(g_1..g_n, y_1..y_n, r_1..r_n) mod q
+    pub fn challenge(&self, y: &[BigUint], r: &[BigUint]) -> BigUint {
+        let values: Vec<&BigUint> = self.generators.iter().chain(y).chain(r).collect();
+        self.group().hash_to_scalar(CHALLENGE_LABEL, &values)
+    }
+
+    pub fn prove(&self, x: &BigUint, y: &[BigUint]) -> VectorProof {
+        let k = ZKP::generate_random_nonzero_below(&self.q);
+        let r = self.exponetiate(&k);
+        let c = self.challenge(y, &r);
+        let s = self.solve(&k, &c, x);
+
+        VectorProof { r, s }
+    }
+
+    pub fn verify_proof(&self, y: &[BigUint], proof: &VectorProof) -> bool {
+        let c = self.challenge(y, &proof.r);
+        self.verify(&proof.r, y, &proof.s, &c)
+    }
+
+    // the scalar arithmetic only depends on p and q
+    fn group(&self) -> ZKP {
+        ZKP::new(
+            self.p.clone(),
+            self.q.clone(),
+            self.generators[0].clone(),
+            self.generators[0].clone(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dhparams::hash_to_subgroup;
+    use crate::params::ParameterSet;
+
+    fn four_generators() -> VectorZKP {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let g3 = hash_to_subgroup(&zkp.p, &zkp.q, b"test/g3", &[]);
+        let g4 = hash_to_subgroup(&zkp.p, &zkp.q, b"test/g4", &[]);
+        VectorZKP::from_zkp(&zkp, &[g3, g4])
+    }
+
+    #[test]
+    fn test_interactive_four_generators() {
+        let vzkp = four_generators();
+        assert_eq!(vzkp.generators.len(), 4);
+
+        let x = ZKP::generate_random_below(&vzkp.q);
+        let k = ZKP::generate_random_below(&vzkp.q);
+        let c = ZKP::generate_random_below(&vzkp.q);
+
+        let y = vzkp.exponetiate(&x);
+        let r = vzkp.exponetiate(&k);
+        let s = vzkp.solve(&k, &c, &x);
+        assert!(vzkp.verify(&r, &y, &s, &c));
+
+        // Fake Secret
+        let s_fake = vzkp.solve(&k, &c, &(&x + 1u32));
+        assert!(!vzkp.verify(&r, &y, &s_fake, &c));
+    }
+
+    #[test]
+    fn test_non_interactive_rejects_mixed_logs() {
+        let vzkp = four_generators();
+        let x = ZKP::generate_random_below(&vzkp.q);
+        let mut y = vzkp.exponetiate(&x);
+
+        let proof = vzkp.prove(&x, &y);
+        assert!(vzkp.verify_proof(&y, &proof));
+        assert!(!vzkp.verify_proof(&y[..3], &proof));
+
+        // y_3 with a different exponent
+        y[2] = ZKP::exponetiate(&vzkp.generators[2], &(&x + 1u32), &vzkp.p);
+        let proof = vzkp.prove(&x, &y);
+        assert!(!vzkp.verify_proof(&y, &proof));
+    }
+}
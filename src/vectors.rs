@@ -0,0 +1,25 @@
+use std::env;
+
+use zkp_chaum_padersen::params::ParameterSet;
+use zkp_chaum_padersen::testvectors::TestVectors;
+
+// usage: vectors [seed] [parameter-set-id ...]
+// Prints the test vectors for the seed (its UTF-8 bytes) as JSON, for every
+// parameter set unless some are given.
+fn main() {
+    let mut args = env::args().skip(1);
+    let seed = args
+        .next()
+        .unwrap_or_else(|| "zkp-chaum-pedersen".to_string());
+    let mut parameter_sets: Vec<ParameterSet> = args
+        .map(|id| id.parse().expect("unknown parameter set"))
+        .collect();
+    if parameter_sets.is_empty() {
+        parameter_sets = ParameterSet::ALL.to_vec();
+    }
+
+    println!(
+        "{}",
+        TestVectors::generate(seed.as_bytes(), &parameter_sets).to_json()
+    );
+}
@@ -0,0 +1,1434 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+use crate::audit::{AuditEvent, AuditRecord, AuditSink};
+use crate::cache::KeyCache;
+use crate::context::ContextDigest;
+use crate::migration::MigrationRequest;
+use crate::proof::PublicKey;
+use crate::ratelimit::RateLimiter;
+use crate::store::{InMemoryUserStore, UserStatus, UserStore};
+use crate::{params::ParameterSet, stats, Error, ZKP};
+
+pub use crate::store::UserInfo;
+
+// Id under which `Verifier::new` stores the group it was built with.
+pub const DEFAULT_PARAMETER_SET: &str = "default";
+
+// How long a challenge waits for its answer.
+pub const DEFAULT_CHALLENGE_TTL: Duration = Duration::from_secs(5 * 60);
+
+// How long a commitment is remembered to catch a reused nonce.
+pub const DEFAULT_REPLAY_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone)]
+pub(crate) struct Challenge {
+    pub(crate) user_name: String,
+    r1: BigUint,
+    r2: BigUint,
+    c: BigUint,
+    pub(crate) purpose: Purpose,
+    pub(crate) issued: Instant,
+}
+
+// What a correct answer to a challenge grants.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Purpose {
+    // a session
+    Login,
+    // the public key to install in place of the current one
+    Rotation(PublicKey),
+    // H(context) of the action it was bound to, see
+    // `Verifier::create_action_challenge`
+    Action(ContextDigest),
+    // new_key in place of the current one, proven with the enrolled
+    // recovery_key instead of the current key, see `crate::recovery`
+    Recovery {
+        recovery_key: PublicKey,
+        new_key: PublicKey,
+    },
+}
+
+// A login challenge kept by the caller rather than by the verifier, for
+// exchanges that live in one connection (the streaming rpc): nothing stays
+// behind in the verifier if the prover never answers.
+#[derive(Debug, Clone)]
+pub struct DetachedChallenge {
+    pub auth_id: String,
+    challenge: Challenge,
+}
+
+impl DetachedChallenge {
+    pub fn c(&self) -> &BigUint {
+        &self.challenge.c
+    }
+}
+
+// Server side of the protocol: keeps the registered public keys, the
+// challenges waiting for an answer and the sessions already granted.
+// Every user is verified with the parameter set they registered under, so
+// users on legacy and current groups can share one verifier.
+#[derive(Debug)]
+pub struct Verifier<S = InMemoryUserStore> {
+    // parameter set id => group parameters
+    parameter_sets: HashMap<String, ZKP>,
+    default_parameter_set: String,
+    pub(crate) users: S,
+    // auth_id => pending challenge
+    pub(crate) challenges: HashMap<String, Challenge>,
+    // (auth_id, issued at) in the order issued, expired ones are dropped
+    // from the front
+    challenge_queue: VecDeque<(String, Instant)>,
+    challenge_ttl: Duration,
+    // session_id => (user_name, opened at)
+    pub(crate) sessions: HashMap<String, (String, Instant)>,
+    // sessions never expire without one
+    session_ttl: Option<Duration>,
+    // open sessions per user, the oldest ends when a login goes over
+    max_sessions_per_user: Option<usize>,
+    // challenges issued per user
+    rate_limiter: RateLimiter,
+    // H(user_name, r1, r2) of the commitments accepted lately, a prover
+    // reusing one is reusing its nonce
+    pub(crate) seen_commitments: SeenCommitments,
+    // users whose stored key already passed the subgroup check
+    pub(crate) key_cache: KeyCache,
+    audit_sinks: AuditSinks,
+}
+
+#[derive(Default)]
+struct AuditSinks(Vec<Box<dyn AuditSink + Send>>);
+
+// Fingerprints seen within the window, with the order they came in so the
+// old ones can be dropped without a scan.
+#[derive(Debug)]
+pub(crate) struct SeenCommitments {
+    seen: HashSet<[u8; 32]>,
+    order: VecDeque<([u8; 32], Instant)>,
+    window: Duration,
+}
+
+impl Default for SeenCommitments {
+    fn default() -> Self {
+        SeenCommitments {
+            seen: HashSet::new(),
+            order: VecDeque::new(),
+            window: DEFAULT_REPLAY_WINDOW,
+        }
+    }
+}
+
+impl SeenCommitments {
+    // false if the fingerprint was already seen within the window
+    pub(crate) fn insert(&mut self, fingerprint: [u8; 32]) -> bool {
+        while let Some((old, _)) = self
+            .order
+            .front()
+            .filter(|(_, seen_at)| seen_at.elapsed() >= self.window)
+        {
+            self.seen.remove(old);
+            self.order.pop_front();
+        }
+        if !self.seen.insert(fingerprint) {
+            return false;
+        }
+        self.order.push_back((fingerprint, Instant::now()));
+        true
+    }
+}
+
+impl std::fmt::Debug for AuditSinks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "AuditSinks({})", self.0.len())
+    }
+}
+
+impl Verifier<InMemoryUserStore> {
+    pub fn new(zkp: ZKP) -> Self {
+        let mut parameter_sets = HashMap::new();
+        parameter_sets.insert(DEFAULT_PARAMETER_SET.to_string(), zkp);
+
+        Verifier {
+            parameter_sets,
+            default_parameter_set: DEFAULT_PARAMETER_SET.to_string(),
+            users: InMemoryUserStore::new(),
+            challenges: HashMap::new(),
+            challenge_queue: VecDeque::new(),
+            challenge_ttl: DEFAULT_CHALLENGE_TTL,
+            sessions: HashMap::new(),
+            session_ttl: None,
+            max_sessions_per_user: None,
+            rate_limiter: RateLimiter::default(),
+            seen_commitments: SeenCommitments::default(),
+            key_cache: KeyCache::default(),
+            audit_sinks: AuditSinks::default(),
+        }
+    }
+
+    // New registrations without an explicit parameter set land on `default`,
+    // users can still register under any of `accepted`.
+    pub fn with_parameter_sets(default: ParameterSet, accepted: &[ParameterSet]) -> Self {
+        let mut parameter_sets = HashMap::new();
+        for set in accepted.iter().chain(std::iter::once(&default)) {
+            parameter_sets.insert(set.id().to_string(), set.zkp());
+        }
+
+        Verifier {
+            parameter_sets,
+            default_parameter_set: default.id().to_string(),
+            users: InMemoryUserStore::new(),
+            challenges: HashMap::new(),
+            challenge_queue: VecDeque::new(),
+            challenge_ttl: DEFAULT_CHALLENGE_TTL,
+            sessions: HashMap::new(),
+            session_ttl: None,
+            max_sessions_per_user: None,
+            rate_limiter: RateLimiter::default(),
+            seen_commitments: SeenCommitments::default(),
+            key_cache: KeyCache::default(),
+            audit_sinks: AuditSinks::default(),
+        }
+    }
+}
+
+impl<S: UserStore> Verifier<S> {
+    // Moves the verifier onto another user store, users registered so far
+    // are not copied.
+    pub fn with_store<T: UserStore>(self, store: T) -> Verifier<T> {
+        Verifier {
+            parameter_sets: self.parameter_sets,
+            default_parameter_set: self.default_parameter_set,
+            users: store,
+            challenges: self.challenges,
+            challenge_queue: self.challenge_queue,
+            challenge_ttl: self.challenge_ttl,
+            sessions: self.sessions,
+            session_ttl: self.session_ttl,
+            max_sessions_per_user: self.max_sessions_per_user,
+            rate_limiter: self.rate_limiter,
+            seen_commitments: self.seen_commitments,
+            key_cache: KeyCache::default(),
+            audit_sinks: self.audit_sinks,
+        }
+    }
+
+    pub fn store(&self) -> &S {
+        &self.users
+    }
+
+    // Every registration, challenge and verification is recorded to all
+    // sinks. A failing sink doesn't stop the protocol.
+    pub fn add_audit_sink(&mut self, sink: impl AuditSink + Send + 'static) {
+        self.audit_sinks.0.push(Box::new(sink));
+    }
+
+    // Stored keys are checked to be in the subgroup before every proof check,
+    // unless the cache says this key already was.
+    pub fn set_key_cache(&mut self, cache: KeyCache) {
+        self.key_cache = cache;
+    }
+
+    pub fn key_cache(&self) -> &KeyCache {
+        &self.key_cache
+    }
+
+    // Sessions are no longer recognized `ttl` after they were opened.
+    pub fn set_session_ttl(&mut self, ttl: Option<Duration>) {
+        self.session_ttl = ttl;
+    }
+
+    // Challenges not answered within `ttl` are dropped, an answer after that
+    // fails with `Error::ChallengeNotFound`.
+    pub fn set_challenge_ttl(&mut self, ttl: Duration) {
+        self.challenge_ttl = ttl;
+    }
+
+    // A commitment seen again within `window` is refused as replayed. A
+    // longer window catches more reused nonces and keeps more fingerprints,
+    // one per challenge issued in it.
+    pub fn set_replay_window(&mut self, window: Duration) {
+        self.seen_commitments.window = window;
+    }
+
+    // A login over the limit ends the user's oldest session, see
+    // `crate::sessions`. Some(0) is refused, it would leave every login
+    // without a session.
+    pub fn set_max_sessions_per_user(&mut self, max: Option<usize>) -> Result<(), Error> {
+        if max == Some(0) {
+            return Err(Error::InvalidParameters(
+                "at least one session per user has to be allowed".to_string(),
+            ));
+        }
+        self.max_sessions_per_user = max;
+        Ok(())
+    }
+
+    // Challenges asked for over the limit fail with `Error::RateLimited`.
+    pub fn set_rate_limiter(&mut self, rate_limiter: RateLimiter) {
+        self.rate_limiter = rate_limiter;
+    }
+
+    pub fn add_parameter_set(&mut self, id: &str, zkp: ZKP) {
+        self.parameter_sets.insert(id.to_string(), zkp);
+    }
+
+    pub fn default_parameter_set(&self) -> &str {
+        &self.default_parameter_set
+    }
+
+    pub fn parameter_set_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.parameter_sets.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    pub fn parameter_set(&self, id: &str) -> Option<&ZKP> {
+        self.parameter_sets.get(id)
+    }
+
+    pub fn user(&self, user_name: &str) -> Option<UserInfo> {
+        self.users.get(user_name)
+    }
+
+    // users still registered under `parameter_set`, those left to migrate
+    // before it can be dropped
+    pub fn users_on(&self, parameter_set: &str) -> Vec<String> {
+        self.users
+            .user_names()
+            .into_iter()
+            .filter(|user_name| {
+                self.users
+                    .get(user_name)
+                    .is_some_and(|user_info| user_info.parameter_set == parameter_set)
+            })
+            .collect()
+    }
+
+    pub fn register(&mut self, user_name: &str, y1: BigUint, y2: BigUint) -> Result<(), Error> {
+        let default = self.default_parameter_set.clone();
+        self.register_with_parameter_set(user_name, &default, y1, y2)
+    }
+
+    // A name is registered once: anyone may call this, so it never replaces
+    // a key. Keys change through rotation or recovery, which prove the
+    // current or an enrolled key; `delete_user` frees the name.
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(user = %user_name, parameter_set = %parameter_set))
+    )]
+    pub fn register_with_parameter_set(
+        &mut self,
+        user_name: &str,
+        parameter_set: &str,
+        y1: BigUint,
+        y2: BigUint,
+    ) -> Result<(), Error> {
+        let rejected = |error: Error| AuditEvent::RegistrationRejected {
+            user: user_name.to_string(),
+            parameter_set: parameter_set.to_string(),
+            reason: error.to_string(),
+        };
+        let zkp = match self.parameter_sets.get(parameter_set) {
+            Some(zkp) => zkp,
+            None => {
+                trace_warn!("registration with unknown parameter set");
+                let error = Error::UnknownParameterSet(parameter_set.to_string());
+                self.audit(rejected(error.clone()));
+                return Err(error);
+            }
+        };
+        if self.users.get(user_name).is_some() {
+            trace_warn!("registration for a name already taken");
+            let error = Error::AlreadyRegistered(user_name.to_string());
+            self.audit(rejected(error.clone()));
+            return Err(error);
+        }
+        if !zkp.is_subgroup_element(&y1) || !zkp.is_subgroup_element(&y2) {
+            trace_warn!("registration with invalid public key");
+            self.audit(rejected(Error::InvalidPublicKey));
+            return Err(Error::InvalidPublicKey);
+        }
+
+        let user_info = UserInfo {
+            user_name: user_name.to_string(),
+            parameter_set: parameter_set.to_string(),
+            y1,
+            y2,
+        };
+        if let Err(error) = self.users.insert(user_info.clone()) {
+            trace_warn!("user store write failed");
+            self.audit(rejected(error.clone()));
+            return Err(error);
+        }
+        self.key_cache.insert(&user_info);
+        stats::record_registration();
+        trace_info!("user registered");
+        self.audit(AuditEvent::Registered {
+            user: user_name.to_string(),
+            parameter_set: parameter_set.to_string(),
+        });
+
+        Ok(())
+    }
+
+    // output => (auth_id, c)
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(user = %user_name, auth_id))
+    )]
+    pub fn create_challenge(
+        &mut self,
+        user_name: &str,
+        r1: BigUint,
+        r2: BigUint,
+    ) -> Result<(String, BigUint), Error> {
+        self.issue_challenge(user_name, r1, r2, Purpose::Login, None)
+    }
+
+    // Same round as a login, except that a correct answer replaces the
+    // user's public key with (new_y1, new_y2) instead of opening a session.
+    // output => (auth_id, c)
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(user = %user_name, auth_id))
+    )]
+    pub fn create_rotation_challenge(
+        &mut self,
+        user_name: &str,
+        r1: BigUint,
+        r2: BigUint,
+        new_y1: BigUint,
+        new_y2: BigUint,
+    ) -> Result<(String, BigUint), Error> {
+        let new_key = PublicKey {
+            y1: new_y1,
+            y2: new_y2,
+        };
+        self.issue_rotation_challenge(user_name, r1, r2, new_key, None)
+    }
+
+    pub(crate) fn issue_rotation_challenge(
+        &mut self,
+        user_name: &str,
+        r1: BigUint,
+        r2: BigUint,
+        new_key: PublicKey,
+        c: Option<BigUint>,
+    ) -> Result<(String, BigUint), Error> {
+        if let Some(user_info) = self.users.get(user_name) {
+            let zkp = self.zkp_for(&user_info)?;
+            if !zkp.is_subgroup_element(&new_key.y1) || !zkp.is_subgroup_element(&new_key.y2) {
+                trace_warn!("rotation to an invalid public key");
+                return Err(Error::InvalidPublicKey);
+            }
+        }
+
+        self.issue_challenge(user_name, r1, r2, Purpose::Rotation(new_key), c)
+    }
+
+    // Challenge whose answer authorizes exactly one action instead of opening
+    // a session. c is derived from context and the commitment rather than
+    // drawn, so the prover can check which action it is approving, and the
+    // answer is only accepted by `verify_action` for the same context.
+    // output => (auth_id, c)
+    pub fn create_action_challenge(
+        &mut self,
+        user_name: &str,
+        r1: BigUint,
+        r2: BigUint,
+        context: &[u8],
+    ) -> Result<(String, BigUint), Error> {
+        self.create_action_challenge_digest(user_name, r1, r2, &ContextDigest::of(context))
+    }
+
+    // for a context hashed beforehand, see `crate::context`
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(user = %user_name, auth_id))
+    )]
+    pub fn create_action_challenge_digest(
+        &mut self,
+        user_name: &str,
+        r1: BigUint,
+        r2: BigUint,
+        digest: &ContextDigest,
+    ) -> Result<(String, BigUint), Error> {
+        let c = self.users.get(user_name).and_then(|user_info| {
+            let zkp = self.zkp_for(&user_info).ok()?;
+            let public_key = PublicKey {
+                y1: user_info.y1,
+                y2: user_info.y2,
+            };
+            Some(zkp.action_challenge_digest(&public_key, digest, &r1, &r2))
+        });
+
+        self.issue_challenge(user_name, r1, r2, Purpose::Action(*digest), c)
+    }
+
+    // `c` is only given when replaying a recorded transcript or for action
+    // challenges, the verifier draws it otherwise
+    pub(crate) fn issue_challenge(
+        &mut self,
+        user_name: &str,
+        r1: BigUint,
+        r2: BigUint,
+        purpose: Purpose,
+        c: Option<BigUint>,
+    ) -> Result<(String, BigUint), Error> {
+        let (auth_id, challenge) = self.new_challenge(user_name, r1, r2, purpose, c)?;
+        let c = challenge.c.clone();
+        self.drop_expired_challenges();
+        self.challenge_queue
+            .push_back((auth_id.clone(), challenge.issued));
+        self.challenges.insert(auth_id.clone(), challenge);
+
+        Ok((auth_id, c))
+    }
+
+    fn drop_expired_challenges(&mut self) {
+        while let Some((auth_id, _)) = self
+            .challenge_queue
+            .front()
+            .filter(|(_, issued)| issued.elapsed() >= self.challenge_ttl)
+        {
+            self.challenges.remove(auth_id);
+            self.challenge_queue.pop_front();
+        }
+    }
+
+    // Same as `create_challenge`, except the challenge is handed back to be
+    // answered with `verify_detached` instead of being stored.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(user = %user_name, auth_id))
+    )]
+    pub fn create_detached_challenge(
+        &mut self,
+        user_name: &str,
+        r1: BigUint,
+        r2: BigUint,
+    ) -> Result<DetachedChallenge, Error> {
+        let (auth_id, challenge) = self.new_challenge(user_name, r1, r2, Purpose::Login, None)?;
+        Ok(DetachedChallenge { auth_id, challenge })
+    }
+
+    fn new_challenge(
+        &mut self,
+        user_name: &str,
+        r1: BigUint,
+        r2: BigUint,
+        purpose: Purpose,
+        c: Option<BigUint>,
+    ) -> Result<(String, Challenge), Error> {
+        let rejected = |error: &Error| AuditEvent::ChallengeRejected {
+            user: user_name.to_string(),
+            reason: error.to_string(),
+        };
+        let user_info = match self.users.get(user_name) {
+            Some(user_info) => user_info,
+            None => {
+                trace_warn!("challenge requested for unknown user");
+                let error = Error::UserNotFound(user_name.to_string());
+                self.audit(rejected(&error));
+                return Err(error);
+            }
+        };
+        let q = self.zkp_for(&user_info)?.q.clone();
+        if let Err(error) = self.check_status(user_name, &purpose) {
+            self.audit(rejected(&error));
+            return Err(error);
+        }
+
+        if !self.rate_limiter.allow(user_name) {
+            trace_warn!("challenge rate limit reached");
+            self.audit(rejected(&Error::RateLimited));
+            return Err(Error::RateLimited);
+        }
+
+        let fingerprint = commitment_fingerprint(user_name, &r1, &r2);
+        if !self.seen_commitments.insert(fingerprint) {
+            trace_warn!("commitment replayed");
+            self.audit(rejected(&Error::ReplayedCommitment));
+            return Err(Error::ReplayedCommitment);
+        }
+
+        let c = c.unwrap_or_else(|| ZKP::generate_random_below(&q));
+        let auth_id = ZKP::generate_random_string(12);
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("auth_id", auth_id.as_str());
+
+        self.audit(AuditEvent::ChallengeIssued {
+            user: user_name.to_string(),
+            parameter_set: user_info.parameter_set,
+            auth_id: auth_id.clone(),
+            rotation: matches!(purpose, Purpose::Rotation(_)),
+        });
+        stats::record_challenge();
+        trace_info!("challenge issued");
+
+        Ok((
+            auth_id,
+            Challenge {
+                user_name: user_name.to_string(),
+                r1,
+                r2,
+                c,
+                purpose,
+                issued: Instant::now(),
+            },
+        ))
+    }
+
+    // The challenge is consumed whatever the outcome, so every auth_id can be
+    // answered only once.
+    // output => session_id
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(auth_id = %auth_id, user))
+    )]
+    pub fn verify(&mut self, auth_id: &str, s: &BigUint) -> Result<String, Error> {
+        let (challenge, _) = self.check_answer(auth_id, s, |purpose| *purpose == Purpose::Login)?;
+        Ok(self.open_session(&challenge.user_name))
+    }
+
+    // output => session_id
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(auth_id = %detached.auth_id, user))
+    )]
+    pub fn verify_detached(
+        &mut self,
+        detached: DetachedChallenge,
+        s: &BigUint,
+    ) -> Result<String, Error> {
+        let (challenge, _) = self.check_challenge(&detached.auth_id, detached.challenge, s)?;
+        Ok(self.open_session(&challenge.user_name))
+    }
+
+    fn open_session(&mut self, user_name: &str) -> String {
+        if let Some(ttl) = self.session_ttl {
+            self.sessions
+                .retain(|_, (_, opened)| opened.elapsed() < ttl);
+        }
+        if let Some(max) = self.max_sessions_per_user {
+            self.keep_newest_sessions(user_name, max.saturating_sub(1));
+        }
+        let session_id = ZKP::generate_random_string(12);
+        self.sessions
+            .insert(session_id.clone(), (user_name.to_string(), Instant::now()));
+        trace_info!(outcome = "accepted", "authentication succeeded");
+
+        session_id
+    }
+
+    // Installs the new public key of a rotation challenge and ends the
+    // sessions opened with the old one.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(auth_id = %auth_id, user))
+    )]
+    pub fn verify_rotation(&mut self, auth_id: &str, s: &BigUint) -> Result<(), Error> {
+        let (challenge, user_info) = self.check_answer(auth_id, s, |purpose| {
+            matches!(purpose, Purpose::Rotation(_))
+        })?;
+        let new_key = match challenge.purpose {
+            Purpose::Rotation(new_key) => new_key,
+            _ => unreachable!("checked by check_answer"),
+        };
+
+        let rotated = UserInfo {
+            y1: new_key.y1,
+            y2: new_key.y2,
+            ..user_info.clone()
+        };
+        // the record changed since the answer was checked, so the proof was
+        // made for a key that is gone
+        if !self.users.compare_and_swap(&user_info, rotated.clone())? {
+            trace_warn!("user record changed during rotation");
+            return Err(Error::InvalidProof);
+        }
+        self.key_cache.insert(&rotated);
+        self.sessions
+            .retain(|_, (user_name, _)| *user_name != challenge.user_name);
+        self.clear_rotation_required(&challenge.user_name)?;
+        trace_info!(outcome = "accepted", "public key rotated");
+        self.audit(AuditEvent::KeyRotated {
+            user: challenge.user_name,
+            parameter_set: user_info.parameter_set,
+        });
+
+        Ok(())
+    }
+
+    // Re-registers the user under `request.to_set` if both halves verify,
+    // the old record is kept by the store as superseded. Like a rotation,
+    // sessions opened with the old key end.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(user = %request.user_name, to = %request.to_set))
+    )]
+    pub fn migrate(&mut self, request: &MigrationRequest) -> Result<(), Error> {
+        let user_info = self
+            .users
+            .get(&request.user_name)
+            .ok_or_else(|| Error::UserNotFound(request.user_name.clone()))?;
+        if user_info.parameter_set != request.from_set {
+            trace_warn!("migration from a parameter set the user is not on");
+            return Err(Error::InvalidParameters(format!(
+                "user is registered under {}",
+                user_info.parameter_set
+            )));
+        }
+        // it replaces the key, so allowed while a rotation is required
+        self.check_status(
+            &request.user_name,
+            &Purpose::Rotation(request.new_key.clone()),
+        )?;
+        self.check_stored_key(&user_info)?;
+        let old_zkp = self.zkp_for(&user_info)?;
+        let new_zkp = self
+            .parameter_sets
+            .get(&request.to_set)
+            .ok_or_else(|| Error::UnknownParameterSet(request.to_set.clone()))?;
+        let new_key = &request.new_key;
+        if !new_zkp.is_subgroup_element(&new_key.y1) || !new_zkp.is_subgroup_element(&new_key.y2) {
+            trace_warn!("migration to an invalid public key");
+            return Err(Error::InvalidPublicKey);
+        }
+
+        let old_key = PublicKey {
+            y1: user_info.y1.clone(),
+            y2: user_info.y2.clone(),
+        };
+        if !request.verify(old_zkp, &old_key, new_zkp) {
+            trace_warn!(outcome = "rejected", "migration proof failed");
+            return Err(Error::InvalidProof);
+        }
+        let fingerprint = commitment_fingerprint(
+            &request.user_name,
+            &request.old_proof.r1,
+            &request.old_proof.r2,
+        );
+        if !self.seen_commitments.insert(fingerprint) {
+            trace_warn!("commitment replayed");
+            return Err(Error::ReplayedCommitment);
+        }
+
+        let migrated = UserInfo {
+            user_name: request.user_name.clone(),
+            parameter_set: request.to_set.clone(),
+            y1: new_key.y1.clone(),
+            y2: new_key.y2.clone(),
+        };
+        if !self.users.supersede(&user_info, migrated.clone())? {
+            trace_warn!("user record changed during migration");
+            return Err(Error::InvalidProof);
+        }
+        self.key_cache.insert(&migrated);
+        self.sessions
+            .retain(|_, (user_name, _)| *user_name != request.user_name);
+        self.clear_rotation_required(&request.user_name)?;
+        trace_info!(outcome = "accepted", "user migrated");
+        self.audit(AuditEvent::Migrated {
+            user: request.user_name.clone(),
+            from: request.from_set.clone(),
+            to: request.to_set.clone(),
+        });
+
+        Ok(())
+    }
+
+    // Accepts the answer to an action challenge only for the context it was
+    // issued for, no session is opened.
+    // output => the user who approved it
+    pub fn verify_action(
+        &mut self,
+        auth_id: &str,
+        s: &BigUint,
+        context: &[u8],
+    ) -> Result<String, Error> {
+        self.verify_action_digest(auth_id, s, &ContextDigest::of(context))
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(auth_id = %auth_id, user))
+    )]
+    pub fn verify_action_digest(
+        &mut self,
+        auth_id: &str,
+        s: &BigUint,
+        digest: &ContextDigest,
+    ) -> Result<String, Error> {
+        let (challenge, _) =
+            self.check_answer(auth_id, s, |purpose| matches!(purpose, Purpose::Action(_)))?;
+        if challenge.purpose != Purpose::Action(*digest) {
+            trace_warn!("answer presented for another action");
+            return Err(Error::ActionMismatch);
+        }
+        trace_info!(outcome = "accepted", "action authorized");
+
+        Ok(challenge.user_name)
+    }
+
+    // Consumes the challenge and checks s against the current public key,
+    // or the recovery key of a recovery challenge. Challenges issued for
+    // another kind of purpose are unknown here.
+    pub(crate) fn check_answer(
+        &mut self,
+        auth_id: &str,
+        s: &BigUint,
+        kind: impl Fn(&Purpose) -> bool,
+    ) -> Result<(Challenge, UserInfo), Error> {
+        let challenge = match self.challenges.remove(auth_id) {
+            Some(challenge) if kind(&challenge.purpose) => challenge,
+            _ => {
+                trace_warn!("unknown auth_id");
+                self.audit(AuditEvent::UnknownChallenge {
+                    auth_id: auth_id.to_string(),
+                });
+                return Err(Error::ChallengeNotFound(auth_id.to_string()));
+            }
+        };
+        self.check_challenge(auth_id, challenge, s)
+    }
+
+    fn check_challenge(
+        &mut self,
+        auth_id: &str,
+        challenge: Challenge,
+        s: &BigUint,
+    ) -> Result<(Challenge, UserInfo), Error> {
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("user", challenge.user_name.as_str());
+
+        if challenge.issued.elapsed() >= self.challenge_ttl {
+            trace_warn!("challenge expired");
+            return Err(Error::ChallengeNotFound(auth_id.to_string()));
+        }
+        let user_info = self
+            .users
+            .get(&challenge.user_name)
+            .ok_or_else(|| Error::UserNotFound(challenge.user_name.clone()))?;
+        self.check_status(&challenge.user_name, &challenge.purpose)?;
+        self.check_stored_key(&user_info)?;
+
+        let zkp = self.zkp_for(&user_info)?;
+
+        let started = Instant::now();
+        let (y1, y2) = match &challenge.purpose {
+            Purpose::Recovery { recovery_key, .. } => (&recovery_key.y1, &recovery_key.y2),
+            _ => (&user_info.y1, &user_info.y2),
+        };
+        let verification = zkp.verify(&challenge.r1, &challenge.r2, y1, y2, s, &challenge.c);
+        stats::record_verification(verification, started.elapsed());
+        trace_debug!(verification, "chaum-pedersen check done");
+        self.audit(AuditEvent::Verified {
+            user: challenge.user_name.clone(),
+            parameter_set: user_info.parameter_set.clone(),
+            auth_id: auth_id.to_string(),
+            passed: verification,
+        });
+
+        if !verification {
+            trace_warn!(outcome = "rejected", "authentication failed");
+            return Err(Error::InvalidProof);
+        }
+
+        Ok((challenge, user_info))
+    }
+
+    // user a pending challenge was issued to
+    pub fn challenge_user(&self, auth_id: &str) -> Option<&str> {
+        self.challenges
+            .get(auth_id)
+            .map(|challenge| challenge.user_name.as_str())
+    }
+
+    pub fn session_user(&self, session_id: &str) -> Option<&str> {
+        let (user_name, opened) = self.sessions.get(session_id)?;
+        if self.session_ttl.is_some_and(|ttl| opened.elapsed() >= ttl) {
+            return None;
+        }
+        Some(user_name)
+    }
+
+    // Disabled users get nothing, users asked to rotate only what replaces
+    // their key.
+    pub(crate) fn check_status(&self, user_name: &str, purpose: &Purpose) -> Result<(), Error> {
+        let status = self.users.status(user_name);
+        if status.disabled {
+            trace_warn!("user is disabled");
+            return Err(Error::UserDisabled(user_name.to_string()));
+        }
+        let rekeying = matches!(purpose, Purpose::Rotation(_) | Purpose::Recovery { .. });
+        if status.rotation_required && !rekeying {
+            trace_warn!("user has to rotate their key");
+            return Err(Error::RotationRequired(user_name.to_string()));
+        }
+        Ok(())
+    }
+
+    // a forced rotation is done once the key is replaced
+    pub(crate) fn clear_rotation_required(&mut self, user_name: &str) -> Result<(), Error> {
+        let status = self.users.status(user_name);
+        if status.rotation_required {
+            let status = UserStatus {
+                rotation_required: false,
+                ..status
+            };
+            self.users.set_status(user_name, status)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn audit(&mut self, event: AuditEvent) {
+        if self.audit_sinks.0.is_empty() {
+            return;
+        }
+        let record = AuditRecord::now(event);
+        for sink in self.audit_sinks.0.iter_mut() {
+            if let Err(_error) = sink.record(&record) {
+                trace_warn!(error = %_error, "audit sink failed");
+            }
+        }
+    }
+
+    // Stored keys are checked again before use: the store may be shared,
+    // outlive the checks done at registration or hold records of a
+    // parameter set this verifier dropped.
+    pub(crate) fn check_stored_key(&mut self, user_info: &UserInfo) -> Result<(), Error> {
+        if self.key_cache.contains(user_info) {
+            return Ok(());
+        }
+        let zkp = self.zkp_for(user_info)?;
+        if !zkp.is_subgroup_element(&user_info.y1) || !zkp.is_subgroup_element(&user_info.y2) {
+            trace_warn!("stored public key is not in the subgroup");
+            return Err(Error::InvalidPublicKey);
+        }
+        self.key_cache.insert(user_info);
+
+        Ok(())
+    }
+
+    fn zkp_for(&self, user_info: &UserInfo) -> Result<&ZKP, Error> {
+        self.parameter_sets
+            .get(&user_info.parameter_set)
+            .ok_or_else(|| Error::UnknownParameterSet(user_info.parameter_set.clone()))
+    }
+}
+
+fn commitment_fingerprint(user_name: &str, r1: &BigUint, r2: &BigUint) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update((user_name.len() as u32).to_be_bytes());
+    hasher.update(user_name.as_bytes());
+    for r in [r1, r2] {
+        let bytes = r.to_bytes_be();
+        hasher.update((bytes.len() as u32).to_be_bytes());
+        hasher.update(&bytes);
+    }
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::proof::Secret;
+
+    fn toy_zkp() -> ZKP {
+        ZKP::new(
+            BigUint::from(23u32),
+            BigUint::from(11u32),
+            BigUint::from(4u32),
+            BigUint::from(9u32),
+        )
+    }
+
+    #[test]
+    fn test_register_challenge_verify() {
+        let zkp = toy_zkp();
+        let mut verifier = Verifier::new(zkp.clone());
+
+        let x = BigUint::from(6u32);
+        let y1 = ZKP::exponetiate(&zkp.alpha, &x, &zkp.p);
+        let y2 = ZKP::exponetiate(&zkp.beta, &x, &zkp.p);
+        verifier.register("alice", y1, y2).unwrap();
+
+        let k = BigUint::from(7u32);
+        let r1 = ZKP::exponetiate(&zkp.alpha, &k, &zkp.p);
+        let r2 = ZKP::exponetiate(&zkp.beta, &k, &zkp.p);
+        let (auth_id, c) = verifier.create_challenge("alice", r1, r2).unwrap();
+
+        let s = zkp.solve(&k, &c, &x);
+        let session_id = verifier.verify(&auth_id, &s).unwrap();
+        assert_eq!(verifier.session_user(&session_id), Some("alice"));
+
+        // the challenge can't be answered twice
+        assert_eq!(
+            verifier.verify(&auth_id, &s),
+            Err(Error::ChallengeNotFound(auth_id))
+        );
+
+        // nor can the name be taken over by registering it again
+        let y1 = ZKP::exponetiate(&zkp.alpha, &BigUint::from(3u32), &zkp.p);
+        let y2 = ZKP::exponetiate(&zkp.beta, &BigUint::from(3u32), &zkp.p);
+        assert_eq!(
+            verifier.register("alice", y1.clone(), y2),
+            Err(Error::AlreadyRegistered("alice".to_string()))
+        );
+        assert_ne!(verifier.store().get("alice").unwrap().y1, y1);
+        assert_eq!(verifier.session_user(&session_id), Some("alice"));
+    }
+
+    #[test]
+    fn test_detached_challenge() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let mut verifier = Verifier::new(zkp.clone());
+        let x = BigUint::from(6u32);
+        let y1 = ZKP::exponetiate(&zkp.alpha, &x, &zkp.p);
+        let y2 = ZKP::exponetiate(&zkp.beta, &x, &zkp.p);
+        verifier.register("alice", y1, y2).unwrap();
+
+        let commit = |k: u32| {
+            let k = BigUint::from(k);
+            let r1 = ZKP::exponetiate(&zkp.alpha, &k, &zkp.p);
+            let r2 = ZKP::exponetiate(&zkp.beta, &k, &zkp.p);
+            (k, r1, r2)
+        };
+
+        let (k, r1, r2) = commit(7);
+        let detached = verifier.create_detached_challenge("alice", r1, r2).unwrap();
+        let s = zkp.solve(&k, detached.c(), &x);
+        // the verifier doesn't know about it
+        let auth_id = detached.auth_id.clone();
+        assert_eq!(
+            verifier.verify(&auth_id, &s),
+            Err(Error::ChallengeNotFound(auth_id))
+        );
+        let session_id = verifier.verify_detached(detached, &s).unwrap();
+        assert_eq!(verifier.session_user(&session_id), Some("alice"));
+
+        let (k, r1, r2) = commit(8);
+        let detached = verifier.create_detached_challenge("alice", r1, r2).unwrap();
+        let s = zkp.solve(&k, detached.c(), &BigUint::from(5u32));
+        assert_eq!(
+            verifier.verify_detached(detached, &s),
+            Err(Error::InvalidProof)
+        );
+    }
+
+    #[test]
+    fn test_stored_keys_are_checked() {
+        let zkp = toy_zkp();
+        let mut verifier = Verifier::new(zkp.clone());
+        let x = BigUint::from(6u32);
+        let y1 = ZKP::exponetiate(&zkp.alpha, &x, &zkp.p);
+        let y2 = ZKP::exponetiate(&zkp.beta, &x, &zkp.p);
+        verifier.register("alice", y1, y2).unwrap();
+        assert_eq!(verifier.key_cache().len(), 1);
+
+        // someone with access to the store swaps in a key of order 2
+        verifier
+            .users
+            .insert(UserInfo {
+                user_name: "alice".to_string(),
+                parameter_set: DEFAULT_PARAMETER_SET.to_string(),
+                y1: BigUint::from(22u32),
+                y2: BigUint::from(22u32),
+            })
+            .unwrap();
+        let k = BigUint::from(7u32);
+        let r1 = ZKP::exponetiate(&zkp.alpha, &k, &zkp.p);
+        let r2 = ZKP::exponetiate(&zkp.beta, &k, &zkp.p);
+        let (auth_id, c) = verifier.create_challenge("alice", r1, r2).unwrap();
+        assert_eq!(
+            verifier.verify(&auth_id, &zkp.solve(&k, &c, &x)),
+            Err(Error::InvalidPublicKey)
+        );
+    }
+
+    #[test]
+    fn test_unknown_user_and_bad_solution() {
+        let zkp = toy_zkp();
+        let mut verifier = Verifier::new(zkp.clone());
+
+        let one = BigUint::from(1u32);
+        assert_eq!(
+            verifier.create_challenge("bob", one.clone(), one.clone()),
+            Err(Error::UserNotFound("bob".to_string()))
+        );
+
+        let x = BigUint::from(6u32);
+        let y1 = ZKP::exponetiate(&zkp.alpha, &x, &zkp.p);
+        let y2 = ZKP::exponetiate(&zkp.beta, &x, &zkp.p);
+        verifier.register("bob", y1, y2).unwrap();
+
+        let k = BigUint::from(7u32);
+        let r1 = ZKP::exponetiate(&zkp.alpha, &k, &zkp.p);
+        let r2 = ZKP::exponetiate(&zkp.beta, &k, &zkp.p);
+        let (auth_id, c) = verifier.create_challenge("bob", r1, r2).unwrap();
+
+        // Fake Secret
+        let s_fake = zkp.solve(&k, &c, &BigUint::from(7u32));
+        if c != BigUint::from(0u32) {
+            assert_eq!(verifier.verify(&auth_id, &s_fake), Err(Error::InvalidProof));
+        }
+    }
+
+    #[test]
+    fn test_session_ttl_and_rate_limit() {
+        let set = ParameterSet::Rfc5114_1024_160;
+        let zkp = set.zkp();
+        let mut verifier = Verifier::with_parameter_sets(set, &[]);
+        verifier.set_rate_limiter(RateLimiter::new(2, Duration::from_secs(60)));
+        let secret = Secret::random(&zkp);
+        let public_key = zkp.public_key(&secret);
+        verifier
+            .register("alice", public_key.y1, public_key.y2)
+            .unwrap();
+
+        let login = |verifier: &mut Verifier| {
+            let k = ZKP::generate_random_below(&zkp.q);
+            let (r1, r2) = (zkp.pow(&zkp.alpha, &k), zkp.pow(&zkp.beta, &k));
+            let (auth_id, c) = verifier.create_challenge("alice", r1, r2)?;
+            verifier.verify(&auth_id, &zkp.solve(&k, &c, secret.x()))
+        };
+        let session_id = login(&mut verifier).unwrap();
+        assert_eq!(verifier.session_user(&session_id), Some("alice"));
+
+        verifier.set_session_ttl(Some(Duration::ZERO));
+        assert_eq!(verifier.session_user(&session_id), None);
+
+        assert!(login(&mut verifier).is_ok());
+        assert_eq!(login(&mut verifier), Err(Error::RateLimited));
+    }
+
+    #[test]
+    fn test_challenge_ttl_and_replay_window() {
+        let zkp = toy_zkp();
+        let mut verifier = Verifier::new(zkp.clone());
+        let x = BigUint::from(6u32);
+        let (y1, y2) = (zkp.pow(&zkp.alpha, &x), zkp.pow(&zkp.beta, &x));
+        verifier.register("alice", y1, y2).unwrap();
+        let k = BigUint::from(7u32);
+        let (r1, r2) = (zkp.pow(&zkp.alpha, &k), zkp.pow(&zkp.beta, &k));
+
+        verifier.set_challenge_ttl(Duration::ZERO);
+        let (auth_id, c) = verifier
+            .create_challenge("alice", r1.clone(), r2.clone())
+            .unwrap();
+        assert_eq!(
+            verifier.verify(&auth_id, &zkp.solve(&k, &c, &x)),
+            Err(Error::ChallengeNotFound(auth_id))
+        );
+
+        // the commitment is remembered past its challenge, within the window
+        assert_eq!(
+            verifier.create_challenge("alice", r1.clone(), r2.clone()),
+            Err(Error::ReplayedCommitment)
+        );
+        verifier.set_replay_window(Duration::ZERO);
+        verifier.create_challenge("alice", r1, r2).unwrap();
+
+        // expired challenges and fingerprints are dropped as new ones come
+        let k = BigUint::from(8u32);
+        let (r1, r2) = (zkp.pow(&zkp.alpha, &k), zkp.pow(&zkp.beta, &k));
+        verifier.create_challenge("alice", r1, r2).unwrap();
+        assert_eq!(verifier.challenges.len(), 1);
+        assert_eq!(verifier.seen_commitments.seen.len(), 1);
+    }
+
+    #[test]
+    fn test_users_on_different_parameter_sets() {
+        let mut verifier = Verifier::with_parameter_sets(
+            ParameterSet::Rfc3526_2048,
+            &[ParameterSet::Rfc5114_1024_160],
+        );
+
+        for (user_name, set) in [
+            ("legacy", ParameterSet::Rfc5114_1024_160),
+            ("new", ParameterSet::Rfc3526_2048),
+        ] {
+            let zkp = set.zkp();
+            let x = ZKP::generate_random_below(&zkp.q);
+            let y1 = ZKP::exponetiate(&zkp.alpha, &x, &zkp.p);
+            let y2 = ZKP::exponetiate(&zkp.beta, &x, &zkp.p);
+            verifier
+                .register_with_parameter_set(user_name, set.id(), y1, y2)
+                .unwrap();
+            assert_eq!(verifier.user(user_name).unwrap().parameter_set, set.id());
+
+            let k = ZKP::generate_random_below(&zkp.q);
+            let r1 = ZKP::exponetiate(&zkp.alpha, &k, &zkp.p);
+            let r2 = ZKP::exponetiate(&zkp.beta, &k, &zkp.p);
+            let (auth_id, c) = verifier.create_challenge(user_name, r1, r2).unwrap();
+
+            let s = zkp.solve(&k, &c, &x);
+            assert!(verifier.verify(&auth_id, &s).is_ok());
+        }
+
+        let one = BigUint::from(1u32);
+        assert_eq!(
+            verifier.register_with_parameter_set("eve", "rfc3526-3072", one.clone(), one),
+            Err(Error::UnknownParameterSet("rfc3526-3072".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_migration() {
+        let (from, to) = (ParameterSet::Rfc5114_1024_160, ParameterSet::Rfc3526_2048);
+        let (old_zkp, new_zkp) = (from.zkp(), to.zkp());
+        let mut verifier = Verifier::with_parameter_sets(to, &[from]);
+
+        let old_secret = Secret::random(&old_zkp);
+        let old_key = old_zkp.public_key(&old_secret);
+        verifier
+            .register_with_parameter_set("alice", from.id(), old_key.y1, old_key.y2)
+            .unwrap();
+        assert_eq!(verifier.users_on(from.id()), vec!["alice".to_string()]);
+
+        // a proof made with some other old secret is refused
+        let new_secret = Secret::random(&new_zkp);
+        let forged = MigrationRequest::prove(
+            "alice",
+            (from.id(), &old_zkp),
+            &Secret::random(&old_zkp),
+            (to.id(), &new_zkp),
+            &new_secret,
+        );
+        assert_eq!(verifier.migrate(&forged), Err(Error::InvalidProof));
+
+        let request = MigrationRequest::prove(
+            "alice",
+            (from.id(), &old_zkp),
+            &old_secret,
+            (to.id(), &new_zkp),
+            &new_secret,
+        );
+        verifier.migrate(&request).unwrap();
+        let user_info = verifier.user("alice").unwrap();
+        assert_eq!(user_info.parameter_set, to.id());
+        assert_eq!(user_info.y1, request.new_key.y1);
+        assert!(verifier.users_on(from.id()).is_empty());
+        let superseded = verifier.store().superseded("alice");
+        assert_eq!(superseded.len(), 1);
+        assert_eq!(superseded[0].parameter_set, from.id());
+
+        // done once, replaying it finds the user on the new set already
+        assert!(matches!(
+            verifier.migrate(&request),
+            Err(Error::InvalidParameters(_))
+        ));
+    }
+
+    #[test]
+    fn test_migration_checks_the_user_status() {
+        let (from, to) = (ParameterSet::Rfc5114_1024_160, ParameterSet::Rfc3526_2048);
+        let (old_zkp, new_zkp) = (from.zkp(), to.zkp());
+        let mut verifier = Verifier::with_parameter_sets(to, &[from]);
+        let old_secret = Secret::random(&old_zkp);
+        let old_key = old_zkp.public_key(&old_secret);
+        verifier
+            .register_with_parameter_set("alice", from.id(), old_key.y1, old_key.y2)
+            .unwrap();
+        let request = MigrationRequest::prove(
+            "alice",
+            (from.id(), &old_zkp),
+            &old_secret,
+            (to.id(), &new_zkp),
+            &Secret::random(&new_zkp),
+        );
+
+        verifier.disable_user("alice").unwrap();
+        assert_eq!(
+            verifier.migrate(&request),
+            Err(Error::UserDisabled("alice".to_string()))
+        );
+        verifier.enable_user("alice").unwrap();
+
+        // replacing the key is what a forced rotation asks for
+        verifier.force_key_rotation("alice").unwrap();
+        verifier.migrate(&request).unwrap();
+        assert!(!verifier.list_users()[0].status.rotation_required);
+    }
+
+    #[test]
+    fn test_key_rotation() {
+        // c = 0 would accept any secret in the toy group
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let mut verifier = Verifier::new(zkp.clone());
+        let (x, x_new) = (BigUint::from(6u32), BigUint::from(3u32));
+        let y = |x: &BigUint| {
+            (
+                ZKP::exponetiate(&zkp.alpha, x, &zkp.p),
+                ZKP::exponetiate(&zkp.beta, x, &zkp.p),
+            )
+        };
+        let (y1, y2) = y(&x);
+        verifier.register("alice", y1, y2).unwrap();
+
+        let login = |verifier: &mut Verifier, x: &BigUint, k: u32| {
+            let k = BigUint::from(k);
+            let (r1, r2) = y(&k);
+            let (auth_id, c) = verifier.create_challenge("alice", r1, r2).unwrap();
+            verifier.verify(&auth_id, &zkp.solve(&k, &c, x))
+        };
+        let session_id = login(&mut verifier, &x, 7).unwrap();
+
+        // a login challenge can't be answered as a rotation
+        let (r1, r2) = y(&BigUint::from(2u32));
+        let (auth_id, c) = verifier.create_challenge("alice", r1, r2).unwrap();
+        let s = zkp.solve(&BigUint::from(2u32), &c, &x);
+        assert_eq!(
+            verifier.verify_rotation(&auth_id, &s),
+            Err(Error::ChallengeNotFound(auth_id))
+        );
+
+        let (new_y1, new_y2) = y(&x_new);
+        let k = BigUint::from(5u32);
+        let (r1, r2) = y(&k);
+        let (auth_id, c) = verifier
+            .create_rotation_challenge("alice", r1, r2, new_y1.clone(), new_y2.clone())
+            .unwrap();
+        verifier
+            .verify_rotation(&auth_id, &zkp.solve(&k, &c, &x))
+            .unwrap();
+
+        let user_info = verifier.user("alice").unwrap();
+        assert_eq!((user_info.y1, user_info.y2), (new_y1, new_y2));
+        assert_eq!(verifier.session_user(&session_id), None);
+        assert!(login(&mut verifier, &x_new, 8).is_ok());
+        assert_eq!(login(&mut verifier, &x, 9), Err(Error::InvalidProof));
+    }
+
+    #[test]
+    fn test_action_challenge() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let mut verifier = Verifier::new(zkp.clone());
+        let x = BigUint::from(6u32);
+        let public_key = PublicKey {
+            y1: ZKP::exponetiate(&zkp.alpha, &x, &zkp.p),
+            y2: ZKP::exponetiate(&zkp.beta, &x, &zkp.p),
+        };
+        verifier
+            .register("alice", public_key.y1.clone(), public_key.y2.clone())
+            .unwrap();
+
+        let transfer = b"POST /transfer amount=100 to=bob nonce=1";
+        let answer = |verifier: &mut Verifier, k: u32| {
+            let k = BigUint::from(k);
+            let r1 = ZKP::exponetiate(&zkp.alpha, &k, &zkp.p);
+            let r2 = ZKP::exponetiate(&zkp.beta, &k, &zkp.p);
+            let (auth_id, c) = verifier
+                .create_action_challenge("alice", r1.clone(), r2.clone(), transfer)
+                .unwrap();
+            // the prover sees which action it approves
+            assert_eq!(c, zkp.action_challenge(&public_key, transfer, &r1, &r2));
+            (auth_id, zkp.solve(&k, &c, &x))
+        };
+
+        let (auth_id, s) = answer(&mut verifier, 7);
+        assert_eq!(
+            verifier.verify_action(&auth_id, &s, transfer),
+            Ok("alice".to_string())
+        );
+        assert_eq!(
+            verifier.verify_action(&auth_id, &s, transfer),
+            Err(Error::ChallengeNotFound(auth_id))
+        );
+
+        let (auth_id, s) = answer(&mut verifier, 8);
+        assert_eq!(
+            verifier.verify_action(&auth_id, &s, b"POST /transfer amount=9999 to=eve"),
+            Err(Error::ActionMismatch)
+        );
+
+        // and it doesn't open a session either
+        let (auth_id, s) = answer(&mut verifier, 9);
+        assert_eq!(
+            verifier.verify(&auth_id, &s),
+            Err(Error::ChallengeNotFound(auth_id))
+        );
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedSink(std::sync::Arc<std::sync::Mutex<Vec<AuditEvent>>>);
+
+    impl AuditSink for SharedSink {
+        fn record(&mut self, record: &AuditRecord) -> Result<(), Error> {
+            self.0.lock().unwrap().push(record.event.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_audit_events() {
+        let zkp = toy_zkp();
+        let mut verifier = Verifier::new(zkp.clone());
+        let sink = SharedSink::default();
+        verifier.add_audit_sink(sink.clone());
+
+        let one = BigUint::from(1u32);
+        assert!(verifier
+            .register_with_parameter_set("eve", "default", one.clone(), one.clone())
+            .is_err());
+        assert!(verifier.create_challenge("eve", one.clone(), one).is_err());
+
+        let x = BigUint::from(6u32);
+        verifier
+            .register(
+                "alice",
+                ZKP::exponetiate(&zkp.alpha, &x, &zkp.p),
+                ZKP::exponetiate(&zkp.beta, &x, &zkp.p),
+            )
+            .unwrap();
+        let k = BigUint::from(7u32);
+        let (auth_id, c) = verifier
+            .create_challenge(
+                "alice",
+                ZKP::exponetiate(&zkp.alpha, &k, &zkp.p),
+                ZKP::exponetiate(&zkp.beta, &k, &zkp.p),
+            )
+            .unwrap();
+        verifier.verify(&auth_id, &zkp.solve(&k, &c, &x)).unwrap();
+        assert!(verifier.verify(&auth_id, &BigUint::from(0u32)).is_err());
+
+        let user = |name: &str| name.to_string();
+        let default = DEFAULT_PARAMETER_SET.to_string();
+        assert_eq!(
+            *sink.0.lock().unwrap(),
+            vec![
+                AuditEvent::RegistrationRejected {
+                    user: user("eve"),
+                    parameter_set: default.clone(),
+                    reason: Error::InvalidPublicKey.to_string(),
+                },
+                AuditEvent::ChallengeRejected {
+                    user: user("eve"),
+                    reason: Error::UserNotFound(user("eve")).to_string(),
+                },
+                AuditEvent::Registered {
+                    user: user("alice"),
+                    parameter_set: default.clone(),
+                },
+                AuditEvent::ChallengeIssued {
+                    user: user("alice"),
+                    parameter_set: default.clone(),
+                    auth_id: auth_id.clone(),
+                    rotation: false,
+                },
+                AuditEvent::Verified {
+                    user: user("alice"),
+                    parameter_set: default,
+                    auth_id: auth_id.clone(),
+                    passed: true,
+                },
+                AuditEvent::UnknownChallenge { auth_id },
+            ]
+        );
+    }
+}
@@ -0,0 +1,292 @@
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+use crate::encoding::from_bytes_be_exact;
+use crate::modmath::mod_reduce;
+use crate::proof::{Proof, PublicKey, Secret};
+use crate::{Error, ZKP};
+
+// Serialized proofs for storage (audit evidence and the like). Every blob
+// starts with a magic and a format version, and the version pins both the
+// byte layout and the Fiat-Shamir rules the proof was made with, so a proof
+// stored today still verifies after the crate changes its rules.
+//
+//     magic    "ZKPP"
+//     version  u8
+//     V1       r1, r2, s, each as u32 length + minimal big-endian bytes;
+//              c hashed over minimal encodings
+//     V2       r1, r2 padded to the byte length of p, s to the byte length
+//              of q; c hashed over padded encodings
+//     V3       the V2 layout; c also binds the group (`ZKP::challenge`)
+//
+// V1 and V2 challenges cover only y1, y2, r1, r2 ("weak" Fiat-Shamir), which
+// lets anyone who can choose the group forge them. They still decode, but
+// `verify_versioned` rejects them unless the "unsafe-weak-fiat-shamir"
+// feature is on.
+const MAGIC: &[u8] = b"ZKPP";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ProofFormat {
+    V1,
+    V2,
+    V3,
+}
+
+impl ProofFormat {
+    pub const CURRENT: ProofFormat = ProofFormat::V3;
+
+    fn tag(&self) -> u8 {
+        match self {
+            ProofFormat::V1 => 1,
+            ProofFormat::V2 => 2,
+            ProofFormat::V3 => 3,
+        }
+    }
+
+    // the challenge doesn't bind the group
+    pub fn is_weak(&self) -> bool {
+        *self < ProofFormat::V3
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionedProof {
+    pub format: ProofFormat,
+    pub proof: Proof,
+}
+
+impl ZKP {
+    // Always writes the current format.
+    pub fn encode_proof(&self, proof: &Proof) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(ProofFormat::CURRENT.tag());
+        bytes.extend_from_slice(&self.element_to_bytes(&proof.r1));
+        bytes.extend_from_slice(&self.element_to_bytes(&proof.r2));
+        bytes.extend_from_slice(&self.scalar_to_bytes(&proof.s));
+        bytes
+    }
+
+    // Reads every format this crate ever wrote.
+    pub fn decode_proof(&self, bytes: &[u8]) -> Result<VersionedProof, Error> {
+        let body = bytes
+            .strip_prefix(MAGIC)
+            .ok_or_else(|| Error::InvalidEncoding("not a serialized proof".to_string()))?;
+        let (tag, body) = body
+            .split_first()
+            .ok_or_else(|| Error::InvalidEncoding("missing proof format".to_string()))?;
+
+        match tag {
+            1 => decode_v1(body),
+            2 => self.decode_padded(ProofFormat::V2, body),
+            3 => self.decode_padded(ProofFormat::V3, body),
+            _ => Err(Error::InvalidEncoding(format!(
+                "unknown proof format {}",
+                tag
+            ))),
+        }
+    }
+
+    // Checks the proof under the rules of its format, weak formats only with
+    // the "unsafe-weak-fiat-shamir" feature.
+    pub fn verify_versioned(&self, public_key: &PublicKey, versioned: &VersionedProof) -> bool {
+        if versioned.format.is_weak() && !cfg!(feature = "unsafe-weak-fiat-shamir") {
+            trace_warn!("weak Fiat-Shamir proof rejected");
+            return false;
+        }
+        self.verify_format(public_key, versioned)
+    }
+
+    fn verify_format(&self, public_key: &PublicKey, versioned: &VersionedProof) -> bool {
+        let proof = &versioned.proof;
+        let c = match versioned.format {
+            ProofFormat::V1 => self.challenge_v1(public_key, &proof.r1, &proof.r2),
+            ProofFormat::V2 => self.challenge_v2(public_key, &proof.r1, &proof.r2),
+            ProofFormat::V3 => self.challenge(public_key, &proof.r1, &proof.r2),
+        };
+        self.verify(
+            &proof.r1,
+            &proof.r2,
+            &public_key.y1,
+            &public_key.y2,
+            &proof.s,
+            &c,
+        )
+    }
+
+    // A proof can't be moved to new rules without the secret: the old one is
+    // checked and a fresh proof is made in the current format. Weak formats
+    // are checked here whatever the features, a forged old proof gains
+    // nothing as the new one is made from the secret.
+    pub fn upgrade_proof(
+        &self,
+        secret: &Secret,
+        public_key: &PublicKey,
+        old: &VersionedProof,
+    ) -> Result<Vec<u8>, Error> {
+        if !self.verify_format(public_key, old) {
+            return Err(Error::InvalidProof);
+        }
+        Ok(self.encode_proof(&self.prove(secret, public_key)))
+    }
+
+    fn decode_padded(&self, format: ProofFormat, body: &[u8]) -> Result<VersionedProof, Error> {
+        let (element_len, scalar_len) = (self.element_len(), self.scalar_len());
+        if body.len() != 2 * element_len + scalar_len {
+            return Err(Error::InvalidEncoding("bad proof length".to_string()));
+        }
+
+        let (r1, rest) = body.split_at(element_len);
+        let (r2, s) = rest.split_at(element_len);
+        Ok(VersionedProof {
+            format,
+            proof: Proof {
+                r1: self.element_from_bytes(r1)?,
+                r2: self.element_from_bytes(r2)?,
+                s: self.scalar_from_bytes(s)?,
+            },
+        })
+    }
+
+    // c = H(y1, y2, r1, r2) mod q over minimal big-endian encodings, the
+    // rule before values were padded
+    fn challenge_v1(&self, public_key: &PublicKey, r1: &BigUint, r2: &BigUint) -> BigUint {
+        let mut hasher = Sha256::new();
+        hasher.update(b"zkp-chaum-pedersen/fiat-shamir/v1");
+        for n in [&public_key.y1, &public_key.y2, r1, r2] {
+            let bytes = n.to_bytes_be();
+            hasher.update((bytes.len() as u32).to_be_bytes());
+            hasher.update(&bytes);
+        }
+
+        mod_reduce(&BigUint::from_bytes_be(&hasher.finalize()), &self.q)
+    }
+
+    // c = H(y1, y2, r1, r2) mod q over padded encodings, the rule before the
+    // group was bound
+    fn challenge_v2(&self, public_key: &PublicKey, r1: &BigUint, r2: &BigUint) -> BigUint {
+        self.hash_values(
+            b"zkp-chaum-pedersen/fiat-shamir/v1",
+            &[&public_key.y1, &public_key.y2, r1, r2],
+        )
+    }
+}
+
+fn decode_v1(mut body: &[u8]) -> Result<VersionedProof, Error> {
+    let mut values = Vec::with_capacity(3);
+    for _ in 0..3 {
+        if body.len() < 4 {
+            return Err(Error::InvalidEncoding("truncated proof".to_string()));
+        }
+        let (len, rest) = body.split_at(4);
+        let len = u32::from_be_bytes(len.try_into().unwrap()) as usize;
+        if rest.len() < len {
+            return Err(Error::InvalidEncoding("truncated proof".to_string()));
+        }
+        let (value, rest) = rest.split_at(len);
+        values.push(from_bytes_be_exact(value, len)?);
+        body = rest;
+    }
+    if !body.is_empty() {
+        return Err(Error::InvalidEncoding(
+            "trailing bytes after proof".to_string(),
+        ));
+    }
+
+    let s = values.pop().unwrap();
+    let r2 = values.pop().unwrap();
+    let r1 = values.pop().unwrap();
+    Ok(VersionedProof {
+        format: ProofFormat::V1,
+        proof: Proof { r1, r2, s },
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::ParameterSet;
+
+    // a proof as the crate wrote them in format 1
+    fn proof_v1(zkp: &ZKP, secret: &Secret, public_key: &PublicKey) -> Vec<u8> {
+        let k = ZKP::generate_random_below(&zkp.q);
+        let r1 = ZKP::exponetiate(&zkp.alpha, &k, &zkp.p);
+        let r2 = ZKP::exponetiate(&zkp.beta, &k, &zkp.p);
+        let c = zkp.challenge_v1(public_key, &r1, &r2);
+        let s = zkp.solve(&k, &c, secret.x());
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(1);
+        for n in [&r1, &r2, &s] {
+            let n = n.to_bytes_be();
+            bytes.extend_from_slice(&(n.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(&n);
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_current_format() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let secret = Secret::random(&zkp);
+        let public_key = zkp.public_key(&secret);
+
+        let proof = zkp.prove(&secret, &public_key);
+        let bytes = zkp.encode_proof(&proof);
+        assert_eq!(bytes.len(), 5 + 2 * 128 + 20);
+
+        let decoded = zkp.decode_proof(&bytes).unwrap();
+        assert_eq!(decoded.format, ProofFormat::V3);
+        assert_eq!(decoded.proof, proof);
+        assert!(zkp.verify_versioned(&public_key, &decoded));
+
+        assert!(zkp.decode_proof(&bytes[..bytes.len() - 1]).is_err());
+        let mut unknown = bytes.clone();
+        unknown[4] = 9;
+        assert!(zkp.decode_proof(&unknown).is_err());
+    }
+
+    #[test]
+    fn test_weak_proofs_only_verify_when_allowed_but_upgrade() {
+        let zkp = ParameterSet::Rfc5114_1024_160.zkp();
+        let secret = Secret::random(&zkp);
+        let public_key = zkp.public_key(&secret);
+        let allowed = cfg!(feature = "unsafe-weak-fiat-shamir");
+
+        let old = zkp
+            .decode_proof(&proof_v1(&zkp, &secret, &public_key))
+            .unwrap();
+        assert_eq!(old.format, ProofFormat::V1);
+        assert_eq!(zkp.verify_versioned(&public_key, &old), allowed);
+
+        // a V2 blob is the current layout with the old challenge
+        let k = ZKP::generate_random_below(&zkp.q);
+        let (r1, r2) = (zkp.pow(&zkp.alpha, &k), zkp.pow(&zkp.beta, &k));
+        let c = zkp.challenge_v2(&public_key, &r1, &r2);
+        let mut v2 = zkp.encode_proof(&Proof {
+            s: zkp.solve(&k, &c, secret.x()),
+            r1,
+            r2,
+        });
+        v2[4] = 2;
+        let v2 = zkp.decode_proof(&v2).unwrap();
+        assert_eq!(v2.format, ProofFormat::V2);
+        assert_eq!(zkp.verify_versioned(&public_key, &v2), allowed);
+        let as_v3 = VersionedProof {
+            format: ProofFormat::V3,
+            ..v2
+        };
+        assert!(!zkp.verify_versioned(&public_key, &as_v3));
+
+        let upgraded = zkp
+            .decode_proof(&zkp.upgrade_proof(&secret, &public_key, &old).unwrap())
+            .unwrap();
+        assert_eq!(upgraded.format, ProofFormat::CURRENT);
+        assert!(zkp.verify_versioned(&public_key, &upgraded));
+
+        let other_key = zkp.public_key(&Secret::random(&zkp));
+        assert_eq!(
+            zkp.upgrade_proof(&secret, &other_key, &old),
+            Err(Error::InvalidProof)
+        );
+    }
+}